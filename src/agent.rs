@@ -0,0 +1,388 @@
+//! Scanning spread over several hosts. The `agent` subcommand runs on each host and scans the
+//! directories it is asked for, sending back the extensions of every directory below them. A
+//! coordinator given `--agent` options merges the subtrees of all agents into one tree.
+//!
+//! The coordinator sends one request line, and the agent answers with a status line, the
+//! directories in the line format of checkpoints, children before parents, and an end line:
+//!
+//! ```text
+//! rextc-agent  2  <token>  <depth>  <no size>  <path>
+//! ok
+//! <path>  <extension>  <count>  <bytes>  <hardlinked count>  <hardlinked bytes>  <extension>  ...
+//! end
+//! ```
+//!
+//! A failed scan is answered with `error  <message>` instead. Fields are tab-separated. Agents
+//! only scan directories under the roots they are allowed, and only for coordinators sending the
+//! token they were given, if any. Requests longer than `MAX_REQUEST_LEN` bytes, or not sent within
+//! `REQUEST_TIMEOUT`, are refused.
+
+use crate::checkpoint;
+use crate::file::{Directory, Extension, ScanOptions};
+use crate::storage;
+use anyhow::{anyhow, bail, Context, Result};
+use std::collections::BTreeMap;
+use std::io::{BufRead, BufReader, BufWriter, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+
+const MAGIC: &str = "rextc-agent";
+const VERSION: &str = "2";
+
+/// Longest request line accepted, newline included.
+const MAX_REQUEST_LEN: u64 = 16 * 1024;
+
+/// Time a coordinator has to send its request, and to accept each write of the answer.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Name of the directory under which the subtrees of all agents are merged.
+pub const ROOT: &str = "agents";
+
+#[derive(clap::Args, Debug)]
+pub struct AgentArgs {
+    /// Address and port to accept scan requests on. Only local connections are accepted by
+    /// default; listen on e.g. 0.0.0.0:7878 to accept those of other hosts.
+    #[clap(long, value_name = "ADDRESS", default_value = "127.0.0.1:7878")]
+    listen: String,
+
+    /// Only scan directories under this one. Repeat to allow several.
+    #[clap(long, value_name = "PATH", multiple_occurrences = true, required = true)]
+    allow_root: Vec<PathBuf>,
+
+    /// Only answer coordinators sending the token held in this file, given to them with
+    /// --agent-token-file.
+    #[clap(long, value_name = "FILE")]
+    token_file: Option<PathBuf>,
+}
+
+/// What an agent accepts to scan, and for whom.
+#[derive(Debug, Default)]
+struct Policy {
+    /// Canonical directories under which requested paths must be.
+    allowed_roots: Vec<PathBuf>,
+
+    /// Token the coordinators must send, empty when any is accepted.
+    token: String,
+}
+
+impl Policy {
+    /// Whether a request may be answered, returning the canonical path to scan if it may, or the
+    /// reason if it may not.
+    fn check(&self, token: &str, path: &Path) -> std::result::Result<PathBuf, &'static str> {
+        // Compare every byte, so that the time taken does not tell how much of the token matched.
+        let same_token = token.len() == self.token.len()
+            && token
+                .bytes()
+                .zip(self.token.bytes())
+                .fold(0, |diff, (a, b)| diff | (a ^ b))
+                == 0;
+        if !same_token {
+            return Err("wrong token");
+        }
+        // Resolving links and `..` keeps requests from climbing out of the allowed roots.
+        match std::fs::canonicalize(path) {
+            Ok(path) if self.allowed_roots.iter().any(|root| path.starts_with(root)) => Ok(path),
+            _ => Err("path not allowed"),
+        }
+    }
+}
+
+/// Read the token shared by an agent and its coordinators from a file, ignoring a final newline.
+pub fn read_token(path: &Path) -> Result<String> {
+    let contents = std::fs::read_to_string(path).with_context(|| format!("could not read {}", path.display()))?;
+    let token = contents.trim_end_matches(['\n', '\r']);
+    if token.is_empty() || token.contains(['\t', '\n']) {
+        bail!("{} must hold a token on a single line", path.display());
+    }
+    Ok(token.to_string())
+}
+
+/// Directory to be scanned by an agent, given as `host:port=path`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Assignment {
+    address: String,
+    path: PathBuf,
+}
+
+impl Assignment {
+    fn parse(text: &str) -> Result<Self> {
+        let (address, path) = text
+            .split_once('=')
+            .ok_or_else(|| anyhow!("agent must be given as host:port=path, not {}", text))?;
+        if !address.contains(':') || !path.starts_with('/') {
+            bail!(
+                "agent must be given as host:port=path with an absolute path, not {}",
+                text
+            );
+        }
+        Ok(Self {
+            address: address.to_string(),
+            path: PathBuf::from(path),
+        })
+    }
+
+    /// Where the subtree of the agent is placed in the merged tree: under its host name, so that
+    /// the same path scanned on different hosts stays apart.
+    fn merged_root(&self) -> PathBuf {
+        let host = self
+            .address
+            .rsplit_once(':')
+            .map_or(self.address.as_str(), |(host, _)| host);
+        let relative = self.path.strip_prefix("/").unwrap_or(&self.path);
+        Path::new(ROOT)
+            .join(host.trim_start_matches('[').trim_end_matches(']'))
+            .join(relative)
+    }
+}
+
+/// Accept scan requests until stopped, serving each connection on a thread of its own.
+pub fn run(args: &AgentArgs) -> Result<()> {
+    let policy = Arc::new(Policy {
+        allowed_roots: args
+            .allow_root
+            .iter()
+            .map(|root| std::fs::canonicalize(root).with_context(|| format!("could not resolve {}", root.display())))
+            .collect::<Result<_>>()?,
+        token: args
+            .token_file
+            .as_deref()
+            .map(read_token)
+            .transpose()?
+            .unwrap_or_default(),
+    });
+    let listener = TcpListener::bind(&args.listen).with_context(|| format!("could not listen on {}", args.listen))?;
+    eprintln!("listening on {}", listener.local_addr()?);
+    for stream in listener.incoming() {
+        let stream = stream?;
+        let policy = Arc::clone(&policy);
+        std::thread::spawn(move || {
+            let peer = stream.peer_addr().map(|peer| peer.to_string()).unwrap_or_default();
+            if let Err(error) = serve(stream, &policy) {
+                eprintln!("request from {} failed: {:#}", peer, error);
+            }
+        });
+    }
+    Ok(())
+}
+
+/// Answer a single scan request, if the policy allows it.
+fn serve(stream: TcpStream, policy: &Policy) -> Result<()> {
+    stream.set_read_timeout(Some(REQUEST_TIMEOUT))?;
+    stream.set_write_timeout(Some(REQUEST_TIMEOUT))?;
+    let mut reader = BufReader::new(stream.try_clone()?.take(MAX_REQUEST_LEN));
+    let mut request = String::new();
+    let read = reader.read_line(&mut request);
+    let mut out = BufWriter::new(stream);
+    if read.is_err() || !request.ends_with('\n') {
+        writeln!(out, "error\tunreadable request")?;
+        out.flush()?;
+        bail!("unreadable request");
+    }
+    let fields: Vec<&str> = request.trim_end_matches('\n').splitn(6, '\t').collect();
+    let (token, depth, no_size, requested) = match fields[..] {
+        [MAGIC, VERSION, token, depth, no_size, path] => (token, depth, no_size == "1", PathBuf::from(path)),
+        _ => {
+            writeln!(out, "error\tunsupported request")?;
+            out.flush()?;
+            bail!("unsupported request");
+        }
+    };
+    let path = match policy.check(token, &requested) {
+        Ok(path) => path,
+        Err(reason) => {
+            writeln!(out, "error\t{}", reason)?;
+            out.flush()?;
+            bail!("refused to scan {}: {}", requested.display(), reason);
+        }
+    };
+    let max_depth = match depth.parse() {
+        Ok(max_depth) => max_depth,
+        Err(_) => {
+            writeln!(out, "error\tinvalid depth {}", depth)?;
+            out.flush()?;
+            bail!("invalid depth {}", depth);
+        }
+    };
+
+    // The canonical path approved by the policy is scanned, and its records are sent back under
+    // the path the coordinator asked for.
+    eprintln!("scanning {}", path.display());
+    let options = ScanOptions {
+        max_depth,
+        no_size,
+        skip_unreadable: true,
+        pseudo_mounts: storage::pseudo_mounts(),
+        ..Default::default()
+    };
+    let directory = match Directory::new(path.clone(), 0, &options) {
+        Ok(directory) => directory,
+        Err(error) => {
+            writeln!(out, "error\t{:#}", error)?;
+            return Err(error);
+        }
+    };
+    writeln!(out, "ok")?;
+    directory.for_each_record(&mut |scanned, extensions| {
+        let reported = requested.join(scanned.strip_prefix(&path)?);
+        Ok(writeln!(out, "{}", checkpoint::format_line(&reported, extensions))?)
+    })?;
+    writeln!(out, "end")?;
+    Ok(out.flush()?)
+}
+
+/// Ask an agent to scan its directory, returning the extensions of each directory keyed by where
+/// it is placed in the merged tree.
+fn request(
+    assignment: &Assignment,
+    token: &str,
+    max_depth: usize,
+    no_size: bool,
+) -> Result<BTreeMap<PathBuf, Vec<Extension>>> {
+    let stream = TcpStream::connect(&assignment.address)?;
+    let mut writer = stream.try_clone()?;
+    writeln!(
+        writer,
+        "{}\t{}\t{}\t{}\t{}\t{}",
+        MAGIC,
+        VERSION,
+        token,
+        max_depth,
+        no_size as u8,
+        assignment.path.display()
+    )?;
+
+    let mut lines = BufReader::new(stream).lines();
+    match lines.next().transpose()? {
+        Some(status) if status == "ok" => {}
+        Some(status) => bail!("{}", status.strip_prefix("error\t").unwrap_or(&status)),
+        None => bail!("connection closed without an answer"),
+    }
+
+    let merged_root = assignment.merged_root();
+    let mut records = BTreeMap::new();
+    for line in lines {
+        let line = line?;
+        if line == "end" {
+            return Ok(records);
+        }
+        let (path, extensions) = checkpoint::parse_line(&line).ok_or_else(|| anyhow!("malformed answer {:?}", line))?;
+        let relative = path.strip_prefix(&assignment.path)?;
+        records.insert(merged_root.join(relative), extensions);
+    }
+    bail!("connection closed before the scan was complete")
+}
+
+/// Have every agent scan its directory at once, and merge their subtrees into one tree. The token
+/// is sent to every agent, and is empty for agents accepting any.
+pub fn gather(agents: &[String], token: &str, max_depth: usize, no_size: bool) -> Result<Directory> {
+    let assignments = agents
+        .iter()
+        .map(|agent| Assignment::parse(agent))
+        .collect::<Result<Vec<_>>>()?;
+    let answers = std::thread::scope(|scope| {
+        let handles: Vec<_> = assignments
+            .iter()
+            .map(|assignment| scope.spawn(move || request(assignment, token, max_depth, no_size)))
+            .collect();
+        handles
+            .into_iter()
+            .map(|handle| handle.join().expect("agent request panicked"))
+            .collect::<Vec<_>>()
+    });
+
+    let mut records = BTreeMap::new();
+    for (assignment, answer) in assignments.iter().zip(answers) {
+        let answer = answer.with_context(|| {
+            format!(
+                "agent {} could not scan {}",
+                assignment.address,
+                assignment.path.display()
+            )
+        })?;
+        records.extend(answer);
+    }
+    Ok(Directory::from_records(PathBuf::from(ROOT), 0, &records))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_assignment() {
+        let assignment = Assignment::parse("nfs1:7878=/export/home").expect("could not parse agent");
+        assert_eq!(assignment.address, "nfs1:7878");
+        assert_eq!(assignment.merged_root(), Path::new("agents/nfs1/export/home"));
+        assert_eq!(
+            Assignment::parse("[::1]:7878=/")
+                .expect("could not parse agent")
+                .merged_root(),
+            Path::new("agents/::1")
+        );
+        assert!(Assignment::parse("nfs1=/export").is_err());
+        assert!(Assignment::parse("nfs1:7878=export").is_err());
+    }
+
+    #[test]
+    fn test_gather() {
+        let dir = std::env::temp_dir().join(format!("rextc-agent-{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("sub")).expect("could not create directories");
+        std::fs::write(dir.join("a.txt"), "abc").expect("could not write file");
+        std::fs::write(dir.join("sub/b.rs"), "fn main() {}").expect("could not write file");
+
+        let policy = Policy {
+            allowed_roots: vec![std::fs::canonicalize(&dir).expect("could not resolve directory")],
+            token: "secret".to_string(),
+        };
+        let listener = TcpListener::bind("127.0.0.1:0").expect("could not listen");
+        let address = listener.local_addr().expect("no local address");
+        let server = std::thread::spawn(move || {
+            for _ in 0..6 {
+                let (stream, _) = listener.accept().expect("could not accept");
+                let _ = serve(stream, &policy);
+            }
+        });
+
+        let agent = format!("{}={}", address, dir.display());
+        assert!(gather(std::slice::from_ref(&agent), "wrong", 5, false).is_err());
+        let outside = format!("{}={}", address, dir.join("sub/..").join("..").display());
+        assert!(gather(&[outside], "secret", 5, false).is_err());
+        let answer = |request: &[u8]| {
+            let mut stream = TcpStream::connect(address).expect("could not connect");
+            stream.write_all(request).expect("could not send request");
+            let mut answer = String::new();
+            BufReader::new(stream)
+                .read_line(&mut answer)
+                .expect("could not read answer");
+            answer
+        };
+        let bad_depth = format!("{}\t{}\tsecret\tdeep\t0\t{}\n", MAGIC, VERSION, dir.display());
+        assert_eq!(answer(bad_depth.as_bytes()), "error\tinvalid depth deep\n");
+        assert_eq!(answer(&[b'a'; MAX_REQUEST_LEN as usize]), "error\tunreadable request\n");
+        let roundabout = format!("{}={}", address, dir.join("sub/..").display());
+        let directory = gather(&[roundabout], "secret", 5, false).expect("could not gather");
+        assert_eq!(directory.extension_totals().iter().map(|e| e.count).sum::<usize>(), 2);
+        let directory = gather(&[agent], "secret", 5, false).expect("could not gather");
+        server.join().expect("server panicked");
+        std::fs::remove_dir_all(&dir).expect("could not clean up");
+
+        let totals = directory.extension_totals();
+        assert_eq!(totals.iter().map(|e| e.count).sum::<usize>(), 2);
+        assert_eq!(totals.iter().map(|e| e.total_size_bytes).sum::<u64>(), 15);
+        let merged_root = Path::new(ROOT)
+            .join("127.0.0.1")
+            .join(dir.strip_prefix("/").expect("relative temp dir"));
+        let mut records = Vec::new();
+        directory
+            .for_each_record(&mut |path, extensions| {
+                records.push((path.to_path_buf(), extensions.len()));
+                Ok(())
+            })
+            .expect("could not walk");
+        assert!(records.contains(&(merged_root.join("sub"), 1)));
+        assert!(records.contains(&(merged_root, 1)));
+        assert_eq!(records.last(), Some(&(PathBuf::from(ROOT), 0)));
+    }
+}
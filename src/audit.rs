@@ -0,0 +1,291 @@
+use std::collections::HashMap;
+use std::path::Path;
+
+/// Problems detected on individual files while scanning, counted per directory.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Finding {
+    /// File name contains a control character other than a newline.
+    ControlCharacter,
+
+    /// File name contains a newline, which breaks line-oriented tools.
+    Newline,
+
+    /// File name is not valid UTF-8.
+    InvalidUnicode,
+
+    /// File name ends in a space, which Windows and many scripts silently strip.
+    TrailingSpace,
+
+    /// File name ends in a dot, which Windows silently strips.
+    TrailingDot,
+
+    /// File is readable, writable and executable by everyone (mode 777).
+    FullAccess,
+
+    /// File is writable by users other than its owner and group.
+    WorldWritable,
+
+    /// File is writable by its group.
+    GroupWritable,
+
+    /// File runs with the permissions of its owner or group (setuid or setgid bit).
+    SetId,
+
+    /// File content does not match its extension, e.g. a ZIP archive named `.jpg`.
+    TypeMismatch,
+
+    /// Absolute path of the file is longer than the limit of the path length check.
+    LongPath,
+
+    /// Another entry of the same directory has the same name except for case.
+    CaseCollision,
+
+    /// Name is reserved for a device on Windows, such as `CON` or `nul.txt`.
+    ReservedName,
+}
+
+impl Finding {
+    /// Findings reported by the file name audit.
+    pub const NAME_AUDIT: &'static [Finding] = &[
+        Finding::ControlCharacter,
+        Finding::Newline,
+        Finding::InvalidUnicode,
+        Finding::TrailingSpace,
+        Finding::TrailingDot,
+    ];
+
+    /// Findings reported by the permission audit.
+    pub const PERMISSION_AUDIT: &'static [Finding] = &[
+        Finding::FullAccess,
+        Finding::WorldWritable,
+        Finding::GroupWritable,
+        Finding::SetId,
+    ];
+
+    /// Findings reported by the file type verification.
+    pub const TYPE_VERIFICATION: &'static [Finding] = &[Finding::TypeMismatch];
+
+    /// Findings reported by the path length check.
+    pub const PATH_LENGTH: &'static [Finding] = &[Finding::LongPath];
+
+    /// Findings reported by the case collision check.
+    pub const CASE_COLLISIONS: &'static [Finding] = &[Finding::CaseCollision];
+
+    /// Findings reported by the Windows name check.
+    pub const WINDOWS_NAMES: &'static [Finding] =
+        &[Finding::ReservedName, Finding::TrailingSpace, Finding::TrailingDot];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Finding::ControlCharacter => "control character",
+            Finding::Newline => "newline",
+            Finding::InvalidUnicode => "invalid UTF-8",
+            Finding::TrailingSpace => "trailing space",
+            Finding::TrailingDot => "trailing dot",
+            Finding::FullAccess => "mode 777",
+            Finding::WorldWritable => "world-writable",
+            Finding::GroupWritable => "group-writable",
+            Finding::SetId => "setuid or setgid",
+            Finding::TypeMismatch => "content mismatch",
+            Finding::LongPath => "long path",
+            Finding::CaseCollision => "case collision",
+            Finding::ReservedName => "reserved name",
+        }
+    }
+}
+
+/// Check the name of a file or directory for characters that make it hard to handle in scripts or on other platforms.
+pub fn audit_name(file: &Path) -> Vec<Finding> {
+    let mut findings = Vec::new();
+    let name = match file.file_name() {
+        Some(name) => name,
+        None => return findings,
+    };
+
+    if name.to_str().is_none() {
+        findings.push(Finding::InvalidUnicode);
+    }
+
+    let name = name.to_string_lossy();
+    if name.contains('\n') {
+        findings.push(Finding::Newline);
+    }
+    if name.chars().any(|c| c.is_control() && c != '\n') {
+        findings.push(Finding::ControlCharacter);
+    }
+    if name.ends_with(' ') {
+        findings.push(Finding::TrailingSpace);
+    }
+    if name.ends_with('.') {
+        findings.push(Finding::TrailingDot);
+    }
+
+    findings
+}
+
+/// Length of a path as counted by Windows, in UTF-16 code units.
+pub fn path_length(file: &Path) -> usize {
+    file.to_string_lossy().encode_utf16().count()
+}
+
+/// Names of the devices of Windows, which cannot be used as file names even with an extension.
+const RESERVED_NAMES: &[&str] = &["con", "prn", "aux", "nul"];
+
+/// Check a file or directory name for what cannot be synced to Windows: device names such as `CON`,
+/// `COM1` or `nul.txt`, and trailing spaces or dots.
+pub fn audit_windows_name(entry: &Path) -> Vec<Finding> {
+    let mut findings = Vec::new();
+    let name = match entry.file_name() {
+        Some(name) => name.to_string_lossy().to_lowercase(),
+        None => return findings,
+    };
+
+    let stem = name.split('.').next().unwrap_or_default().trim_end_matches(' ');
+    let numbered = ["com", "lpt"].iter().any(|prefix| {
+        stem.strip_prefix(prefix).is_some_and(|suffix| {
+            matches!(
+                suffix,
+                "0" | "1" | "2" | "3" | "4" | "5" | "6" | "7" | "8" | "9" | "\u{b9}" | "\u{b2}" | "\u{b3}"
+            )
+        })
+    });
+    if RESERVED_NAMES.contains(&stem) || numbered {
+        findings.push(Finding::ReservedName);
+    }
+    if name.ends_with(' ') {
+        findings.push(Finding::TrailingSpace);
+    }
+    if name.ends_with('.') {
+        findings.push(Finding::TrailingDot);
+    }
+    findings
+}
+
+/// Entries of a single directory whose names only differ by case from another entry, and would
+/// overwrite each other on a case-insensitive file system, in the order given.
+pub fn case_collisions<'a>(entries: impl Iterator<Item = &'a Path>) -> Vec<&'a Path> {
+    let entries: Vec<(&Path, String)> = entries
+        .map(|entry| {
+            (
+                entry,
+                entry.file_name().unwrap_or_default().to_string_lossy().to_lowercase(),
+            )
+        })
+        .collect();
+    let mut counts: HashMap<&str, usize> = HashMap::new();
+    for (_, name) in &entries {
+        *counts.entry(name).or_default() += 1;
+    }
+    entries
+        .iter()
+        .filter(|(_, name)| counts[name.as_str()] > 1)
+        .map(|(entry, _)| *entry)
+        .collect()
+}
+
+/// Check the permission bits of a file for access wider than usually intended.
+pub fn audit_mode(mode: u32) -> Vec<Finding> {
+    let mut findings = Vec::new();
+    if mode & 0o777 == 0o777 {
+        findings.push(Finding::FullAccess);
+    }
+    if mode & 0o002 != 0 {
+        findings.push(Finding::WorldWritable);
+    }
+    if mode & 0o020 != 0 {
+        findings.push(Finding::GroupWritable);
+    }
+    if mode & 0o6000 != 0 {
+        findings.push(Finding::SetId);
+    }
+    findings
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    #[test]
+    fn test_audit_name() {
+        assert!(audit_name(Path::new("/a/clean.txt")).is_empty());
+        assert_eq!(audit_name(Path::new("/a/two\nlines")), vec![Finding::Newline]);
+        assert_eq!(audit_name(Path::new("/a/bell\x07")), vec![Finding::ControlCharacter]);
+        assert_eq!(audit_name(Path::new("/a/spaced ")), vec![Finding::TrailingSpace]);
+        assert_eq!(audit_name(Path::new("/a/dotted.")), vec![Finding::TrailingDot]);
+    }
+
+    #[test]
+    fn test_path_length() {
+        assert_eq!(path_length(Path::new("/a/b.txt")), 8);
+        assert_eq!(path_length(Path::new("/a/\u{1F600}")), 5);
+    }
+
+    #[test]
+    fn test_case_collisions() {
+        let entries = [
+            PathBuf::from("/r/README.md"),
+            PathBuf::from("/r/src"),
+            PathBuf::from("/r/Readme.md"),
+            PathBuf::from("/r/Src"),
+            PathBuf::from("/r/lib.rs"),
+        ];
+        assert_eq!(
+            case_collisions(entries.iter().map(PathBuf::as_path)),
+            vec![
+                Path::new("/r/README.md"),
+                Path::new("/r/src"),
+                Path::new("/r/Readme.md"),
+                Path::new("/r/Src")
+            ]
+        );
+    }
+
+    #[test]
+    fn test_audit_windows_name() {
+        for reserved in [
+            "/a/CON",
+            "/a/nul.txt",
+            "/a/Com1",
+            "/a/lpt9.tar.gz",
+            "/a/aux .c",
+            "/a/COM\u{b9}",
+        ] {
+            assert_eq!(
+                audit_windows_name(Path::new(reserved)),
+                vec![Finding::ReservedName],
+                "{}",
+                reserved
+            );
+        }
+        for allowed in ["/a/console", "/a/com10", "/a/nully.txt", "/a/lpt", "/a/icon.png"] {
+            assert!(audit_windows_name(Path::new(allowed)).is_empty(), "{}", allowed);
+        }
+        assert_eq!(audit_windows_name(Path::new("/a/dir.")), vec![Finding::TrailingDot]);
+        assert_eq!(
+            audit_windows_name(Path::new("/a/prn ")),
+            vec![Finding::ReservedName, Finding::TrailingSpace]
+        );
+    }
+
+    #[test]
+    fn test_audit_mode() {
+        assert!(audit_mode(0o100644).is_empty());
+        assert_eq!(audit_mode(0o664), vec![Finding::GroupWritable]);
+        assert_eq!(audit_mode(0o4755), vec![Finding::SetId]);
+        assert_eq!(
+            audit_mode(0o777),
+            vec![Finding::FullAccess, Finding::WorldWritable, Finding::GroupWritable]
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_invalid_unicode() {
+        use std::ffi::OsStr;
+        use std::os::unix::ffi::OsStrExt;
+
+        let path = Path::new(OsStr::from_bytes(b"/a/latin1-\xe9.txt"));
+        assert_eq!(audit_name(path), vec![Finding::InvalidUnicode]);
+    }
+}
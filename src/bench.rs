@@ -0,0 +1,219 @@
+//! The `bench` subcommand: times repeated scans of a directory, to compare scanning strategies and
+//! hardware. Timings can be saved to a file and compared against in later runs, in the format:
+//!
+//! ```text
+//! min     0.412081
+//! median  0.437734
+//! files   120345
+//! ```
+
+use crate::file::{Directory, ScanOptions};
+use crate::fs;
+#[cfg(target_os = "linux")]
+use crate::{storage, uring};
+use anyhow::{bail, Context, Result};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+#[derive(clap::Args, Debug)]
+pub struct BenchArgs {
+    /// Directory to scan.
+    directory: PathBuf,
+
+    /// Number of timed scans.
+    #[clap(long, default_value_t = 5)]
+    runs: usize,
+
+    /// Drop the page, dentry and inode caches before each scan, so that every scan reads from disk
+    /// (Linux only, needs root). Without it, all but the first scan mostly measure cached lookups.
+    #[clap(long)]
+    drop_caches: bool,
+
+    /// Only count files, skipping the metadata lookups needed for sizes.
+    #[clap(long)]
+    no_size: bool,
+
+    /// Look up file sizes in batches through io_uring (Linux only).
+    #[clap(long)]
+    io_uring: bool,
+
+    /// Save the timings to this file.
+    #[clap(long, value_name = "PATH")]
+    save: Option<PathBuf>,
+
+    /// Compare the timings against those saved in this file.
+    #[clap(long, value_name = "PATH")]
+    compare: Option<PathBuf>,
+}
+
+/// Timings of a series of scans.
+#[derive(Debug, Clone, PartialEq)]
+struct Timings {
+    min: Duration,
+    median: Duration,
+    files: usize,
+}
+
+impl Timings {
+    fn new(mut durations: Vec<Duration>, files: usize) -> Self {
+        durations.sort();
+        Timings {
+            min: durations[0],
+            median: durations[durations.len() / 2],
+            files,
+        }
+    }
+
+    fn to_file_contents(&self) -> String {
+        format!(
+            "min\t{:.6}\nmedian\t{:.6}\nfiles\t{}\n",
+            self.min.as_secs_f64(),
+            self.median.as_secs_f64(),
+            self.files
+        )
+    }
+
+    fn parse(contents: &str) -> Result<Self> {
+        let (mut min, mut median, mut files) = (None, None, None);
+        for (idx, line) in contents.lines().enumerate().filter(|(_, l)| !l.trim().is_empty()) {
+            let (key, value) = line
+                .split_once(char::is_whitespace)
+                .with_context(|| format!("line {}: expected a name and a value", idx + 1))?;
+            let value = value.trim();
+            let seconds = || -> Result<Duration> {
+                let seconds = value.parse().with_context(|| format!("line {}", idx + 1))?;
+                Duration::try_from_secs_f64(seconds)
+                    .with_context(|| format!("line {}: invalid duration {}", idx + 1, value))
+            };
+            match key {
+                "min" => min = Some(seconds()?),
+                "median" => median = Some(seconds()?),
+                "files" => files = Some(value.parse().with_context(|| format!("line {}", idx + 1))?),
+                _ => bail!("line {}: unknown timing {}", idx + 1, key),
+            }
+        }
+        match (min, median, files) {
+            (Some(min), Some(median), Some(files)) => Ok(Timings { min, median, files }),
+            _ => bail!("expected min, median and files"),
+        }
+    }
+}
+
+/// Relative change from `before` to `after`, e.g. `-12.5%`.
+fn change(before: Duration, after: Duration) -> String {
+    if before.is_zero() {
+        return "n/a".to_string();
+    }
+    format!("{:+.1}%", (after.as_secs_f64() / before.as_secs_f64() - 1.0) * 100.0)
+}
+
+/// Ask the kernel to drop its caches, after writing back dirty pages so that they can be dropped.
+fn drop_caches() -> Result<()> {
+    if !cfg!(target_os = "linux") {
+        bail!("dropping caches is only available on Linux");
+    }
+    std::process::Command::new("sync")
+        .status()
+        .context("could not run sync")?;
+    std::fs::write("/proc/sys/vm/drop_caches", "3").context("could not drop caches, which needs root")
+}
+
+fn scan_options(args: &BenchArgs) -> Result<ScanOptions> {
+    #[cfg(not(target_os = "linux"))]
+    if args.io_uring {
+        bail!("io_uring is only available on Linux");
+    }
+    Ok(ScanOptions {
+        no_size: args.no_size,
+        file_system: Box::new(fs::OsFileSystem {
+            #[cfg(target_os = "linux")]
+            ring: if args.io_uring {
+                let queue_depth = storage::Storage::detect(&args.directory).queue_depth();
+                Some(uring::Ring::new(queue_depth).context("could not set up io_uring")?)
+            } else {
+                None
+            },
+        }),
+        ..Default::default()
+    })
+}
+
+fn scan(directory: &Path, options: &ScanOptions) -> Result<(Duration, usize)> {
+    let start = Instant::now();
+    let directory = Directory::new(directory.to_path_buf(), 0, options)?;
+    let elapsed = start.elapsed();
+    Ok((elapsed, directory.extension_totals().iter().map(|e| e.count).sum()))
+}
+
+/// Run the `bench` subcommand.
+pub fn run(args: &BenchArgs) -> Result<()> {
+    if args.runs == 0 {
+        bail!("--runs must be at least 1");
+    }
+    let baseline = args
+        .compare
+        .as_deref()
+        .map(|path| -> Result<Timings> {
+            let contents =
+                std::fs::read_to_string(path).with_context(|| format!("could not read {}", path.display()))?;
+            Timings::parse(&contents).with_context(|| format!("invalid timings {}", path.display()))
+        })
+        .transpose()?;
+
+    let options = scan_options(args)?;
+    let mut durations = Vec::with_capacity(args.runs);
+    let mut files = 0;
+    for run in 1..=args.runs {
+        if args.drop_caches {
+            drop_caches()?;
+        }
+        let (elapsed, count) = scan(&args.directory, &options)?;
+        println!("run {:>3}    ── {:>10.3} ms", run, elapsed.as_secs_f64() * 1000.0);
+        durations.push(elapsed);
+        files = count;
+    }
+
+    let timings = Timings::new(durations, files);
+    println!("min        ── {:>10.3} ms", timings.min.as_secs_f64() * 1000.0);
+    println!("median     ── {:>10.3} ms", timings.median.as_secs_f64() * 1000.0);
+    println!(
+        "throughput ── {:>10.0} files/s",
+        files as f64 / timings.median.as_secs_f64().max(f64::EPSILON)
+    );
+    if let Some(baseline) = &baseline {
+        if baseline.files != timings.files {
+            eprintln!("baseline scanned {} files, this scan {}", baseline.files, timings.files);
+        }
+        println!(
+            "vs baseline ── min {} ── median {}",
+            change(baseline.min, timings.min),
+            change(baseline.median, timings.median)
+        );
+    }
+    if let Some(path) = &args.save {
+        std::fs::write(path, timings.to_file_contents())
+            .with_context(|| format!("could not write {}", path.display()))?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_timings() {
+        let durations = [3, 1, 2, 5, 4]
+            .iter()
+            .map(|ms| Duration::from_millis(*ms * 100))
+            .collect();
+        let timings = Timings::new(durations, 42);
+        assert_eq!(timings.min, Duration::from_millis(100));
+        assert_eq!(timings.median, Duration::from_millis(300));
+        assert_eq!(Timings::parse(&timings.to_file_contents()).ok(), Some(timings));
+        assert!(Timings::parse("min 0.1\nfiles 3\n").is_err());
+        assert!(Timings::parse("min -1\nmedian 0.1\nfiles 3\n").is_err());
+        assert!(Timings::parse("min NaN\nmedian 1e300\nfiles 3\n").is_err());
+        assert_eq!(change(Duration::from_millis(400), Duration::from_millis(300)), "-25.0%");
+    }
+}
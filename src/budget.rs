@@ -0,0 +1,163 @@
+//! Size budgets, read from a file in a subset of TOML mapping extensions to the largest cumulative
+//! size their files may take up:
+//!
+//! ```toml
+//! # applies to the whole scan
+//! [repo]
+//! mp4 = "1G"
+//! "category:video" = "5G"
+//!
+//! # applies to every directory, with everything below it
+//! [directory]
+//! psd = "50M"
+//! "tar.*" = "200M"
+//! ```
+//!
+//! Keys are an extension, compared case-insensitively, a category as used by `--group-by category`
+//! prefixed with `category:`, or a glob pattern over extensions in which `*` matches any number of
+//! characters and `?` exactly one. Limits are in bytes, optionally followed by a binary unit: K, M,
+//! G or T. Budgets before the first section apply to the whole scan. Empty lines and lines starting
+//! with `#` are ignored.
+
+use crate::file::Extension;
+use crate::filter::glob_matches;
+use crate::group;
+use crate::quota::parse_size;
+use anyhow::{bail, Context, Result};
+use std::path::Path;
+
+/// Where the files counted against a budget are taken from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Scope {
+    /// All files of the scan.
+    Repo,
+
+    /// All files below any single directory.
+    Directory,
+}
+
+/// The extensions whose files count against a budget.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Selector {
+    Extension(String),
+    Category(String),
+    Glob(String),
+}
+
+impl Selector {
+    fn parse(key: &str) -> Result<Self> {
+        if let Some(category) = key.strip_prefix("category:") {
+            if !group::is_category(category) {
+                bail!("unknown category {}", category);
+            }
+            Ok(Selector::Category(category.to_string()))
+        } else if key.contains(['*', '?']) {
+            Ok(Selector::Glob(key.to_lowercase()))
+        } else if key.is_empty() {
+            bail!("expected an extension, category or pattern");
+        } else {
+            Ok(Selector::Extension(key.to_lowercase()))
+        }
+    }
+
+    /// Whether files with the given extension count against the budget.
+    pub fn matches(&self, extension: Option<&str>) -> bool {
+        let lowercase = extension.map(str::to_lowercase);
+        match (self, lowercase.as_deref()) {
+            (Selector::Category(category), _) => group::category(extension) == category,
+            (Selector::Extension(name), Some(extension)) => name == extension,
+            (Selector::Glob(pattern), Some(extension)) => glob_matches(pattern, extension),
+            (_, None) => false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Budget {
+    pub scope: Scope,
+
+    /// Key as written in the budget file.
+    pub key: String,
+
+    pub selector: Selector,
+
+    /// Limit in bytes.
+    pub limit: u64,
+}
+
+impl Budget {
+    /// Size of the files of the extensions counting against the budget.
+    pub fn used(&self, extensions: &[Extension]) -> u64 {
+        extensions
+            .iter()
+            .filter(|e| self.selector.matches(e.name.as_deref()))
+            .map(|e| e.total_size_bytes)
+            .sum()
+    }
+}
+
+fn parse_budgets(budgets: &str) -> Result<Vec<Budget>> {
+    let mut parsed = Vec::new();
+    let mut scope = Scope::Repo;
+    for (idx, line) in budgets.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        if let Some(section) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+            scope = match section.trim() {
+                "repo" => Scope::Repo,
+                "directory" => Scope::Directory,
+                section => bail!("line {}: unknown section {}", idx + 1, section),
+            };
+            continue;
+        }
+        let (key, limit) = match line.split_once('=') {
+            Some((key, limit)) => (key.trim().trim_matches('"'), limit.trim().trim_matches('"')),
+            None => bail!("line {}: expected an extension and a limit", idx + 1),
+        };
+        parsed.push(Budget {
+            scope,
+            key: key.to_string(),
+            selector: Selector::parse(key).with_context(|| format!("line {}", idx + 1))?,
+            limit: parse_size(limit).with_context(|| format!("line {}", idx + 1))?,
+        });
+    }
+    Ok(parsed)
+}
+
+/// Read a budget file.
+pub fn read_budgets(path: &Path) -> Result<Vec<Budget>> {
+    let budgets = std::fs::read_to_string(path).with_context(|| format!("could not read {}", path.display()))?;
+    parse_budgets(&budgets).with_context(|| format!("invalid budgets {}", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_budgets() {
+        let budgets =
+            parse_budgets("# budgets\nMP4 = \"1G\"\n\n[directory]\n\"category:image\" = \"10M\"\n\"tar.*\" = 512\n")
+                .expect("could not parse budgets");
+        assert_eq!(budgets.len(), 3);
+        assert_eq!(budgets[0].scope, Scope::Repo);
+        assert_eq!(budgets[0].selector, Selector::Extension("mp4".to_string()));
+        assert_eq!(budgets[0].limit, 1024u64.pow(3));
+        assert_eq!(budgets[1].scope, Scope::Directory);
+        assert_eq!(budgets[1].selector, Selector::Category("image".to_string()));
+        assert_eq!(budgets[2].limit, 512);
+
+        assert!(budgets[0].selector.matches(Some("mp4")));
+        assert!(budgets[1].selector.matches(Some("PNG")));
+        assert!(budgets[2].selector.matches(Some("tar.gz")));
+        assert!(!budgets[2].selector.matches(None));
+
+        assert!(parse_budgets("[files]").is_err());
+        assert!(parse_budgets("\"category:smell\" = 1").is_err());
+        assert!(parse_budgets("mp4 = 1Q").is_err());
+        assert!(parse_budgets("mp4").is_err());
+    }
+}
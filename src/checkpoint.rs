@@ -0,0 +1,256 @@
+//! Progress of a scan persisted to disk, so that an interrupted scan can resume where it left off.
+//!
+//! The checkpoint file starts with a header identifying the scan, followed by one line per
+//! directory whose subtree has been completely scanned, children before parents:
+//!
+//! ```text
+//! rextc-checkpoint  3  <root>  <depth>  <options>
+//! <path>  <extension>  <count>  <bytes>  <hardlinked count>  <hardlinked bytes>  <extension>  ...  \.
+//! ```
+//!
+//! The header records the options changing what is counted, such as `--no-size`, `--exclude`,
+//! `--where` and the ignore rules, so that a scan is only resumed or compared with the same ones.
+//! Each directory line lists the extensions of the files directly in it, or of all files below it
+//! when the recursion limit was reached there. Extensions are prefixed with a dot, files without
+//! an extension have an empty name. Fields are tab-separated, with tabs, newlines and backslashes
+//! escaped, and every line ends with a `\.` field, which escaping cannot produce. Lines are only
+//! ever appended, and a line missing its terminator, truncated by an interruption, is ignored.
+
+use crate::file::Extension;
+use anyhow::{bail, Context, Result};
+use std::cell::{Cell, RefCell};
+use std::collections::BTreeMap;
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+const MAGIC: &str = "rextc-checkpoint";
+const VERSION: &str = "3";
+
+/// Last field of every directory line, telling complete lines from truncated ones.
+const TERMINATOR: &str = "\\.";
+
+/// Minimum time between two flushes of the checkpoint file.
+const FLUSH_INTERVAL: Duration = Duration::from_secs(1);
+
+#[derive(Debug)]
+pub struct Checkpoint {
+    /// Directories completed in a previous run, with the extensions recorded for them.
+    completed: BTreeMap<PathBuf, Vec<Extension>>,
+
+    writer: RefCell<BufWriter<File>>,
+
+    last_flush: Cell<Instant>,
+}
+
+fn escape(field: &str) -> String {
+    field.replace('\\', "\\\\").replace('\t', "\\t").replace('\n', "\\n")
+}
+
+fn unescape(field: &str) -> String {
+    let mut unescaped = String::with_capacity(field.len());
+    let mut chars = field.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            unescaped.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('t') => unescaped.push('\t'),
+            Some('n') => unescaped.push('\n'),
+            Some(c) => unescaped.push(c),
+            None => {}
+        }
+    }
+    unescaped
+}
+
+fn header(root: &Path, max_depth: usize, settings: &str) -> String {
+    format!(
+        "{}\t{}\t{}\t{}\t{}",
+        MAGIC,
+        VERSION,
+        escape(&root.to_string_lossy()),
+        max_depth,
+        escape(settings)
+    )
+}
+
+/// Format the line recording a directory and its extensions.
+pub fn format_line(dir: &Path, extensions: &[Extension]) -> String {
+    let mut line = escape(&dir.to_string_lossy());
+    for extension in extensions {
+        let name = extension.name.as_ref().map(|name| format!(".{}", name));
+        line.push_str(&format!(
+            "\t{}\t{}\t{}\t{}\t{}",
+            escape(name.as_deref().unwrap_or_default()),
+            extension.count,
+            extension.total_size_bytes,
+            extension.hardlinked_count,
+            extension.hardlinked_size_bytes
+        ));
+    }
+    line.push('\t');
+    line.push_str(TERMINATOR);
+    line
+}
+
+/// Parse a directory line, returning `None` if it is malformed or truncated.
+pub fn parse_line(line: &str) -> Option<(PathBuf, Vec<Extension>)> {
+    let mut fields = line.split('\t');
+    let path = PathBuf::from(unescape(fields.next()?));
+    let mut fields: Vec<&str> = fields.collect();
+    if fields.pop()? != TERMINATOR || !fields.len().is_multiple_of(5) {
+        return None;
+    }
+
+    let extensions = fields
+        .chunks(5)
+        .map(|chunk| {
+            let name = unescape(chunk[0]);
+            Some(Extension {
+                name: match name.strip_prefix('.') {
+                    Some(name) => Some(name.to_string()),
+                    None if name.is_empty() => None,
+                    None => return None,
+                },
+                count: chunk[1].parse().ok()?,
+                total_size_bytes: chunk[2].parse().ok()?,
+                hardlinked_count: chunk[3].parse().ok()?,
+                hardlinked_size_bytes: chunk[4].parse().ok()?,
+                ..Default::default()
+            })
+        })
+        .collect::<Option<Vec<_>>>()?;
+    Some((path, extensions))
+}
+
+/// Read the directories recorded in an existing checkpoint file, which must start with the
+/// expected header unless it is empty.
+fn read_completed(path: &Path, expected_header: &str) -> Result<BTreeMap<PathBuf, Vec<Extension>>> {
+    let reader = BufReader::new(File::open(path).context("could not open checkpoint")?);
+    let mut lines = reader.lines();
+    match lines.next().transpose()? {
+        Some(header) if header == expected_header => {}
+        Some(_) => bail!("{} is the checkpoint of a different scan", path.display()),
+        None => {}
+    }
+
+    let mut completed = BTreeMap::new();
+    for line in lines {
+        if let Some((path, extensions)) = parse_line(&line?) {
+            completed.insert(path, extensions);
+        }
+    }
+    Ok(completed)
+}
+
+/// Read the directories recorded by an earlier scan of `root` with the same depth and counting
+/// options, keyed by path, to compare a new scan against.
+pub fn read_baseline(
+    path: &Path,
+    root: &Path,
+    max_depth: usize,
+    settings: &str,
+) -> Result<BTreeMap<PathBuf, Vec<Extension>>> {
+    let root = std::fs::canonicalize(root)?;
+    read_completed(path, &header(&root, max_depth, settings))
+}
+
+impl Checkpoint {
+    /// Open the checkpoint of a scan of `root`, creating it if it does not exist yet. Fails if the
+    /// file is the checkpoint of a different scan, or of one with different counting options.
+    pub fn open(path: &Path, root: &Path, max_depth: usize, settings: &str) -> Result<Self> {
+        let root = std::fs::canonicalize(root)?;
+        let expected_header = header(&root, max_depth, settings);
+
+        let exists = path.exists();
+        let completed = if exists {
+            read_completed(path, &expected_header)?
+        } else {
+            BTreeMap::new()
+        };
+
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .context("could not open checkpoint")?;
+        let mut writer = BufWriter::new(file);
+        if !exists || std::fs::metadata(path)?.len() == 0 {
+            writeln!(writer, "{}", expected_header)?;
+        } else {
+            // Terminate a line truncated by an interruption, so that it does not corrupt the next.
+            writeln!(writer)?;
+        }
+
+        Ok(Self {
+            completed,
+            writer: RefCell::new(writer),
+            last_flush: Cell::new(Instant::now()),
+        })
+    }
+
+    /// Extensions and subdirectories of a directory completed in a previous run.
+    pub fn restore(&self, dir: &Path) -> Option<(Vec<Extension>, Vec<PathBuf>)> {
+        let extensions = self.completed.get(dir)?.clone();
+        let subdirectories = self
+            .completed
+            .range(dir.to_path_buf()..)
+            .skip(1)
+            .take_while(|(path, _)| path.starts_with(dir))
+            .filter(|(path, _)| path.parent() == Some(dir))
+            .map(|(path, _)| path.clone())
+            .collect();
+        Some((extensions, subdirectories))
+    }
+
+    /// Record a directory whose subtree has been completely scanned.
+    pub fn record(&self, dir: &Path, extensions: &[Extension]) -> Result<()> {
+        let mut writer = self.writer.borrow_mut();
+        writeln!(writer, "{}", format_line(dir, extensions))?;
+        if self.last_flush.get().elapsed() >= FLUSH_INTERVAL {
+            writer.flush()?;
+            self.last_flush.set(Instant::now());
+        }
+        Ok(())
+    }
+}
+
+impl Drop for Checkpoint {
+    fn drop(&mut self) {
+        let _ = self.writer.borrow_mut().flush();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_line() {
+        let (path, extensions) =
+            parse_line("/a/b\\tc\t.rs\t2\t30\t1\t10\t\t1\t5\t0\t0\t\\.").expect("could not parse line");
+        assert_eq!(path, PathBuf::from("/a/b\tc"));
+        assert_eq!(extensions.len(), 2);
+        assert_eq!(extensions[0].name.as_deref(), Some("rs"));
+        assert_eq!(extensions[0].hardlinked_size_bytes, 10);
+        assert_eq!(extensions[1].name, None);
+        assert_eq!(extensions[1].total_size_bytes, 5);
+
+        assert!(parse_line("/a/b\t.rs\t2\t30\t\\.").is_none());
+        assert!(parse_line("/a/b\t.rs\t2\tthirty\t0\t0\t\\.").is_none());
+        // Cut off between two extensions, or within the last field of one.
+        assert!(parse_line("/a/b\t.rs\t2\t30\t1\t10").is_none());
+        assert!(parse_line("/a/b\t.rs\t2\t30\t1\t1").is_none());
+
+        let (path, extensions) = parse_line(&format_line(Path::new("/a"), &extensions)).expect("could not parse line");
+        assert_eq!(path, PathBuf::from("/a"));
+        assert_eq!(extensions.len(), 2);
+        assert_eq!(
+            parse_line(&format_line(Path::new("/a"), &[])).map(|(_, e)| e.len()),
+            Some(0)
+        );
+    }
+}
@@ -0,0 +1,526 @@
+//! The `classify` subcommand: explains how a single file is counted, from the suffix following its
+//! last dot to the extension and category it ends up under, or to its group with `--group-by` and
+//! `--group-by-regex`, followed by the filters that would leave it out of a scan. It takes the same
+//! options deciding this as a scan. Each step is printed with its outcome:
+//!
+//! ```text
+//! suffix        ── 001
+//! split archive ── part of a split archive, counted as 7z.split
+//! counted under ── 7z.split
+//! category      ── other
+//! ignore rules  ── none apply
+//! scan          ── counted
+//! ```
+
+use crate::file::{Extensionless, ScanOptions};
+use crate::fs::Metadata;
+use crate::group::GroupBy;
+use crate::normalize::{normalize, Normalization};
+use crate::sanity::{self, BogusExtensions, ExtensionRules};
+use crate::{config, filter, group, ignore, languages, regex, trash, width};
+use anyhow::{bail, Context, Result};
+use clap::ArgEnum;
+use std::collections::HashMap;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+#[derive(clap::Args, Debug)]
+pub struct ClassifyArgs {
+    /// File to classify. It does not have to exist, in which case neither its contents nor its
+    /// metadata are looked at.
+    file: PathBuf,
+
+    /// Directory the scan would start from, whose ignore files apply along with those of the
+    /// directories down to the file. Defaults to the current directory.
+    #[clap(long, value_name = "DIR")]
+    root: Option<PathBuf>,
+
+    #[clap(flatten)]
+    classifier: ClassifierArgs,
+}
+
+/// The options deciding what a file is counted under and whether it is counted at all, shared by
+/// scans and `classify` so that both classify files alike.
+#[derive(clap::Args, Debug)]
+pub struct ClassifierArgs {
+    /// Skip files and directories whose name matches this pattern, like tree -I. Alternatives are
+    /// separated by |, e.g. 'target|*.tmp'.
+    #[clap(short = 'I', long, value_name = "PATTERN")]
+    pub exclude: Option<String>,
+
+    /// Only count files whose name matches this pattern, like tree -P. Alternatives are separated
+    /// by |, e.g. '*.rs|*.toml'.
+    #[clap(short = 'P', long, value_name = "PATTERN")]
+    pub include: Option<String>,
+
+    /// Skip files and directories matching the gitignore-style rules in this file, which are
+    /// relative to the scanned directory.
+    #[clap(long, value_name = "PATH")]
+    pub ignore_file: Option<PathBuf>,
+
+    /// Do not apply the rules of the .ignore files found in the tree.
+    #[clap(long)]
+    pub no_ignore: bool,
+
+    /// Do not apply the rules of the global ignore file, ~/.config/extension-count/ignore.
+    #[clap(long)]
+    pub no_global_ignore: bool,
+
+    /// How files in trash locations such as .Trash-1000, ~/.local/share/Trash and $RECYCLE.BIN
+    /// are counted. Reported trash is left out of the tree and listed after it.
+    #[clap(long, arg_enum, default_value = "include")]
+    pub trash: trash::Trash,
+
+    /// How files without an extension are grouped: all together, by file name (Makefile, LICENSE)
+    /// or by content into scripts and binaries.
+    #[clap(long, arg_enum, value_name = "GROUPING", default_value = "single")]
+    pub extensionless: Extensionless,
+
+    /// Do not count suffixes longer than this as extensions.
+    #[clap(long, value_name = "N")]
+    pub max_extension_length: Option<usize>,
+
+    /// Do not count suffixes made of digits only as extensions, e.g. data.00000123.
+    #[clap(long)]
+    pub no_numeric_extensions: bool,
+
+    /// Only count suffixes made of these characters as extensions, e.g. 'a-zA-Z0-9_'.
+    #[clap(long, value_name = "SET")]
+    pub extension_chars: Option<String>,
+
+    /// What suffixes rejected as extensions are counted as: no extension, or their shape with
+    /// digits replaced by # (e.g. <########>).
+    #[clap(long, arg_enum, value_name = "HANDLING", default_value = "none")]
+    pub bogus_extensions: BogusExtensions,
+
+    /// Count the numbered parts of split archives (.001, .r00, .z01) under a single extension,
+    /// e.g. 7z.split.
+    #[clap(long)]
+    pub split_archives: bool,
+
+    /// Count files by programming language rather than extension, e.g. .c, .h and .cpp files as
+    /// C/C++. Extensions of no known language are kept.
+    #[clap(long)]
+    pub languages: bool,
+
+    /// With --languages, read additional or overriding mappings from this file, with one extension
+    /// and language per line.
+    #[clap(long, requires = "languages")]
+    pub language_map: Option<PathBuf>,
+
+    /// Only count files matching this expression, e.g. 'size > 100MiB && ext in (mp4, mkv) &&
+    /// mtime < 2023-01-01'. Fields: size, mtime, ext, name. Operators: == != < <= > >= ~ (glob),
+    /// in (...), &&, ||, !.
+    #[clap(long = "where", value_name = "EXPRESSION")]
+    pub filter: Option<String>,
+
+    /// Unicode normalization applied to extensions and directory names before they are compared.
+    #[clap(long, arg_enum)]
+    pub normalize: Option<Normalization>,
+
+    /// What files are grouped by in the tree: their extension, owner, year or month of last
+    /// modification, directory directly below the scanned one, or a category such as image or code
+    /// guessed from the extension. --languages, --extensionless and --normalize only apply when
+    /// grouping by extension.
+    #[clap(long, arg_enum, value_name = "DIMENSION", default_value = "extension")]
+    pub group_by: group::GroupBy,
+
+    /// Group files by the first capture group of a regular expression in their name, or by the
+    /// whole match if it has no groups, e.g. '^(IMG|DSC|MVI)' for camera files. Files whose name
+    /// does not match go under N/A.
+    #[clap(long, value_name = "PATTERN", conflicts_with = "group-by")]
+    pub group_by_regex: Option<regex::Regex>,
+}
+
+impl ClassifierArgs {
+    /// Scan options classifying files as these arguments ask, with the defaults for everything
+    /// else and no ignore rules, which depend on the scanned directory.
+    pub fn scan_options(&self) -> Result<ScanOptions> {
+        Ok(ScanOptions {
+            exclude: self.exclude.clone(),
+            include: self.include.clone(),
+            group_by: self.group_by,
+            group_by_regex: self.group_by_regex.clone(),
+            user_names: if self.group_by == GroupBy::Owner {
+                group::user_names()
+            } else {
+                HashMap::new()
+            },
+            extensionless: self.extensionless,
+            languages: self
+                .languages
+                .then(|| {
+                    let language_map = self
+                        .language_map
+                        .clone()
+                        .or_else(|| config::config_file(config::LANGUAGES_FILE));
+                    languages::read_languages(language_map.as_deref())
+                })
+                .transpose()?,
+            extension_rules: ExtensionRules {
+                max_length: self.max_extension_length,
+                reject_numeric: self.no_numeric_extensions,
+                allowed: self
+                    .extension_chars
+                    .as_deref()
+                    .map(sanity::parse_character_set)
+                    .transpose()?,
+                bogus: self.bogus_extensions,
+                split_archives: self.split_archives,
+            },
+            filter: self.filter.as_deref().map(filter::Filter::parse).transpose()?,
+            normalization: self.normalize,
+            trash: self.trash,
+            ..Default::default()
+        })
+    }
+
+    /// Ignore rules of a scan of the canonical directory `root`.
+    pub fn ignore(&self, root: &Path) -> Result<ignore::Ignore> {
+        ignore::Ignore::new(
+            self.ignore_file.as_deref(),
+            root,
+            !self.no_ignore,
+            self.no_global_ignore,
+        )
+    }
+}
+
+/// Label of a file without an extension.
+fn label(extension: Option<&str>) -> String {
+    extension.map_or_else(|| "no extension".to_string(), str::to_string)
+}
+
+/// The steps deciding how a file below the scan root is counted, each with its outcome, ending
+/// with whether the file is counted at all.
+fn explain(file: &Path, root: &Path, options: &ScanOptions) -> Result<Vec<(&'static str, String)>> {
+    let mut steps = Vec::new();
+    if options.group_by_regex.is_none() && options.group_by == GroupBy::Extension {
+        explain_extension(file, options, &mut steps)?;
+    } else {
+        explain_group(file, root, options, &mut steps)?;
+    }
+
+    // The directories from the root down to the file, and the file itself.
+    let mut entries = Vec::new();
+    let mut path = root.to_path_buf();
+    for component in file.strip_prefix(root)?.components() {
+        path.push(component);
+        entries.push(path.clone());
+    }
+    let name = |path: &Path| path.file_name().unwrap_or_default().to_string_lossy().to_string();
+    let relative = |path: &Path| path.strip_prefix(root).unwrap_or(path).display().to_string();
+    let mut skipped = false;
+
+    if let Some(patterns) = &options.exclude {
+        let excluded = entries.iter().find(|entry| filter::matches_any(patterns, &name(entry)));
+        skipped |= excluded.is_some();
+        steps.push((
+            "--exclude",
+            excluded.map_or_else(
+                || "no match".to_string(),
+                |entry| format!("matches {}", relative(entry)),
+            ),
+        ));
+    }
+
+    let mut ignored = None;
+    for (idx, entry) in entries.iter().enumerate() {
+        options
+            .ignore
+            .enter(entry.parent().unwrap_or(root), options.file_system.as_ref());
+        if options.ignore.is_ignored(entry, idx + 1 < entries.len()) {
+            ignored = Some(entry);
+            break;
+        }
+    }
+    skipped |= ignored.is_some();
+    steps.push((
+        "ignore rules",
+        ignored.map_or_else(
+            || "none apply".to_string(),
+            |entry| format!("{} is ignored", relative(entry)),
+        ),
+    ));
+
+    if options.trash != trash::Trash::Include {
+        let dirs = &entries[..entries.len() - 1];
+        let trash = dirs.iter().find(|dir| trash::is_trash(dir));
+        skipped |= trash.is_some();
+        steps.push((
+            "--trash",
+            trash.map_or_else(
+                || "not in a trash location".to_string(),
+                |dir| format!("{} is a trash location", relative(dir)),
+            ),
+        ));
+    }
+
+    if let Some(patterns) = &options.include {
+        let included = filter::matches_any(patterns, &name(file));
+        skipped |= !included;
+        steps.push(("--include", if included { "matches" } else { "no match" }.to_string()));
+    }
+
+    if let Some(filter) = &options.filter {
+        steps.push((
+            "--where",
+            match options.file_system.metadata(file) {
+                Ok(metadata) => {
+                    let matches = filter.matches(file, &metadata);
+                    skipped |= !matches;
+                    if matches { "matches" } else { "no match" }.to_string()
+                }
+                Err(error) => format!("not evaluated, could not read the metadata: {}", error),
+            },
+        ));
+    }
+
+    steps.push(("scan", if skipped { "skipped" } else { "counted" }.to_string()));
+    Ok(steps)
+}
+
+/// The steps leading from the suffix of a file to the extension it is counted under when grouping
+/// by extension.
+fn explain_extension(file: &Path, options: &ScanOptions, steps: &mut Vec<(&'static str, String)>) -> Result<()> {
+    let rules = &options.extension_rules;
+    let suffix = file.extension().map(|e| e.to_string_lossy().to_string());
+    steps.push(("suffix", label(suffix.as_deref())));
+
+    let split = sanity::split_archive_extension(file).filter(|_| rules.split_archives);
+    if rules.split_archives {
+        steps.push((
+            "split archive",
+            match &split {
+                Some(extension) => format!("part of a split archive, counted as {}", extension),
+                None => "not part of a split archive".to_string(),
+            },
+        ));
+    }
+    let extension = rules.extension(file);
+    if let (None, Some(suffix)) = (&split, &suffix) {
+        steps.push((
+            "extension rules",
+            match rules.rejection(suffix) {
+                Some(reason) => format!("rejected as {}, counted as {}", reason, label(extension.as_deref())),
+                None => "accepted".to_string(),
+            },
+        ));
+    }
+
+    let unnormalized = match &extension {
+        Some(extension) => match &options.languages {
+            Some(languages) => {
+                let language = languages.language(extension);
+                steps.push((
+                    "language",
+                    match language {
+                        Some(language) => format!("{} is {}", extension, language),
+                        None => format!("no language known for {}, kept", extension),
+                    },
+                ));
+                Some(language.map_or_else(|| extension.clone(), str::to_string))
+            }
+            None => Some(extension.clone()),
+        },
+        None => {
+            let group = options.extensionless_group(file)?;
+            steps.push((
+                "extensionless",
+                match options.extensionless {
+                    Extensionless::Single => "grouped with all files without one".to_string(),
+                    Extensionless::Name | Extensionless::Content => format!("grouped as {}", label(group.as_deref())),
+                },
+            ));
+            group
+        }
+    };
+    if let Some(form) = options.normalization {
+        let normalized = unnormalized.as_deref().map(|extension| normalize(extension, form));
+        steps.push((
+            "normalization",
+            if normalized == unnormalized {
+                "unchanged".to_string()
+            } else {
+                format!(
+                    "{} becomes {}",
+                    label(unnormalized.as_deref()),
+                    label(normalized.as_deref())
+                )
+            },
+        ));
+    }
+    let bucket = options.extension_of(file)?;
+    steps.push(("counted under", label(bucket.as_deref())));
+    steps.push(("category", group::category(bucket.as_deref()).to_string()));
+    Ok(())
+}
+
+/// The steps leading to the group a file is counted under with `--group-by` or `--group-by-regex`.
+fn explain_group(
+    file: &Path,
+    root: &Path,
+    options: &ScanOptions,
+    steps: &mut Vec<(&'static str, String)>,
+) -> Result<()> {
+    let grouping = match &options.group_by_regex {
+        Some(_) => "by the first capture of --group-by-regex in the name".to_string(),
+        None => format!(
+            "by {}",
+            options
+                .group_by
+                .to_possible_value()
+                .map_or("extension", |value| value.get_name())
+        ),
+    };
+    steps.push(("grouping", grouping));
+    // Owners and modification times are only known for files that exist.
+    let needs_metadata = options.group_by_regex.is_none()
+        && matches!(
+            options.group_by,
+            GroupBy::Owner | GroupBy::MtimeYear | GroupBy::MtimeMonth
+        );
+    let metadata = match options.file_system.metadata(file) {
+        Ok(metadata) => metadata,
+        Err(error) if needs_metadata => {
+            steps.push((
+                "counted under",
+                format!("not determined, could not read the metadata: {}", error),
+            ));
+            return Ok(());
+        }
+        Err(_) => Metadata::default(),
+    };
+    let bucket = options.group_of(file, &metadata, root)?;
+    steps.push(("counted under", bucket.unwrap_or_else(|| "N/A".to_string())));
+    Ok(())
+}
+
+pub fn run(args: &ClassifyArgs) -> Result<()> {
+    let mut options = ScanOptions {
+        fail_fast: true,
+        ..args.classifier.scan_options()?
+    };
+
+    let root = options
+        .file_system
+        .canonicalize(args.root.as_deref().unwrap_or_else(|| Path::new(".")))?;
+    let name = match args.file.file_name() {
+        Some(name) => name,
+        None => bail!("{} does not name a file", args.file.display()),
+    };
+    // Files that do not exist are classified by name, in the directory they would be in.
+    let dir = args.file.parent().filter(|dir| !dir.as_os_str().is_empty());
+    let dir = options
+        .file_system
+        .canonicalize(dir.unwrap_or_else(|| Path::new(".")))
+        .with_context(|| format!("could not find the directory of {}", args.file.display()))?;
+    let file = dir.join(name);
+    if !file.starts_with(&root) {
+        bail!("{} is not below {}", args.file.display(), root.display());
+    }
+    options.ignore = args.classifier.ignore(&root)?;
+
+    let steps = explain(&file, &root, &options)?;
+    let max_label_chars = steps.iter().map(|(label, _)| width::width(label)).max().unwrap_or(0);
+    let mut out = io::stdout().lock();
+    writeln!(out, "{}", file.display())?;
+    for (label, outcome) in steps {
+        writeln!(out, "{} ── {}", width::pad(label, max_label_chars), outcome)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fs::MemoryFileSystem;
+
+    fn outcome<'a>(steps: &'a [(&'static str, String)], label: &str) -> Option<&'a str> {
+        steps
+            .iter()
+            .find(|(l, _)| *l == label)
+            .map(|(_, outcome)| outcome.as_str())
+    }
+
+    #[test]
+    fn test_explain() {
+        let mut file_system = MemoryFileSystem::default();
+        file_system
+            .add_file("/repo/backup.7z.001", 1)
+            .add_file("/repo/data.00000123", 1)
+            .add_file_with_contents("/repo/logs/.ignore", b"*.log\n")
+            .add_file("/repo/logs/debug.log", 1)
+            .add_file("/repo/.Trash-1000/old.txt", 1);
+        let options = ScanOptions {
+            exclude: Some("*.tmp".to_string()),
+            trash: trash::Trash::Exclude,
+            extension_rules: ExtensionRules {
+                reject_numeric: true,
+                bogus: BogusExtensions::Pattern,
+                split_archives: true,
+                ..Default::default()
+            },
+            ignore: ignore::Ignore::new(None, Path::new("/repo"), true, true).expect("could not read ignore rules"),
+            file_system: Box::new(file_system),
+            ..Default::default()
+        };
+        let explain = |file: &str| explain(Path::new(file), Path::new("/repo"), &options).expect("could not explain");
+
+        let steps = explain("/repo/backup.7z.001");
+        assert_eq!(outcome(&steps, "suffix"), Some("001"));
+        assert_eq!(outcome(&steps, "extension rules"), None);
+        assert_eq!(outcome(&steps, "counted under"), Some("7z.split"));
+        assert_eq!(outcome(&steps, "category"), Some("other"));
+        assert_eq!(outcome(&steps, "scan"), Some("counted"));
+
+        let steps = explain("/repo/data.00000123");
+        assert_eq!(
+            outcome(&steps, "extension rules"),
+            Some("rejected as made of digits only, counted as <########>")
+        );
+        assert_eq!(outcome(&steps, "counted under"), Some("<########>"));
+
+        let steps = explain("/repo/logs/debug.log");
+        assert_eq!(outcome(&steps, "ignore rules"), Some("logs/debug.log is ignored"));
+        assert_eq!(outcome(&steps, "scan"), Some("skipped"));
+
+        let steps = explain("/repo/.Trash-1000/old.txt");
+        assert_eq!(outcome(&steps, "--trash"), Some(".Trash-1000 is a trash location"));
+        assert_eq!(outcome(&steps, "--exclude"), Some("no match"));
+        assert_eq!(outcome(&steps, "scan"), Some("skipped"));
+    }
+
+    #[test]
+    fn test_explain_group() {
+        let mut file_system = MemoryFileSystem::default();
+        file_system.add_file("/photos/2023/IMG_0001.jpg", 1);
+        let mut options = ScanOptions {
+            group_by_regex: Some("^(IMG|DSC)".parse().expect("could not compile regex")),
+            file_system: Box::new(file_system),
+            ..Default::default()
+        };
+        let explain = |file: &str, options: &ScanOptions| {
+            explain(Path::new(file), Path::new("/photos"), options).expect("could not explain")
+        };
+
+        let steps = explain("/photos/2023/IMG_0001.jpg", &options);
+        assert_eq!(outcome(&steps, "suffix"), None);
+        assert_eq!(outcome(&steps, "counted under"), Some("IMG"));
+        assert_eq!(outcome(&steps, "scan"), Some("counted"));
+        assert_eq!(
+            outcome(&explain("/photos/2023/scan.jpg", &options), "counted under"),
+            Some("N/A")
+        );
+
+        options.group_by_regex = None;
+        options.group_by = GroupBy::TopLevelDir;
+        let steps = explain("/photos/2023/IMG_0001.jpg", &options);
+        assert_eq!(outcome(&steps, "grouping"), Some("by top-level-dir"));
+        assert_eq!(outcome(&steps, "counted under"), Some("2023"));
+
+        options.group_by = GroupBy::MtimeYear;
+        let steps = explain("/photos/2023/missing.jpg", &options);
+        assert!(outcome(&steps, "counted under").is_some_and(|outcome| outcome.starts_with("not determined")));
+    }
+}
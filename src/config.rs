@@ -0,0 +1,140 @@
+//! Files read from the user's configuration directory, `~/.config/extension-count` on Unix, and the
+//! `config init` subcommand writing commented templates of them.
+
+use anyhow::{Context, Result};
+use clap::Subcommand;
+use std::path::{Path, PathBuf};
+
+/// Directory holding the configuration files, below the user's configuration directory.
+const CONFIG_DIR: &str = "extension-count";
+
+/// Ignore rules applied to every scan, unless --no-global-ignore is given.
+pub const IGNORE_FILE: &str = "ignore";
+
+/// Language mappings used by --languages, unless --language-map is given.
+pub const LANGUAGES_FILE: &str = "languages";
+
+/// Colors used instead of those of the palette, unless --theme is given.
+pub const THEME_FILE: &str = "theme.toml";
+
+const IGNORE_TEMPLATE: &str = "\
+# Ignore rules applied to every scan, in the format of .gitignore. Patterns containing a / are
+# relative to the scanned directory, others match names at any depth. Disable with
+# --no-global-ignore.
+#
+# .git/
+# node_modules/
+# *.tmp
+";
+
+const LANGUAGES_TEMPLATE: &str = "\
+# Additional or overriding language mappings used by --languages, with one extension and language
+# per line. Replaced by the file given with --language-map.
+#
+# extension  language
+# inc        PHP
+# v          Verilog HDL
+";
+
+const THEME_TEMPLATE: &str = "\
+# Colors of the output, overriding those of --palette. Replaced by the file given with --theme.
+# Colors are a basic terminal color name, orange, a 256-color index or a hexadecimal RGB value.
+#
+# growth = \"red\"
+# shrinkage = \"#0072B2\"
+";
+
+#[derive(Subcommand, Debug)]
+pub enum ConfigCommand {
+    /// Write commented templates of the configuration files to the configuration directory.
+    Init {
+        /// Also write templates of the language map and theme files.
+        #[clap(long)]
+        templates: bool,
+
+        /// Overwrite files that already exist.
+        #[clap(long)]
+        force: bool,
+    },
+}
+
+/// The user's configuration directory: `$XDG_CONFIG_HOME`, or `~/.config` when unset, on Unix and
+/// `%APPDATA%` on Windows.
+pub fn config_dir() -> Option<PathBuf> {
+    let config_dir = if cfg!(windows) {
+        std::env::var_os("APPDATA").map(PathBuf::from)
+    } else {
+        std::env::var_os("XDG_CONFIG_HOME")
+            .filter(|dir| !dir.is_empty())
+            .map(PathBuf::from)
+            .or_else(|| std::env::var_os("HOME").map(|home| Path::new(&home).join(".config")))
+    }?;
+    Some(config_dir.join(CONFIG_DIR))
+}
+
+/// Path of a file of the configuration directory, if it exists.
+pub fn config_file(name: &str) -> Option<PathBuf> {
+    config_dir().map(|dir| dir.join(name)).filter(|path| path.is_file())
+}
+
+/// Write the templates of the configuration files to a directory, skipping those that already exist
+/// unless `force` is set. Returns the files written.
+fn init(dir: &Path, templates: bool, force: bool) -> Result<Vec<PathBuf>> {
+    std::fs::create_dir_all(dir).with_context(|| format!("could not create {}", dir.display()))?;
+    let mut files = vec![(IGNORE_FILE, IGNORE_TEMPLATE)];
+    if templates {
+        files.extend([(LANGUAGES_FILE, LANGUAGES_TEMPLATE), (THEME_FILE, THEME_TEMPLATE)]);
+    }
+
+    let mut written = Vec::new();
+    for (name, template) in files {
+        let path = dir.join(name);
+        if path.exists() && !force {
+            continue;
+        }
+        std::fs::write(&path, template).with_context(|| format!("could not write {}", path.display()))?;
+        written.push(path);
+    }
+    Ok(written)
+}
+
+/// Run a `config` subcommand.
+pub fn run(command: &ConfigCommand) -> Result<()> {
+    match command {
+        ConfigCommand::Init { templates, force } => {
+            let dir = config_dir().context("could not find the configuration directory")?;
+            let written = init(&dir, *templates, *force)?;
+            if written.is_empty() {
+                println!("{} is already set up, use --force to overwrite", dir.display());
+            }
+            for path in written {
+                println!("wrote {}", path.display());
+            }
+            Ok(())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_init() {
+        let dir = std::env::temp_dir().join(format!("rextc-config-{}", std::process::id()));
+        let written = init(&dir, false, false).expect("could not write templates");
+        assert_eq!(written, vec![dir.join(IGNORE_FILE)]);
+
+        std::fs::write(dir.join(IGNORE_FILE), "*.log\n").expect("could not write ignore file");
+        let written = init(&dir, true, false).expect("could not write templates");
+        assert_eq!(written, vec![dir.join(LANGUAGES_FILE), dir.join(THEME_FILE)]);
+        assert_eq!(
+            std::fs::read_to_string(dir.join(IGNORE_FILE)).ok().as_deref(),
+            Some("*.log\n")
+        );
+
+        let written = init(&dir, true, true).expect("could not write templates");
+        assert_eq!(written.len(), 3);
+        std::fs::remove_dir_all(&dir).expect("could not remove directory");
+    }
+}
@@ -0,0 +1,200 @@
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Kind of a directory entry, as far as the scan is concerned. Symlinks are not followed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EntryKind {
+    File,
+    Directory,
+    Symlink,
+
+    /// Windows junction, or other reparse point standing in for a directory, such as a OneDrive
+    /// folder.
+    Junction,
+
+    Other,
+
+    /// Entry deleted between being listed and its kind being looked up, to be left out.
+    Vanished,
+}
+
+impl From<std::fs::FileType> for EntryKind {
+    fn from(filetype: std::fs::FileType) -> Self {
+        if filetype.is_file() {
+            EntryKind::File
+        } else if filetype.is_dir() {
+            EntryKind::Directory
+        } else if filetype.is_symlink() {
+            EntryKind::Symlink
+        } else {
+            EntryKind::Other
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct Entry {
+    pub path: PathBuf,
+    pub kind: EntryKind,
+}
+
+/// List the entries of a directory, excluding `.` and `..`. On Linux, entries are read with
+/// `getdents64` into a large buffer and their kind taken from `d_type`, so that no metadata lookup
+/// is needed unless the file system does not report types. Elsewhere the standard library is used.
+pub fn read_dir(dir: &Path) -> io::Result<Vec<Entry>> {
+    #[cfg(target_os = "linux")]
+    {
+        match linux::read_dir(dir) {
+            Err(e) if e.raw_os_error() == Some(libc::ENOSYS) => read_dir_portable(dir),
+            result => result,
+        }
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    {
+        read_dir_portable(dir)
+    }
+}
+
+fn read_dir_portable(dir: &Path) -> io::Result<Vec<Entry>> {
+    dir.read_dir()?
+        .map(|entry| {
+            let entry = entry?;
+            #[cfg(windows)]
+            match entry.metadata() {
+                Ok(metadata) if is_directory_reparse_point(&metadata) => {
+                    return Ok(Entry {
+                        path: entry.path(),
+                        kind: EntryKind::Junction,
+                    })
+                }
+                Err(e) if e.kind() != io::ErrorKind::NotFound => return Err(e),
+                _ => {}
+            }
+            Ok(Entry {
+                path: entry.path(),
+                kind: vanished_or(entry.file_type())?,
+            })
+        })
+        .collect()
+}
+
+/// The kind of an entry from its looked up file type, or `Vanished` if it no longer exists.
+fn vanished_or(file_type: io::Result<std::fs::FileType>) -> io::Result<EntryKind> {
+    match file_type {
+        Ok(file_type) => Ok(file_type.into()),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(EntryKind::Vanished),
+        Err(e) => Err(e),
+    }
+}
+
+/// Whether the entry is a directory that is also a reparse point, i.e. a junction, a directory
+/// symlink or a cloud storage placeholder. The metadata must not follow reparse points.
+#[cfg(windows)]
+fn is_directory_reparse_point(metadata: &std::fs::Metadata) -> bool {
+    use std::os::windows::fs::MetadataExt;
+    const FILE_ATTRIBUTE_DIRECTORY: u32 = 0x10;
+    const FILE_ATTRIBUTE_REPARSE_POINT: u32 = 0x400;
+    let attributes = metadata.file_attributes();
+    attributes & FILE_ATTRIBUTE_DIRECTORY != 0 && attributes & FILE_ATTRIBUTE_REPARSE_POINT != 0
+}
+
+#[cfg(target_os = "linux")]
+mod linux {
+    use super::{vanished_or, Entry, EntryKind};
+    use std::ffi::{CString, OsStr};
+    use std::io;
+    use std::os::unix::ffi::OsStrExt;
+    use std::os::unix::io::{AsRawFd, FromRawFd, OwnedFd};
+    use std::path::Path;
+
+    const BUFFER_SIZE: usize = 256 * 1024;
+
+    /// Offsets of the fields of `struct linux_dirent64`.
+    const RECLEN_OFFSET: usize = 16;
+    const TYPE_OFFSET: usize = 18;
+    const NAME_OFFSET: usize = 19;
+
+    pub fn read_dir(dir: &Path) -> io::Result<Vec<Entry>> {
+        let c_path = CString::new(dir.as_os_str().as_bytes())?;
+        // SAFETY: `c_path` is a valid NUL-terminated string for the duration of the call.
+        let fd = unsafe { libc::open(c_path.as_ptr(), libc::O_RDONLY | libc::O_DIRECTORY | libc::O_CLOEXEC) };
+        if fd < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        // SAFETY: `fd` was just opened and is exclusively owned here, so it is closed exactly once.
+        let fd = unsafe { OwnedFd::from_raw_fd(fd) };
+
+        let mut buffer = vec![0u8; BUFFER_SIZE];
+        let mut entries = Vec::new();
+        loop {
+            // SAFETY: the kernel writes at most `buffer.len()` bytes into `buffer`.
+            let read =
+                unsafe { libc::syscall(libc::SYS_getdents64, fd.as_raw_fd(), buffer.as_mut_ptr(), buffer.len()) };
+            if read < 0 {
+                return Err(io::Error::last_os_error());
+            } else if read == 0 {
+                return Ok(entries);
+            }
+
+            let mut offset = 0;
+            while offset < read as usize {
+                let record = &buffer[offset..];
+                let reclen = u16::from_ne_bytes([record[RECLEN_OFFSET], record[RECLEN_OFFSET + 1]]) as usize;
+                let name = &record[NAME_OFFSET..reclen];
+                let name = &name[..name.iter().position(|&b| b == 0).unwrap_or(name.len())];
+                offset += reclen;
+
+                if name == b"." || name == b".." {
+                    continue;
+                }
+
+                let path = dir.join(OsStr::from_bytes(name));
+                let kind = match record[TYPE_OFFSET] {
+                    libc::DT_REG => EntryKind::File,
+                    libc::DT_DIR => EntryKind::Directory,
+                    libc::DT_LNK => EntryKind::Symlink,
+                    libc::DT_UNKNOWN => vanished_or(std::fs::symlink_metadata(&path).map(|m| m.file_type()))?,
+                    _ => EntryKind::Other,
+                };
+                entries.push(Entry { path, kind });
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sorted(mut entries: Vec<Entry>) -> Vec<(PathBuf, EntryKind)> {
+        entries.sort_unstable_by(|e1, e2| e1.path.cmp(&e2.path));
+        entries.into_iter().map(|e| (e.path, e.kind)).collect()
+    }
+
+    #[test]
+    fn test_read_dir() {
+        let root = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests").join("dirA");
+        let entries = sorted(read_dir(&root).expect("could not read directory"));
+        assert_eq!(
+            entries,
+            vec![
+                (root.join("16.foo"), EntryKind::File),
+                (root.join("5.bar"), EntryKind::File),
+            ]
+        );
+        assert_eq!(
+            entries,
+            sorted(read_dir_portable(&root).expect("could not read directory"))
+        );
+    }
+
+    #[test]
+    fn test_vanished_or() {
+        let missing = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests").join("missing");
+        let kind = vanished_or(std::fs::symlink_metadata(&missing).map(|m| m.file_type()));
+        assert_eq!(kind.expect("could not look up kind"), EntryKind::Vanished);
+        let denied = io::Error::from(io::ErrorKind::PermissionDenied);
+        assert!(vanished_or(Err(denied)).is_err());
+    }
+}
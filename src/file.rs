@@ -1,10 +1,38 @@
-use anyhow::{anyhow, Context, Result};
+use crate::audit::{self, Finding};
+use crate::checkpoint::Checkpoint;
+use crate::filter::{self, Filter};
+use crate::fs::{Entry, EntryKind, FileSystem, Metadata};
+use crate::group::GroupBy;
+use crate::ignore::Ignore;
+use crate::languages::Languages;
+use crate::locale::Locale;
+use crate::magic;
+use crate::media;
+use crate::normalize::{normalize, Normalization};
+use crate::progress::Progress;
+use crate::regex::Regex;
+use crate::report::{
+    self as report, CompressedPairs, DepthStats, Encodings, LargestFiles, LineEndingCounter, LineEndings, NameLengths,
+    Periods, Warning, Warnings, Workspaces,
+};
+use crate::retry::Retry;
+use crate::sanity::ExtensionRules;
+use crate::theme::Theme;
+use crate::throttle::{ByteThrottle, Throttle};
+use crate::trash::{self, Trash};
+use crate::width;
+use anyhow::{anyhow, bail, Context, Result};
 use clap::ArgEnum;
+use std::cell::{Cell, OnceCell, RefCell};
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::io::{self, BufWriter, Write};
 use std::path::{Path, PathBuf};
+use std::time::Duration;
 
 const TPIPE: &str = "├";
 const LPIPE: &str = "└";
-const NOEXT: &str = "N/A";
+const JUNCTIONS: &str = "<junctions>";
 
 /// Applies to extensions only, directories are always sorted alphabetically.
 #[derive(Debug, Clone, ArgEnum)]
@@ -22,16 +50,471 @@ pub enum ExtensionSortingMethod {
     FileSize,
 }
 
+/// How files without an extension are grouped.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ArgEnum)]
+pub enum Extensionless {
+    /// All in a single group.
+    #[default]
+    Single,
+
+    /// By file name, so that e.g. Makefile and LICENSE files each get their own group.
+    Name,
+
+    /// By content, into scripts starting with a shebang and native binaries. Other files stay in a
+    /// single group.
+    Content,
+}
+
+/// How directories and files are identified in the output.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ArgEnum)]
+pub enum PathDisplay {
+    /// Name only in the tree, path relative to the scan root in reports listing directories or
+    /// files from anywhere in the tree.
+    Name,
+
+    /// Path relative to the scan root.
+    Relative,
+
+    /// Absolute, canonicalized path.
+    Absolute,
+}
+
+impl PathDisplay {
+    /// The display used in flat listings, where bare names would be ambiguous.
+    pub fn qualified(self) -> Self {
+        match self {
+            PathDisplay::Name => PathDisplay::Relative,
+            paths => paths,
+        }
+    }
+
+    /// Format the path of a file found under the scan root.
+    pub fn format(self, file: &Path, scan_root: &Path) -> String {
+        match self {
+            PathDisplay::Name => file.file_name().unwrap_or_default().to_string_lossy().to_string(),
+            PathDisplay::Relative => match file.strip_prefix(scan_root) {
+                Ok(relative) if !relative.as_os_str().is_empty() => relative.to_string_lossy().to_string(),
+                _ => PathDisplay::Name.format(file, scan_root),
+            },
+            PathDisplay::Absolute => file.to_string_lossy().to_string(),
+        }
+    }
+}
+
+/// Settings controlling how a scanned directory tree is printed.
 #[derive(Debug)]
-struct Extension {
+pub struct DrawOptions {
+    /// Print directories containing no files.
+    pub empty: bool,
+
+    /// How directory nodes are labelled.
+    pub paths: PathDisplay,
+
+    /// Print the cumulative size of each extension.
+    pub size: bool,
+
+    /// Mark directories living on a different file system than their parent.
+    pub mounts: bool,
+
+    /// Mark each directory with the extension taking up most of the size of the files below it,
+    /// or most of the files when sizes were not gathered, and its share.
+    pub dominant: bool,
+
+    /// Print how many files of each extension are hard links, and their cumulative size.
+    pub hardlinks: bool,
+
+    /// Print how much of the size of each extension is stored locally, and how much only in the
+    /// cloud.
+    pub cloud: bool,
+
+    /// Print how many files of each extension are text and how many binary.
+    pub text_binary: bool,
+
+    /// Extensions recorded per directory by an earlier scan, against which the growth of each
+    /// extension is printed.
+    pub baseline: Option<BTreeMap<PathBuf, Vec<Extension>>>,
+
+    /// Colors of growth and shrinkage, or `None` to print without colors.
+    pub color: Option<Theme>,
+
+    /// Label of the group of files without an extension.
+    pub extensionless_label: String,
+
+    /// Print the total playing time of each extension with audio or video files.
+    pub media_duration: bool,
+
+    /// Language of labels, units and report text.
+    pub locale: Locale,
+
+    /// Print items with their depth and indentation instead of box-drawing pipes, for screen
+    /// readers and plain-text processing.
+    pub plain: bool,
+}
+
+/// Settings controlling how a directory tree is scanned.
+#[derive(Debug, Default)]
+pub struct ScanOptions {
+    /// Depth of recursion, below which all files are aggregated into the deepest directory.
+    pub max_depth: usize,
+
+    /// Only count the files matching this expression.
+    pub filter: Option<Filter>,
+
+    /// What files are grouped by, the extension unless told otherwise.
+    pub group_by: GroupBy,
+
+    /// Group files by the first capture group of this expression in their name instead.
+    pub group_by_regex: Option<Regex>,
+
+    /// User names by user ID, for grouping by owner.
+    pub user_names: HashMap<u32, String>,
+
+    /// Directory the scan started from, set once it begins.
+    pub scan_root: OnceCell<PathBuf>,
+
+    /// How files without an extension are grouped.
+    pub extensionless: Extensionless,
+
+    /// Conditions under which a suffix is counted as an extension.
+    pub extension_rules: ExtensionRules,
+
+    /// Count files under the programming language of their extension, where it is known.
+    pub languages: Option<Languages>,
+
+    /// Skip the files and directories whose name matches one of these `|`-separated patterns.
+    pub exclude: Option<String>,
+
+    /// Only count the files whose name matches one of these `|`-separated patterns.
+    pub include: Option<String>,
+
+    /// Skip the files and directories matching the rules of ignore files.
+    pub ignore: Ignore,
+
+    /// Gather file name and path length statistics.
+    pub name_lengths: bool,
+
+    /// Only count files, without looking up their metadata.
+    pub no_size: bool,
+
+    /// Unicode normalization applied to extensions and directory names, so that differently
+    /// encoded but otherwise identical names are counted together.
+    pub normalization: Option<Normalization>,
+
+    /// Check file names for control characters, invalid UTF-8 and trailing spaces or dots.
+    pub name_audit: bool,
+
+    /// Check file permissions for world- or group-writable files and setuid or setgid bits.
+    pub permission_audit: bool,
+
+    /// Flag files whose absolute path is longer than this many UTF-16 code units.
+    pub max_path_length: Option<usize>,
+
+    /// Flag entries whose name only differs by case from another entry of the same directory.
+    pub case_collisions: bool,
+
+    /// Flag entries with names that cannot be used on Windows.
+    pub windows_names: bool,
+
+    /// Pair files with compressed copies of themselves found next to them.
+    pub compressed_pairs: bool,
+
+    /// Check that the content of files matches their extension.
+    pub verify_types: bool,
+
+    /// Sample the content of files to find those that look encrypted or compressed.
+    pub entropy: bool,
+
+    /// Look for symlinks whose target does not exist.
+    pub broken_symlinks: bool,
+
+    /// Read the playing time of audio and video files.
+    pub media_duration: bool,
+
+    /// Sniff the start of every file to tell text from binary files.
+    pub text_binary: bool,
+
+    /// Count the line endings of every text file.
+    pub line_endings: bool,
+
+    /// Detect the encoding of every text file.
+    pub encodings: bool,
+
+    /// Look for world-writable files and directories, and setuid or setgid files.
+    pub security: bool,
+
+    /// Break extensions down by the owner of the files.
+    pub owners: bool,
+
+    /// Look up which file system each directory lives on.
+    pub devices: bool,
+
+    /// Rate limit applied to directory reads and metadata lookups.
+    pub throttle: Option<Throttle>,
+
+    /// Rate limit applied to the bytes of file contents read.
+    pub read_throttle: Option<ByteThrottle>,
+
+    /// Retries of directory reads and metadata lookups failing with transient errors.
+    pub retry: Option<Retry>,
+
+    /// Scan the directories junctions point to, rather than only counting the junctions.
+    pub follow_junctions: bool,
+
+    /// Targets of the junctions followed so far, along with the scan root, so that each is scanned
+    /// at most once.
+    pub followed_junctions: RefCell<HashSet<PathBuf>>,
+
+    /// File system the tree is read from.
+    pub file_system: Box<dyn FileSystem>,
+
+    /// Record of completed directories, restored from a previous run and extended by this one.
+    pub checkpoint: Option<Checkpoint>,
+
+    /// Stop at the first I/O error, rather than skipping the files and directories that cannot be
+    /// read.
+    pub fail_fast: bool,
+
+    /// How files in trash locations are counted.
+    pub trash: Trash,
+
+    /// Mount points of pseudo file systems, which are skipped along with everything below them.
+    pub pseudo_mounts: HashSet<PathBuf>,
+
+    /// Count directories below the root that cannot be read for lack of permission as empty,
+    /// rather than stopping the scan.
+    pub skip_unreadable: bool,
+
+    /// Scan each file system once, skipping the directories where a file system already scanned
+    /// elsewhere in the tree is mounted again, such as bind mounts. Needs `devices`.
+    pub once_per_volume: bool,
+
+    /// Topmost directory scanned on each file system, so that file systems mounted more than once
+    /// are only scanned below the first of their mounts.
+    pub volumes: RefCell<HashMap<u64, PathBuf>>,
+
+    /// Soft limit in bytes on the memory held by the tree. Once it is exceeded, directories
+    /// completed afterwards below a shrinking depth are folded into their parent, like files below
+    /// the recursion limit.
+    pub max_memory: Option<u64>,
+
+    /// Estimated memory held by the directories kept in the tree so far.
+    pub tree_memory: Cell<u64>,
+
+    /// Non-fatal issues met during the scan, such as entries that could not be read or
+    /// disappeared between being listed and being read.
+    pub warnings: Warnings,
+
+    /// Deepest level at which directories are still kept separately, once the memory limit has
+    /// been exceeded.
+    pub kept_depth: Cell<Option<usize>>,
+
+    /// Progress events printed while scanning.
+    pub progress: Option<Progress>,
+
+    /// Largest files seen so far across the whole scan.
+    pub largest: Option<LargestFiles>,
+
+    /// Bucket files by the period they were last modified in.
+    pub periods: Option<Periods>,
+
+    /// Depths of the directories reached so far.
+    pub depths: Option<DepthStats>,
+
+    /// Group files by the package they belong to.
+    pub workspaces: Option<Workspaces>,
+
+    /// Count the packages installed in `node_modules` as a whole instead of descending into them.
+    pub node_modules: bool,
+}
+
+impl ScanOptions {
+    /// Wait until the throttle, if any, allows the next file system operation.
+    fn throttle(&self) {
+        if let Some(throttle) = &self.throttle {
+            throttle.wait();
+        }
+    }
+
+    /// Read up to `len` bytes at the start of a file, within the read throttle, if any.
+    fn read_header(&self, file: &Path, len: usize) -> io::Result<Vec<u8>> {
+        self.read_at(file, 0, len)
+    }
+
+    /// Read up to `len` bytes of a file from `offset`, within the read throttle, if any.
+    fn read_at(&self, file: &Path, offset: u64, len: usize) -> io::Result<Vec<u8>> {
+        let bytes = self.file_system.read_at(file, offset, len)?;
+        if let Some(throttle) = &self.read_throttle {
+            throttle.consume(bytes.len());
+        }
+        Ok(bytes)
+    }
+
+    /// Metadata of the given files, or empty metadata when sizes are not gathered.
+    fn metadata(&self, files: &[PathBuf]) -> Vec<io::Result<Metadata>> {
+        if self.no_size {
+            return files.iter().map(|_| Ok(Metadata::default())).collect();
+        }
+
+        files.iter().for_each(|_| self.throttle());
+        let batch = self.file_system.metadata_batch(files).into_iter().zip(files);
+        match &self.retry {
+            Some(retry) => batch
+                .map(|(metadata, file)| retry.run(metadata, || self.file_system.metadata(file)))
+                .collect(),
+            None => batch.map(|(metadata, _)| metadata).collect(),
+        }
+    }
+
+    /// Run a file system operation, repeating it while it fails with a transient error if retries
+    /// are enabled.
+    fn with_retries<T>(&self, mut operation: impl FnMut() -> io::Result<T>) -> io::Result<T> {
+        let result = operation();
+        match &self.retry {
+            Some(retry) => retry.run(result, operation),
+            None => result,
+        }
+    }
+
+    /// Extension a file is counted under when grouping by extension, after applying languages,
+    /// the grouping of files without one and normalization.
+    pub fn extension_of(&self, file: &Path) -> Result<Option<String>> {
+        let extension = match self.extension_rules.extension(file) {
+            Some(extension) => {
+                let language = self
+                    .languages
+                    .as_ref()
+                    .and_then(|languages| languages.language(&extension));
+                Some(language.map_or(extension, str::to_string))
+            }
+            None => self.extensionless_group(file)?,
+        };
+        Ok(extension.map(|extension| match self.normalization {
+            Some(form) => normalize(&extension, form),
+            None => extension,
+        }))
+    }
+
+    /// Group a file is counted under: its extension as given by [`Self::extension_of`], or its
+    /// key under `--group-by` or `--group-by-regex`.
+    pub fn group_of(&self, file: &Path, metadata: &Metadata, scan_root: &Path) -> Result<Option<String>> {
+        Ok(match (&self.group_by_regex, self.group_by) {
+            (Some(regex), _) => file
+                .file_name()
+                .and_then(|name| regex.first_capture(&name.to_string_lossy())),
+            (None, GroupBy::Extension) => self.extension_of(file)?,
+            (None, group_by) => group_by.key(file, metadata, scan_root, &self.user_names),
+        })
+    }
+
+    /// Name of the group of a file without an extension, or `None` for the group of files that
+    /// could not be told apart.
+    pub fn extensionless_group(&self, file: &Path) -> Result<Option<String>> {
+        match self.extensionless {
+            Extensionless::Single => Ok(None),
+            Extensionless::Name => Ok(file.file_name().map(|name| name.to_string_lossy().to_string())),
+            Extensionless::Content => {
+                self.throttle();
+                let header = self.tolerate(self.read_header(file, magic::HEADER_LEN), file)?;
+                Ok(header
+                    .as_deref()
+                    .and_then(magic::classify_extensionless)
+                    .map(|kind| format!("<{}>", kind)))
+            }
+        }
+    }
+
+    /// Whether a directory entry is considered at all, given the exclude and include patterns, the
+    /// ignore rules, the handling of trash locations and the pseudo file systems. Include patterns
+    /// only apply to files, so that directories are still searched.
+    fn is_listed(&self, entry: &Entry) -> bool {
+        let name = entry.path.file_name().unwrap_or_default().to_string_lossy();
+        let is_directory = matches!(entry.kind, EntryKind::Directory | EntryKind::Junction);
+        let skipped = is_directory
+            && ((self.trash != Trash::Include && trash::is_trash(&entry.path))
+                || self.pseudo_mounts.contains(&entry.path));
+        let included =
+            entry.kind != EntryKind::File || self.include.as_ref().is_none_or(|p| filter::matches_any(p, &name));
+        !self.is_excluded(entry) && !skipped && included
+    }
+
+    /// Whether a directory entry matches the exclude patterns or the ignore rules.
+    fn is_excluded(&self, entry: &Entry) -> bool {
+        let name = entry.path.file_name().unwrap_or_default().to_string_lossy();
+        let is_directory = matches!(entry.kind, EntryKind::Directory | EntryKind::Junction);
+        self.exclude.as_ref().is_some_and(|p| filter::matches_any(p, &name))
+            || self.ignore.is_ignored(&entry.path, is_directory)
+    }
+
+    /// Leave out the entries deleted before their kind could be looked up, counting them.
+    fn without_vanished(&self, mut entries: Vec<Entry>) -> Vec<Entry> {
+        let listed = entries.len();
+        entries.retain(|entry| entry.kind != EntryKind::Vanished);
+        self.warnings.add_many(Warning::Vanished, listed - entries.len());
+        entries
+    }
+
+    /// Whether an operation on a listed path failed because the path no longer exists, counting it
+    /// if so. Paths that still exist, such as junctions to a missing directory, did not vanish.
+    fn vanished(&self, error: &io::Error, path: &Path) -> bool {
+        let vanished = error.kind() == io::ErrorKind::NotFound
+            && self
+                .file_system
+                .file_type(path)
+                .is_err_and(|e| e.kind() == io::ErrorKind::NotFound);
+        if vanished {
+            self.warnings.add(Warning::Vanished);
+        }
+        vanished
+    }
+
+    /// Skip a path whose operation failed, returning `None`, or stop the scan with an error naming
+    /// the path when failing fast.
+    /// Entries that disappeared since being listed are always skipped, as the tree changed rather
+    /// than failed to be read.
+    fn tolerate<T>(&self, result: io::Result<T>, path: &Path) -> Result<Option<T>> {
+        match result {
+            Ok(value) => Ok(Some(value)),
+            Err(e) if self.vanished(&e, path) => Ok(None),
+            Err(e) if self.fail_fast => Err(e).with_context(|| format!("could not read {}", path.display())),
+            Err(_) => {
+                self.warnings.add(Warning::Unreadable);
+                Ok(None)
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Extension {
     /// Extension string or None in case none exists. Symlinks are not considered.
-    name: Option<String>,
+    pub(crate) name: Option<String>,
 
     /// Number of files with the current extension.
-    count: usize,
+    pub(crate) count: usize,
 
     /// Total size in bytes of files with the current extension.
-    total_size_bytes: u64,
+    pub(crate) total_size_bytes: u64,
+
+    /// Number of files with the current extension having more than one link.
+    pub(crate) hardlinked_count: usize,
+
+    /// Total size in bytes of the files with more than one link.
+    pub(crate) hardlinked_size_bytes: u64,
+
+    /// Number of files with the current extension that are cloud placeholders.
+    pub(crate) cloud_count: usize,
+
+    /// Total size in bytes of the cloud placeholders, which is not taken up locally.
+    pub(crate) cloud_size_bytes: u64,
+
+    /// Number of files with the current extension whose content looks like text.
+    pub(crate) text_count: usize,
+
+    /// Number of files with the current extension whose content looks binary.
+    pub(crate) binary_count: usize,
+
+    /// Total playing time of the audio and video files whose container records it.
+    pub(crate) media_duration: Duration,
 }
 
 #[derive(Debug)]
@@ -47,6 +530,61 @@ pub struct Directory {
 
     /// Recursion depth, determines indentation depth.
     depth: usize,
+
+    /// Number of junctions found in this directory, whether followed or not.
+    junctions: usize,
+
+    /// File name and path length statistics, only gathered on demand.
+    pub(crate) name_lengths: NameLengths,
+
+    /// Problems found on the files in this directory, along with the affected file.
+    pub(crate) findings: Vec<(Finding, PathBuf)>,
+
+    /// Whether the directory itself is world-writable, only looked up on demand.
+    pub(crate) world_writable: bool,
+
+    /// Extensions of the world-writable files in this directory, only gathered on demand.
+    pub(crate) world_writable_files: Vec<Extension>,
+
+    /// Extensions of the files in this directory that look encrypted or compressed, only gathered
+    /// on demand.
+    pub(crate) high_entropy_files: Vec<Extension>,
+
+    /// Line endings of the text files directly in this directory, by extension, only gathered on
+    /// demand.
+    pub(crate) line_endings: BTreeMap<Option<String>, LineEndings>,
+
+    /// Encodings of the text files directly in this directory, by extension, only gathered on
+    /// demand.
+    pub(crate) encodings: BTreeMap<Option<String>, Encodings>,
+
+    /// Files directly in this directory with a compressed copy next to them, by extension, only
+    /// gathered on demand.
+    pub(crate) compressed_pairs: BTreeMap<Option<String>, CompressedPairs>,
+
+    /// Symlinks in this directory whose target does not exist, along with that target, only
+    /// gathered on demand.
+    pub(crate) broken_symlinks: Vec<(PathBuf, PathBuf)>,
+
+    /// Setuid and setgid files in this directory along with their permission bits, only gathered
+    /// on demand.
+    pub(crate) set_id_files: Vec<(PathBuf, u32)>,
+
+    /// Extensions of the files in this directory by user ID of their owner, only gathered on
+    /// demand.
+    pub(crate) owners: BTreeMap<u32, Vec<Extension>>,
+
+    /// File system the directory lives on, only looked up on demand. Files below the recursion
+    /// limit are attributed to the file system of the deepest scanned directory.
+    pub(crate) device: Option<u64>,
+
+    /// Type of the file system mounted on this directory, when it lives on a different file system
+    /// than its parent. An empty string when the type could not be determined.
+    mount_type: Option<String>,
+
+    /// Trash locations in this directory or below the recursion limit, each with all files below
+    /// it, only gathered on demand.
+    pub(crate) trash: Vec<Directory>,
 }
 
 impl Extension {
@@ -55,131 +593,902 @@ impl Extension {
             name: extension,
             count: 1,
             total_size_bytes: size,
+            ..Default::default()
+        }
+    }
+
+    /// Name of the extension as printed, or the label of files without an extension.
+    pub(crate) fn label<'a>(&'a self, options: &'a DrawOptions) -> &'a str {
+        self.name.as_deref().unwrap_or(&options.extensionless_label)
+    }
+
+    /// Format an extension as ``$NAME ── $COUNT ── $SIZE``, minimizing white space. The size is
+    /// left out when it was not gathered. Hard links and playing time are appended in the same
+    /// format when asked for.
+    pub(crate) fn to_string_formatted(
+        &self,
+        max_extension_chars: usize,
+        max_count_chars: usize,
+        options: &DrawOptions,
+    ) -> String {
+        let mut formatted = if options.plain {
+            format!(
+                "{} ── {} {}",
+                self.label(options),
+                self.count,
+                options.locale.text(if self.count == 1 { "file" } else { "files" })
+            )
+        } else {
+            format!(
+                "{} ── {:max_count_chars$}",
+                width::pad(self.label(options), max_extension_chars),
+                self.count,
+            )
+        };
+        if options.size {
+            formatted.push_str(&format!(
+                " ── {:>10}",
+                size_human_readable(self.total_size_bytes, 2, options.locale)
+            ));
+        }
+        if options.hardlinks {
+            formatted.push_str(&format!(
+                " ── {:max_count_chars$} {}",
+                self.hardlinked_count,
+                options.locale.text("hardlinked")
+            ));
+            if options.size {
+                formatted.push_str(&format!(
+                    " ── {:>10}",
+                    size_human_readable(self.hardlinked_size_bytes, 2, options.locale)
+                ));
+            }
+        }
+        if options.cloud {
+            if options.size {
+                formatted.push_str(&format!(
+                    " ── {} {:>10} ── {} {:>10}",
+                    options.locale.text("local"),
+                    size_human_readable(self.total_size_bytes - self.cloud_size_bytes, 2, options.locale),
+                    options.locale.text("cloud"),
+                    size_human_readable(self.cloud_size_bytes, 2, options.locale)
+                ));
+            } else {
+                formatted.push_str(&format!(
+                    " ── {:max_count_chars$} {}",
+                    self.cloud_count,
+                    options.locale.text("in cloud")
+                ));
+            }
+        }
+        if options.text_binary {
+            formatted.push_str(&format!(
+                " ── {:max_count_chars$} {} ── {:max_count_chars$} {}",
+                self.text_count,
+                options.locale.text("text"),
+                self.binary_count,
+                options.locale.text("binary")
+            ));
         }
+        if options.media_duration && !self.media_duration.is_zero() {
+            formatted.push_str(&format!(
+                " ── {} h",
+                options.locale.decimal(self.media_duration.as_secs_f64() / 3600.0, 2)
+            ));
+        }
+        formatted
     }
 
-    /// Convert bytes to easily-readable binary-scaled units.
-    fn size_human_readable(&self, decimals: usize) -> String {
-        // TODO: avoid this repetition with a macro
-        if self.total_size_bytes < 1024 {
-            format!("{} B  ", self.total_size_bytes)
-        } else if self.total_size_bytes < 1024u64.pow(2) {
-            format!("{:.1$} kiB", self.total_size_bytes as f64 / 1024.0, decimals)
-        } else if self.total_size_bytes < 1024u64.pow(3) {
-            format!("{:.1$} MiB", self.total_size_bytes as f64 / 1024.0f64.powi(2), decimals)
-        } else if self.total_size_bytes < 1024u64.pow(4) {
-            format!("{:.1$} GiB", self.total_size_bytes as f64 / 1024.0f64.powi(3), decimals)
+    /// Format the change in count and size since the extension was recorded in the baseline, as
+    /// additional columns of `to_string_formatted`.
+    pub(crate) fn growth_formatted(
+        &self,
+        baseline: Option<&Extension>,
+        max_count_chars: usize,
+        options: &DrawOptions,
+    ) -> String {
+        let (count, bytes) = baseline.map_or((0, 0), |b| (b.count, b.total_size_bytes));
+        let count_delta = self.count as i64 - count as i64;
+        let bytes_delta = self.total_size_bytes as i128 - bytes as i128;
+
+        let mut formatted = format!(
+            " ── {:>1$} {2}",
+            signed(count_delta.signum(), &count_delta.abs().to_string()),
+            max_count_chars + 1,
+            options.locale.text("files")
+        );
+        if options.size {
+            let size = size_human_readable(bytes_delta.unsigned_abs() as u64, 2, options.locale);
+            formatted.push_str(&format!(" ── {:>11}", signed(bytes_delta.signum() as i64, &size)));
+        }
+
+        let sign = if options.size {
+            bytes_delta.signum() as i64
         } else {
-            format!("{:.1$} TiB", self.total_size_bytes as f64 / 1024.0f64.powi(4), decimals)
+            count_delta.signum()
+        };
+        match (sign, &options.color) {
+            (1, Some(theme)) => format!("{}{}\x1b[0m", theme.growth, formatted),
+            (-1, Some(theme)) => format!("{}{}\x1b[0m", theme.shrinkage, formatted),
+            _ => formatted,
         }
     }
+}
 
-    /// Format an extension as ``$NAME ── $COUNT ── $SIZE``, minimizing white space.
-    fn to_string_formatted(&self, max_extension_chars: usize, max_count_chars: usize) -> String {
-        format!(
-            "{:max_extension_chars$} ── {:max_count_chars$} ── {:>10}",
-            self.name.as_ref().unwrap_or(&NOEXT.to_string()),
-            self.count,
-            self.size_human_readable(2),
-        )
+/// Prefix a magnitude with the sign of a change, leaving it bare when there was none.
+fn signed(sign: i64, magnitude: &str) -> String {
+    match sign {
+        1 => format!("+{}", magnitude),
+        -1 => format!("-{}", magnitude),
+        _ => magnitude.to_string(),
     }
 }
 
+/// Number of bytes read at once when counting line endings.
+const LINE_ENDINGS_CHUNK_LEN: usize = 64 * 1024;
+
+/// Line endings used throughout a file, or `None` for binary files and files without line breaks.
+fn line_endings(file: &Path, options: &ScanOptions) -> io::Result<Option<LineEndings>> {
+    let mut counter = LineEndingCounter::default();
+    let mut offset = 0;
+    loop {
+        options.throttle();
+        let chunk = options.read_at(file, offset, LINE_ENDINGS_CHUNK_LEN)?;
+        if offset == 0 && !magic::is_text(&chunk[..chunk.len().min(magic::TEXT_SAMPLE_LEN)]) {
+            return Ok(None);
+        }
+        counter.feed(&chunk);
+        if chunk.len() < LINE_ENDINGS_CHUNK_LEN {
+            return Ok(counter.finish());
+        }
+        offset += chunk.len() as u64;
+    }
+}
+
+/// Callback receiving each directory as soon as its subtree has been scanned.
+pub type Emit<'a> = &'a mut dyn FnMut(&Directory) -> Result<()>;
+
 impl Directory {
-    pub fn new(mut root: PathBuf, depth: usize, max_depth: usize) -> Result<Self> {
-        root = std::fs::canonicalize(root)?;
+    pub fn new(root: PathBuf, depth: usize, options: &ScanOptions) -> Result<Self> {
+        Self::scan(root, depth, options, &mut None)
+    }
+
+    /// Scan like `new`, but hand every directory to `emit` as soon as its subtree has been
+    /// scanned, then fold it into its parent instead of keeping it. Only the ancestors of the
+    /// directory being scanned are held in memory, each with the extensions of everything below
+    /// it, so the returned directory has no subdirectories.
+    pub fn new_streaming(root: PathBuf, depth: usize, options: &ScanOptions, emit: Emit) -> Result<Self> {
+        Self::scan(root, depth, options, &mut Some(emit))
+    }
 
-        let mut directory = Self {
-            root: root.clone(),
+    /// Rebuild the tree below `root` from the extensions recorded for each directory, such as those
+    /// of a checkpoint. Directories between `root` and the recorded ones are added empty.
+    pub fn from_records(root: PathBuf, depth: usize, records: &BTreeMap<PathBuf, Vec<Extension>>) -> Self {
+        let mut directory = Self::empty(root, depth);
+        directory.extensions = records.get(&directory.root).cloned().unwrap_or_default();
+        let mut children: Vec<PathBuf> = records
+            .keys()
+            .filter_map(|path| path.strip_prefix(&directory.root).ok()?.components().next())
+            .map(|child| directory.root.join(child))
+            .collect();
+        children.dedup();
+        directory.subdirectories = children
+            .into_iter()
+            .map(|child| Self::from_records(child, depth + 1, records))
+            .collect();
+        directory
+    }
+
+    /// Visit the path of the directory and of every directory below it, along with the extensions
+    /// of the files directly in each, subdirectories before their parent.
+    pub fn for_each_record(&self, visit: &mut dyn FnMut(&Path, &[Extension]) -> Result<()>) -> Result<()> {
+        for subdirectory in &self.subdirectories {
+            subdirectory.for_each_record(visit)?;
+        }
+        visit(&self.root, &self.extensions)
+    }
+
+    /// Directory with nothing counted in it yet.
+    fn empty(root: PathBuf, depth: usize) -> Self {
+        Self {
+            root,
             extensions: Vec::new(),
             subdirectories: Vec::new(),
             depth,
+            junctions: 0,
+            name_lengths: NameLengths::default(),
+            findings: Vec::new(),
+            world_writable: false,
+            world_writable_files: Vec::new(),
+            set_id_files: Vec::new(),
+            broken_symlinks: Vec::new(),
+            high_entropy_files: Vec::new(),
+            line_endings: BTreeMap::new(),
+            encodings: BTreeMap::new(),
+            compressed_pairs: BTreeMap::new(),
+            owners: BTreeMap::new(),
+            device: None,
+            mount_type: None,
+            trash: Vec::new(),
+        }
+    }
+
+    fn scan(root: PathBuf, depth: usize, options: &ScanOptions, emit: &mut Option<Emit>) -> Result<Self> {
+        let root = match options.file_system.canonicalize(&root) {
+            Err(e) if depth > 0 && options.vanished(&e, &root) => return Ok(Self::empty(root, depth)),
+            root => root?,
         };
+        if depth == 0 && options.file_system.file_type(&root)? != EntryKind::Directory {
+            bail!("{} is not a directory", root.display());
+        }
+        if depth == 0 {
+            let _ = options.scan_root.set(root.clone());
+        }
+        if depth == 0 && options.follow_junctions {
+            options.followed_junctions.borrow_mut().insert(root.clone());
+        }
+        if let Some(depths) = &options.depths {
+            depths.add(&root, depth);
+        }
+
+        let mut directory = Self::empty(root.clone(), depth);
+        if options.devices {
+            options.throttle();
+            let device = options.file_system.device(&root)?;
+            directory.device = Some(device);
+            if options.once_per_volume {
+                let mut volumes = options.volumes.borrow_mut();
+                let first = volumes.entry(device).or_insert_with(|| root.clone());
+                if !root.starts_with(first) {
+                    return Ok(directory);
+                }
+            }
+        }
+        if options.security {
+            options.throttle();
+            directory.world_writable = options.file_system.metadata(&root)?.mode & 0o002 != 0;
+        }
 
-        // When recursion limit is reached, every file below gets globbed and appended to the
-        // current directory extensions.
-        if depth >= max_depth {
-            root.push("**");
-            root.push("*");
-            let pattern = root
-                .to_str()
-                .ok_or_else(|| anyhow!("could not convert PathBuf to &str"))?;
+        // Directories completed in a previous run are rebuilt from the checkpoint.
+        if let Some((extensions, subdirectories)) = options.checkpoint.as_ref().and_then(|c| c.restore(&root)) {
+            directory.extensions = extensions;
+            for path in subdirectories {
+                let subdirectory = Self::scan(path, depth + 1, options, emit)?;
+                directory.add_subdirectory(subdirectory, options, emit)?;
+            }
 
-            for entry in glob::glob(pattern)
-                .context("failed to read glob pattern")?
-                .flatten()
-                .filter(|entry| entry.is_file())
-            {
-                Self::add_file(entry.as_path(), &mut directory.extensions);
+        // When recursion limit is reached, or the directory is an npm package to be counted as a
+        // whole, every file below gets appended to the current directory extensions.
+        } else if depth >= options.max_depth || (options.node_modules && report::package_name(&root).is_some()) {
+            directory.add_files_recursively(&root, options)?;
+            if let Some(checkpoint) = &options.checkpoint {
+                checkpoint.record(&root, &directory.extensions)?;
             }
 
         // Until recursion limit is reached, only files directly in the current directory get
         // added, while directories get parsed as subdirectories and recursively processed.
         } else {
-            for entry in root.read_dir()? {
-                let entry = entry?;
-                let filetype = entry.file_type()?;
-
-                if filetype.is_file() {
-                    Self::add_file(entry.path().as_path(), &mut directory.extensions);
-                } else if filetype.is_dir() {
-                    directory
-                        .subdirectories
-                        .push(Self::new(entry.path(), depth + 1, max_depth)?)
+            options.throttle();
+            let mut files = Vec::new();
+            let mut subdirectories = Vec::new();
+            let mut symlinks = Vec::new();
+            let entries = match options.with_retries(|| options.file_system.read_dir(&root)) {
+                Err(e) if depth > 0 && options.vanished(&e, &root) => Vec::new(),
+                Err(e) if depth > 0 && options.skip_unreadable && e.kind() == io::ErrorKind::PermissionDenied => {
+                    options.warnings.add(Warning::UnreadableDirectory);
+                    Vec::new()
+                }
+                entries => entries.with_context(|| format!("could not read {}", root.display()))?,
+            };
+            let entries = options.without_vanished(entries);
+            let ignore_rules = options.ignore.enter(&root, options.file_system.as_ref());
+            directory.add_trash(&entries, options)?;
+            directory.audit_entries(&entries, options);
+            for entry in entries.into_iter().filter(|e| options.is_listed(e)) {
+                match entry.kind {
+                    EntryKind::File => files.push(entry.path),
+                    EntryKind::Directory => subdirectories.push(entry.path),
+                    EntryKind::Symlink => symlinks.push(entry.path),
+                    EntryKind::Junction => {
+                        if directory.add_junction(&entry.path, &root, options)? {
+                            subdirectories.push(entry.path);
+                        }
+                    }
+                    EntryKind::Other | EntryKind::Vanished => {}
                 }
             }
+
+            directory.add_files(&files, options)?;
+            directory.add_symlinks(&symlinks, options)?;
+            let own_extensions = options.checkpoint.as_ref().map(|_| directory.extensions.clone());
+            for path in subdirectories {
+                let subdirectory = Self::scan(path, depth + 1, options, emit)?;
+                directory.add_subdirectory(subdirectory, options, emit)?;
+            }
+            options.ignore.leave(ignore_rules);
+            if let (Some(checkpoint), Some(own_extensions)) = (&options.checkpoint, own_extensions) {
+                checkpoint.record(&root, &own_extensions)?;
+            }
+        }
+
+        if let Some(form) = options.normalization {
+            directory.merge_equivalent_subdirectories(form)?;
         }
 
         // Subdirectories are always sorted by name, regardless of extension sorting.
-        // TODO: convert expect to anyhow::Error propagation
-        directory
-            .subdirectories
-            .sort_unstable_by_key(|dir| dir.name().expect("invalid directory name"));
+        directory.sort_subdirectories();
+
+        if let Some(emit) = emit {
+            emit(&directory)?;
+        }
+        if let (0, Some(progress)) = (depth, &options.progress) {
+            progress.finish();
+        }
 
         Ok(directory)
     }
 
     pub fn sort_by(&mut self, method: ExtensionSortingMethod) {
-        match method {
-            ExtensionSortingMethod::Alphabetically => {
-                self.extensions.sort_unstable_by(|e1, e2| e1.name.cmp(&e2.name));
+        sort_extensions(&mut self.extensions, &method);
+    }
+
+    /// Keep only the files counted under an extension, compared regardless of case, along with
+    /// the subdirectories holding any of them. Files without an extension go by `extensionless_label`.
+    pub fn focus(&mut self, extension: &str, extensionless_label: &str) {
+        self.extensions.retain(|e| {
+            e.name
+                .as_deref()
+                .unwrap_or(extensionless_label)
+                .eq_ignore_ascii_case(extension)
+        });
+        self.junctions = 0;
+        for subdirectory in self.subdirectories.iter_mut() {
+            subdirectory.focus(extension, extensionless_label);
+        }
+        self.subdirectories.retain(|d| !d.is_empty());
+    }
+
+    /// Print the tree to stdout, locking it once and buffering the lines.
+    pub fn draw(&self, options: &DrawOptions) -> Result<()> {
+        let mut out = BufWriter::new(io::stdout().lock());
+        self.draw_to(&mut out, options)?;
+        Ok(out.flush()?)
+    }
+
+    /// Write the directory as a block of its own, labelled with its path and listing the extensions
+    /// of everything below it, for printing directories as soon as they have been scanned. Empty
+    /// directories are left out unless asked for.
+    pub fn draw_block(
+        &self,
+        out: &mut impl Write,
+        scan_root: &Path,
+        method: &ExtensionSortingMethod,
+        options: &DrawOptions,
+    ) -> Result<()> {
+        if !options.empty && self.is_empty() {
+            return Ok(());
+        }
+
+        let mut extensions = self.extension_totals();
+        let mut name = self.display_name(scan_root, options.paths.qualified())?;
+        if let Some(dominant) = dominant_formatted(&extensions, options) {
+            name.push_str(&dominant);
+        }
+        if options.plain {
+            writeln!(out, "{}", plain_item(&name, 0, options))?;
+        } else {
+            writeln!(out, "{}", name)?;
+        }
+        sort_extensions(&mut extensions, method);
+        let max_extension_chars = max_extension_chars(&extensions, options);
+        let max_count_chars = max_count_chars(&extensions);
+        for (idx, extension) in extensions.iter().enumerate() {
+            let mut formatted = extension.to_string_formatted(max_extension_chars, max_count_chars, options);
+            if options.plain {
+                formatted = format!("{} ── {}", name, formatted);
             }
-            ExtensionSortingMethod::FileCount => {
-                self.extensions.sort_unstable_by(|e1, e2| e2.count.cmp(&e1.count));
+            write_item(
+                out,
+                &formatted,
+                self.junctions == 0 && idx + 1 == extensions.len(),
+                1,
+                &[],
+                options,
+            )?;
+        }
+        if self.junctions > 0 {
+            write_item(
+                out,
+                &format!("{} ── {}", JUNCTIONS, self.junctions),
+                true,
+                1,
+                &[],
+                options,
+            )?;
+        }
+        Ok(writeln!(out)?)
+    }
+
+    /// Write the tree to `out`, e.g. to render it into a buffer. Subdirectories are drawn as the
+    /// root of a tree of their own.
+    pub fn draw_to(&self, out: &mut impl Write, options: &DrawOptions) -> Result<()> {
+        let mut skipped = Vec::new();
+        let mut dominants = HashMap::new();
+        if options.dominant {
+            self.collect_dominants(options, &mut dominants);
+        }
+        self.draw_aux(out, true, &mut skipped, &self.root, self.depth, &dominants, options)
+    }
+
+    pub(crate) fn subdirectories(&self) -> &[Directory] {
+        &self.subdirectories
+    }
+
+    /// Add every file below the given directory to this one, without creating subdirectories.
+    /// Directories that cannot be read are skipped, unless failing fast. File types come from the
+    /// directory listing, so no metadata lookups are needed to tell files and directories apart.
+    fn add_files_recursively(&mut self, dir: &Path, options: &ScanOptions) -> Result<()> {
+        // The directory itself was already counted when it was scanned.
+        if let (Some(depths), Ok(below)) = (&options.depths, dir.strip_prefix(&self.root)) {
+            let below = below.components().count();
+            if below > 0 {
+                depths.add(dir, self.depth + below);
             }
-            ExtensionSortingMethod::FileSize => {
-                self.extensions
-                    .sort_unstable_by(|e1, e2| e2.total_size_bytes.cmp(&e1.total_size_bytes));
+        }
+        options.throttle();
+        let entries = match options.tolerate(options.with_retries(|| options.file_system.read_dir(dir)), dir)? {
+            Some(entries) => options.without_vanished(entries),
+            None => return Ok(()),
+        };
+
+        let ignore_rules = options.ignore.enter(dir, options.file_system.as_ref());
+        self.add_trash(&entries, options)?;
+        self.audit_entries(&entries, options);
+        let mut files = Vec::new();
+        let mut symlinks = Vec::new();
+        for entry in entries.into_iter().filter(|e| options.is_listed(e)) {
+            match entry.kind {
+                EntryKind::File => files.push(entry.path),
+                EntryKind::Directory => self.add_files_recursively(&entry.path, options)?,
+                EntryKind::Symlink => symlinks.push(entry.path),
+                EntryKind::Junction => {
+                    if self.add_junction(&entry.path, dir, options)? {
+                        self.add_files_recursively(&entry.path, options)?;
+                    }
+                }
+                EntryKind::Other | EntryKind::Vanished => {}
             }
         }
+        options.ignore.leave(ignore_rules);
+        self.add_files(&files, options)?;
+        self.add_symlinks(&symlinks, options)
     }
 
-    pub fn draw(&self, draw_empty: bool) -> Result<()> {
-        let mut skipped = Vec::new();
-        self.draw_aux(true, &mut skipped, draw_empty)
+    /// Count a junction found in `dir`, and tell whether it should be followed. Junctions pointing
+    /// to an ancestor of `dir`, or to a directory already reached through another junction, are
+    /// never followed, so that cycles end.
+    fn add_junction(&mut self, junction: &Path, dir: &Path, options: &ScanOptions) -> Result<bool> {
+        self.junctions += 1;
+        if !options.follow_junctions {
+            return Ok(false);
+        }
+
+        let (target, dir) = match (
+            options.tolerate(options.file_system.canonicalize(junction), junction)?,
+            options.tolerate(options.file_system.canonicalize(dir), dir)?,
+        ) {
+            (Some(target), Some(dir)) => (target, dir),
+            _ => return Ok(false),
+        };
+        Ok(!dir.starts_with(&target) && options.followed_junctions.borrow_mut().insert(target))
     }
 
-    /// If the file's extension already exists, increment the count and add the file size to the
-    /// total. Otherwise create a new entry.
-    fn add_file(file: &Path, extensions: &mut Vec<Extension>) {
-        // TODO: convert expect to anyhow::Error propagation
-        let extension = file
-            .extension()
-            .map(|s| s.to_str().expect("extension is not valid Unicode").to_string());
-        let size_bytes = file.metadata().unwrap().len();
-
-        if let Some(previous_entry) = extensions.iter_mut().find(|e| e.name == extension) {
-            previous_entry.count += 1;
-            previous_entry.total_size_bytes += size_bytes;
+    /// Scan the trash locations among the entries of a directory separately, when they are
+    /// reported. Each is scanned as a single directory holding all files below it.
+    fn add_trash(&mut self, entries: &[Entry], options: &ScanOptions) -> Result<()> {
+        if options.trash != Trash::Report {
+            return Ok(());
+        }
+        for entry in entries {
+            if entry.kind == EntryKind::Directory && trash::is_trash(&entry.path) && !options.is_excluded(entry) {
+                let depth = options.max_depth.max(self.depth + 1);
+                self.trash
+                    .push(Self::scan(entry.path.clone(), depth, options, &mut None)?);
+            }
+        }
+        Ok(())
+    }
+
+    /// Check the names of the entries of a directory against each other, files and directories
+    /// alike.
+    fn audit_entries(&mut self, entries: &[Entry], options: &ScanOptions) {
+        if options.name_audit {
+            // The names of files are audited as they are counted.
+            let directories = entries
+                .iter()
+                .filter(|e| matches!(e.kind, EntryKind::Directory | EntryKind::Junction) && options.is_listed(e));
+            for entry in directories {
+                for finding in audit::audit_name(&entry.path) {
+                    self.findings.push((finding, entry.path.clone()));
+                }
+            }
+        }
+        if options.case_collisions {
+            let listed = entries
+                .iter()
+                .filter(|e| !options.is_excluded(e))
+                .map(|e| e.path.as_path());
+            for entry in audit::case_collisions(listed) {
+                self.findings.push((Finding::CaseCollision, entry.to_path_buf()));
+            }
+        }
+        if options.windows_names {
+            for entry in entries.iter().filter(|e| !options.is_excluded(e)) {
+                for finding in audit::audit_windows_name(&entry.path) {
+                    // The name audit already records trailing spaces and dots of listed entries.
+                    let recorded = options.name_audit
+                        && matches!(entry.kind, EntryKind::File | EntryKind::Directory | EntryKind::Junction)
+                        && options.is_listed(entry);
+                    if finding == Finding::ReservedName || !recorded {
+                        self.findings.push((finding, entry.path.clone()));
+                    }
+                }
+            }
+        }
+    }
+
+    /// Record the symlinks whose target does not exist. Symlinks are otherwise not considered.
+    fn add_symlinks(&mut self, symlinks: &[PathBuf], options: &ScanOptions) -> Result<()> {
+        if !options.broken_symlinks {
+            return Ok(());
+        }
+
+        for symlink in symlinks {
+            options.throttle();
+            // Targets that cannot be looked up for lack of permission may well exist.
+            match options.file_system.metadata(symlink) {
+                Err(e) if e.kind() != io::ErrorKind::PermissionDenied => {
+                    let target = options.tolerate(options.file_system.read_link(symlink), symlink)?;
+                    self.broken_symlinks.push((symlink.clone(), target.unwrap_or_default()));
+                }
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+
+    /// Keep a scanned subdirectory, or fold it into this directory when streaming. Subdirectories
+    /// on another file system get the type of the file system mounted on them.
+    fn add_subdirectory(
+        &mut self,
+        mut subdirectory: Directory,
+        options: &ScanOptions,
+        emit: &Option<Emit>,
+    ) -> Result<()> {
+        if subdirectory.device.is_some() && subdirectory.device != self.device {
+            let mount_type = options.file_system.mount_type(&subdirectory.root)?;
+            subdirectory.mount_type = Some(mount_type.unwrap_or_default());
+        }
+
+        if emit.is_some() {
+            self.fold(subdirectory);
+        } else if options.kept_depth.get().is_some_and(|depth| subdirectory.depth > depth) {
+            self.collapse(subdirectory, options);
         } else {
-            extensions.push(Extension::new(extension, size_bytes));
+            if let Some(max_memory) = options.max_memory {
+                options
+                    .tree_memory
+                    .set(options.tree_memory.get() + subdirectory.memory_estimate());
+                if options.tree_memory.get() > max_memory {
+                    self.lower_kept_depth(subdirectory.depth, options);
+                }
+            }
+            self.subdirectories.push(subdirectory);
+        }
+        Ok(())
+    }
+
+    /// Keep fewer levels of directories after the memory limit was exceeded by a directory at the
+    /// given depth. The directories at the first level below the root are always kept.
+    fn lower_kept_depth(&self, depth: usize, options: &ScanOptions) {
+        let kept_depth = options.kept_depth.get().unwrap_or(depth).min(depth);
+        let lowered = kept_depth.saturating_sub(1).max(1);
+        if options.kept_depth.get() != Some(lowered) {
+            options.warnings.add(Warning::MemoryLimit);
+            options.kept_depth.set(Some(lowered));
         }
     }
 
+    /// Fold a subdirectory and every directory kept below it into this directory.
+    fn collapse(&mut self, mut subdirectory: Directory, options: &ScanOptions) {
+        for below in std::mem::take(&mut subdirectory.subdirectories) {
+            options
+                .tree_memory
+                .set(options.tree_memory.get().saturating_sub(below.memory_estimate()));
+            self.collapse(below, options);
+        }
+        self.fold(subdirectory);
+    }
+
+    /// Rough number of bytes held by the directory itself, without its subdirectories.
+    fn memory_estimate(&self) -> u64 {
+        let extension = |e: &Extension| std::mem::size_of::<Extension>() + e.name.as_ref().map_or(0, String::len);
+        let bytes = std::mem::size_of::<Self>()
+            + self.root.as_os_str().len()
+            + self.extensions.iter().map(extension).sum::<usize>();
+        bytes as u64
+    }
+
+    /// Add files found in the same directory, looking up their sizes together. Files whose
+    /// metadata cannot be looked up are skipped, unless failing fast.
+    fn add_files(&mut self, files: &[PathBuf], options: &ScanOptions) -> Result<()> {
+        let mut counted = HashMap::new();
+        for (file, metadata) in files.iter().zip(options.metadata(files)) {
+            let metadata = match options.tolerate(metadata, file)? {
+                Some(metadata) => metadata,
+                None => continue,
+            };
+            if options.filter.as_ref().is_none_or(|f| f.matches(file, &metadata)) {
+                let extension = self.add_file(file, &metadata, options)?;
+                if let Some(progress) = &options.progress {
+                    progress.file(file, metadata.len);
+                }
+                if options.compressed_pairs {
+                    counted.insert(file.as_path(), (metadata.len, extension));
+                }
+            }
+        }
+        self.add_compressed_pairs(&counted);
+        Ok(())
+    }
+
+    /// Pair the files counted in a directory, with their size and extension, with compressed
+    /// copies of themselves among them.
+    fn add_compressed_pairs(&mut self, counted: &HashMap<&Path, (u64, Option<String>)>) {
+        for (file, (compressed_bytes, _)) in counted {
+            let original = report::original_of(file).and_then(|original| counted.get(original.as_path()));
+            if let Some((original_bytes, extension)) = original {
+                self.compressed_pairs
+                    .entry(extension.clone())
+                    .or_default()
+                    .add(*original_bytes, *compressed_bytes);
+            }
+        }
+    }
+
+    /// If the file's extension already exists, increment the count and add the file size to the
+    /// total. Otherwise create a new entry. Returns the extension the file was counted under.
+    fn add_file(&mut self, file: &Path, metadata: &Metadata, options: &ScanOptions) -> Result<Option<String>> {
+        let scan_root = options.scan_root.get().map_or(self.root.as_path(), PathBuf::as_path);
+        let extension = options.group_of(file, metadata, scan_root)?;
+        let mut extension = Extension::new(extension, metadata.len);
+        if metadata.nlink > 1 {
+            extension.hardlinked_count = 1;
+            extension.hardlinked_size_bytes = metadata.len;
+        }
+        if metadata.placeholder {
+            extension.cloud_count = 1;
+            extension.cloud_size_bytes = metadata.len;
+        }
+        if options.entropy {
+            options.throttle();
+            // Files that cannot be read are not counted, as there is nothing to sample.
+            if let Some(sample) = options.tolerate(options.read_header(file, magic::ENTROPY_SAMPLE_LEN), file)? {
+                let raw_extension = file.extension().map(|e| e.to_string_lossy());
+                if magic::is_unexpectedly_random(raw_extension.as_deref(), &sample) {
+                    merge_extension(&mut self.high_entropy_files, extension.clone());
+                }
+            }
+        }
+        if options.text_binary {
+            options.throttle();
+            // Files that cannot be read are counted as neither.
+            if let Some(sample) = options.tolerate(options.read_header(file, magic::TEXT_SAMPLE_LEN), file)? {
+                if magic::is_text(&sample) {
+                    extension.text_count = 1;
+                } else {
+                    extension.binary_count = 1;
+                }
+            }
+        }
+        if options.line_endings {
+            // Files that cannot be read are left out, like binary files and files without line breaks.
+            if let Some(Some(endings)) = options.tolerate(line_endings(file, options), file)? {
+                self.line_endings
+                    .entry(extension.name.clone())
+                    .or_default()
+                    .merge(&endings);
+            }
+        }
+        if options.encodings {
+            options.throttle();
+            // Files that cannot be read are left out, like binary files.
+            if let Some(sample) = options.tolerate(options.read_header(file, magic::TEXT_SAMPLE_LEN), file)? {
+                if let Some(encoding) = magic::detect_encoding(&sample) {
+                    self.encodings.entry(extension.name.clone()).or_default().add(encoding);
+                }
+            }
+        }
+        if options.media_duration {
+            if let Some(raw_extension) = file.extension() {
+                options.throttle();
+                let read_at = |offset, len| options.read_at(file, offset, len);
+                let duration = media::duration(&raw_extension.to_string_lossy(), &read_at);
+                // Files that cannot be read, or record no duration, add nothing to the total.
+                if let Some(Some(duration)) = options.tolerate(duration, file)? {
+                    extension.media_duration = duration;
+                }
+            }
+        }
+        if options.security && metadata.mode & 0o002 != 0 {
+            merge_extension(&mut self.world_writable_files, extension.clone());
+        }
+        if options.security && metadata.mode & 0o6000 != 0 {
+            self.set_id_files.push((file.to_path_buf(), metadata.mode));
+        }
+        if options.owners {
+            merge_extension(self.owners.entry(metadata.uid).or_default(), extension.clone());
+        }
+        if let Some(largest) = &options.largest {
+            largest.add(file, metadata.len, extension.name.as_deref());
+        }
+        if let Some(periods) = &options.periods {
+            periods.add(metadata.modified, metadata.len, extension.name.as_deref());
+        }
+        if let Some(workspaces) = &options.workspaces {
+            workspaces.add(file, extension.clone(), options);
+        }
+        let name = extension.name.clone();
+        merge_extension(&mut self.extensions, extension);
+
+        if options.name_lengths {
+            self.name_lengths.add(file);
+        }
+        if options.name_audit {
+            for finding in audit::audit_name(file) {
+                self.findings.push((finding, file.to_path_buf()));
+            }
+        }
+        if options.permission_audit {
+            for finding in audit::audit_mode(metadata.mode) {
+                self.findings.push((finding, file.to_path_buf()));
+            }
+        }
+        if options
+            .max_path_length
+            .is_some_and(|limit| audit::path_length(file) > limit)
+        {
+            self.findings.push((Finding::LongPath, file.to_path_buf()));
+        }
+        if options.verify_types {
+            if let Some(extension) = file.extension() {
+                options.throttle();
+                // Files that cannot be read are not counted, as there is nothing to compare.
+                if let Some(header) = options.tolerate(options.read_header(file, magic::HEADER_LEN), file)? {
+                    if magic::is_mismatch(&extension.to_string_lossy(), &header) {
+                        self.findings.push((Finding::TypeMismatch, file.to_path_buf()));
+                    }
+                }
+            }
+        }
+        Ok(name)
+    }
+
+    /// Merge the extensions of this directory and all of its subdirectories into a single list.
+    pub(crate) fn extension_totals(&self) -> Vec<Extension> {
+        let mut totals = Vec::new();
+        self.collect_extensions(&mut totals);
+        totals
+    }
+
+    fn collect_extensions(&self, totals: &mut Vec<Extension>) {
+        for extension in self.extensions.iter() {
+            merge_extension(totals, extension.clone());
+        }
+        for subdirectory in self.subdirectories.iter() {
+            subdirectory.collect_extensions(totals);
+        }
+    }
+
+    /// Merge subdirectories whose names only differ in their Unicode normalization form.
+    fn merge_equivalent_subdirectories(&mut self, form: Normalization) -> Result<()> {
+        let mut merged: Vec<(String, Directory)> = Vec::new();
+        for subdirectory in std::mem::take(&mut self.subdirectories) {
+            let key = normalize(&subdirectory.name()?, form);
+            if let Some((_, equivalent)) = merged.iter_mut().find(|(k, _)| *k == key) {
+                equivalent.absorb(subdirectory, form)?;
+            } else {
+                merged.push((key, subdirectory));
+            }
+        }
+        self.subdirectories = merged.into_iter().map(|(_, d)| d).collect();
+        Ok(())
+    }
+
+    /// Add the contents of an equivalent directory to this one.
+    fn absorb(&mut self, mut other: Directory, form: Normalization) -> Result<()> {
+        self.subdirectories.append(&mut other.subdirectories);
+        self.world_writable |= other.world_writable;
+        self.fold(other);
+        self.merge_equivalent_subdirectories(form)?;
+        self.sort_subdirectories();
+        Ok(())
+    }
+
+    /// Sort the subdirectories by name, comparing the names as they are on disk so that names
+    /// that are not valid UTF-8 are sorted too.
+    fn sort_subdirectories(&mut self) {
+        self.subdirectories
+            .sort_unstable_by(|a, b| a.root.file_name().cmp(&b.root.file_name()));
+    }
+
+    /// Add the files counted in another directory to this one, discarding its subdirectories.
+    fn fold(&mut self, other: Directory) {
+        for extension in other.extensions {
+            merge_extension(&mut self.extensions, extension);
+        }
+        self.junctions += other.junctions;
+        self.name_lengths.merge(&other.name_lengths);
+        self.findings.extend(other.findings);
+        for extension in other.world_writable_files {
+            merge_extension(&mut self.world_writable_files, extension);
+        }
+        self.set_id_files.extend(other.set_id_files);
+        self.broken_symlinks.extend(other.broken_symlinks);
+        self.trash.extend(other.trash);
+        for extension in other.high_entropy_files {
+            merge_extension(&mut self.high_entropy_files, extension);
+        }
+        for (extension, endings) in other.line_endings {
+            self.line_endings.entry(extension).or_default().merge(&endings);
+        }
+        for (extension, encodings) in other.encodings {
+            self.encodings.entry(extension).or_default().merge(&encodings);
+        }
+        for (extension, pairs) in other.compressed_pairs {
+            self.compressed_pairs.entry(extension).or_default().merge(&pairs);
+        }
+        for (uid, extensions) in other.owners {
+            let owned = self.owners.entry(uid).or_default();
+            for extension in extensions {
+                merge_extension(owned, extension);
+            }
+        }
+    }
+
+    /// Extensions of the files directly in this directory, or of all files below it when the
+    /// recursion limit was reached here.
+    pub(crate) fn extensions(&self) -> &[Extension] {
+        &self.extensions
+    }
+
+    /// Visit this directory and all of its subdirectories, parents before children.
+    pub(crate) fn walk<'a>(&'a self, visit: &mut impl FnMut(&'a Directory)) {
+        visit(self);
+        for subdirectory in self.subdirectories.iter() {
+            subdirectory.walk(visit);
+        }
+    }
+
+    /// Canonicalized path of the directory.
+    pub(crate) fn path(&self) -> &Path {
+        &self.root
+    }
+
+    /// Label of the directory, given the root of the scan it was found in.
+    pub(crate) fn display_name(&self, scan_root: &Path, paths: PathDisplay) -> Result<String> {
+        match paths {
+            PathDisplay::Name => self.name(),
+            PathDisplay::Relative if self.root == scan_root => self.name(),
+            paths => Ok(paths.format(&self.root, scan_root)),
+        }
+    }
+
+    /// Name of the directory, or its whole path for roots of file systems such as / or C:\.
     fn name(&self) -> Result<String> {
         self.root
             .file_name()
-            .context("directory cannot be an ellipsis")?
+            .unwrap_or(self.root.as_os_str())
             .to_str()
             .ok_or_else(|| anyhow!("could not convert directory name to string"))
             .map(|s| s.to_string())
@@ -187,76 +1496,169 @@ impl Directory {
 
     /// Returns the highest number of characters necessary to print out the extension (leading dot
     /// not included). Returns 0 if no extensions exist.
-    fn max_extension_chars(&self) -> usize {
-        self.extensions
-            .iter()
-            .map(|e| e.name.as_ref().unwrap_or(&NOEXT.to_string()).chars().count())
-            .max()
-            .unwrap_or(0)
+    fn max_extension_chars(&self, options: &DrawOptions) -> usize {
+        max_extension_chars(&self.extensions, options)
     }
 
     /// Returns the largest number of digits in an extension count. Returns 0 if no extensions exist.
     fn max_count_chars(&self) -> usize {
-        self.extensions
-            .iter()
-            .map(|e| {
-                (0..)
-                    .take_while(|i| 10u64.pow(*i) <= (e.count as usize).try_into().expect("HOW MANY FILES!?"))
-                    .count()
-            })
-            .max()
-            .unwrap_or(0)
+        max_count_chars(&self.extensions)
+    }
+
+    /// Format the dominant extension of this directory and of each directory below it, keyed by
+    /// path. The totals of each directory are merged from those of its subdirectories, which are
+    /// returned, so that the tree is only walked once.
+    fn collect_dominants<'a>(
+        &'a self,
+        options: &DrawOptions,
+        dominants: &mut HashMap<&'a Path, String>,
+    ) -> Vec<Extension> {
+        let mut totals = self.extensions.clone();
+        for subdirectory in self.subdirectories.iter() {
+            for extension in subdirectory.collect_dominants(options, dominants) {
+                merge_extension(&mut totals, extension);
+            }
+        }
+        if let Some(dominant) = dominant_formatted(&totals, options) {
+            dominants.insert(&self.root, dominant);
+        }
+        totals
     }
 
     /// A directory is empty when neither it, nor any of its subdirectories contain any files.
-    fn is_empty(&self) -> bool {
-        self.extensions.is_empty() && self.subdirectories.iter().all(|d| d.is_empty())
+    pub(crate) fn is_empty(&self) -> bool {
+        self.extensions.is_empty() && self.junctions == 0 && self.subdirectories.iter().all(|d| d.is_empty())
     }
 
     /// Recursive auxiliary drawing method.
     ///
     /// # Arguments
     ///
+    /// * `out` - Where the lines are written.
     /// * `last` - Whether the directory is the last in the parent's subdirectories.
     /// * `skipped` - Pipes to be skipped from printing.
-    /// * `draw_empty` - Draw self if empty.
-    fn draw_aux(&self, last: bool, skipped: &mut Vec<usize>, draw_empty: bool) -> Result<()> {
-        // Skip this row of pipes if the directory is the last one.
-        if last {
-            skipped.push(self.depth);
+    /// * `scan_root` - Path of the directory the drawing started from.
+    /// * `offset` - Depth of that directory, so that it is drawn as the root.
+    /// * `dominants` - Dominant extensions of the directories, formatted, when asked for.
+    /// * `options` - Drawing settings.
+    #[allow(clippy::too_many_arguments)]
+    fn draw_aux(
+        &self,
+        out: &mut impl Write,
+        last: bool,
+        skipped: &mut Vec<usize>,
+        scan_root: &Path,
+        offset: usize,
+        dominants: &HashMap<&Path, String>,
+        options: &DrawOptions,
+    ) -> Result<()> {
+        if !options.empty && self.is_empty() {
+            return Ok(());
         }
 
-        if !draw_empty && self.is_empty() {
-            return Ok(());
+        let depth = self.depth - offset;
+        // Skip this row of pipes if the directory is the last one.
+        if last {
+            skipped.push(depth);
         }
 
         // Draw the current directory itself.
-        if self.depth == 0 {
-            println!("{}", self.name()?);
+        let mut name = self.display_name(scan_root, options.paths)?;
+        match &self.mount_type {
+            Some(mount_type) if options.mounts && !mount_type.is_empty() => {
+                name.push_str(&format!(" [{}: {}]", options.locale.text("mount"), mount_type))
+            }
+            Some(_) if options.mounts => name.push_str(&format!(" [{}]", options.locale.text("mount"))),
+            _ => {}
+        }
+        if let Some(dominant) = dominants.get(self.root.as_path()) {
+            name.push_str(dominant);
+        }
+        if depth == 0 && options.plain {
+            writeln!(out, "{}", plain_item(&name, 0, options))?;
+        } else if depth == 0 {
+            writeln!(out, "{}", name)?;
         } else {
-            print_item(&self.name()?, last, self.depth, skipped);
+            write_item(out, &name, last, depth, skipped, options)?;
         }
 
-        // Draw the contained extensions.
-        let max_extension_chars = self.max_extension_chars();
+        // Whether rows are last is told from the subdirectories that are drawn, not from all of them.
+        let subdirectories: Vec<&Directory> = self
+            .subdirectories
+            .iter()
+            .filter(|d| options.empty || !d.is_empty())
+            .collect();
+
+        // Draw the contained extensions, followed by those of the baseline no longer found, whose
+        // files are all gone.
+        let baseline = options.baseline.as_ref().map(|baseline| baseline.get(&self.root));
+        let removed: Vec<Extension> = baseline
+            .flatten()
+            .into_iter()
+            .flatten()
+            .filter(|previous| !self.extensions.iter().any(|e| e.name == previous.name))
+            .map(|previous| Extension {
+                name: previous.name.clone(),
+                count: 0,
+                ..Default::default()
+            })
+            .collect();
+        let max_extension_chars = self
+            .max_extension_chars(options)
+            .max(max_extension_chars(&removed, options));
         let max_count_chars = self.max_count_chars();
-        for (idx, extension) in self.extensions.iter().enumerate() {
-            print_item(
-                &extension.to_string_formatted(max_extension_chars, max_count_chars),
-                self.subdirectories.is_empty() && idx + 1 == self.extensions.len(),
-                self.depth + 1,
+        let rows = self.extensions.len() + removed.len();
+        for (idx, extension) in self.extensions.iter().chain(&removed).enumerate() {
+            let mut formatted = extension.to_string_formatted(max_extension_chars, max_count_chars, options);
+            // Plain items are read on their own, so they repeat the directory they belong to.
+            if options.plain {
+                formatted = format!("{} ── {}", name, formatted);
+            }
+            if let Some(baseline) = baseline {
+                let previous = baseline.and_then(|b| b.iter().find(|e| e.name == extension.name));
+                formatted.push_str(&extension.growth_formatted(previous, max_count_chars, options));
+            }
+            write_item(
+                out,
+                &formatted,
+                subdirectories.is_empty() && self.junctions == 0 && idx + 1 == rows,
+                depth + 1,
                 skipped,
-            )
+                options,
+            )?;
+        }
+
+        // Draw the junctions found, which are not counted as files.
+        if self.junctions > 0 {
+            write_item(
+                out,
+                &format!("{} ── {}", JUNCTIONS, self.junctions),
+                subdirectories.is_empty(),
+                depth + 1,
+                skipped,
+                options,
+            )?;
         }
 
         // Draw the subdirectories.
-        for (idx, subdirectory) in self.subdirectories.iter().enumerate() {
-            subdirectory.draw_aux(idx + 1 == self.subdirectories.len(), skipped, draw_empty)?
+        for (idx, subdirectory) in subdirectories.iter().enumerate() {
+            subdirectory.draw_aux(
+                out,
+                idx + 1 == subdirectories.len(),
+                skipped,
+                scan_root,
+                offset,
+                dominants,
+                options,
+            )?
         }
 
-        // Remove the last depth item once all items have been processed, to avoid missing pipes
-        // for the next directory entry at the same level.
-        skipped.pop();
+        // Remove the depth item of this directory once all items have been processed, to avoid
+        // missing pipes for the next directory entry at the same level. Directories which are not
+        // the last one pushed none, and must not remove that of an ancestor.
+        if last {
+            skipped.pop();
+        }
 
         Ok(())
     }
@@ -281,6 +1683,111 @@ impl Directory {
     }
 }
 
+/// Order extensions by the given method.
+fn sort_extensions(extensions: &mut [Extension], method: &ExtensionSortingMethod) {
+    match method {
+        ExtensionSortingMethod::Alphabetically => {
+            extensions.sort_unstable_by(|e1, e2| e1.name.cmp(&e2.name));
+        }
+        ExtensionSortingMethod::FileCount => {
+            extensions.sort_unstable_by_key(|e| Reverse(e.count));
+        }
+        ExtensionSortingMethod::FileSize => {
+            extensions.sort_unstable_by_key(|e| Reverse(e.total_size_bytes));
+        }
+    }
+}
+
+/// Add an extension's count and size to the matching entry, or append it if none exists.
+pub(crate) fn merge_extension(extensions: &mut Vec<Extension>, extension: Extension) {
+    if let Some(previous_entry) = extensions.iter_mut().find(|e| e.name == extension.name) {
+        previous_entry.count += extension.count;
+        previous_entry.total_size_bytes += extension.total_size_bytes;
+        previous_entry.hardlinked_count += extension.hardlinked_count;
+        previous_entry.hardlinked_size_bytes += extension.hardlinked_size_bytes;
+        previous_entry.cloud_count += extension.cloud_count;
+        previous_entry.cloud_size_bytes += extension.cloud_size_bytes;
+        previous_entry.text_count += extension.text_count;
+        previous_entry.binary_count += extension.binary_count;
+        previous_entry.media_duration = previous_entry.media_duration.saturating_add(extension.media_duration);
+    } else {
+        extensions.push(extension);
+    }
+}
+
+/// Convert bytes to easily-readable binary-scaled units.
+pub(crate) fn size_human_readable(bytes: u64, decimals: usize, locale: Locale) -> String {
+    let units = locale.size_units();
+    if bytes < 1024 {
+        // Padded to the width of the other units.
+        return format!("{} {}  ", bytes, units[0]);
+    }
+    let mut exponent = 1;
+    while exponent + 1 < units.len() && bytes >= 1024u64.pow(exponent as u32 + 1) {
+        exponent += 1;
+    }
+    format!(
+        "{} {}",
+        locale.decimal(bytes as f64 / 1024.0f64.powi(exponent as i32), decimals),
+        units[exponent]
+    )
+}
+
+/// Format the extension taking up most of the size of the given files as `` [$NAME $SHARE%]``, by
+/// number of files when sizes were not gathered. `None` unless asked for, or when there are no
+/// files.
+fn dominant_formatted(extensions: &[Extension], options: &DrawOptions) -> Option<String> {
+    if !options.dominant {
+        return None;
+    }
+    let weight = |e: &Extension| {
+        if options.size {
+            e.total_size_bytes
+        } else {
+            e.count as u64
+        }
+    };
+    let total: u64 = extensions.iter().map(weight).sum();
+    let dominant = extensions.iter().max_by_key(|e| weight(e)).filter(|_| total > 0)?;
+    Some(format!(
+        " [{} {:.0}%]",
+        dominant.label(options),
+        weight(dominant) as f64 / total as f64 * 100.0
+    ))
+}
+
+/// Returns the highest number of columns necessary to print out any of the extensions.
+pub(crate) fn max_extension_chars(extensions: &[Extension], options: &DrawOptions) -> usize {
+    extensions
+        .iter()
+        .map(|e| width::width(e.label(options)))
+        .max()
+        .unwrap_or(0)
+}
+
+/// Returns the largest number of digits in any of the extension counts.
+pub(crate) fn max_count_chars(extensions: &[Extension]) -> usize {
+    extensions
+        .iter()
+        .map(|e| {
+            (0..)
+                .take_while(|i| 10u64.pow(*i) <= e.count.try_into().expect("HOW MANY FILES!?"))
+                .count()
+        })
+        .max()
+        .unwrap_or(0)
+}
+
+/// An item of plain output: ``level $DEPTH: $COLUMN — $COLUMN``.
+fn plain_item(text: &str, depth: usize, options: &DrawOptions) -> String {
+    format!(
+        "{} {}: {}",
+        options.locale.text("level"),
+        depth,
+        text.split("──").map(str::trim).collect::<Vec<_>>().join(" — ")
+    )
+}
+
 /// Depth zero is the depth of the items contained in the root directory the program was called in.
 /// Skipped keeps track of which pipes to render during printing.
 fn pipes(depth: usize, skipped: &[usize]) -> String {
@@ -300,11 +1807,41 @@ fn pipes(depth: usize, skipped: &[usize]) -> String {
 /// # Arguments
 ///
 /// * `last` - Whether the item is the last in the list and should therefore use an L-pipe rather
-/// than a T-pipe.
+///   than a T-pipe.
 /// * `depth` - Recursion depth, gives indentation.
 /// * `skipped` - Notes which pipes to skip drawing.
-fn print_item(text: &str, last: bool, depth: usize, skipped: &[usize]) {
-    println!(
+///
+/// With plain output, the item is indented and prefixed with its depth instead, and its columns are
+/// separated by single dashes without padding.
+pub(crate) fn print_item(
+    text: &str,
+    last: bool,
+    depth: usize,
+    skipped: &[usize],
+    options: &DrawOptions,
+) -> io::Result<()> {
+    write_item(&mut io::stdout().lock(), text, last, depth, skipped, options)
+}
+
+/// Print the title of a report, above its items.
+pub(crate) fn print_title(title: &str) -> io::Result<()> {
+    writeln!(io::stdout().lock(), "{}", title)
+}
+
+/// Write an item like `print_item`, to `out`.
+pub(crate) fn write_item(
+    out: &mut impl Write,
+    text: &str,
+    last: bool,
+    depth: usize,
+    skipped: &[usize],
+    options: &DrawOptions,
+) -> io::Result<()> {
+    if options.plain {
+        return writeln!(out, "{}{}", "  ".repeat(depth), plain_item(text, depth, options));
+    }
+    writeln!(
+        out,
         "{}{}── {}",
         pipes(depth, skipped),
         if last { LPIPE } else { TPIPE },
@@ -315,12 +1852,103 @@ fn print_item(text: &str, last: bool, depth: usize, skipped: &[usize]) {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::fs::MemoryFileSystem;
 
     const TESTS_DIR: &str = env!("CARGO_MANIFEST_DIR");
 
     fn tests_dir(max_depth: usize) -> Directory {
         let root = PathBuf::from(TESTS_DIR).join("tests");
-        Directory::new(root, 0, max_depth).expect("could not create directory")
+        // Git does not track empty directories, so the empty fixture is created on demand.
+        std::fs::create_dir_all(root.join("dirB").join("dirC")).expect("could not create empty directory");
+        let options = ScanOptions {
+            max_depth,
+            ..Default::default()
+        };
+        Directory::new(root, 0, &options).expect("could not create directory")
+    }
+
+    fn draw_options() -> DrawOptions {
+        DrawOptions {
+            empty: false,
+            paths: PathDisplay::Name,
+            size: true,
+            mounts: false,
+            dominant: false,
+            hardlinks: false,
+            cloud: false,
+            text_binary: false,
+            baseline: None,
+            color: None,
+            extensionless_label: "N/A".to_string(),
+            media_duration: false,
+            locale: Locale::En,
+            plain: false,
+        }
+    }
+
+    mod extension {
+        use super::*;
+
+        #[test]
+        fn test_plain_formatted() {
+            let mut options = draw_options();
+            options.plain = true;
+            let extension = Extension::new(Some("rs".to_string()), 2048);
+            let formatted = extension.to_string_formatted(8, 4, &options);
+            assert_eq!(formatted, "rs ── 1 file ──   2.00 kiB");
+            assert_eq!(
+                plain_item(&format!("src ── {}", formatted), 2, &options),
+                "level 2: src — rs — 1 file — 2.00 kiB"
+            );
+        }
+
+        #[test]
+        fn test_growth_formatted() {
+            let mut options = draw_options();
+            let extension = Extension::new(Some("txt".to_string()), 2048);
+            let previous = Extension {
+                count: 3,
+                ..Extension::new(Some("txt".to_string()), 1024)
+            };
+
+            assert_eq!(
+                extension.growth_formatted(Some(&previous), 1, &options),
+                " ── -2 files ──   +1.00 kiB"
+            );
+            assert_eq!(
+                extension.growth_formatted(None, 1, &options),
+                " ── +1 files ──   +2.00 kiB"
+            );
+            assert_eq!(
+                extension.growth_formatted(Some(&extension), 1, &options),
+                " ──  0 files ──       0 B  "
+            );
+
+            options.size = false;
+            options.color = Some(Theme::default());
+            assert_eq!(
+                extension.growth_formatted(Some(&previous), 1, &options),
+                "\x1b[32m ── -2 files\x1b[0m"
+            );
+        }
+
+        #[test]
+        fn test_translated_formatted() {
+            let mut options = draw_options();
+            options.size = false;
+            options.hardlinks = true;
+            options.text_binary = true;
+            options.locale = Locale::De;
+            let extension = Extension {
+                text_count: 1,
+                ..Extension::new(Some("txt".to_string()), 2048)
+            };
+            assert_eq!(
+                extension.to_string_formatted(3, 1, &options),
+                "txt ── 1 ── 0 hart verlinkt ── 1 Text ── 0 binär"
+            );
+            assert_eq!(extension.growth_formatted(None, 1, &options), " ── +1 Dateien");
+        }
     }
 
     mod directory {
@@ -360,7 +1988,7 @@ mod tests {
             let directory = tests_dir(2);
             let subdirectory = directory.subdirectories.first().expect("no subdirectories found");
 
-            assert_eq!(subdirectory.max_extension_chars(), 3);
+            assert_eq!(subdirectory.max_extension_chars(&draw_options()), 3);
             assert_eq!(subdirectory.max_count_chars(), 1);
             assert_eq!(subdirectory.name().expect("could not read directory name"), "dirA");
             assert_eq!(subdirectory.count(Some("bar")), 1);
@@ -374,15 +2002,1107 @@ mod tests {
                 .first()
                 .expect("no subsubdirectory found");
 
-            assert_eq!(subsubdirectory.max_extension_chars(), 0);
+            assert_eq!(subsubdirectory.max_extension_chars(&draw_options()), 0);
             assert_eq!(subsubdirectory.max_count_chars(), 0);
         }
 
+        #[test]
+        fn test_new_streaming() {
+            let root = PathBuf::from(TESTS_DIR).join("tests");
+            let options = ScanOptions {
+                max_depth: 2,
+                ..Default::default()
+            };
+
+            let mut emitted = Vec::new();
+            let directory = Directory::new_streaming(root, 0, &options, &mut |d| {
+                emitted.push((d.name()?, d.extension_totals().iter().map(|e| e.count).sum::<usize>()));
+                Ok(())
+            })
+            .expect("could not create directory");
+
+            assert!(directory.subdirectories.is_empty());
+            assert_eq!(directory.count(Some("foo")), 2);
+            assert_eq!(emitted.len(), 4);
+            assert_eq!(emitted.last(), Some(&("tests".to_string(), 5)));
+        }
+
+        #[test]
+        fn test_file_system() {
+            let mut file_system = MemoryFileSystem::default();
+            file_system
+                .add_file("/data/a.txt", 3)
+                .add_file("/data/b.TXT", 4)
+                .add_file("/data/logs/c.txt", 5)
+                .add_file("/data/logs/deep/d.log", 6);
+            let options = ScanOptions {
+                max_depth: 1,
+                file_system: Box::new(file_system),
+                ..Default::default()
+            };
+
+            let directory = Directory::new(PathBuf::from("/data"), 0, &options).expect("could not create directory");
+            assert_eq!(directory.count(Some("txt")), 1);
+            assert_eq!(directory.size(Some("TXT")), Some(4));
+            let logs = directory.subdirectories.first().expect("no subdirectories found");
+            assert_eq!(logs.name().expect("could not read directory name"), "logs");
+            assert_eq!(logs.size(Some("log")), Some(6));
+
+            assert!(Directory::new(PathBuf::from("/data/a.txt"), 0, &options).is_err());
+        }
+
+        #[test]
+        fn test_mount_type() {
+            let mut file_system = MemoryFileSystem::default();
+            file_system
+                .add_file("/home/a.txt", 1)
+                .add_file("/home/local/b.txt", 2)
+                .add_file("/home/nfs/c.txt", 3)
+                .mount("/home/nfs", 1);
+            let options = ScanOptions {
+                max_depth: 1,
+                devices: true,
+                file_system: Box::new(file_system),
+                ..Default::default()
+            };
+
+            let directory = Directory::new(PathBuf::from("/home"), 0, &options).expect("could not create directory");
+            assert_eq!(directory.mount_type, None);
+            assert_eq!(directory.subdirectories[0].mount_type, None);
+            assert_eq!(directory.subdirectories[1].mount_type.as_deref(), Some("memfs"));
+        }
+
+        #[test]
+        fn test_hardlinks() {
+            let mut file_system = MemoryFileSystem::default();
+            file_system
+                .add_file("/backup/a.txt", 3)
+                .add_file("/backup/b.txt", 4)
+                .add_file("/backup/c.log", 5)
+                .link("/backup/b.txt", 2)
+                .link("/backup/c.log", 3);
+            let options = ScanOptions {
+                file_system: Box::new(file_system),
+                ..Default::default()
+            };
+
+            let directory = Directory::new(PathBuf::from("/backup"), 0, &options).expect("could not create directory");
+            let txt = directory
+                .extensions
+                .iter()
+                .find(|e| e.name.as_deref() == Some("txt"))
+                .unwrap();
+            assert_eq!((txt.count, txt.hardlinked_count, txt.hardlinked_size_bytes), (2, 1, 4));
+            let log = directory
+                .extensions
+                .iter()
+                .find(|e| e.name.as_deref() == Some("log"))
+                .unwrap();
+            assert_eq!((log.count, log.hardlinked_count, log.hardlinked_size_bytes), (1, 1, 5));
+        }
+
+        #[test]
+        fn test_cloud_placeholders() {
+            let mut file_system = MemoryFileSystem::default();
+            file_system
+                .add_file("/OneDrive/a.docx", 300)
+                .add_file("/OneDrive/b.docx", 400)
+                .add_file("/OneDrive/c.mp4", 500)
+                .evict("/OneDrive/b.docx")
+                .evict("/OneDrive/c.mp4");
+            let options = ScanOptions {
+                file_system: Box::new(file_system),
+                ..Default::default()
+            };
+
+            let directory =
+                Directory::new(PathBuf::from("/OneDrive"), 0, &options).expect("could not create directory");
+            let docx = directory
+                .extensions
+                .iter()
+                .find(|e| e.name.as_deref() == Some("docx"))
+                .unwrap();
+            assert_eq!((docx.count, docx.cloud_count, docx.cloud_size_bytes), (2, 1, 400));
+
+            let mut draw_options = draw_options();
+            draw_options.cloud = true;
+            assert_eq!(
+                docx.to_string_formatted(4, 1, &draw_options),
+                "docx ── 2 ──    700 B   ── local    300 B   ── cloud    400 B  "
+            );
+        }
+
+        #[test]
+        fn test_text_binary() {
+            let mut file_system = MemoryFileSystem::default();
+            file_system
+                .add_file_with_contents("/data/notes.dat", b"plain text\n")
+                .add_file_with_contents("/data/table.dat", b"\x01\0\0\x02")
+                .add_file_with_contents("/data/dump.dat", b"\x7FELF\x02\x01\x01\0");
+            let options = ScanOptions {
+                text_binary: true,
+                file_system: Box::new(file_system),
+                ..Default::default()
+            };
+
+            let directory = Directory::new(PathBuf::from("/data"), 0, &options).expect("could not create directory");
+            let dat = &directory.extensions[0];
+            assert_eq!((dat.count, dat.text_count, dat.binary_count), (3, 1, 2));
+
+            let mut draw_options = draw_options();
+            draw_options.size = false;
+            draw_options.text_binary = true;
+            assert_eq!(
+                dat.to_string_formatted(3, 1, &draw_options),
+                "dat ── 3 ── 1 text ── 2 binary"
+            );
+        }
+
+        #[test]
+        fn test_line_endings() {
+            let mut file_system = MemoryFileSystem::default();
+            file_system
+                .add_file_with_contents("/repo/unix.txt", b"a\nb\n")
+                .add_file_with_contents("/repo/win/dos.txt", b"a\r\nb\r\n")
+                .add_file_with_contents("/repo/win/both.txt", b"a\r\nb\n")
+                .add_file_with_contents("/repo/win/image.bmp", b"BM\0\r\n");
+            let options = ScanOptions {
+                max_depth: 2,
+                line_endings: true,
+                file_system: Box::new(file_system),
+                ..Default::default()
+            };
+
+            let directory = Directory::new(PathBuf::from("/repo"), 0, &options).expect("could not create directory");
+            let txt = Some("txt".to_string());
+            assert_eq!(
+                directory.line_endings[&txt],
+                LineEndings {
+                    lf: 1,
+                    crlf: 0,
+                    mixed: 0
+                }
+            );
+            let win = &directory.subdirectories[0];
+            assert_eq!(win.line_endings.len(), 1);
+            assert_eq!(
+                win.line_endings[&txt],
+                LineEndings {
+                    lf: 0,
+                    crlf: 1,
+                    mixed: 1
+                }
+            );
+        }
+
+        #[test]
+        fn test_encodings() {
+            let mut file_system = MemoryFileSystem::default();
+            file_system
+                .add_file_with_contents("/share/new.csv", "caf\u{e9};1\n".as_bytes())
+                .add_file_with_contents("/share/old/a.csv", b"caf\xE9;1\n")
+                .add_file_with_contents("/share/old/b.csv", b"cr\xE8me;2\n")
+                .add_file_with_contents("/share/old/c.txt", b"\xFF\xFEh\0i\0");
+            let options = ScanOptions {
+                max_depth: 2,
+                encodings: true,
+                file_system: Box::new(file_system),
+                ..Default::default()
+            };
+
+            let directory = Directory::new(PathBuf::from("/share"), 0, &options).expect("could not create directory");
+            let encodings = |found: &[magic::Encoding]| {
+                let mut encodings = Encodings::default();
+                found.iter().for_each(|encoding| encodings.add(*encoding));
+                encodings
+            };
+            let csv = Some("csv".to_string());
+            assert_eq!(directory.encodings[&csv], encodings(&[magic::Encoding::Utf8]));
+            let old = &directory.subdirectories[0];
+            assert_eq!(
+                old.encodings[&csv],
+                encodings(&[magic::Encoding::Latin1, magic::Encoding::Latin1])
+            );
+            assert_eq!(
+                old.encodings[&Some("txt".to_string())],
+                encodings(&[magic::Encoding::Utf16LeBom])
+            );
+        }
+
+        #[test]
+        fn test_depth_stats() {
+            let mut file_system = MemoryFileSystem::default();
+            file_system
+                .add_file("/r/a/b/c/deep.txt", 1)
+                .add_file("/r/a/x.txt", 1)
+                .add_dir("/r/e");
+            let options = ScanOptions {
+                max_depth: 1,
+                depths: Some(DepthStats::default()),
+                file_system: Box::new(file_system),
+                ..Default::default()
+            };
+
+            Directory::new(PathBuf::from("/r"), 0, &options).expect("could not create directory");
+            let depths = options.depths.as_ref().unwrap();
+            assert_eq!(depths.directories(), vec![1, 2, 1, 1]);
+        }
+
+        #[test]
+        fn test_max_path_length() {
+            let mut file_system = MemoryFileSystem::default();
+            file_system
+                .add_file("/sync/short.txt", 1)
+                .add_file("/sync/a/much_longer_name.txt", 1);
+            let options = ScanOptions {
+                max_depth: 2,
+                max_path_length: Some(20),
+                file_system: Box::new(file_system),
+                ..Default::default()
+            };
+
+            let directory = Directory::new(PathBuf::from("/sync"), 0, &options).expect("could not create directory");
+            assert!(directory.findings.is_empty());
+            assert_eq!(
+                directory.subdirectories[0].findings,
+                vec![(Finding::LongPath, PathBuf::from("/sync/a/much_longer_name.txt"))]
+            );
+        }
+
+        #[test]
+        fn test_case_collisions() {
+            let mut file_system = MemoryFileSystem::default();
+            file_system
+                .add_file("/repo/README.md", 1)
+                .add_file("/repo/Readme.md", 1)
+                .add_file("/repo/docs/a.md", 1)
+                .add_file("/repo/Docs/b.md", 1)
+                .add_file("/repo/docs/A.MD", 1);
+            let options = ScanOptions {
+                max_depth: 1,
+                case_collisions: true,
+                file_system: Box::new(file_system),
+                ..Default::default()
+            };
+
+            let directory = Directory::new(PathBuf::from("/repo"), 0, &options).expect("could not create directory");
+            assert_eq!(directory.findings.len(), 4);
+            let docs = directory
+                .subdirectories
+                .iter()
+                .find(|d| d.root.ends_with("docs"))
+                .unwrap();
+            assert_eq!(docs.findings.len(), 2);
+        }
+
+        #[test]
+        fn test_windows_names() {
+            let mut file_system = MemoryFileSystem::default();
+            file_system
+                .add_file("/share/nul.txt", 1)
+                .add_file("/share/notes.txt.", 1)
+                .add_file("/share/AUX/readme.md", 1)
+                .add_file("/share/old /a.txt", 1)
+                .add_file("/share/fine.txt", 1);
+            let options = ScanOptions {
+                windows_names: true,
+                name_audit: true,
+                file_system: Box::new(file_system),
+                ..Default::default()
+            };
+
+            let directory = Directory::new(PathBuf::from("/share"), 0, &options).expect("could not create directory");
+            let mut findings = directory.findings.clone();
+            findings.sort();
+            assert_eq!(
+                findings,
+                vec![
+                    (Finding::TrailingSpace, PathBuf::from("/share/old ")),
+                    (Finding::TrailingDot, PathBuf::from("/share/notes.txt.")),
+                    (Finding::ReservedName, PathBuf::from("/share/AUX")),
+                    (Finding::ReservedName, PathBuf::from("/share/nul.txt")),
+                ]
+            );
+        }
+
+        #[test]
+        fn test_compressed_pairs() {
+            let mut file_system = MemoryFileSystem::default();
+            file_system
+                .add_file("/logs/access.log", 1000)
+                .add_file("/logs/access.log.gz", 100)
+                .add_file("/logs/error.log", 500)
+                .add_file("/logs/old.log.zst", 50)
+                .add_file("/logs/data.csv", 300)
+                .add_file("/logs/data.csv.xz", 30);
+            let options = ScanOptions {
+                compressed_pairs: true,
+                file_system: Box::new(file_system),
+                ..Default::default()
+            };
+
+            let directory = Directory::new(PathBuf::from("/logs"), 0, &options).expect("could not create directory");
+            let pairs = &directory.compressed_pairs;
+            assert_eq!(pairs.len(), 2);
+            assert_eq!(
+                pairs[&Some("log".to_string())],
+                CompressedPairs {
+                    count: 1,
+                    original_bytes: 1000,
+                    compressed_bytes: 100
+                }
+            );
+            assert_eq!(pairs[&Some("csv".to_string())].compressed_bytes, 30);
+        }
+
+        #[test]
+        fn test_permission_audit() {
+            let mut file_system = MemoryFileSystem::default();
+            file_system
+                .add_file("/shared/open.sh", 1)
+                .add_file("/shared/team.txt", 2)
+                .add_file("/shared/private.txt", 3)
+                .chmod("/shared/open.sh", 0o777)
+                .chmod("/shared/team.txt", 0o664);
+            let options = ScanOptions {
+                permission_audit: true,
+                file_system: Box::new(file_system),
+                ..Default::default()
+            };
+
+            let directory = Directory::new(PathBuf::from("/shared"), 0, &options).expect("could not create directory");
+            let group_writable: Vec<_> = directory
+                .findings
+                .iter()
+                .filter(|(f, _)| *f == Finding::GroupWritable)
+                .map(|(_, file)| file.to_string_lossy().to_string())
+                .collect();
+            assert_eq!(group_writable, vec!["/shared/open.sh", "/shared/team.txt"]);
+            assert_eq!(directory.findings.len(), 4);
+        }
+
+        #[test]
+        fn test_security() {
+            let mut file_system = MemoryFileSystem::default();
+            file_system
+                .add_file("/shared/a.txt", 1)
+                .add_file("/shared/b.txt", 2)
+                .add_file("/shared/drop/c.log", 3)
+                .add_file("/shared/drop/run", 4)
+                .chmod("/shared/b.txt", 0o666)
+                .chmod("/shared/drop/run", 0o4755)
+                .chmod("/shared/drop", 0o1777);
+            let options = ScanOptions {
+                max_depth: 1,
+                security: true,
+                file_system: Box::new(file_system),
+                ..Default::default()
+            };
+
+            let directory = Directory::new(PathBuf::from("/shared"), 0, &options).expect("could not create directory");
+            assert!(!directory.world_writable);
+            assert_eq!(directory.world_writable_files.len(), 1);
+            assert_eq!(directory.world_writable_files[0].total_size_bytes, 2);
+            assert!(directory.subdirectories[0].world_writable);
+            assert!(directory.subdirectories[0].world_writable_files.is_empty());
+            assert_eq!(
+                directory.subdirectories[0].set_id_files,
+                vec![(PathBuf::from("/shared/drop/run"), 0o4755)]
+            );
+        }
+
+        #[test]
+        fn test_verify_types() {
+            let mut file_system = MemoryFileSystem::default();
+            file_system
+                .add_file_with_contents("/evidence/photo.jpg", b"\xFF\xD8\xFF\xE0\0\x10JFIF")
+                .add_file_with_contents("/evidence/holiday.jpg", b"PK\x03\x04\x14\0\0\0")
+                .add_file_with_contents("/evidence/notes.txt", b"PK\x03\x04\x14\0\0\0");
+            let options = ScanOptions {
+                verify_types: true,
+                file_system: Box::new(file_system),
+                ..Default::default()
+            };
+
+            let directory =
+                Directory::new(PathBuf::from("/evidence"), 0, &options).expect("could not create directory");
+            assert_eq!(
+                directory.findings,
+                vec![(Finding::TypeMismatch, PathBuf::from("/evidence/holiday.jpg"))]
+            );
+        }
+
+        #[test]
+        fn test_entropy() {
+            let random: Vec<u8> = (0..4096).map(|i| (i * 167 % 256) as u8).collect();
+            let mut file_system = MemoryFileSystem::default();
+            file_system
+                .add_file_with_contents("/share/report.docx.locked", &random)
+                .add_file_with_contents("/share/archive.zip", &random)
+                .add_file_with_contents("/share/notes.txt", "notes ".repeat(500).as_bytes());
+            let options = ScanOptions {
+                entropy: true,
+                file_system: Box::new(file_system),
+                ..Default::default()
+            };
+
+            let directory = Directory::new(PathBuf::from("/share"), 0, &options).expect("could not create directory");
+            assert_eq!(directory.high_entropy_files.len(), 1);
+            assert_eq!(directory.high_entropy_files[0].name.as_deref(), Some("locked"));
+        }
+
+        #[test]
+        fn test_broken_symlinks() {
+            let mut file_system = MemoryFileSystem::default();
+            file_system
+                .add_file("/migrated/data.csv", 1)
+                .add_symlink("/migrated/latest", "data.csv")
+                .add_symlink("/migrated/old", "/legacy/data.csv")
+                .add_symlink("/migrated/deep/older", "../../legacy/older.csv");
+            let options = ScanOptions {
+                broken_symlinks: true,
+                file_system: Box::new(file_system),
+                ..Default::default()
+            };
+
+            let directory =
+                Directory::new(PathBuf::from("/migrated"), 0, &options).expect("could not create directory");
+            assert_eq!(directory.count(Some("csv")), 1);
+            assert_eq!(
+                directory.broken_symlinks,
+                vec![
+                    (
+                        PathBuf::from("/migrated/deep/older"),
+                        PathBuf::from("../../legacy/older.csv")
+                    ),
+                    (PathBuf::from("/migrated/old"), PathBuf::from("/legacy/data.csv")),
+                ]
+            );
+        }
+
+        #[test]
+        fn test_junctions() {
+            let mut file_system = MemoryFileSystem::default();
+            file_system
+                .add_file("/c/users/me/a.txt", 1)
+                .add_file("/d/onedrive/b.txt", 2)
+                .add_junction("/c/users/me/onedrive", "/d/onedrive")
+                .add_junction("/d/onedrive/back", "/c/users");
+            let mut options = ScanOptions {
+                max_depth: 3,
+                file_system: Box::new(file_system),
+                ..Default::default()
+            };
+
+            let directory = Directory::new(PathBuf::from("/c/users"), 0, &options).expect("could not create directory");
+            let me = &directory.subdirectories[0];
+            assert_eq!((me.junctions, me.subdirectories.len()), (1, 0));
+
+            options.follow_junctions = true;
+            let directory = Directory::new(PathBuf::from("/c/users"), 0, &options).expect("could not create directory");
+            let onedrive = &directory.subdirectories[0].subdirectories[0];
+            assert_eq!(onedrive.count(Some("txt")), 1);
+            assert_eq!(onedrive.junctions, 1);
+            assert!(onedrive.subdirectories.is_empty());
+        }
+
+        #[test]
+        fn test_fail_fast() {
+            let mut file_system = MemoryFileSystem::default();
+            file_system
+                .add_file("/c/users/a.txt", 1)
+                .add_junction("/c/users/gone", "/d/gone");
+            let mut options = ScanOptions {
+                max_depth: 1,
+                follow_junctions: true,
+                file_system: Box::new(file_system),
+                ..Default::default()
+            };
+
+            let directory = Directory::new(PathBuf::from("/c/users"), 0, &options).expect("could not create directory");
+            assert_eq!((directory.count(Some("txt")), directory.junctions), (1, 1));
+
+            options.fail_fast = true;
+            let error = Directory::new(PathBuf::from("/c/users"), 0, &options).expect_err("scan did not fail");
+            assert_eq!(error.to_string(), "could not read /c/users/gone");
+        }
+
+        /// File system whose listed entries below `gone` have been removed before being read.
+        #[derive(Debug)]
+        struct Vanishing {
+            file_system: MemoryFileSystem,
+            gone: PathBuf,
+        }
+
+        impl Vanishing {
+            fn check(&self, path: &Path) -> io::Result<()> {
+                if path.starts_with(&self.gone) {
+                    return Err(io::ErrorKind::NotFound.into());
+                }
+                Ok(())
+            }
+        }
+
+        impl FileSystem for Vanishing {
+            fn canonicalize(&self, path: &Path) -> io::Result<PathBuf> {
+                self.check(path)?;
+                self.file_system.canonicalize(path)
+            }
+
+            fn read_dir(&self, dir: &Path) -> io::Result<Vec<Entry>> {
+                self.check(dir)?;
+                self.file_system.read_dir(dir)
+            }
+
+            fn metadata(&self, path: &Path) -> io::Result<Metadata> {
+                self.check(path)?;
+                self.file_system.metadata(path)
+            }
+
+            fn file_type(&self, path: &Path) -> io::Result<EntryKind> {
+                self.check(path)?;
+                self.file_system.file_type(path)
+            }
+
+            fn read_link(&self, link: &Path) -> io::Result<PathBuf> {
+                self.check(link)?;
+                self.file_system.read_link(link)
+            }
+
+            fn read_at(&self, file: &Path, offset: u64, len: usize) -> io::Result<Vec<u8>> {
+                self.check(file)?;
+                self.file_system.read_at(file, offset, len)
+            }
+        }
+
+        #[test]
+        fn test_vanished() {
+            for (max_depth, gone) in [(2, "/data/old"), (1, "/data/old"), (2, "/data/new.txt")] {
+                let mut file_system = MemoryFileSystem::default();
+                file_system
+                    .add_file("/data/new.txt", 1)
+                    .add_file("/data/keep/a.txt", 2)
+                    .add_file("/data/old/b.txt", 3);
+                let options = ScanOptions {
+                    max_depth,
+                    fail_fast: true,
+                    file_system: Box::new(Vanishing {
+                        file_system,
+                        gone: PathBuf::from(gone),
+                    }),
+                    ..Default::default()
+                };
+
+                let directory =
+                    Directory::new(PathBuf::from("/data"), 0, &options).expect("could not create directory");
+                let counted: usize = directory.extension_totals().iter().map(|e| e.count).sum();
+                assert_eq!((counted, options.warnings.count(Warning::Vanished)), (2, 1), "{}", gone);
+            }
+        }
+
+        #[test]
+        fn test_exclude_include() {
+            let mut file_system = MemoryFileSystem::default();
+            file_system
+                .add_file("/src/main.rs", 1)
+                .add_file("/src/notes.txt", 2)
+                .add_file("/src/target/debug/app.rs", 3)
+                .add_file("/src/lib/lib.rs", 4)
+                .add_file("/src/lib/deep/mod.rs", 5)
+                .add_file("/src/lib/deep/data.tmp", 6);
+            let options = ScanOptions {
+                max_depth: 1,
+                exclude: Some("target|*.tmp".to_string()),
+                include: Some("*.rs".to_string()),
+                file_system: Box::new(file_system),
+                ..Default::default()
+            };
+
+            let directory = Directory::new(PathBuf::from("/src"), 0, &options).expect("could not create directory");
+            assert_eq!((directory.count(Some("rs")), directory.count(Some("txt"))), (1, 0));
+            assert_eq!(directory.subdirectories.len(), 1);
+            assert_eq!(directory.subdirectories[0].count(Some("rs")), 2);
+            assert_eq!(directory.subdirectories[0].count(Some("tmp")), 0);
+        }
+
+        #[test]
+        fn test_once_per_volume() {
+            let mut file_system = MemoryFileSystem::default();
+            file_system
+                .add_file("/a.txt", 1)
+                .add_file("/data/b.txt", 2)
+                .add_file("/srv/data/b.txt", 2)
+                .mount("/data", 1)
+                .mount("/srv/data", 1);
+            let options = ScanOptions {
+                max_depth: 2,
+                devices: true,
+                once_per_volume: true,
+                file_system: Box::new(file_system),
+                ..Default::default()
+            };
+
+            let directory = Directory::new(PathBuf::from("/"), 0, &options).expect("could not create directory");
+            assert_eq!(directory.extension_totals()[0].count, 2);
+            assert_eq!(
+                directory.subdirectories[1].subdirectories[0].extension_totals().len(),
+                0
+            );
+        }
+
+        #[test]
+        fn test_max_memory() {
+            let mut file_system = MemoryFileSystem::default();
+            for dir in ["a", "b", "c"] {
+                for subdir in ["x", "y"] {
+                    file_system.add_file(format!("/data/{}/{}/deep/file.txt", dir, subdir), 1);
+                }
+            }
+            let options = ScanOptions {
+                max_depth: 4,
+                max_memory: Some(1),
+                file_system: Box::new(file_system),
+                ..Default::default()
+            };
+
+            let directory = Directory::new(PathBuf::from("/data"), 0, &options).expect("could not create directory");
+            assert_eq!(options.kept_depth.get(), Some(1));
+            assert_eq!(options.warnings.count(Warning::MemoryLimit), 2);
+            assert_eq!(directory.extension_totals()[0].count, 6);
+            // The deepest directory of the first subtree exceeds the limit, later ones are folded.
+            assert_eq!(directory.subdirectories.len(), 3);
+            assert_eq!(directory.subdirectories[2].subdirectories.len(), 0);
+            assert_eq!(directory.subdirectories[2].count(Some("txt")), 2);
+        }
+
+        #[test]
+        fn test_pseudo_mounts() {
+            let mut file_system = MemoryFileSystem::default();
+            file_system
+                .add_file("/etc/hosts", 1)
+                .add_file("/proc/kcore", 1 << 47)
+                .add_file("/home/proc/notes.txt", 2);
+            let options = ScanOptions {
+                max_depth: 0,
+                pseudo_mounts: HashSet::from([PathBuf::from("/proc")]),
+                file_system: Box::new(file_system),
+                ..Default::default()
+            };
+
+            let directory = Directory::new(PathBuf::from("/"), 0, &options).expect("could not create directory");
+            assert_eq!(directory.count(None), 1);
+            assert_eq!(directory.count(Some("txt")), 1);
+            assert_eq!(
+                directory
+                    .extension_totals()
+                    .iter()
+                    .map(|e| e.total_size_bytes)
+                    .sum::<u64>(),
+                3
+            );
+        }
+
+        #[test]
+        fn test_ignore_files() {
+            let mut file_system = MemoryFileSystem::default();
+            file_system
+                .add_file_with_contents("/repo/.ignore", b"*.tmp\n/build/\n")
+                .add_file("/repo/a.tmp", 1)
+                .add_file("/repo/build/app.o", 2)
+                .add_file("/repo/src/build/mod.rs", 3)
+                .add_file_with_contents("/repo/src/.ignore", b"!keep.tmp\n")
+                .add_file("/repo/src/keep.tmp", 4)
+                .add_file("/repo/src/other.tmp", 5)
+                .add_file("/repo/docs/b.tmp", 6);
+            let options = ScanOptions {
+                max_depth: 1,
+                ignore: Ignore::new(None, Path::new("/repo"), true, true).expect("could not set up ignore rules"),
+                file_system: Box::new(file_system),
+                ..Default::default()
+            };
+
+            let directory = Directory::new(PathBuf::from("/repo"), 0, &options).expect("could not create directory");
+            assert_eq!(directory.count(Some("tmp")), 0);
+            let names: Vec<String> = directory.subdirectories.iter().map(|d| d.name().unwrap()).collect();
+            assert_eq!(names, ["docs", "src"]);
+            assert_eq!(directory.subdirectories[0].count(Some("tmp")), 0);
+            assert_eq!(directory.subdirectories[1].count(Some("tmp")), 1);
+            assert_eq!(directory.subdirectories[1].count(Some("rs")), 1);
+        }
+
+        #[test]
+        fn test_extensionless() {
+            let mut file_system = MemoryFileSystem::default();
+            file_system
+                .add_file("/repo/Makefile", 1)
+                .add_file("/repo/LICENSE", 2)
+                .add_file_with_contents("/repo/configure", b"#!/bin/sh\n")
+                .add_file_with_contents("/repo/app", b"\x7FELF\x02\x01\x01");
+            let mut options = ScanOptions {
+                file_system: Box::new(file_system),
+                ..Default::default()
+            };
+
+            let directory = Directory::new(PathBuf::from("/repo"), 0, &options).expect("could not create directory");
+            assert_eq!(directory.count(None), 4);
+
+            options.extensionless = Extensionless::Name;
+            let directory = Directory::new(PathBuf::from("/repo"), 0, &options).expect("could not create directory");
+            assert_eq!((directory.count(Some("Makefile")), directory.count(None)), (1, 0));
+
+            options.extensionless = Extensionless::Content;
+            let directory = Directory::new(PathBuf::from("/repo"), 0, &options).expect("could not create directory");
+            assert_eq!(directory.count(Some("<script>")), 1);
+            assert_eq!(directory.count(Some("<binary>")), 1);
+            assert_eq!(directory.count(None), 2);
+        }
+
+        #[test]
+        fn test_filter() {
+            let options = ScanOptions {
+                max_depth: 1,
+                filter: Some(Filter::parse("size >= 10 && ext != baz").expect("could not parse filter")),
+                ..Default::default()
+            };
+            let directory = Directory::new(PathBuf::from(TESTS_DIR).join("tests"), 0, &options)
+                .expect("could not create directory");
+            assert_eq!(directory.count(Some("baz")), 0);
+            assert_eq!(directory.subdirectories[0].count(Some("foo")), 1);
+            assert_eq!(directory.subdirectories[0].count(Some("bar")), 0);
+            assert_eq!(directory.subdirectories[1].count(Some("foo")), 0);
+            assert_eq!(directory.subdirectories[1].count(None), 1);
+        }
+
+        #[test]
+        fn test_checkpoint() {
+            let root = PathBuf::from(TESTS_DIR).join("tests");
+            let path = std::env::temp_dir().join(format!("rextc-checkpoint-{}", std::process::id()));
+            let _ = std::fs::remove_file(&path);
+
+            // Pretend a previous run already scanned dirA, and found something else there.
+            let dir_a = std::fs::canonicalize(root.join("dirA")).expect("could not canonicalize");
+            Checkpoint::open(&path, &root, 1, "")
+                .expect("could not open checkpoint")
+                .record(&dir_a, &[Extension::new(Some("qux".to_string()), 7)])
+                .expect("could not record directory");
+
+            let options = ScanOptions {
+                max_depth: 1,
+                checkpoint: Some(Checkpoint::open(&path, &root, 1, "").expect("could not open checkpoint")),
+                ..Default::default()
+            };
+            let directory = Directory::new(root.clone(), 0, &options).expect("could not create directory");
+            assert_eq!(directory.subdirectories[0].size(Some("qux")), Some(7));
+            assert_eq!(directory.subdirectories[1].count(Some("foo")), 1);
+            drop(options);
+
+            // The whole tree is now complete, so nothing needs to be scanned again.
+            let options = ScanOptions {
+                max_depth: 1,
+                checkpoint: Some(Checkpoint::open(&path, &root, 1, "").expect("could not open checkpoint")),
+                ..Default::default()
+            };
+            let restored = Directory::new(root.clone(), 0, &options).expect("could not create directory");
+            assert_eq!(restored.count(Some("baz")), 1);
+            assert_eq!(restored.subdirectories.len(), 2);
+            assert!(Checkpoint::open(&path, &root, 2, "").is_err());
+            assert!(Checkpoint::open(&path, &root, 1, "--no-size=true").is_err());
+
+            std::fs::remove_file(&path).expect("could not remove checkpoint");
+        }
+
+        #[test]
+        fn test_display_name() {
+            let directory = tests_dir(2);
+            let root = directory.path();
+            let subsubdirectory = &directory.subdirectories[1].subdirectories[0];
+
+            let name = |paths| {
+                subsubdirectory
+                    .display_name(root, paths)
+                    .expect("invalid directory name")
+            };
+            assert_eq!(name(PathDisplay::Name), "dirC");
+            assert_eq!(name(PathDisplay::Relative), "dirB/dirC");
+            assert_eq!(
+                name(PathDisplay::Absolute),
+                root.join("dirB").join("dirC").to_string_lossy()
+            );
+            assert_eq!(
+                directory
+                    .display_name(root, PathDisplay::Relative)
+                    .expect("invalid name"),
+                "tests"
+            );
+        }
+
         #[test]
         #[ignore = "visual check"]
         fn test_draw() {
             let directory = tests_dir(1);
-            directory.draw(true).expect("could not draw directory");
+            let options = DrawOptions {
+                empty: true,
+                paths: PathDisplay::Name,
+                size: true,
+                mounts: false,
+                dominant: false,
+                hardlinks: false,
+                cloud: false,
+                text_binary: false,
+                baseline: None,
+                color: None,
+                extensionless_label: "N/A".to_string(),
+                media_duration: false,
+                locale: Locale::En,
+                plain: false,
+            };
+            directory.draw(&options).expect("could not draw directory");
+        }
+
+        #[test]
+        fn test_draw_block() {
+            let mut file_system = MemoryFileSystem::default();
+            file_system
+                .add_file("/data/a.txt", 1)
+                .add_file("/data/logs/b.log", 2)
+                .add_file("/data/logs/c.log", 3)
+                .add_file("/data/logs/old/d.log", 4)
+                .add_dir("/data/empty");
+            let options = ScanOptions {
+                max_depth: 1,
+                file_system: Box::new(file_system),
+                ..Default::default()
+            };
+
+            let mut out = Vec::new();
+            let mut draw_options = draw_options();
+            draw_options.size = false;
+            Directory::new_streaming(PathBuf::from("/data"), 0, &options, &mut |d| {
+                d.draw_block(
+                    &mut out,
+                    Path::new("/data"),
+                    &ExtensionSortingMethod::FileCount,
+                    &draw_options,
+                )
+            })
+            .expect("could not create directory");
+            assert_eq!(
+                String::from_utf8(out).expect("invalid UTF-8"),
+                "\
+logs
+└── log ── 3
+
+data
+├── log ── 3
+└── txt ── 1
+
+"
+            );
+        }
+
+        #[test]
+        fn test_draw_to() {
+            let mut file_system = MemoryFileSystem::default();
+            file_system
+                .add_file("/data/a.txt", 1)
+                .add_file("/data/logs/b.log", 2)
+                .add_file("/data/logs/c.log", 3)
+                .add_file("/data/media/d.mp4", 4);
+            let options = ScanOptions {
+                max_depth: 1,
+                file_system: Box::new(file_system),
+                ..Default::default()
+            };
+            let directory = Directory::new(PathBuf::from("/data"), 0, &options).expect("could not create directory");
+
+            let mut out = Vec::new();
+            let mut draw_options = draw_options();
+            draw_options.size = false;
+            directory
+                .draw_to(&mut out, &draw_options)
+                .expect("could not draw directory");
+            assert_eq!(
+                String::from_utf8(out).expect("invalid UTF-8"),
+                "\
+data
+├── txt ── 1
+├── logs
+│   └── log ── 2
+└── media
+    └── mp4 ── 1
+"
+            );
+        }
+
+        #[test]
+        fn test_dominant() {
+            let mut file_system = MemoryFileSystem::default();
+            file_system
+                .add_file("/data/a.txt", 10)
+                .add_file("/data/builds/a.zip", 92)
+                .add_file("/data/builds/b.log", 4)
+                .add_file("/data/builds/c.log", 4);
+            let options = ScanOptions {
+                max_depth: 1,
+                file_system: Box::new(file_system),
+                ..Default::default()
+            };
+            let directory = Directory::new(PathBuf::from("/data"), 0, &options).expect("could not create directory");
+
+            let mut out = Vec::new();
+            let mut draw_options = draw_options();
+            draw_options.dominant = true;
+            draw_options.size = false;
+            directory
+                .draw_to(&mut out, &draw_options)
+                .expect("could not draw directory");
+            assert_eq!(
+                String::from_utf8(out).expect("invalid UTF-8"),
+                "\
+data [log 50%]
+├── txt ── 1
+└── builds [log 67%]
+    ├── zip ── 1
+    └── log ── 2
+"
+            );
+
+            draw_options.size = true;
+            assert_eq!(
+                dominant_formatted(&directory.subdirectories[0].extension_totals(), &draw_options).as_deref(),
+                Some(" [zip 92%]")
+            );
+        }
+
+        #[test]
+        fn test_focus() {
+            let mut file_system = MemoryFileSystem::default();
+            file_system
+                .add_file("/data/a.LOG", 1)
+                .add_file("/data/a.txt", 1)
+                .add_file("/data/app/logs/b.log", 2)
+                .add_file("/data/app/src/main.rs", 3)
+                .add_file("/data/media/d.mp4", 4);
+            let options = ScanOptions {
+                max_depth: 3,
+                file_system: Box::new(file_system),
+                ..Default::default()
+            };
+            let mut directory =
+                Directory::new(PathBuf::from("/data"), 0, &options).expect("could not create directory");
+            directory.focus("log", "N/A");
+
+            let mut out = Vec::new();
+            let mut draw_options = draw_options();
+            draw_options.size = false;
+            directory
+                .draw_to(&mut out, &draw_options)
+                .expect("could not draw directory");
+            assert_eq!(
+                String::from_utf8(out).expect("invalid UTF-8"),
+                "\
+data
+├── LOG ── 1
+└── app
+    └── logs
+        └── log ── 1
+"
+            );
+        }
+
+        #[test]
+        fn test_draw_baseline() {
+            let mut file_system = MemoryFileSystem::default();
+            file_system.add_file("/data/a.rs", 10).add_file("/data/b.rs", 20);
+            let options = ScanOptions {
+                file_system: Box::new(file_system),
+                ..Default::default()
+            };
+            let directory = Directory::new(PathBuf::from("/data"), 0, &options).expect("could not create directory");
+            let mut draw_options = draw_options();
+            draw_options.size = false;
+            draw_options.baseline = Some(BTreeMap::from([(
+                PathBuf::from("/data"),
+                vec![
+                    Extension::new(Some("rs".to_string()), 10),
+                    Extension {
+                        count: 3,
+                        ..Extension::new(Some("log".to_string()), 30)
+                    },
+                ],
+            )]));
+
+            let mut out = Vec::new();
+            directory
+                .draw_to(&mut out, &draw_options)
+                .expect("could not draw directory");
+            assert_eq!(
+                String::from_utf8(out).expect("invalid UTF-8"),
+                "\
+data
+├── rs  ── 2 ── +1 files
+└── log ── 0 ── -3 files
+"
+            );
+        }
+
+        #[test]
+        fn test_draw_hidden_directories() {
+            let mut file_system = MemoryFileSystem::default();
+            file_system
+                .add_file("/repo/web/node_modules/lodash/a.js", 1)
+                .add_symlink("/repo/web/node_modules/zz/link", "/nowhere")
+                .add_file("/repo/web/src/b.ts", 1)
+                .add_file("/repo/web/src/c.js", 1)
+                .add_symlink("/repo/web/tmp/link", "/nowhere")
+                .add_file("/repo/z.js", 1);
+            let options = ScanOptions {
+                max_depth: usize::MAX,
+                file_system: Box::new(file_system),
+                ..Default::default()
+            };
+            let directory = Directory::new(PathBuf::from("/repo"), 0, &options).expect("could not create directory");
+            let mut draw_options = draw_options();
+            draw_options.size = false;
+            let draw = |directory: &Directory| {
+                let mut out = Vec::new();
+                directory
+                    .draw_to(&mut out, &draw_options)
+                    .expect("could not draw directory");
+                String::from_utf8(out).expect("invalid UTF-8")
+            };
+
+            // Directories holding no files are hidden, and so are those without the extension
+            // focused on.
+            assert_eq!(
+                draw(&directory),
+                "\
+repo
+├── js ── 1
+└── web
+    ├── node_modules
+    │   └── lodash
+    │       └── js ── 1
+    └── src
+        ├── ts ── 1
+        └── js ── 1
+"
+            );
+            let mut focused = directory;
+            focused.focus("js", "N/A");
+            assert_eq!(
+                draw(&focused),
+                "\
+repo
+├── js ── 1
+└── web
+    ├── node_modules
+    │   └── lodash
+    │       └── js ── 1
+    └── src
+        └── js ── 1
+"
+            );
         }
     }
 }
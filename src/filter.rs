@@ -0,0 +1,440 @@
+//! Expressions selecting which files are counted, such as
+//!
+//! ```text
+//! size > 100MiB && ext in (mp4, mkv) && mtime < 2023-01-01
+//! ```
+//!
+//! Comparisons have a field on the left and a value on the right:
+//!
+//! * `size` is compared with `==`, `!=`, `<`, `<=`, `>` or `>=` to a size in bytes, optionally
+//!   followed by a binary unit (K, M, G or T).
+//! * `mtime` is compared the same way to a date (`YYYY-MM-DD`, midnight UTC).
+//! * `ext` and `name` are compared with `==` or `!=` to a string, or with `~` to a glob pattern in
+//!   which `*` matches any number of characters and `?` exactly one. Extensions are compared
+//!   without regard to ASCII case, and files without an extension have an empty one.
+//!
+//! `field in (a, b, ...)` holds when any of the values equals the field. Comparisons are combined
+//! with `&&`, `||` and `!`, in decreasing order of precedence, and grouped with parentheses.
+//! Strings containing spaces or operators are quoted with `'` or `"`.
+
+use crate::fs::Metadata;
+use crate::quota::parse_size;
+use anyhow::{anyhow, bail, Context, Result};
+use std::cmp::Ordering;
+use std::path::Path;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Field {
+    Size,
+    Mtime,
+    Ext,
+    Name,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Op {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    Glob,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd)]
+enum Value {
+    Bytes(u64),
+    Time(SystemTime),
+    Text(String),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Expr {
+    And(Box<Expr>, Box<Expr>),
+    Or(Box<Expr>, Box<Expr>),
+    Not(Box<Expr>),
+    Compare(Field, Op, Value),
+    In(Field, Vec<Value>),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Token {
+    Word(String),
+    Quoted(String),
+    Symbol(&'static str),
+}
+
+const SYMBOLS: &[&str] = &[
+    "&&", "||", "==", "!=", "<=", ">=", "<", ">", "=", "~", "!", "(", ")", ",",
+];
+
+fn tokenize(expression: &str) -> Result<Vec<Token>> {
+    let mut tokens = Vec::new();
+    let mut rest = expression.trim_start();
+    while !rest.is_empty() {
+        if let Some(symbol) = SYMBOLS.iter().find(|s| rest.starts_with(*s)) {
+            tokens.push(Token::Symbol(symbol));
+            rest = &rest[symbol.len()..];
+        } else if let Some(quote) = rest.chars().next().filter(|c| *c == '\'' || *c == '"') {
+            let end = rest[1..]
+                .find(quote)
+                .ok_or_else(|| anyhow!("unterminated string {}", rest))?;
+            tokens.push(Token::Quoted(rest[1..=end].to_string()));
+            rest = &rest[end + 2..];
+        } else {
+            let end = rest
+                .find(|c: char| c.is_whitespace() || "&|=!<>~(),'\"".contains(c))
+                .unwrap_or(rest.len());
+            tokens.push(Token::Word(rest[..end].to_string()));
+            rest = &rest[end..];
+        }
+        rest = rest.trim_start();
+    }
+    Ok(tokens)
+}
+
+/// Midnight UTC on the given `YYYY-MM-DD` date.
+pub fn parse_date(date: &str) -> Result<SystemTime> {
+    let parts: Vec<&str> = date.split('-').collect();
+    let (year, month, day): (i64, i64, i64) = match parts[..] {
+        [year, month, day] => (
+            year.parse().with_context(|| format!("invalid year in {}", date))?,
+            month.parse().with_context(|| format!("invalid month in {}", date))?,
+            day.parse().with_context(|| format!("invalid day in {}", date))?,
+        ),
+        _ => bail!("expected a date as YYYY-MM-DD, found {}", date),
+    };
+    if !(1..=12).contains(&month) || !(1..=31).contains(&day) {
+        bail!("invalid date {}", date);
+    }
+
+    // Days since the epoch in the proleptic Gregorian calendar, counting years from March so that
+    // the leap day comes last.
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year.rem_euclid(400);
+    let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    let days = era * 146097 + day_of_era - 719468;
+
+    let seconds = Duration::from_secs(days.unsigned_abs() * 86400);
+    let time = if days >= 0 {
+        UNIX_EPOCH.checked_add(seconds)
+    } else {
+        UNIX_EPOCH.checked_sub(seconds)
+    };
+    time.ok_or_else(|| anyhow!("date out of range {}", date))
+}
+
+/// Whether a file name matches any of the `|`-separated glob patterns, as given to `tree -I` and
+/// `tree -P`.
+pub fn matches_any(patterns: &str, name: &str) -> bool {
+    patterns.split('|').any(|pattern| glob_matches(pattern, name))
+}
+
+/// Whether the text matches a pattern in which `*` matches any number of characters and `?`
+/// exactly one.
+pub(crate) fn glob_matches(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+    let (mut p, mut t) = (0, 0);
+    let mut backtrack = None;
+    while t < text.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                backtrack = Some((p, t));
+                p += 1;
+            }
+            Some(&c) if c == '?' || c == text[t] => {
+                p += 1;
+                t += 1;
+            }
+            _ => match backtrack {
+                Some((star, matched)) => {
+                    p = star + 1;
+                    t = matched + 1;
+                    backtrack = Some((star, matched + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|c| *c == '*')
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    position: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position)
+    }
+
+    fn next(&mut self) -> Result<Token> {
+        let token = self
+            .tokens
+            .get(self.position)
+            .cloned()
+            .ok_or_else(|| anyhow!("unexpected end of expression"))?;
+        self.position += 1;
+        Ok(token)
+    }
+
+    fn eat(&mut self, symbol: &str) -> bool {
+        if matches!(self.peek(), Some(Token::Symbol(s)) if *s == symbol) {
+            self.position += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, symbol: &str) -> Result<()> {
+        if self.eat(symbol) {
+            Ok(())
+        } else {
+            bail!("expected {} at token {}", symbol, self.position + 1)
+        }
+    }
+
+    fn or(&mut self) -> Result<Expr> {
+        let mut expr = self.and()?;
+        while self.eat("||") {
+            expr = Expr::Or(Box::new(expr), Box::new(self.and()?));
+        }
+        Ok(expr)
+    }
+
+    fn and(&mut self) -> Result<Expr> {
+        let mut expr = self.unary()?;
+        while self.eat("&&") {
+            expr = Expr::And(Box::new(expr), Box::new(self.unary()?));
+        }
+        Ok(expr)
+    }
+
+    fn unary(&mut self) -> Result<Expr> {
+        if self.eat("!") {
+            return Ok(Expr::Not(Box::new(self.unary()?)));
+        }
+        if self.eat("(") {
+            let expr = self.or()?;
+            self.expect(")")?;
+            return Ok(expr);
+        }
+        self.comparison()
+    }
+
+    fn comparison(&mut self) -> Result<Expr> {
+        let field = match self.next()? {
+            Token::Word(word) => match word.as_str() {
+                "size" => Field::Size,
+                "mtime" => Field::Mtime,
+                "ext" => Field::Ext,
+                "name" => Field::Name,
+                _ => bail!("unknown field {}, expected size, mtime, ext or name", word),
+            },
+            token => bail!("expected a field, found {:?}", token),
+        };
+
+        if self.peek() == Some(&Token::Word("in".to_string())) {
+            self.position += 1;
+            self.expect("(")?;
+            let mut values = vec![self.value(field)?];
+            while self.eat(",") {
+                values.push(self.value(field)?);
+            }
+            self.expect(")")?;
+            return Ok(Expr::In(field, values));
+        }
+
+        let op = match self.next()? {
+            Token::Symbol("==") | Token::Symbol("=") => Op::Eq,
+            Token::Symbol("!=") => Op::Ne,
+            Token::Symbol("<") => Op::Lt,
+            Token::Symbol("<=") => Op::Le,
+            Token::Symbol(">") => Op::Gt,
+            Token::Symbol(">=") => Op::Ge,
+            Token::Symbol("~") => Op::Glob,
+            token => bail!("expected a comparison, found {:?}", token),
+        };
+        let textual = matches!(field, Field::Ext | Field::Name);
+        match op {
+            Op::Glob if !textual => bail!("~ only applies to ext and name"),
+            Op::Lt | Op::Le | Op::Gt | Op::Ge if textual => bail!("ext and name cannot be ordered"),
+            _ => {}
+        }
+        Ok(Expr::Compare(field, op, self.value(field)?))
+    }
+
+    fn value(&mut self, field: Field) -> Result<Value> {
+        let text = match self.next()? {
+            Token::Word(text) | Token::Quoted(text) => text,
+            token => bail!("expected a value, found {:?}", token),
+        };
+        Ok(match field {
+            Field::Size => Value::Bytes(parse_size(&text)?),
+            Field::Mtime => Value::Time(parse_date(&text)?),
+            Field::Ext => Value::Text(text.to_ascii_lowercase()),
+            Field::Name => Value::Text(text),
+        })
+    }
+}
+
+/// A parsed filter expression, deciding which files are counted.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Filter {
+    expr: Expr,
+}
+
+impl Filter {
+    pub fn parse(expression: &str) -> Result<Self> {
+        let mut parser = Parser {
+            tokens: tokenize(expression)?,
+            position: 0,
+        };
+        let expr = parser.or()?;
+        if parser.position < parser.tokens.len() {
+            bail!(
+                "unexpected {:?} at token {}",
+                parser.tokens[parser.position],
+                parser.position + 1
+            );
+        }
+        Ok(Self { expr })
+    }
+
+    /// Whether the filter looks at file sizes or modification times, which are only known when
+    /// metadata is looked up.
+    pub fn needs_metadata(&self) -> bool {
+        fn visit(expr: &Expr) -> bool {
+            match expr {
+                Expr::And(a, b) | Expr::Or(a, b) => visit(a) || visit(b),
+                Expr::Not(a) => visit(a),
+                Expr::Compare(field, _, _) | Expr::In(field, _) => matches!(field, Field::Size | Field::Mtime),
+            }
+        }
+        visit(&self.expr)
+    }
+
+    pub fn matches(&self, file: &Path, metadata: &Metadata) -> bool {
+        Self::evaluate(&self.expr, file, metadata)
+    }
+
+    fn evaluate(expr: &Expr, file: &Path, metadata: &Metadata) -> bool {
+        let actual = |field: Field| match field {
+            Field::Size => Some(Value::Bytes(metadata.len)),
+            Field::Mtime => metadata.modified.map(Value::Time),
+            Field::Ext => Some(Value::Text(
+                file.extension()
+                    .map(|e| e.to_string_lossy().to_ascii_lowercase())
+                    .unwrap_or_default(),
+            )),
+            Field::Name => Some(Value::Text(
+                file.file_name().unwrap_or_default().to_string_lossy().to_string(),
+            )),
+        };
+
+        match expr {
+            Expr::And(a, b) => Self::evaluate(a, file, metadata) && Self::evaluate(b, file, metadata),
+            Expr::Or(a, b) => Self::evaluate(a, file, metadata) || Self::evaluate(b, file, metadata),
+            Expr::Not(a) => !Self::evaluate(a, file, metadata),
+            Expr::In(field, values) => actual(*field).is_some_and(|actual| values.contains(&actual)),
+            Expr::Compare(field, op, value) => {
+                let actual = match actual(*field) {
+                    Some(actual) => actual,
+                    None => return false,
+                };
+                match (op, &actual, value) {
+                    (Op::Glob, Value::Text(text), Value::Text(pattern)) => glob_matches(pattern, text),
+                    (Op::Glob, _, _) => false,
+                    _ => match actual.partial_cmp(value) {
+                        Some(ordering) => match op {
+                            Op::Eq => ordering == Ordering::Equal,
+                            Op::Ne => ordering != Ordering::Equal,
+                            Op::Lt => ordering == Ordering::Less,
+                            Op::Le => ordering != Ordering::Greater,
+                            Op::Gt => ordering == Ordering::Greater,
+                            Op::Ge => ordering != Ordering::Less,
+                            Op::Glob => unreachable!("handled above"),
+                        },
+                        None => false,
+                    },
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn metadata(len: u64, date: &str) -> Metadata {
+        Metadata {
+            len,
+            modified: Some(parse_date(date).expect("invalid date")),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_parse_date() {
+        let seconds = |date| parse_date(date).ok().and_then(|t| t.duration_since(UNIX_EPOCH).ok());
+        assert_eq!(seconds("1970-01-01"), Some(Duration::ZERO));
+        assert_eq!(seconds("2000-03-01"), Some(Duration::from_secs(951868800)));
+        assert_eq!(seconds("2023-01-01"), Some(Duration::from_secs(1672531200)));
+        assert!(parse_date("2023-13-01").is_err());
+        assert!(parse_date("yesterday").is_err());
+    }
+
+    #[test]
+    fn test_glob_matches() {
+        assert!(glob_matches("IMG_*.jpg", "IMG_0001.jpg"));
+        assert!(glob_matches("*", ""));
+        assert!(glob_matches("a?c*", "abcdef"));
+        assert!(glob_matches("*b*b", "abbab"));
+        assert!(!glob_matches("IMG_*", "DSC_0001.jpg"));
+    }
+
+    #[test]
+    fn test_matches_any() {
+        assert!(matches_any("target|*.tmp", "target"));
+        assert!(matches_any("target|*.tmp", "data.tmp"));
+        assert!(!matches_any("target|*.tmp", "targets"));
+    }
+
+    #[test]
+    fn test_matches() {
+        let filter =
+            Filter::parse("size > 100MiB && ext in (mp4, mkv) && mtime < 2023-01-01").expect("could not parse");
+        let big = 200 * 1024 * 1024;
+        assert!(filter.matches(Path::new("/a/old.MP4"), &metadata(big, "2022-06-01")));
+        assert!(!filter.matches(Path::new("/a/new.mp4"), &metadata(big, "2023-06-01")));
+        assert!(!filter.matches(Path::new("/a/old.avi"), &metadata(big, "2022-06-01")));
+        assert!(!filter.matches(Path::new("/a/small.mkv"), &metadata(1, "2022-06-01")));
+        assert!(filter.needs_metadata());
+
+        let filter = Filter::parse("!(ext == '' || name ~ \"*~\") && ext != tmp").expect("could not parse");
+        assert!(filter.matches(Path::new("/a/notes.txt"), &Metadata::default()));
+        assert!(!filter.matches(Path::new("/a/Makefile"), &Metadata::default()));
+        assert!(!filter.matches(Path::new("/a/notes.txt~"), &Metadata::default()));
+        assert!(!filter.matches(Path::new("/a/x.TMP"), &Metadata::default()));
+        assert!(!filter.needs_metadata());
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert!(Filter::parse("size >").is_err());
+        assert!(Filter::parse("owner == me").is_err());
+        assert!(Filter::parse("ext < mp4").is_err());
+        assert!(Filter::parse("size ~ 1K").is_err());
+        assert!(Filter::parse("(ext == mp4").is_err());
+        assert!(Filter::parse("ext == mp4 ext == mkv").is_err());
+        assert!(Filter::parse("name == 'unterminated").is_err());
+    }
+}
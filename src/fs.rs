@@ -0,0 +1,433 @@
+use crate::dirent;
+pub use crate::dirent::{Entry, EntryKind};
+#[cfg(target_os = "linux")]
+use crate::uring::Ring;
+use std::fmt::Debug;
+use std::io::{self, Read, Seek};
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+/// Metadata of a file, as far as the scan is concerned.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Metadata {
+    /// Size in bytes.
+    pub len: u64,
+
+    /// Number of hard links to the file.
+    pub nlink: u64,
+
+    /// User ID of the owner, 0 on platforms without file ownership.
+    pub uid: u32,
+
+    /// Permission bits, including setuid, setgid and sticky bits. 0 on platforms without them.
+    pub mode: u32,
+
+    /// Time of the last modification, if the platform records it.
+    pub modified: Option<SystemTime>,
+
+    /// Whether the file is a placeholder of a cloud storage client, such as OneDrive Files
+    /// On-Demand or iCloud Drive, whose contents are only downloaded when accessed. Its length is
+    /// then that of the file in the cloud, while it takes up almost no space locally.
+    pub placeholder: bool,
+}
+
+/// Attributes of files whose contents are not stored locally, see GetFileAttributes.
+#[cfg(windows)]
+const OFFLINE_ATTRIBUTES: u32 = 0x1000 // FILE_ATTRIBUTE_OFFLINE
+    | 0x40000 // FILE_ATTRIBUTE_RECALL_ON_OPEN
+    | 0x400000; // FILE_ATTRIBUTE_RECALL_ON_DATA_ACCESS
+
+/// Flag of files whose contents have been evicted to the cloud, see chflags(2).
+#[cfg(target_os = "macos")]
+const SF_DATALESS: u32 = 0x40000000;
+
+/// Whether the file described by the metadata is a cloud placeholder.
+fn is_placeholder(metadata: &std::fs::Metadata) -> bool {
+    #[cfg(windows)]
+    {
+        use std::os::windows::fs::MetadataExt;
+        metadata.file_attributes() & OFFLINE_ATTRIBUTES != 0
+    }
+
+    #[cfg(target_os = "macos")]
+    {
+        use std::os::macos::fs::MetadataExt;
+        metadata.st_flags() & SF_DATALESS != 0
+    }
+
+    #[cfg(not(any(windows, target_os = "macos")))]
+    {
+        let _ = metadata;
+        false
+    }
+}
+
+/// Everything the scan needs from a file system. The operating system's file system is used by
+/// default, other implementations allow scanning archives, remote storage or in-memory trees.
+pub trait FileSystem: Debug {
+    /// Absolute form of the path, with all symlinks resolved.
+    fn canonicalize(&self, path: &Path) -> io::Result<PathBuf>;
+
+    /// Entries of a directory, excluding `.` and `..`.
+    fn read_dir(&self, dir: &Path) -> io::Result<Vec<Entry>>;
+
+    /// Metadata of a file or directory, following symlinks.
+    fn metadata(&self, path: &Path) -> io::Result<Metadata>;
+
+    /// Kind of entry found at the path, without following symlinks.
+    fn file_type(&self, path: &Path) -> io::Result<EntryKind>;
+
+    /// Target of a symlink, as stored in the link.
+    fn read_link(&self, link: &Path) -> io::Result<PathBuf>;
+
+    /// Up to `len` bytes at an offset of a file, fewer when the end of the file is reached.
+    fn read_at(&self, file: &Path, offset: u64, len: usize) -> io::Result<Vec<u8>>;
+
+    /// Up to `len` bytes from the start of a file.
+    fn read_header(&self, file: &Path, len: usize) -> io::Result<Vec<u8>> {
+        self.read_at(file, 0, len)
+    }
+
+    /// Identifier of the file system holding the path, shared by all paths on the same mount.
+    /// Implementations without mounts report everything on a single file system.
+    fn device(&self, path: &Path) -> io::Result<u64> {
+        let _ = path;
+        Ok(0)
+    }
+
+    /// Type of the file system mounted on the directory, if one is mounted there.
+    fn mount_type(&self, dir: &Path) -> io::Result<Option<String>> {
+        let _ = dir;
+        Ok(None)
+    }
+
+    /// Metadata of files found in the same directory. Implementations able to look up several
+    /// files at once should override this.
+    fn metadata_batch(&self, files: &[PathBuf]) -> Vec<io::Result<Metadata>> {
+        files.iter().map(|file| self.metadata(file)).collect()
+    }
+}
+
+impl Default for Box<dyn FileSystem> {
+    fn default() -> Self {
+        Box::new(OsFileSystem::default())
+    }
+}
+
+/// The file system of the operating system.
+#[derive(Debug, Default)]
+pub struct OsFileSystem {
+    /// io_uring instance through which file sizes are looked up in batches.
+    #[cfg(target_os = "linux")]
+    pub ring: Option<Ring>,
+}
+
+impl FileSystem for OsFileSystem {
+    fn canonicalize(&self, path: &Path) -> io::Result<PathBuf> {
+        std::fs::canonicalize(path)
+    }
+
+    fn read_dir(&self, dir: &Path) -> io::Result<Vec<Entry>> {
+        dirent::read_dir(dir)
+    }
+
+    fn metadata(&self, path: &Path) -> io::Result<Metadata> {
+        let metadata = std::fs::metadata(path)?;
+        #[cfg(unix)]
+        let (nlink, uid, mode) = {
+            use std::os::unix::fs::MetadataExt;
+            (metadata.nlink(), metadata.uid(), metadata.mode() & 0o7777)
+        };
+        #[cfg(not(unix))]
+        let (nlink, uid, mode) = (1, 0, 0);
+        Ok(Metadata {
+            len: metadata.len(),
+            nlink,
+            uid,
+            mode,
+            modified: metadata.modified().ok(),
+            placeholder: is_placeholder(&metadata),
+        })
+    }
+
+    fn file_type(&self, path: &Path) -> io::Result<EntryKind> {
+        Ok(std::fs::symlink_metadata(path)?.file_type().into())
+    }
+
+    fn read_link(&self, link: &Path) -> io::Result<PathBuf> {
+        std::fs::read_link(link)
+    }
+
+    fn read_at(&self, file: &Path, offset: u64, len: usize) -> io::Result<Vec<u8>> {
+        let mut file = std::fs::File::open(file)?;
+        file.seek(io::SeekFrom::Start(offset))?;
+        let mut bytes = Vec::with_capacity(len);
+        file.take(len as u64).read_to_end(&mut bytes)?;
+        Ok(bytes)
+    }
+
+    #[cfg(unix)]
+    fn device(&self, path: &Path) -> io::Result<u64> {
+        use std::os::unix::fs::MetadataExt;
+        Ok(std::fs::metadata(path)?.dev())
+    }
+
+    fn mount_type(&self, dir: &Path) -> io::Result<Option<String>> {
+        Ok(crate::storage::mount_type(dir))
+    }
+
+    fn metadata_batch(&self, files: &[PathBuf]) -> Vec<io::Result<Metadata>> {
+        #[cfg(target_os = "linux")]
+        if let Some(ring) = &self.ring {
+            return ring
+                .metadata(files)
+                .into_iter()
+                .zip(files)
+                .map(|(metadata, file)| metadata.or_else(|_| self.metadata(file)))
+                .collect();
+        }
+
+        files.iter().map(|file| self.metadata(file)).collect()
+    }
+}
+
+/// A file system held entirely in memory, for tests.
+#[cfg(test)]
+#[derive(Debug, Default)]
+pub struct MemoryFileSystem {
+    /// Every file and directory, keyed by absolute path. Files have metadata, directories do not.
+    nodes: std::collections::BTreeMap<PathBuf, Option<Metadata>>,
+
+    /// Devices of the directories file systems are mounted on, everything else is on device 0.
+    mounts: std::collections::BTreeMap<PathBuf, u64>,
+
+    /// Permission bits of directories, 755 unless set otherwise.
+    directory_modes: std::collections::BTreeMap<PathBuf, u32>,
+
+    /// Contents of files, which are empty unless set otherwise.
+    contents: std::collections::BTreeMap<PathBuf, Vec<u8>>,
+
+    /// Symlinks and their targets, relative to the directory of the link unless absolute.
+    symlinks: std::collections::BTreeMap<PathBuf, PathBuf>,
+
+    /// Junctions and their absolute targets, which are resolved when canonicalizing.
+    junctions: std::collections::BTreeMap<PathBuf, PathBuf>,
+}
+
+#[cfg(test)]
+impl MemoryFileSystem {
+    /// Add a file of the given size, along with any missing parent directories.
+    pub fn add_file(&mut self, path: impl Into<PathBuf>, len: u64) -> &mut Self {
+        let path = path.into();
+        self.add_dir(path.parent().expect("files have a parent"));
+        self.nodes.insert(
+            path,
+            Some(Metadata {
+                len,
+                nlink: 1,
+                uid: 0,
+                mode: 0o644,
+                modified: None,
+                placeholder: false,
+            }),
+        );
+        self
+    }
+
+    /// Add a file with the given contents, along with any missing parent directories.
+    pub fn add_file_with_contents(&mut self, path: impl Into<PathBuf>, contents: &[u8]) -> &mut Self {
+        let path = path.into();
+        self.add_file(path.clone(), contents.len() as u64);
+        self.contents.insert(path, contents.to_vec());
+        self
+    }
+
+    /// Add a symlink to the given target, which need not exist.
+    pub fn add_symlink(&mut self, path: impl Into<PathBuf>, target: impl Into<PathBuf>) -> &mut Self {
+        let path = path.into();
+        self.add_dir(path.parent().expect("symlinks have a parent"));
+        self.symlinks.insert(path, target.into());
+        self
+    }
+
+    /// Add a junction to the given directory.
+    pub fn add_junction(&mut self, path: impl Into<PathBuf>, target: impl Into<PathBuf>) -> &mut Self {
+        let path = path.into();
+        self.add_dir(path.parent().expect("junctions have a parent"));
+        self.junctions.insert(path, target.into());
+        self
+    }
+
+    /// Make an existing file a cloud placeholder.
+    pub fn evict(&mut self, path: impl AsRef<Path>) -> &mut Self {
+        if let Some(Some(metadata)) = self.nodes.get_mut(path.as_ref()) {
+            metadata.placeholder = true;
+        }
+        self
+    }
+
+    /// Set the number of hard links to an existing file.
+    pub fn link(&mut self, path: impl AsRef<Path>, nlink: u64) -> &mut Self {
+        if let Some(Some(metadata)) = self.nodes.get_mut(path.as_ref()) {
+            metadata.nlink = nlink;
+        }
+        self
+    }
+
+    /// Add a directory, along with any missing parent directories.
+    pub fn add_dir(&mut self, path: impl AsRef<Path>) -> &mut Self {
+        for ancestor in path.as_ref().ancestors() {
+            self.nodes.entry(ancestor.to_path_buf()).or_insert(None);
+        }
+        self
+    }
+
+    /// Set the owner of an existing file.
+    pub fn chown(&mut self, path: impl AsRef<Path>, uid: u32) -> &mut Self {
+        if let Some(Some(metadata)) = self.nodes.get_mut(path.as_ref()) {
+            metadata.uid = uid;
+        }
+        self
+    }
+
+    /// Set the permission bits of an existing file or directory.
+    pub fn chmod(&mut self, path: impl AsRef<Path>, mode: u32) -> &mut Self {
+        match self.nodes.get_mut(path.as_ref()) {
+            Some(Some(metadata)) => metadata.mode = mode,
+            Some(None) => {
+                self.directory_modes.insert(path.as_ref().to_path_buf(), mode);
+            }
+            None => {}
+        }
+        self
+    }
+
+    /// Mount another file system on a directory, creating it if needed.
+    pub fn mount(&mut self, path: impl AsRef<Path>, device: u64) -> &mut Self {
+        self.add_dir(path.as_ref());
+        self.mounts.insert(path.as_ref().to_path_buf(), device);
+        self
+    }
+
+    fn node(&self, path: &Path) -> io::Result<&Option<Metadata>> {
+        self.nodes
+            .get(path)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, path.display().to_string()))
+    }
+}
+
+#[cfg(test)]
+impl FileSystem for MemoryFileSystem {
+    fn canonicalize(&self, path: &Path) -> io::Result<PathBuf> {
+        for (junction, target) in self.junctions.iter() {
+            if let Ok(rest) = path.strip_prefix(junction) {
+                return self.canonicalize(&target.join(rest));
+            }
+        }
+        self.node(path).map(|_| path.to_path_buf())
+    }
+
+    fn read_dir(&self, dir: &Path) -> io::Result<Vec<Entry>> {
+        if self.node(dir)?.is_some() {
+            return Err(io::Error::other("not a directory"));
+        }
+        let nodes = self.nodes.iter().map(|(path, node)| Entry {
+            path: path.clone(),
+            kind: if node.is_some() {
+                EntryKind::File
+            } else {
+                EntryKind::Directory
+            },
+        });
+        let symlinks = self.symlinks.keys().map(|path| Entry {
+            path: path.clone(),
+            kind: EntryKind::Symlink,
+        });
+        let junctions = self.junctions.keys().map(|path| Entry {
+            path: path.clone(),
+            kind: EntryKind::Junction,
+        });
+        Ok(nodes
+            .chain(symlinks)
+            .chain(junctions)
+            .filter(|entry| entry.path.parent() == Some(dir))
+            .collect())
+    }
+
+    fn metadata(&self, path: &Path) -> io::Result<Metadata> {
+        if let Some(target) = self.symlinks.get(path) {
+            return self.metadata(&path.parent().expect("symlinks have a parent").join(target));
+        }
+        Ok(self.node(path)?.clone().unwrap_or_else(|| Metadata {
+            nlink: 1,
+            mode: self.directory_modes.get(path).copied().unwrap_or(0o755),
+            ..Default::default()
+        }))
+    }
+
+    fn file_type(&self, path: &Path) -> io::Result<EntryKind> {
+        if self.symlinks.contains_key(path) {
+            return Ok(EntryKind::Symlink);
+        }
+        if self.junctions.contains_key(path) {
+            return Ok(EntryKind::Junction);
+        }
+        Ok(match self.node(path)? {
+            Some(_) => EntryKind::File,
+            None => EntryKind::Directory,
+        })
+    }
+
+    fn read_link(&self, link: &Path) -> io::Result<PathBuf> {
+        self.symlinks
+            .get(link)
+            .cloned()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "not a symlink"))
+    }
+
+    fn read_at(&self, file: &Path, offset: u64, len: usize) -> io::Result<Vec<u8>> {
+        if self.node(file)?.is_none() {
+            return Err(io::Error::other("is a directory"));
+        }
+        let contents = self.contents.get(file).map(Vec::as_slice).unwrap_or_default();
+        let start = (offset as usize).min(contents.len());
+        Ok(contents[start..(start + len).min(contents.len())].to_vec())
+    }
+
+    fn device(&self, path: &Path) -> io::Result<u64> {
+        self.node(path)?;
+        Ok(path
+            .ancestors()
+            .find_map(|ancestor| self.mounts.get(ancestor))
+            .copied()
+            .unwrap_or(0))
+    }
+
+    fn mount_type(&self, dir: &Path) -> io::Result<Option<String>> {
+        Ok(self.mounts.get(dir).map(|_| "memfs".to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_memory_file_system() {
+        let mut fs = MemoryFileSystem::default();
+        fs.add_file("/root/a.rs", 3)
+            .add_file("/root/sub/b.rs", 4)
+            .add_dir("/root/empty");
+
+        let mut entries = fs.read_dir(Path::new("/root")).expect("could not read directory");
+        entries.sort_unstable_by(|e1, e2| e1.path.cmp(&e2.path));
+        let kinds: Vec<_> = entries.iter().map(|e| e.kind).collect();
+        assert_eq!(kinds, vec![EntryKind::File, EntryKind::Directory, EntryKind::Directory]);
+        assert_eq!(fs.metadata(Path::new("/root/sub/b.rs")).map(|m| m.len).ok(), Some(4));
+        assert!(fs.metadata(Path::new("/root/missing")).is_err());
+
+        fs.mount("/root/sub", 1);
+        assert_eq!(fs.device(Path::new("/root/a.rs")).ok(), Some(0));
+        assert_eq!(fs.device(Path::new("/root/sub/b.rs")).ok(), Some(1));
+    }
+}
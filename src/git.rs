@@ -0,0 +1,311 @@
+//! Files of a git repository as recorded by git rather than found in the working tree, read with
+//! the `git` command and counted as a listing: the files staged in the index, so that filters and
+//! budgets can be checked from a pre-commit hook against only what is being committed, or the
+//! files of any revision, without checking it out. Sizes are those of the contents stored by git.
+//!
+//! The files changed since a commit are instead scanned in the working tree, through a file system
+//! hiding every other file.
+
+use crate::dirent::{Entry, EntryKind};
+use crate::fs::{FileSystem, Metadata};
+use crate::listing::{file_metadata, ListingFileSystem};
+use anyhow::{anyhow, bail, Context, Result};
+use std::collections::HashSet;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+/// Modes of the regular files git tracks, as opposed to symlinks and submodules.
+const FILE_MODES: [&str; 2] = ["100644", "100755"];
+
+/// Run git in a directory with the given arguments and input, and return its output.
+fn git(dir: &Path, args: &[&str], input: &[u8]) -> Result<Vec<u8>> {
+    let mut child = Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .context("could not run git")?;
+    let mut stdin = child.stdin.take().expect("stdin is piped");
+    let input = input.to_vec();
+    // Feed git from another thread, so that neither side blocks on a full pipe.
+    let feeder = std::thread::spawn(move || stdin.write_all(&input));
+    let output = child.wait_with_output()?;
+    feeder.join().expect("the feeder does not panic")?;
+    if !output.status.success() {
+        bail!(
+            "git {} failed: {}",
+            args.join(" "),
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(output.stdout)
+}
+
+/// Object IDs and paths of the regular files added or modified in the output of
+/// `git diff --raw -z`, in which each change is a `:<old mode> <new mode> <old id> <new id>
+/// <status>` field followed by a path field.
+fn parse_raw_diff(output: &[u8]) -> Result<Vec<(String, PathBuf)>> {
+    let fields: Vec<&[u8]> = output.split(|&b| b == 0).filter(|f| !f.is_empty()).collect();
+    let mut staged = Vec::new();
+    for change in fields.chunks(2) {
+        let (status, path) = match change {
+            [status, path] => (String::from_utf8_lossy(status), String::from_utf8_lossy(path)),
+            _ => bail!("unexpected output of git diff"),
+        };
+        match status.trim_start_matches(':').split(' ').collect::<Vec<_>>()[..] {
+            [_, mode, _, id, _] if FILE_MODES.contains(&mode) => {
+                staged.push((id.to_string(), PathBuf::from(path.as_ref())));
+            }
+            [_, _, _, _, _] => {}
+            _ => bail!("unexpected output of git diff: {}", status),
+        }
+    }
+    Ok(staged)
+}
+
+/// Sizes of objects in the output of `git cat-file --batch-check`, one `<id> <type> <size>` line
+/// per object.
+fn parse_batch_check(output: &str) -> Result<Vec<u64>> {
+    output
+        .lines()
+        .map(|line| match line.split(' ').collect::<Vec<_>>()[..] {
+            [_, _, size] => size.parse().with_context(|| format!("invalid size in {}", line)),
+            _ => bail!("could not look up {}", line),
+        })
+        .collect()
+}
+
+/// Object sizes and paths of the regular files in the output of `git ls-tree -r -l -z`, in which
+/// each file is a `<mode> <type> <id> <size>\t<path>` field.
+fn parse_ls_tree(output: &[u8]) -> Result<Vec<(u64, PathBuf)>> {
+    let mut files = Vec::new();
+    for entry in output.split(|&b| b == 0).filter(|f| !f.is_empty()) {
+        let entry = String::from_utf8_lossy(entry);
+        let (object, path) = entry
+            .split_once('\t')
+            .ok_or_else(|| anyhow!("unexpected output of git ls-tree: {}", entry))?;
+        match object.split_whitespace().collect::<Vec<_>>()[..] {
+            [mode, _, _, size] if FILE_MODES.contains(&mode) => {
+                files.push((size.parse()?, PathBuf::from(path)));
+            }
+            [_, _, _, _] => {}
+            _ => bail!("unexpected output of git ls-tree: {}", entry),
+        }
+    }
+    Ok(files)
+}
+
+/// An empty listing named after a directory.
+fn listing_of(dir: &Path) -> ListingFileSystem {
+    let name = std::fs::canonicalize(dir)
+        .ok()
+        .and_then(|dir| dir.file_name().map(|name| name.to_os_string()))
+        .unwrap_or_else(|| "git".into());
+    ListingFileSystem::new(Path::new("/").join(name))
+}
+
+/// Read the regular files added or modified in the index of the repository holding a directory,
+/// relative to it and with the size of their staged contents. Renamed files count as added.
+pub fn read_staged(dir: &Path) -> Result<ListingFileSystem> {
+    let diff = git(
+        dir,
+        &[
+            "diff",
+            "--cached",
+            "--raw",
+            "--no-abbrev",
+            "-z",
+            "--no-renames",
+            "--relative",
+            "--diff-filter=AMT",
+        ],
+        &[],
+    )?;
+    let staged = parse_raw_diff(&diff)?;
+    let ids: String = staged.iter().map(|(id, _)| format!("{}\n", id)).collect();
+    let sizes = parse_batch_check(&String::from_utf8_lossy(&git(
+        dir,
+        &["cat-file", "--batch-check"],
+        ids.as_bytes(),
+    )?))?;
+
+    let mut listing = listing_of(dir);
+    for ((_, path), size) in staged.iter().zip(sizes) {
+        listing.add_file(path, file_metadata(size));
+    }
+    Ok(listing)
+}
+
+/// Read the regular files of a revision below a directory of its repository, relative to it, from
+/// the tree objects of the revision.
+pub fn read_revision(dir: &Path, revision: &str) -> Result<ListingFileSystem> {
+    if revision.starts_with('-') {
+        bail!("invalid revision {}", revision);
+    }
+    let tree = git(dir, &["ls-tree", "-r", "-l", "-z", revision, "--"], &[])?;
+    let mut listing = listing_of(dir);
+    for (size, path) in parse_ls_tree(&tree)? {
+        listing.add_file(&path, file_metadata(size));
+    }
+    Ok(listing)
+}
+
+/// A file system showing only some files of another one, along with the directories leading to
+/// them.
+#[derive(Debug)]
+pub struct ChangedFileSystem {
+    file_system: Box<dyn FileSystem>,
+
+    /// Absolute paths of the files shown and of their ancestors.
+    shown: HashSet<PathBuf>,
+}
+
+impl ChangedFileSystem {
+    pub fn new(file_system: Box<dyn FileSystem>, files: impl IntoIterator<Item = PathBuf>) -> Self {
+        let mut shown = HashSet::new();
+        for file in files {
+            shown.extend(file.ancestors().map(Path::to_path_buf));
+        }
+        Self { file_system, shown }
+    }
+}
+
+impl FileSystem for ChangedFileSystem {
+    fn canonicalize(&self, path: &Path) -> io::Result<PathBuf> {
+        self.file_system.canonicalize(path)
+    }
+
+    fn read_dir(&self, dir: &Path) -> io::Result<Vec<Entry>> {
+        let mut entries = self.file_system.read_dir(dir)?;
+        entries.retain(|entry| self.shown.contains(&entry.path));
+        Ok(entries)
+    }
+
+    fn metadata(&self, path: &Path) -> io::Result<Metadata> {
+        self.file_system.metadata(path)
+    }
+
+    fn file_type(&self, path: &Path) -> io::Result<EntryKind> {
+        self.file_system.file_type(path)
+    }
+
+    fn read_link(&self, link: &Path) -> io::Result<PathBuf> {
+        self.file_system.read_link(link)
+    }
+
+    fn read_at(&self, file: &Path, offset: u64, len: usize) -> io::Result<Vec<u8>> {
+        self.file_system.read_at(file, offset, len)
+    }
+
+    fn device(&self, path: &Path) -> io::Result<u64> {
+        self.file_system.device(path)
+    }
+
+    fn mount_type(&self, dir: &Path) -> io::Result<Option<String>> {
+        self.file_system.mount_type(dir)
+    }
+
+    fn metadata_batch(&self, files: &[PathBuf]) -> Vec<io::Result<Metadata>> {
+        self.file_system.metadata_batch(files)
+    }
+}
+
+/// Show only the files below a directory that were added or modified since a commit, whether
+/// committed, staged or not, as listed by `git diff --name-only`. Untracked files are left out.
+pub fn read_changed(dir: &Path, commit: &str, file_system: Box<dyn FileSystem>) -> Result<ChangedFileSystem> {
+    if commit.starts_with('-') {
+        bail!("invalid commit {}", commit);
+    }
+    let diff = git(
+        dir,
+        &[
+            "diff",
+            "--name-only",
+            "-z",
+            "--no-renames",
+            "--relative",
+            "--diff-filter=d",
+            commit,
+            "--",
+        ],
+        &[],
+    )?;
+    let dir = file_system.canonicalize(dir)?;
+    let files = diff
+        .split(|&b| b == 0)
+        .filter(|f| !f.is_empty())
+        .map(|path| dir.join(String::from_utf8_lossy(path).as_ref()));
+    Ok(ChangedFileSystem::new(file_system, files))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fs::MemoryFileSystem;
+
+    #[test]
+    fn test_parse_raw_diff() {
+        let output = b":000000 100644 0000000 8baef1b A\0docs/new file.md\0\
+            :100644 100644 8baef1b 2d0e4f1 M\0src/main.rs\0\
+            :000000 120000 0000000 3c4e9cd A\0link\0\
+            :000000 160000 0000000 9f1a2b3 A\0vendor/lib\0";
+        assert_eq!(
+            parse_raw_diff(output).expect("could not parse diff"),
+            vec![
+                ("8baef1b".to_string(), PathBuf::from("docs/new file.md")),
+                ("2d0e4f1".to_string(), PathBuf::from("src/main.rs")),
+            ]
+        );
+        assert!(parse_raw_diff(b":100644 M\0a\0").is_err());
+    }
+
+    #[test]
+    fn test_parse_ls_tree() {
+        let output = b"100644 blob 422c2b7       4\ta.txt\0\
+            100755 blob b9412b1    3000\tsub/run me.sh\0\
+            120000 blob 3c4e9cd      11\tlink\0\
+            160000 commit 9f1a2b3       -\tvendor/lib\0";
+        assert_eq!(
+            parse_ls_tree(output).expect("could not parse tree"),
+            vec![(4, PathBuf::from("a.txt")), (3000, PathBuf::from("sub/run me.sh"))]
+        );
+        assert!(parse_ls_tree(b"100644 blob 422c2b7 4 a.txt\0").is_err());
+    }
+
+    #[test]
+    fn test_changed_file_system() {
+        let mut file_system = MemoryFileSystem::default();
+        file_system
+            .add_file("/repo/a.txt", 1)
+            .add_file("/repo/src/b.rs", 2)
+            .add_file("/repo/src/c.rs", 3)
+            .add_file("/repo/docs/d.md", 4);
+        let changed = ChangedFileSystem::new(Box::new(file_system), [PathBuf::from("/repo/src/b.rs")]);
+
+        let names = |dir: &str| -> Vec<PathBuf> {
+            let mut names: Vec<_> = changed
+                .read_dir(Path::new(dir))
+                .expect("could not read directory")
+                .into_iter()
+                .map(|e| e.path)
+                .collect();
+            names.sort();
+            names
+        };
+        assert_eq!(names("/repo"), vec![PathBuf::from("/repo/src")]);
+        assert_eq!(names("/repo/src"), vec![PathBuf::from("/repo/src/b.rs")]);
+    }
+
+    #[test]
+    fn test_parse_batch_check() {
+        assert_eq!(
+            parse_batch_check("8baef1b blob 120\n2d0e4f1 blob 7\n").ok(),
+            Some(vec![120, 7])
+        );
+        assert!(parse_batch_check("8baef1b missing\n").is_err());
+    }
+}
@@ -0,0 +1,215 @@
+//! Dimensions other than the extension that files can be grouped by with `--group-by`.
+
+use crate::fs::Metadata;
+use clap::ArgEnum;
+use std::collections::HashMap;
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// What files are grouped by in the tree.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ArgEnum)]
+pub enum GroupBy {
+    /// File extension, subject to --languages, --extensionless and --normalize.
+    #[default]
+    Extension,
+
+    /// Name of the owning user, or their user ID if it has no name.
+    Owner,
+
+    /// Year of the last modification, in UTC.
+    MtimeYear,
+
+    /// Year and month of the last modification, in UTC.
+    MtimeMonth,
+
+    /// Directory directly below the scanned one that the file is in.
+    TopLevelDir,
+
+    /// Kind of content, such as image or code, guessed from the extension.
+    Category,
+}
+
+/// Categories and the extensions that belong to them.
+const CATEGORIES: &[(&str, &[&str])] = &[
+    (
+        "image",
+        &[
+            "jpg", "jpeg", "png", "gif", "bmp", "tif", "tiff", "webp", "heic", "heif", "avif", "svg", "ico", "psd",
+            "raw", "cr2", "nef", "dng",
+        ],
+    ),
+    (
+        "video",
+        &[
+            "mp4", "m4v", "mov", "mkv", "avi", "webm", "wmv", "flv", "mpg", "mpeg", "3gp",
+        ],
+    ),
+    (
+        "audio",
+        &[
+            "mp3", "m4a", "wav", "flac", "ogg", "oga", "opus", "aac", "wma", "aiff", "mid",
+        ],
+    ),
+    (
+        "document",
+        &[
+            "pdf", "doc", "docx", "odt", "rtf", "txt", "md", "rst", "tex", "epub", "xls", "xlsx", "ods", "csv", "ppt",
+            "pptx", "odp",
+        ],
+    ),
+    (
+        "archive",
+        &[
+            "zip", "tar", "gz", "tgz", "bz2", "xz", "zst", "7z", "rar", "lz4", "iso", "dmg", "deb", "rpm",
+        ],
+    ),
+    (
+        "code",
+        &[
+            "rs", "c", "h", "cc", "cpp", "hpp", "py", "js", "ts", "jsx", "tsx", "java", "kt", "go", "rb", "php", "cs",
+            "swift", "scala", "hs", "lua", "pl", "sh", "bash", "ps1", "html", "css", "scss", "json", "yaml", "yml",
+            "toml", "xml", "sql",
+        ],
+    ),
+    ("font", &["ttf", "otf", "woff", "woff2"]),
+    (
+        "executable",
+        &["exe", "dll", "so", "dylib", "bin", "msi", "apk", "app", "o", "a"],
+    ),
+];
+
+/// Category of files with the given extension, compared case-insensitively. Extensions that
+/// belong to no category, and files without one, are in the "other" category.
+pub fn category(extension: Option<&str>) -> &'static str {
+    let extension = match extension {
+        Some(extension) => extension.to_lowercase(),
+        None => return "other",
+    };
+    CATEGORIES
+        .iter()
+        .find(|(_, extensions)| extensions.contains(&extension.as_str()))
+        .map_or("other", |(category, _)| category)
+}
+
+/// Whether a name is that of a category, including "other".
+pub fn is_category(name: &str) -> bool {
+    name == "other" || CATEGORIES.iter().any(|(category, _)| *category == name)
+}
+
+/// Year and month (1 to 12) of a time, in UTC.
+pub fn year_month(time: SystemTime) -> (i64, u32) {
+    let seconds = match time.duration_since(UNIX_EPOCH) {
+        Ok(since) => since.as_secs() as i64,
+        Err(before) => -(before.duration().as_secs() as i64),
+    };
+
+    // Inverse of the conversion in `filter::parse_date`, with years starting in March.
+    let days = seconds.div_euclid(86400) + 719468;
+    let era = days.div_euclid(146097);
+    let day_of_era = days.rem_euclid(146097);
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_from_march = (5 * day_of_year + 2) / 153;
+    let month = if month_from_march < 10 {
+        month_from_march + 3
+    } else {
+        month_from_march - 9
+    };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    (year, month as u32)
+}
+
+/// User names by user ID in a passwd(5) database.
+pub fn parse_user_names(passwd: &str) -> HashMap<u32, String> {
+    passwd
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.split(':');
+            let name = fields.next()?;
+            let uid = fields.nth(1)?.parse().ok()?;
+            Some((uid, name.to_string()))
+        })
+        .collect()
+}
+
+/// User names by user ID in `/etc/passwd`, empty where it cannot be read.
+pub fn user_names() -> HashMap<u32, String> {
+    parse_user_names(&std::fs::read_to_string("/etc/passwd").unwrap_or_default())
+}
+
+impl GroupBy {
+    /// Group of a file below `scan_root` for any grouping other than by extension. Files directly
+    /// in the scanned directory have no top-level directory, and files without a modification
+    /// time no year or month.
+    pub fn key(
+        self,
+        file: &Path,
+        metadata: &Metadata,
+        scan_root: &Path,
+        user_names: &HashMap<u32, String>,
+    ) -> Option<String> {
+        match self {
+            GroupBy::Extension => file.extension().map(|e| e.to_string_lossy().into_owned()),
+            GroupBy::Owner => Some(
+                user_names
+                    .get(&metadata.uid)
+                    .cloned()
+                    .unwrap_or_else(|| metadata.uid.to_string()),
+            ),
+            GroupBy::MtimeYear => metadata.modified.map(|time| year_month(time).0.to_string()),
+            GroupBy::MtimeMonth => metadata.modified.map(|time| {
+                let (year, month) = year_month(time);
+                format!("{}-{:02}", year, month)
+            }),
+            GroupBy::TopLevelDir => {
+                let relative = file.strip_prefix(scan_root).ok()?;
+                let parent = relative.parent()?;
+                parent
+                    .components()
+                    .next()
+                    .map(|dir| dir.as_os_str().to_string_lossy().into_owned())
+            }
+            GroupBy::Category => {
+                let extension = file.extension().map(|e| e.to_string_lossy());
+                Some(category(extension.as_deref()).to_string())
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::filter::parse_date;
+
+    #[test]
+    fn test_year_month() {
+        for (date, expected) in [
+            ("1970-01-01", (1970, 1)),
+            ("2000-02-29", (2000, 2)),
+            ("2023-12-31", (2023, 12)),
+            ("1969-07-20", (1969, 7)),
+        ] {
+            assert_eq!(year_month(parse_date(date).unwrap()), expected, "{}", date);
+        }
+    }
+
+    #[test]
+    fn test_key() {
+        let metadata = Metadata {
+            uid: 1000,
+            modified: Some(parse_date("2021-03-04").unwrap()),
+            ..Metadata::default()
+        };
+        let names = parse_user_names("root:x:0:0:root:/root:/bin/sh\nalice:x:1000:1000::/home/alice:/bin/sh\n");
+        let key = |group_by: GroupBy, file: &str| group_by.key(Path::new(file), &metadata, Path::new("/r"), &names);
+
+        assert_eq!(key(GroupBy::Owner, "/r/a.rs").as_deref(), Some("alice"));
+        assert_eq!(key(GroupBy::MtimeYear, "/r/a.rs").as_deref(), Some("2021"));
+        assert_eq!(key(GroupBy::MtimeMonth, "/r/a.rs").as_deref(), Some("2021-03"));
+        assert_eq!(key(GroupBy::TopLevelDir, "/r/src/bin/a.rs").as_deref(), Some("src"));
+        assert_eq!(key(GroupBy::TopLevelDir, "/r/a.rs"), None);
+        assert_eq!(key(GroupBy::Category, "/r/photo.JPG").as_deref(), Some("image"));
+        assert_eq!(key(GroupBy::Category, "/r/Makefile").as_deref(), Some("other"));
+    }
+}
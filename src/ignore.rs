@@ -0,0 +1,200 @@
+//! Ignore rules in the format of `.gitignore`, read from `.ignore` files found in the tree, as
+//! ripgrep does, from a file given on the command line and from a global file in the user's
+//! configuration directory, `~/.config/extension-count/ignore` on Unix.
+//!
+//! Each line holds a glob pattern, in which `*` and `?` do not match `/` and `**` matches any
+//! number of directories. Patterns containing a `/` other than a trailing one are relative to the
+//! directory of the file they are in, others match names at any depth. A trailing `/` restricts a
+//! pattern to directories, and a leading `!` re-includes what an earlier pattern ignored. The last
+//! matching pattern wins, and patterns of deeper `.ignore` files come after those of shallower
+//! ones. Empty lines and lines starting with `#` are ignored.
+
+use crate::config;
+use crate::filter;
+use crate::fs::FileSystem;
+use anyhow::{Context, Result};
+use std::cell::RefCell;
+use std::path::{Component, Path, PathBuf};
+
+/// Name of the ignore files looked for in every directory scanned.
+pub const IGNORE_FILE: &str = ".ignore";
+
+/// Ignore files larger than this are only read up to this length.
+const IGNORE_FILE_MAX_LEN: usize = 64 * 1024;
+
+#[derive(Debug, Clone)]
+struct Rule {
+    /// Directory the pattern is relative to.
+    base: PathBuf,
+
+    /// Pattern split into path segments.
+    segments: Vec<String>,
+
+    /// Whether the pattern is matched against the path below `base`, rather than the name only.
+    anchored: bool,
+
+    /// Whether the pattern only applies to directories.
+    directories_only: bool,
+
+    /// Whether matching paths are re-included rather than ignored.
+    negated: bool,
+}
+
+/// Parse the rules of an ignore file found in `base`.
+fn parse_rules(contents: &str, base: &Path) -> Vec<Rule> {
+    contents
+        .lines()
+        .filter_map(|line| {
+            let line = line.trim_end();
+            if line.is_empty() || line.starts_with('#') {
+                return None;
+            }
+            let (negated, pattern) = match line.strip_prefix('!') {
+                Some(pattern) => (true, pattern),
+                None => (false, line.strip_prefix('\\').unwrap_or(line)),
+            };
+            let (directories_only, pattern) = match pattern.strip_suffix('/') {
+                Some(pattern) => (true, pattern),
+                None => (false, pattern),
+            };
+            let anchored = pattern.contains('/');
+            let segments: Vec<String> = pattern.trim_start_matches('/').split('/').map(str::to_string).collect();
+            if segments.iter().all(String::is_empty) {
+                return None;
+            }
+            Some(Rule {
+                base: base.to_path_buf(),
+                segments,
+                anchored,
+                directories_only,
+                negated,
+            })
+        })
+        .collect()
+}
+
+/// Whether path segments match pattern segments, in which `**` matches any number of segments.
+fn segments_match(pattern: &[String], path: &[String]) -> bool {
+    match pattern.split_first() {
+        None => path.is_empty(),
+        Some((first, rest)) if first == "**" => (0..=path.len()).any(|skip| segments_match(rest, &path[skip..])),
+        Some((first, rest)) => match path.split_first() {
+            Some((name, path_rest)) => filter::glob_matches(first, name) && segments_match(rest, path_rest),
+            None => false,
+        },
+    }
+}
+
+impl Rule {
+    fn matches(&self, path: &Path, is_directory: bool) -> bool {
+        if self.directories_only && !is_directory {
+            return false;
+        }
+        let relative = match path.strip_prefix(&self.base) {
+            Ok(relative) => relative,
+            Err(_) => return false,
+        };
+        let names: Vec<String> = relative
+            .components()
+            .filter_map(|c| match c {
+                Component::Normal(name) => Some(name.to_string_lossy().to_string()),
+                _ => None,
+            })
+            .collect();
+        if self.anchored {
+            segments_match(&self.segments, &names)
+        } else {
+            names
+                .last()
+                .is_some_and(|name| segments_match(&self.segments, std::slice::from_ref(name)))
+        }
+    }
+}
+
+/// Ignore rules in effect while scanning, kept as a stack that grows as directories with an ignore
+/// file are entered and shrinks as they are left.
+#[derive(Debug, Default)]
+pub struct Ignore {
+    /// Look for ignore files in the directories scanned.
+    read_ignore_files: bool,
+
+    /// Rules of the file given on the command line, followed by those of the ignore files of the
+    /// directories being scanned, outermost first.
+    rules: RefCell<Vec<Rule>>,
+}
+
+impl Ignore {
+    /// Rules read from the global ignore file unless `no_global` is set, then from `ignore_file`,
+    /// both relative to the canonical scan root, then from the ignore files in the tree if
+    /// `read_ignore_files` is set.
+    pub fn new(ignore_file: Option<&Path>, root: &Path, read_ignore_files: bool, no_global: bool) -> Result<Self> {
+        let mut rules = Vec::new();
+        // The global ignore file is optional, unlike the one given on the command line.
+        let global = config::config_file(config::IGNORE_FILE).filter(|_| !no_global);
+        for path in global.iter().map(PathBuf::as_path).chain(ignore_file) {
+            let contents =
+                std::fs::read_to_string(path).with_context(|| format!("could not read {}", path.display()))?;
+            rules.extend(parse_rules(&contents, root));
+        }
+        Ok(Self {
+            read_ignore_files,
+            rules: RefCell::new(rules),
+        })
+    }
+
+    /// Add the rules of the ignore file in a directory about to be scanned, if any. Returns the
+    /// number of rules to go back to with `leave` once the directory has been scanned.
+    pub fn enter(&self, dir: &Path, file_system: &dyn FileSystem) -> usize {
+        let mut rules = self.rules.borrow_mut();
+        let len = rules.len();
+        if self.read_ignore_files {
+            // Ignore files that cannot be read are treated as absent.
+            if let Ok(contents) = file_system.read_header(&dir.join(IGNORE_FILE), IGNORE_FILE_MAX_LEN) {
+                rules.extend(parse_rules(&String::from_utf8_lossy(&contents), dir));
+            }
+        }
+        len
+    }
+
+    /// Drop the rules added since `enter` returned `len`.
+    pub fn leave(&self, len: usize) {
+        self.rules.borrow_mut().truncate(len);
+    }
+
+    /// Whether a file or directory is ignored by the rules in effect.
+    pub fn is_ignored(&self, path: &Path, is_directory: bool) -> bool {
+        self.rules
+            .borrow()
+            .iter()
+            .rev()
+            .find(|rule| rule.matches(path, is_directory))
+            .is_some_and(|rule| !rule.negated)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn is_ignored(contents: &str, path: &str, is_directory: bool) -> bool {
+        let ignore = Ignore::default();
+        *ignore.rules.borrow_mut() = parse_rules(contents, Path::new("/repo"));
+        ignore.is_ignored(Path::new(path), is_directory)
+    }
+
+    #[test]
+    fn test_is_ignored() {
+        assert!(is_ignored("*.log", "/repo/a/b/debug.log", false));
+        assert!(!is_ignored("*.log", "/repo/a/b/debug.txt", false));
+        assert!(is_ignored("node_modules/", "/repo/web/node_modules", true));
+        assert!(!is_ignored("node_modules/", "/repo/web/node_modules", false));
+        assert!(is_ignored("/build", "/repo/build", true));
+        assert!(!is_ignored("/build", "/repo/src/build", true));
+        assert!(is_ignored("docs/*.pdf", "/repo/docs/manual.pdf", false));
+        assert!(!is_ignored("docs/*.pdf", "/repo/docs/old/manual.pdf", false));
+        assert!(is_ignored("docs/**/*.pdf", "/repo/docs/old/manual.pdf", false));
+        assert!(!is_ignored("*.log\n!keep.log", "/repo/keep.log", false));
+        assert!(!is_ignored("*.log", "/elsewhere/debug.log", false));
+        assert!(!is_ignored("# comment\n\n", "/repo/# comment", false));
+    }
+}
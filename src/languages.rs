@@ -0,0 +1,139 @@
+//! Programming languages files are written in, told from their extension. The built-in map can be
+//! extended or overridden with a file of one extension and language per line:
+//!
+//! ```text
+//! # extension  language
+//! inc          PHP
+//! h            Objective-C
+//! ```
+//!
+//! The language is the rest of the line after the extension, and may contain spaces. Empty lines
+//! and lines starting with `#` are ignored.
+
+use anyhow::{bail, Context, Result};
+use std::collections::HashMap;
+use std::path::Path;
+
+#[rustfmt::skip]
+const LANGUAGES: &[(&str, &[&str])] = &[
+    ("Rust", &["rs"]),
+    ("Python", &["py", "pyi", "pyw", "pyx"]),
+    ("C/C++", &["c", "h", "cc", "cpp", "cxx", "c++", "hh", "hpp", "hxx", "h++", "inl", "ipp"]),
+    ("C#", &["cs", "csx"]),
+    ("Go", &["go"]),
+    ("Java", &["java"]),
+    ("Kotlin", &["kt", "kts"]),
+    ("Scala", &["scala", "sc"]),
+    ("JavaScript", &["js", "mjs", "cjs", "jsx"]),
+    ("TypeScript", &["ts", "mts", "cts", "tsx"]),
+    ("Ruby", &["rb", "rake", "gemspec"]),
+    ("PHP", &["php", "phtml"]),
+    ("Swift", &["swift"]),
+    ("Objective-C", &["m", "mm"]),
+    ("Shell", &["sh", "bash", "zsh", "fish", "ksh"]),
+    ("PowerShell", &["ps1", "psm1", "psd1"]),
+    ("Perl", &["pl", "pm"]),
+    ("Lua", &["lua"]),
+    ("R", &["r"]),
+    ("Julia", &["jl"]),
+    ("Haskell", &["hs", "lhs"]),
+    ("OCaml", &["ml", "mli"]),
+    ("Elixir", &["ex", "exs"]),
+    ("Erlang", &["erl", "hrl"]),
+    ("Clojure", &["clj", "cljs", "cljc", "edn"]),
+    ("Dart", &["dart"]),
+    ("Zig", &["zig"]),
+    ("Nim", &["nim"]),
+    ("Fortran", &["f", "for", "f90", "f95", "f03", "f08"]),
+    ("Assembly", &["s", "asm"]),
+    ("SQL", &["sql"]),
+    ("HTML", &["html", "htm", "xhtml"]),
+    ("CSS", &["css", "scss", "sass", "less"]),
+    ("Markdown", &["md", "markdown"]),
+    ("TOML", &["toml"]),
+    ("YAML", &["yml", "yaml"]),
+    ("JSON", &["json"]),
+    ("XML", &["xml", "xsd", "xsl"]),
+];
+
+/// Map from lowercase extension to language.
+#[derive(Debug, Clone)]
+pub struct Languages(HashMap<String, String>);
+
+impl Default for Languages {
+    fn default() -> Self {
+        Self(
+            LANGUAGES
+                .iter()
+                .flat_map(|(language, extensions)| {
+                    extensions.iter().map(move |e| (e.to_string(), language.to_string()))
+                })
+                .collect(),
+        )
+    }
+}
+
+impl Languages {
+    /// Language of files with the given extension, if known. Extensions are compared regardless of
+    /// case.
+    pub fn language(&self, extension: &str) -> Option<&str> {
+        self.0.get(&extension.to_lowercase()).map(String::as_str)
+    }
+
+    /// Add the mappings of an overrides file to the map, replacing the built-in ones.
+    fn apply_overrides(&mut self, overrides: &str) -> Result<()> {
+        for (idx, line) in overrides.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let (extension, language) = match line.split_once(char::is_whitespace) {
+                Some((extension, language)) if !language.trim().is_empty() => (extension, language.trim()),
+                _ => bail!("line {}: expected an extension and a language", idx + 1),
+            };
+            self.0
+                .insert(extension.trim_start_matches('.').to_lowercase(), language.to_string());
+        }
+        Ok(())
+    }
+}
+
+/// The built-in map, extended with the mappings read from a file if one is given.
+pub fn read_languages(overrides: Option<&Path>) -> Result<Languages> {
+    let mut languages = Languages::default();
+    if let Some(path) = overrides {
+        let contents = std::fs::read_to_string(path).with_context(|| format!("could not read {}", path.display()))?;
+        languages
+            .apply_overrides(&contents)
+            .with_context(|| format!("invalid language map {}", path.display()))?;
+    }
+    Ok(languages)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_language() {
+        let languages = Languages::default();
+        assert_eq!(languages.language("rs"), Some("Rust"));
+        assert_eq!(languages.language("HPP"), Some("C/C++"));
+        assert_eq!(languages.language("c"), Some("C/C++"));
+        assert_eq!(languages.language("exe"), None);
+    }
+
+    #[test]
+    fn test_apply_overrides() {
+        let mut languages = Languages::default();
+        languages
+            .apply_overrides("# extension language\n\n.inc  PHP\nh Objective-C\nv   Verilog HDL \n")
+            .expect("could not apply overrides");
+        assert_eq!(languages.language("inc"), Some("PHP"));
+        assert_eq!(languages.language("h"), Some("Objective-C"));
+        assert_eq!(languages.language("v"), Some("Verilog HDL"));
+        assert_eq!(languages.language("c"), Some("C/C++"));
+        assert!(languages.apply_overrides("rs").is_err());
+    }
+}
@@ -0,0 +1,495 @@
+//! A file system made of a listing of files and their metadata, such as the members of an archive,
+//! scanned without access to the files themselves. Contents cannot be read.
+//!
+//! Listings taken on another system can be read in one of these formats:
+//!
+//! - `find`: the output of `find . -type f -printf '%s %p\n'`, a size and a path per line.
+//! - `ls`: the output of `ls -lR`, in which each directory is introduced by its path and a colon.
+//! - `csv`: a path and a size per line, separated by a comma, with an optional header line. Paths
+//!   may be quoted, with `""` standing for a quote.
+
+use crate::fs::{Entry, EntryKind, FileSystem, Metadata};
+use anyhow::{bail, Context, Result};
+use clap::ArgEnum;
+use std::collections::{BTreeMap, HashMap};
+use std::io::{self, Read};
+use std::path::{Component, Path, PathBuf};
+
+/// Number of symlinks followed in a row before giving up on a loop, as with ELOOP on Linux.
+const MAX_SYMLINK_HOPS: usize = 40;
+
+/// Format of a listing read with `--input-listing`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ArgEnum)]
+pub enum ListingFormat {
+    /// Told from the first lines.
+    Auto,
+    Find,
+    Ls,
+    Csv,
+}
+
+#[derive(Debug)]
+pub struct ListingFileSystem {
+    /// Directory every listed path is placed under.
+    root: PathBuf,
+
+    /// Entries of every directory, keyed by the path of the directory.
+    children: HashMap<PathBuf, BTreeMap<PathBuf, EntryKind>>,
+
+    /// Metadata of files, and of the directories that were listed with theirs.
+    metadata: HashMap<PathBuf, Metadata>,
+
+    /// Symlinks and their targets, as stored in the link.
+    symlinks: HashMap<PathBuf, PathBuf>,
+}
+
+impl ListingFileSystem {
+    pub fn new(root: PathBuf) -> Self {
+        let mut children = HashMap::new();
+        children.insert(root.clone(), BTreeMap::new());
+        Self {
+            root,
+            children,
+            metadata: HashMap::new(),
+            symlinks: HashMap::new(),
+        }
+    }
+
+    /// Directory every listed path is placed under.
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Absolute path of a listed path, which is relative to the root even if it starts with `/`.
+    /// `.` components are dropped, and `..` components go up a directory without leaving the root.
+    fn resolve(&self, listed: &Path) -> PathBuf {
+        let mut path = self.root.clone();
+        for component in listed.components() {
+            match component {
+                Component::Normal(name) => path.push(name),
+                Component::ParentDir if path != self.root => {
+                    path.pop();
+                }
+                _ => {}
+            }
+        }
+        path
+    }
+
+    /// Record an entry and the directories leading to it, replacing an earlier entry at the same
+    /// path. Returns its absolute path.
+    fn insert(&mut self, listed: &Path, kind: EntryKind) -> PathBuf {
+        let path = self.resolve(listed);
+        if path == self.root {
+            return path;
+        }
+        // Record the entry itself, then its ancestors as directories, up to the first one already
+        // recorded.
+        let mut child = path.clone();
+        let mut kind = kind;
+        loop {
+            let parent = child.parent().expect("listed paths are below the root").to_path_buf();
+            if kind == EntryKind::Directory {
+                self.children.entry(child.clone()).or_default();
+            }
+            let known = self
+                .children
+                .entry(parent.clone())
+                .or_default()
+                .insert(child, kind)
+                .is_some();
+            if parent == self.root || (known && kind == EntryKind::Directory) {
+                break;
+            }
+            child = parent;
+            kind = EntryKind::Directory;
+        }
+        path
+    }
+
+    /// Add a file with its metadata, along with any missing parent directories.
+    pub fn add_file(&mut self, listed: &Path, metadata: Metadata) {
+        let path = self.insert(listed, EntryKind::File);
+        self.metadata.insert(path, metadata);
+    }
+
+    /// Add a directory, along with any missing parent directories.
+    pub fn add_dir(&mut self, listed: &Path, metadata: Option<Metadata>) {
+        let path = self.insert(listed, EntryKind::Directory);
+        if let Some(metadata) = metadata {
+            self.metadata.insert(path, metadata);
+        }
+    }
+
+    /// Add a symlink to the given target, which need not be listed.
+    pub fn add_symlink(&mut self, listed: &Path, target: PathBuf) {
+        let path = self.insert(listed, EntryKind::Symlink);
+        self.symlinks.insert(path, target);
+    }
+
+    /// Metadata of a listed file, or `None` if it was not listed.
+    pub fn file_metadata_mut(&mut self, listed: &Path) -> Option<&mut Metadata> {
+        let path = self.resolve(listed);
+        self.metadata.get_mut(&path)
+    }
+
+    fn not_found(path: &Path) -> io::Error {
+        io::Error::new(io::ErrorKind::NotFound, path.display().to_string())
+    }
+}
+
+impl FileSystem for ListingFileSystem {
+    fn canonicalize(&self, path: &Path) -> io::Result<PathBuf> {
+        self.file_type(path).map(|_| path.to_path_buf())
+    }
+
+    fn read_dir(&self, dir: &Path) -> io::Result<Vec<Entry>> {
+        let entries = self.children.get(dir).ok_or_else(|| Self::not_found(dir))?;
+        Ok(entries
+            .iter()
+            .map(|(path, kind)| Entry {
+                path: path.clone(),
+                kind: *kind,
+            })
+            .collect())
+    }
+
+    fn metadata(&self, path: &Path) -> io::Result<Metadata> {
+        let mut path = path.to_path_buf();
+        let mut hops = 0;
+        while let Some(target) = self.symlinks.get(&path) {
+            hops += 1;
+            if hops > MAX_SYMLINK_HOPS {
+                return Err(io::Error::other("symlink loop"));
+            }
+            // Absolute targets are taken to be relative to the root of the listing.
+            let parent = path.parent().unwrap_or(&self.root);
+            path = if target.is_absolute() {
+                self.resolve(target)
+            } else {
+                self.resolve(&parent.strip_prefix(&self.root).unwrap_or(parent).join(target))
+            };
+        }
+        match self.metadata.get(&path) {
+            Some(metadata) => Ok(metadata.clone()),
+            None if self.children.contains_key(&path) => Ok(Metadata {
+                nlink: 1,
+                mode: 0o755,
+                ..Default::default()
+            }),
+            None => Err(Self::not_found(&path)),
+        }
+    }
+
+    fn file_type(&self, path: &Path) -> io::Result<EntryKind> {
+        if path == self.root {
+            return Ok(EntryKind::Directory);
+        }
+        path.parent()
+            .and_then(|parent| self.children.get(parent))
+            .and_then(|entries| entries.get(path))
+            .copied()
+            .ok_or_else(|| Self::not_found(path))
+    }
+
+    fn read_link(&self, link: &Path) -> io::Result<PathBuf> {
+        self.symlinks
+            .get(link)
+            .cloned()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "not a symlink"))
+    }
+
+    fn read_at(&self, _file: &Path, _offset: u64, _len: usize) -> io::Result<Vec<u8>> {
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "the contents of listed files cannot be read",
+        ))
+    }
+}
+
+/// An empty listing named after an input file, or `stdin` if the path is `-`, and a reader of the
+/// input.
+pub fn open_input(input: &Path) -> Result<(ListingFileSystem, Box<dyn Read + Send>)> {
+    if input == Path::new("-") {
+        return Ok((ListingFileSystem::new(PathBuf::from("/stdin")), Box::new(io::stdin())));
+    }
+    let file = std::fs::File::open(input).with_context(|| format!("could not open {}", input.display()))?;
+    let name = input.file_name().map_or("listing".into(), |n| n.to_os_string());
+    Ok((ListingFileSystem::new(Path::new("/").join(name)), Box::new(file)))
+}
+
+/// Whether a line starts with the permissions of `ls -l`, such as `-rw-r--r--`.
+fn is_ls_entry(line: &str) -> bool {
+    let mode = line.as_bytes();
+    mode.len() >= 10 && b"-dlcbps".contains(&mode[0]) && mode[1..10].iter().all(|b| b"rwxsStT-".contains(b))
+}
+
+/// Format of a listing, told from its first non-empty line.
+fn detect_format(contents: &str) -> ListingFormat {
+    let first = contents
+        .lines()
+        .find(|line| !line.trim().is_empty())
+        .unwrap_or_default();
+    if first.ends_with(':') || first.starts_with("total ") || is_ls_entry(first) {
+        ListingFormat::Ls
+    } else if first
+        .split_once(char::is_whitespace)
+        .is_some_and(|(size, _)| size.parse::<u64>().is_ok())
+    {
+        ListingFormat::Find
+    } else {
+        ListingFormat::Csv
+    }
+}
+
+pub fn file_metadata(len: u64) -> Metadata {
+    Metadata {
+        len,
+        nlink: 1,
+        mode: 0o644,
+        ..Default::default()
+    }
+}
+
+fn read_find(contents: &str, listing: &mut ListingFileSystem) -> Result<()> {
+    for (idx, line) in contents.lines().enumerate().filter(|(_, l)| !l.is_empty()) {
+        match line.split_once(' ') {
+            Some((size, path)) if size.parse::<u64>().is_ok() => {
+                listing.add_file(Path::new(path), file_metadata(size.parse()?));
+            }
+            _ => bail!("line {}: expected a size and a path", idx + 1),
+        }
+    }
+    Ok(())
+}
+
+/// The first `n` whitespace-separated fields of a line, and the rest of the line after them.
+fn split_fields(line: &str, n: usize) -> Option<(Vec<&str>, &str)> {
+    let mut fields = Vec::with_capacity(n);
+    let mut rest = line.trim_start();
+    while fields.len() < n {
+        let end = rest.find(char::is_whitespace)?;
+        fields.push(&rest[..end]);
+        rest = rest[end..].trim_start();
+    }
+    Some((fields, rest))
+}
+
+fn read_ls(contents: &str, listing: &mut ListingFileSystem) -> Result<()> {
+    // Directories are named as given to ls, so they are taken relative to the first one.
+    let mut first_dir: Option<PathBuf> = None;
+    let mut dir = PathBuf::new();
+    for (idx, line) in contents.lines().enumerate() {
+        if let Some(header) = line.strip_suffix(':').filter(|_| !is_ls_entry(line)) {
+            let header = PathBuf::from(header);
+            let first = first_dir.get_or_insert_with(|| header.clone());
+            dir = header.strip_prefix(&*first).map_or(header.clone(), Path::to_path_buf);
+            listing.add_dir(&dir, None);
+            continue;
+        }
+        if line.trim().is_empty() || line.starts_with("total ") {
+            continue;
+        }
+        add_ls_entry(listing, &dir, line).with_context(|| format!("line {}", idx + 1))?;
+    }
+    Ok(())
+}
+
+/// Add an entry of `ls -l` listed in a directory, returning its path if it is a subdirectory.
+pub fn add_ls_entry(listing: &mut ListingFileSystem, dir: &Path, line: &str) -> Result<Option<PathBuf>> {
+    if !is_ls_entry(line) {
+        bail!("expected an entry of ls -l");
+    }
+
+    // Permissions, links, owner, group, size, month, day and time or year come before the name.
+    let (fields, name) = match split_fields(line, 8) {
+        Some((fields, name)) if !name.is_empty() => (fields, name),
+        _ => bail!("expected an entry of ls -l"),
+    };
+    let entry = name.split(" -> ").next().unwrap_or(name);
+    // Names are joined to the listed directory, so they must not lead out of it.
+    if entry.contains('/') {
+        bail!("invalid name {}", entry);
+    }
+    if entry == "." || entry == ".." {
+        return Ok(None);
+    }
+    let path = dir.join(entry);
+    match line.as_bytes()[0] {
+        b'-' => {
+            let len = fields[4]
+                .parse()
+                .with_context(|| format!("invalid size {}", fields[4]))?;
+            let mut metadata = file_metadata(len);
+            metadata.nlink = fields[1].parse().unwrap_or(1);
+            listing.add_file(&path, metadata);
+        }
+        b'd' => {
+            listing.add_dir(&path, None);
+            return Ok(Some(path));
+        }
+        b'l' => match name.split_once(" -> ") {
+            Some((_, target)) => listing.add_symlink(&path, PathBuf::from(target)),
+            None => bail!("expected the target of a symlink"),
+        },
+        // Devices, pipes and sockets hold no data and are not counted.
+        _ => {}
+    }
+    Ok(None)
+}
+
+fn read_csv(contents: &str, listing: &mut ListingFileSystem) -> Result<()> {
+    for (idx, line) in contents.lines().enumerate().filter(|(_, l)| !l.trim().is_empty()) {
+        let (path, size) = match line.rsplit_once(',') {
+            Some((path, size)) => (path.trim(), size.trim()),
+            None => bail!("line {}: expected a path and a size", idx + 1),
+        };
+        let size = match size.parse() {
+            Ok(size) => size,
+            // A header line.
+            Err(_) if idx == 0 => continue,
+            Err(_) => bail!("line {}: invalid size {}", idx + 1, size),
+        };
+        let path = match path.strip_prefix('"').and_then(|p| p.strip_suffix('"')) {
+            Some(quoted) => quoted.replace("\"\"", "\""),
+            None => path.to_string(),
+        };
+        listing.add_file(Path::new(&path), file_metadata(size));
+    }
+    Ok(())
+}
+
+/// Read a listing from a file, or from stdin if the path is `-`, into a listing rooted at a
+/// directory named after the file.
+pub fn read_listing(input: &Path, format: ListingFormat) -> Result<ListingFileSystem> {
+    let (mut listing, mut reader) = open_input(input)?;
+    let mut contents = String::new();
+    reader
+        .read_to_string(&mut contents)
+        .with_context(|| format!("could not read {}", input.display()))?;
+    read_contents(&contents, format, &mut listing).with_context(|| format!("invalid listing {}", input.display()))?;
+    Ok(listing)
+}
+
+/// Add the entries of a listing already read to a listing file system.
+pub fn read_contents(contents: &str, format: ListingFormat, listing: &mut ListingFileSystem) -> Result<()> {
+    let format = match format {
+        ListingFormat::Auto => detect_format(contents),
+        format => format,
+    };
+    match format {
+        ListingFormat::Find => read_find(contents, listing),
+        ListingFormat::Ls => read_ls(contents, listing),
+        _ => read_csv(contents, listing),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_listing_file_system() {
+        let mut fs = ListingFileSystem::new(PathBuf::from("/stdin"));
+        let file = |len| Metadata {
+            len,
+            nlink: 1,
+            ..Default::default()
+        };
+        fs.add_file(Path::new("./src/main.rs"), file(10));
+        fs.add_dir(Path::new("src/"), None);
+        fs.add_file(Path::new("/src/lib/../lib.rs"), file(20));
+        fs.add_dir(Path::new("empty"), None);
+        fs.add_symlink(Path::new("link.rs"), PathBuf::from("src/main.rs"));
+        fs.add_symlink(Path::new("a"), PathBuf::from("b"));
+        fs.add_symlink(Path::new("b"), PathBuf::from("/a"));
+
+        let mut names: Vec<_> = fs
+            .read_dir(Path::new("/stdin/src"))
+            .expect("could not read directory")
+            .into_iter()
+            .map(|e| (e.path, e.kind))
+            .collect();
+        names.sort_by(|a, b| a.0.cmp(&b.0));
+        assert_eq!(
+            names,
+            vec![
+                (PathBuf::from("/stdin/src/lib.rs"), EntryKind::File),
+                (PathBuf::from("/stdin/src/main.rs"), EntryKind::File),
+            ]
+        );
+        assert_eq!(fs.read_dir(Path::new("/stdin")).map(|e| e.len()).ok(), Some(5));
+        assert_eq!(fs.metadata(Path::new("/stdin/link.rs")).map(|m| m.len).ok(), Some(10));
+        assert!(fs.metadata(Path::new("/stdin/a")).is_err());
+        assert_eq!(fs.file_type(Path::new("/stdin/empty")).ok(), Some(EntryKind::Directory));
+        assert!(fs.read_header(Path::new("/stdin/src/main.rs"), 4).is_err());
+    }
+
+    fn sizes(listing: &ListingFileSystem, paths: &[&str]) -> Vec<Option<u64>> {
+        paths
+            .iter()
+            .map(|path| listing.metadata(&listing.root().join(path)).ok().map(|m| m.len))
+            .collect()
+    }
+
+    #[test]
+    fn test_read_find() {
+        let contents = "120 ./src/main.rs\n7 ./my notes.txt\n";
+        assert_eq!(detect_format(contents), ListingFormat::Find);
+        let mut listing = ListingFileSystem::new(PathBuf::from("/stdin"));
+        read_find(contents, &mut listing).expect("could not read listing");
+        assert_eq!(
+            sizes(&listing, &["src/main.rs", "my notes.txt"]),
+            vec![Some(120), Some(7)]
+        );
+        assert!(read_find("./a.rs 3", &mut listing).is_err());
+    }
+
+    #[test]
+    fn test_read_ls() {
+        let contents = "\
+/srv/data:
+total 12
+drwxr-xr-x 2 alice staff 4096 Jan  1 12:00 src
+-rw-r--r-- 1 alice staff  512 Mar 14  2023 read me.md
+lrwxrwxrwx 1 alice staff    9 Jan  1 12:00 latest -> src/a.rs
+crw-rw-rw- 1 root  root  1, 3 Jan  1 12:00 null
+
+/srv/data/src:
+total 4
+-rw-r--r-- 2 alice staff 1000 Jan  1 12:00 a.rs
+";
+        assert_eq!(detect_format(contents), ListingFormat::Ls);
+        let mut listing = ListingFileSystem::new(PathBuf::from("/ls.txt"));
+        read_ls(contents, &mut listing).expect("could not read listing");
+        assert_eq!(
+            sizes(&listing, &["read me.md", "src/a.rs", "latest", "null"]),
+            vec![Some(512), Some(1000), Some(1000), None]
+        );
+        assert_eq!(
+            listing.metadata(Path::new("/ls.txt/src/a.rs")).map(|m| m.nlink).ok(),
+            Some(2)
+        );
+
+        let mut entry = |line| add_ls_entry(&mut listing, Path::new("/ls.txt"), line);
+        assert!(entry("drwxr-xr-x 2 alice staff 4096 Jan  1 12:00 ../../x").is_err());
+        assert!(entry("-rw-r--r-- 1 alice staff  512 Jan  1 12:00 a/b").is_err());
+        assert_eq!(
+            entry("drwxr-xr-x 2 alice staff 4096 Jan  1 12:00 ..").expect("invalid entry"),
+            None
+        );
+    }
+
+    #[test]
+    fn test_read_csv() {
+        let contents = "path,size\ndocs/a,b.txt,10\n\"docs/\"\"q\"\".md\",20\n";
+        assert_eq!(detect_format(contents), ListingFormat::Csv);
+        let mut listing = ListingFileSystem::new(PathBuf::from("/stdin"));
+        read_csv(contents, &mut listing).expect("could not read listing");
+        assert_eq!(
+            sizes(&listing, &["docs/a,b.txt", "docs/\"q\".md"]),
+            vec![Some(10), Some(20)]
+        );
+        assert!(read_csv("a.txt,10\nb.txt,big\n", &mut listing).is_err());
+    }
+}
@@ -0,0 +1,425 @@
+//! Translations of the labels, units and report text printed for humans. Porcelain output and the
+//! output of `--du` are meant for scripts and are never translated. Error messages are translated
+//! by matching them against English templates, keeping the values they name.
+
+use clap::ArgEnum;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ArgEnum)]
+pub enum Locale {
+    #[default]
+    En,
+    De,
+    Fr,
+    Es,
+}
+
+/// English text followed by its German, French and Spanish translations.
+#[rustfmt::skip]
+const TRANSLATIONS: &[(&str, [&str; 3])] = &[
+    ("N/A", ["k. A.", "N/D", "N/D"]),
+    ("level", ["Ebene", "niveau", "nivel"]),
+    ("file", ["Datei", "fichier", "archivo"]),
+    ("files", ["Dateien", "fichiers", "archivos"]),
+    ("none found", ["nichts gefunden", "aucun résultat", "nada encontrado"]),
+    ("hardlinked", ["hart verlinkt", "liés en dur", "con enlace duro"]),
+    ("in cloud", ["in der Cloud", "dans le cloud", "en la nube"]),
+    ("local", ["lokal", "local", "local"]),
+    ("cloud", ["Cloud", "cloud", "nube"]),
+    ("text", ["Text", "texte", "texto"]),
+    ("binary", ["binär", "binaire", "binario"]),
+    ("mount", ["Einhängepunkt", "montage", "montaje"]),
+    ("mixed", ["gemischt", "mixtes", "mixtos"]),
+    (
+        "tree changed during scan",
+        [
+            "Baum während des Scans verändert",
+            "arborescence modifiée pendant l'analyse",
+            "árbol modificado durante el análisis",
+        ],
+    ),
+    ("vanished entries", ["verschwundene Einträge", "entrées disparues", "entradas desaparecidas"]),
+    ("warnings", ["Warnungen", "avertissements", "advertencias"]),
+    ("new extensions", ["neue Erweiterungen", "nouvelles extensions", "extensiones nuevas"]),
+    (
+        "new extensions since baseline",
+        [
+            "neue Erweiterungen seit der Basislinie",
+            "nouvelles extensions depuis la référence",
+            "extensiones nuevas desde la referencia",
+        ],
+    ),
+    ("unreadable entries", ["unlesbare Einträge", "entrées illisibles", "entradas ilegibles"]),
+    (
+        "unreadable directories",
+        ["unlesbare Verzeichnisse", "répertoires illisibles", "directorios ilegibles"],
+    ),
+    ("audit findings", ["Prüfungsbefunde", "constats d'audit", "hallazgos de auditoría"]),
+    (
+        "depth lowered for memory",
+        [
+            "Tiefe wegen Speicher verringert",
+            "profondeur réduite pour la mémoire",
+            "profundidad reducida por memoria",
+        ],
+    ),
+    ("file systems", ["Dateisysteme", "systèmes de fichiers", "sistemas de archivos"]),
+    ("high entropy", ["hohe Entropie", "entropie élevée", "entropía alta"]),
+    ("name audit", ["Namensprüfung", "audit des noms", "auditoría de nombres"]),
+    ("type verification", ["Typprüfung", "vérification des types", "verificación de tipos"]),
+    ("permission audit", ["Rechteprüfung", "audit des permissions", "auditoría de permisos"]),
+    ("file name lengths", ["Dateinamenlängen", "longueurs des noms", "longitudes de nombres"]),
+    ("names", ["Namen", "noms", "nombres"]),
+    ("paths", ["Pfade", "chemins", "rutas"]),
+    (
+        "longest name per directory",
+        ["längster Name je Verzeichnis", "nom le plus long par répertoire", "nombre más largo por directorio"],
+    ),
+    (
+        "longest path per directory",
+        ["längster Pfad je Verzeichnis", "chemin le plus long par répertoire", "ruta más larga por directorio"],
+    ),
+    ("long paths", ["lange Pfade", "chemins longs", "rutas largas"]),
+    ("longest paths", ["längste Pfade", "chemins les plus longs", "rutas más largas"]),
+    ("case collisions", ["Groß-/Kleinschreibungskonflikte", "conflits de casse", "conflictos de mayúsculas"]),
+    ("Windows names", ["Windows-Namen", "noms Windows", "nombres de Windows"]),
+    ("compressed copies", ["komprimierte Kopien", "copies compressées", "copias comprimidas"]),
+    ("compressed", ["komprimiert", "compressé", "comprimido"]),
+    ("quotas", ["Kontingente", "quotas", "cuotas"]),
+    ("no quotas defined", ["keine Kontingente definiert", "aucun quota défini", "ninguna cuota definida"]),
+    ("of", ["von", "sur", "de"]),
+    ("left", ["frei", "restant", "libre"]),
+    ("over", ["überschritten", "de dépassement", "excedido"]),
+    ("budgets", ["Budgets", "budgets", "presupuestos"]),
+    ("no budgets defined", ["keine Budgets definiert", "aucun budget défini", "ningún presupuesto definido"]),
+    ("whole scan", ["gesamter Scan", "analyse entière", "análisis completo"]),
+    ("per directory", ["je Verzeichnis", "par répertoire", "por directorio"]),
+    ("pass", ["eingehalten", "respecté", "cumplido"]),
+    ("fail", ["überschritten", "dépassé", "excedido"]),
+    ("manifest check", ["Manifestprüfung", "vérification du manifeste", "verificación del manifiesto"]),
+    ("no discrepancies", ["keine Abweichungen", "aucun écart", "ninguna discrepancia"]),
+    ("missing files", ["fehlende Dateien", "fichiers manquants", "archivos faltantes"]),
+    ("unexpected files", ["unerwartete Dateien", "fichiers inattendus", "archivos inesperados"]),
+    ("changed sizes", ["geänderte Größen", "tailles modifiées", "tamaños cambiados"]),
+    ("changed contents", ["geänderte Inhalte", "contenus modifiés", "contenidos cambiados"]),
+    ("workspaces", ["Arbeitsbereiche", "espaces de travail", "espacios de trabajo"]),
+    ("outside packages", ["außerhalb von Paketen", "hors des paquets", "fuera de paquetes"]),
+    ("node_modules packages", ["Pakete in node_modules", "paquets de node_modules", "paquetes de node_modules"]),
+    ("copies", ["Kopien", "copies", "copias"]),
+    ("treemap", ["Treemap", "carte proportionnelle", "mapa de árbol"]),
+    ("others", ["andere", "autres", "otros"]),
+    ("security", ["Sicherheit", "sécurité", "seguridad"]),
+    ("world-writable", ["für alle schreibbar", "modifiable par tous", "modificable por todos"]),
+    ("setuid or setgid", ["setuid oder setgid", "setuid ou setgid", "setuid o setgid"]),
+    ("broken symlinks", ["defekte symbolische Links", "liens symboliques cassés", "enlaces simbólicos rotos"]),
+    ("largest directories", ["größte Verzeichnisse", "plus grands répertoires", "directorios más grandes"]),
+    ("largest files", ["größte Dateien", "plus grands fichiers", "archivos más grandes"]),
+    ("files by month", ["Dateien nach Monat", "fichiers par mois", "archivos por mes"]),
+    ("files by year", ["Dateien nach Jahr", "fichiers par année", "archivos por año"]),
+    ("depths", ["Tiefen", "profondeurs", "profundidades"]),
+    ("maximum", ["Maximum", "maximum", "máximo"]),
+    ("average", ["Durchschnitt", "moyenne", "promedio"]),
+    ("deepest", ["am tiefsten", "le plus profond", "el más profundo"]),
+    ("directories per depth", ["Verzeichnisse je Tiefe", "répertoires par profondeur", "directorios por profundidad"]),
+    ("trash", ["Papierkorb", "corbeille", "papelera"]),
+    ("line endings", ["Zeilenenden", "fins de ligne", "finales de línea"]),
+    ("by extension", ["je Endung", "par extension", "por extensión"]),
+    ("with CRLF", ["mit CRLF", "avec CRLF", "con CRLF"]),
+    ("encodings", ["Kodierungen", "encodages", "codificaciones"]),
+    ("with legacy encodings", ["mit Altkodierungen", "avec encodages anciens", "con codificaciones antiguas"]),
+    ("extension variants", ["Endungsvarianten", "variantes d'extension", "variantes de extensión"]),
+    ("Error", ["Fehler", "Erreur", "Error"]),
+    ("Caused by", ["Ursache", "Causé par", "Causado por"]),
+];
+
+/// English error messages followed by their German, French and Spanish translations, with `{}`
+/// standing for the values they name.
+#[rustfmt::skip]
+const MESSAGES: &[(&str, [&str; 3])] = &[
+    ("--by-period-extension requires --by-month or --by-year", ["--by-period-extension erfordert --by-month oder --by-year", "--by-period-extension nécessite --by-month ou --by-year", "--by-period-extension requiere --by-month o --by-year"]),
+    ("--extensionless content cannot read the contents of archive members, listed or remote files", ["--extensionless content kann den Inhalt von Archivmitgliedern, gelisteten oder entfernten Dateien nicht lesen", "--extensionless content ne peut pas lire le contenu des membres d'archive, ni des fichiers listés ou distants", "--extensionless content no puede leer el contenido de miembros de archivo, ni de archivos listados o remotos"]),
+    ("--runs must be at least 1", ["--runs muss mindestens 1 sein", "--runs doit valoir au moins 1", "--runs debe ser al menos 1"]),
+    ("--where cannot look at size or mtime with --no-size", ["--where kann mit --no-size weder Größe noch mtime prüfen", "--where ne peut pas examiner la taille ni mtime avec --no-size", "--where no puede examinar el tamaño ni mtime con --no-size"]),
+    ("agent must be given as host:port=path with an absolute path, not {}", ["Agent muss als host:port=pfad mit absolutem Pfad angegeben werden, nicht {}", "l'agent doit être donné sous la forme hôte:port=chemin avec un chemin absolu, pas {}", "el agente debe indicarse como host:puerto=ruta con una ruta absoluta, no {}"]),
+    ("agent must be given as host:port=path, not {}", ["Agent muss als host:port=pfad angegeben werden, nicht {}", "l'agent doit être donné sous la forme hôte:port=chemin, pas {}", "el agente debe indicarse como host:puerto=ruta, no {}"]),
+    ("archive ends in the middle of a member", ["Archiv endet mitten in einem Mitglied", "l'archive se termine au milieu d'un membre", "el archivo termina en medio de un miembro"]),
+    ("budgets exceeded: {}", ["Budgets überschritten: {}", "budgets dépassés : {}", "presupuestos excedidos: {}"]),
+    ("character set is empty", ["Zeichenmenge ist leer", "l'ensemble de caractères est vide", "el conjunto de caracteres está vacío"]),
+    ("connection closed before the scan was complete", ["Verbindung vor Abschluss des Scans geschlossen", "connexion fermée avant la fin de l'analyse", "conexión cerrada antes de completar el análisis"]),
+    ("connection closed by the server", ["Verbindung vom Server geschlossen", "connexion fermée par le serveur", "conexión cerrada por el servidor"]),
+    ("connection closed without an answer", ["Verbindung ohne Antwort geschlossen", "connexion fermée sans réponse", "conexión cerrada sin respuesta"]),
+    ("could not connect to {}:{}", ["konnte keine Verbindung zu {}:{} herstellen", "impossible de se connecter à {}:{}", "no se pudo conectar a {}:{}"]),
+    ("could not convert directory name to string", ["konnte Verzeichnisnamen nicht in Text umwandeln", "impossible de convertir le nom du répertoire en texte", "no se pudo convertir el nombre del directorio en texto"]),
+    ("could not create {}", ["konnte {} nicht erstellen", "impossible de créer {}", "no se pudo crear {}"]),
+    ("could not drop caches, which needs root", ["konnte Caches nicht leeren, dafür sind Root-Rechte nötig", "impossible de vider les caches, ce qui nécessite root", "no se pudieron vaciar las cachés, lo que requiere root"]),
+    ("could not enter passive mode: {}", ["konnte nicht in den passiven Modus wechseln: {}", "impossible de passer en mode passif : {}", "no se pudo entrar en modo pasivo: {}"]),
+    ("could not find the configuration directory", ["konnte das Konfigurationsverzeichnis nicht finden", "impossible de trouver le répertoire de configuration", "no se encontró el directorio de configuración"]),
+    ("could not find the directory of {}", ["konnte das Verzeichnis von {} nicht finden", "impossible de trouver le répertoire de {}", "no se encontró el directorio de {}"]),
+    ("could not list {}", ["konnte {} nicht auflisten", "impossible de lister {}", "no se pudo listar {}"]),
+    ("could not list {}: {}", ["konnte {} nicht auflisten: {}", "impossible de lister {} : {}", "no se pudo listar {}: {}"]),
+    ("could not listen on {}", ["konnte nicht auf {} lauschen", "impossible d'écouter sur {}", "no se pudo escuchar en {}"]),
+    ("could not log in as {}: {}", ["konnte nicht als {} anmelden: {}", "impossible de se connecter en tant que {} : {}", "no se pudo iniciar sesión como {}: {}"]),
+    ("could not look up {}", ["konnte {} nicht nachschlagen", "impossible de consulter {}", "no se pudo consultar {}"]),
+    ("could not open checkpoint", ["konnte Checkpoint nicht öffnen", "impossible d'ouvrir le point de reprise", "no se pudo abrir el punto de control"]),
+    ("could not open data connection", ["konnte Datenverbindung nicht öffnen", "impossible d'ouvrir la connexion de données", "no se pudo abrir la conexión de datos"]),
+    ("could not open {}", ["konnte {} nicht öffnen", "impossible d'ouvrir {}", "no se pudo abrir {}"]),
+    ("could not read reply", ["konnte Antwort nicht lesen", "impossible de lire la réponse", "no se pudo leer la respuesta"]),
+    ("could not read response", ["konnte Antwort nicht lesen", "impossible de lire la réponse", "no se pudo leer la respuesta"]),
+    ("could not read the archive", ["konnte das Archiv nicht lesen", "impossible de lire l'archive", "no se pudo leer el archivo comprimido"]),
+    ("could not read {}", ["konnte {} nicht lesen", "impossible de lire {}", "no se pudo leer {}"]),
+    ("could not resolve {}", ["konnte {} nicht auflösen", "impossible de résoudre {}", "no se pudo resolver {}"]),
+    ("could not run git", ["konnte git nicht ausführen", "impossible d'exécuter git", "no se pudo ejecutar git"]),
+    ("could not run sync", ["konnte sync nicht ausführen", "impossible d'exécuter sync", "no se pudo ejecutar sync"]),
+    ("could not run {} to decompress the archive", ["konnte {} zum Entpacken des Archivs nicht ausführen", "impossible d'exécuter {} pour décompresser l'archive", "no se pudo ejecutar {} para descomprimir el archivo"]),
+    ("could not send command", ["konnte Befehl nicht senden", "impossible d'envoyer la commande", "no se pudo enviar el comando"]),
+    ("could not send request", ["konnte Anfrage nicht senden", "impossible d'envoyer la requête", "no se pudo enviar la solicitud"]),
+    ("could not set up io_uring", ["konnte io_uring nicht einrichten", "impossible de mettre en place io_uring", "no se pudo configurar io_uring"]),
+    ("could not write {}", ["konnte {} nicht schreiben", "impossible d'écrire {}", "no se pudo escribir {}"]),
+    ("date out of range {}", ["Datum außerhalb des Bereichs {}", "date hors limites {}", "fecha fuera de rango {}"]),
+    ("dropping caches is only available on Linux", ["Leeren der Caches ist nur unter Linux verfügbar", "le vidage des caches n'est disponible que sous Linux", "vaciar las cachés solo está disponible en Linux"]),
+    ("expected a comparison, found {}", ["Vergleich erwartet, {} gefunden", "comparaison attendue, {} trouvé", "se esperaba una comparación, se encontró {}"]),
+    ("expected a date as YYYY-MM-DD, found {}", ["Datum als JJJJ-MM-TT erwartet, {} gefunden", "date attendue au format AAAA-MM-JJ, {} trouvé", "se esperaba una fecha como AAAA-MM-DD, se encontró {}"]),
+    ("expected a field, found {}", ["Feld erwartet, {} gefunden", "champ attendu, {} trouvé", "se esperaba un campo, se encontró {}"]),
+    ("expected a host in {}", ["Host in {} erwartet", "hôte attendu dans {}", "se esperaba un host en {}"]),
+    ("expected a value, found {}", ["Wert erwartet, {} gefunden", "valeur attendue, {} trouvé", "se esperaba un valor, se encontró {}"]),
+    ("expected an entry of ls -l", ["Eintrag von ls -l erwartet", "entrée de ls -l attendue", "se esperaba una entrada de ls -l"]),
+    ("expected an extension, category or pattern", ["Endung, Kategorie oder Muster erwartet", "extension, catégorie ou motif attendu", "se esperaba una extensión, categoría o patrón"]),
+    ("expected an ftp:// URL, found {}", ["ftp://-URL erwartet, {} gefunden", "URL ftp:// attendue, {} trouvé", "se esperaba una URL ftp://, se encontró {}"]),
+    ("expected an http:// URL, found {}", ["http://-URL erwartet, {} gefunden", "URL http:// attendue, {} trouvé", "se esperaba una URL http://, se encontró {}"]),
+    ("expected facts and a name", ["Fakten und ein Name erwartet", "faits et nom attendus", "se esperaban hechos y un nombre"]),
+    ("expected min, median and files", ["min, median und files erwartet", "min, median et files attendus", "se esperaban min, median y files"]),
+    ("expected the target of a symlink", ["Ziel eines symbolischen Links erwartet", "cible d'un lien symbolique attendue", "se esperaba el destino de un enlace simbólico"]),
+    ("expected {} at token {}", ["{} bei Token {} erwartet", "{} attendu au jeton {}", "se esperaba {} en el token {}"]),
+    ("ext and name cannot be ordered", ["ext und name können nicht geordnet werden", "ext et name ne peuvent pas être ordonnés", "ext y name no se pueden ordenar"]),
+    ("extension header of {} bytes is too large", ["Erweiterungskopf von {} Bytes ist zu groß", "en-tête d'extension de {} octets trop grand", "la cabecera de extensión de {} bytes es demasiado grande"]),
+    ("extensions not in the baseline: {}", ["Endungen nicht in der Basislinie: {}", "extensions absentes de la référence : {}", "extensiones que no están en la referencia: {}"]),
+    ("files not matching the manifest: {}", ["Dateien, die nicht zum Manifest passen: {}", "fichiers ne correspondant pas au manifeste : {}", "archivos que no coinciden con el manifiesto: {}"]),
+    ("git {} failed: {}", ["git {} fehlgeschlagen: {}", "git {} a échoué : {}", "git {} falló: {}"]),
+    ("https:// roots are not supported, only http://", ["https://-Wurzeln werden nicht unterstützt, nur http://", "les racines https:// ne sont pas prises en charge, seulement http://", "las raíces https:// no se admiten, solo http://"]),
+    ("incomplete chunk", ["unvollständiger Block", "bloc incomplet", "bloque incompleto"]),
+    ("incomplete response", ["unvollständige Antwort", "réponse incomplète", "respuesta incompleta"]),
+    ("invalid budgets {}", ["ungültige Budgets {}", "budgets invalides {}", "presupuestos no válidos {}"]),
+    ("invalid chunk size", ["ungültige Blockgröße", "taille de bloc invalide", "tamaño de bloque no válido"]),
+    ("invalid commit {}", ["ungültiger Commit {}", "commit invalide {}", "commit no válido {}"]),
+    ("invalid date {}", ["ungültiges Datum {}", "date invalide {}", "fecha no válida {}"]),
+    ("invalid day in {}", ["ungültiger Tag in {}", "jour invalide dans {}", "día no válido en {}"]),
+    ("invalid depth {}", ["ungültige Tiefe {}", "profondeur invalide {}", "profundidad no válida {}"]),
+    ("invalid escape in {}", ["ungültige Maskierung in {}", "échappement invalide dans {}", "escape no válido en {}"]),
+    ("invalid language map {}", ["ungültige Sprachzuordnung {}", "table de langages invalide {}", "mapa de lenguajes no válido {}"]),
+    ("invalid limit {}", ["ungültiges Limit {}", "limite invalide {}", "límite no válido {}"]),
+    ("invalid listing {}", ["ungültige Auflistung {}", "liste invalide {}", "listado no válido {}"]),
+    ("invalid manifest {}", ["ungültiges Manifest {}", "manifeste invalide {}", "manifiesto no válido {}"]),
+    ("invalid month in {}", ["ungültiger Monat in {}", "mois invalide dans {}", "mes no válido en {}"]),
+    ("invalid name {}", ["ungültiger Name {}", "nom invalide {}", "nombre no válido {}"]),
+    ("invalid number {}", ["ungültige Zahl {}", "nombre invalide {}", "número no válido {}"]),
+    ("invalid port {}", ["ungültiger Port {}", "port invalide {}", "puerto no válido {}"]),
+    ("invalid range {}-{}", ["ungültiger Bereich {}-{}", "plage invalide {}-{}", "rango no válido {}-{}"]),
+    ("invalid range {}-{} in character set", ["ungültiger Bereich {}-{} in Zeichenmenge", "plage invalide {}-{} dans l'ensemble de caractères", "rango no válido {}-{} en el conjunto de caracteres"]),
+    ("invalid repetition {{}}", ["ungültige Wiederholung {{}}", "répétition invalide {{}}", "repetición no válida {{}}"]),
+    ("invalid revision {}", ["ungültige Revision {}", "révision invalide {}", "revisión no válida {}"]),
+    ("invalid size in {}", ["ungültige Größe in {}", "taille invalide dans {}", "tamaño no válido en {}"]),
+    ("invalid size {} of {}", ["ungültige Größe {} von {}", "taille invalide {} de {}", "tamaño no válido {} de {}"]),
+    ("invalid size {}", ["ungültige Größe {}", "taille invalide {}", "tamaño no válido {}"]),
+    ("invalid theme {}", ["ungültiges Farbschema {}", "thème invalide {}", "tema no válido {}"]),
+    ("invalid timings {}", ["ungültige Zeitmessungen {}", "mesures de temps invalides {}", "tiempos no válidos {}"]),
+    ("invalid year in {}", ["ungültiges Jahr in {}", "année invalide dans {}", "año no válido en {}"]),
+    ("io_uring is only available on Linux", ["io_uring ist nur unter Linux verfügbar", "io_uring n'est disponible que sous Linux", "io_uring solo está disponible en Linux"]),
+    ("line {}", ["Zeile {}", "ligne {}", "línea {}"]),
+    ("line {}: invalid duration {}", ["Zeile {}: ungültige Dauer {}", "ligne {} : durée invalide {}", "línea {}: duración no válida {}"]),
+    ("line {}: expected a SHA-256 hash and a path", ["Zeile {}: SHA-256-Hash und Pfad erwartet", "ligne {} : empreinte SHA-256 et chemin attendus", "línea {}: se esperaba un hash SHA-256 y una ruta"]),
+    ("line {}: expected a name and a value", ["Zeile {}: Name und Wert erwartet", "ligne {} : nom et valeur attendus", "línea {}: se esperaba un nombre y un valor"]),
+    ("line {}: expected a path and a size", ["Zeile {}: Pfad und Größe erwartet", "ligne {} : chemin et taille attendus", "línea {}: se esperaba una ruta y un tamaño"]),
+    ("line {}: expected a size and a path", ["Zeile {}: Größe und Pfad erwartet", "ligne {} : taille et chemin attendus", "línea {}: se esperaba un tamaño y una ruta"]),
+    ("line {}: expected a user and a limit", ["Zeile {}: Benutzer und Limit erwartet", "ligne {} : utilisateur et limite attendus", "línea {}: se esperaba un usuario y un límite"]),
+    ("line {}: expected an element and a color", ["Zeile {}: Element und Farbe erwartet", "ligne {} : élément et couleur attendus", "línea {}: se esperaba un elemento y un color"]),
+    ("line {}: expected an extension and a language", ["Zeile {}: Endung und Sprache erwartet", "ligne {} : extension et langage attendus", "línea {}: se esperaba una extensión y un lenguaje"]),
+    ("line {}: expected an extension and a limit", ["Zeile {}: Endung und Limit erwartet", "ligne {} : extension et limite attendues", "línea {}: se esperaba una extensión y un límite"]),
+    ("line {}: invalid size {}", ["Zeile {}: ungültige Größe {}", "ligne {} : taille invalide {}", "línea {}: tamaño no válido {}"]),
+    ("line {}: unknown element {}", ["Zeile {}: unbekanntes Element {}", "ligne {} : élément inconnu {}", "línea {}: elemento desconocido {}"]),
+    ("line {}: unknown section {}", ["Zeile {}: unbekannter Abschnitt {}", "ligne {} : section inconnue {}", "línea {}: sección desconocida {}"]),
+    ("line {}: unknown timing {}", ["Zeile {}: unbekannte Zeitmessung {}", "ligne {} : mesure de temps inconnue {}", "línea {}: tiempo desconocido {}"]),
+    ("line {}: unknown user {}", ["Zeile {}: unbekannter Benutzer {}", "ligne {} : utilisateur inconnu {}", "línea {}: usuario desconocido {}"]),
+    ("malformed answer {}", ["fehlerhafte Antwort {}", "réponse mal formée {}", "respuesta mal formada {}"]),
+    ("negative limit {}", ["negatives Limit {}", "limite négative {}", "límite negativo {}"]),
+    ("not a tar archive, or corrupt header at offset {}", ["kein tar-Archiv oder beschädigter Kopf bei Offset {}", "pas une archive tar, ou en-tête corrompu à la position {}", "no es un archivo tar, o cabecera dañada en la posición {}"]),
+    ("not a tar archive, or one ending in the middle of a header", ["kein tar-Archiv oder eines, das mitten in einem Kopf endet", "pas une archive tar, ou une archive se terminant au milieu d'un en-tête", "no es un archivo tar, o uno que termina en medio de una cabecera"]),
+    ("not authorized: {}", ["nicht berechtigt: {}", "non autorisé : {}", "no autorizado: {}"]),
+    ("nothing to repeat before {}", ["nichts zu wiederholen vor {}", "rien à répéter avant {}", "nada que repetir antes de {}"]),
+    ("only (?:...) groups are supported", ["nur (?:...)-Gruppen werden unterstützt", "seuls les groupes (?:...) sont pris en charge", "solo se admiten grupos (?:...)"]),
+    ("read throttle must be a positive number of bytes per second", ["Lesedrosselung muss eine positive Anzahl Bytes pro Sekunde sein", "la limite de lecture doit être un nombre positif d'octets par seconde", "el límite de lectura debe ser un número positivo de bytes por segundo"]),
+    ("refused to scan {}: {}", ["Scan von {} abgelehnt: {}", "analyse de {} refusée : {}", "análisis de {} rechazado: {}"]),
+    ("retry delay {} is too long", ["Wiederholungsverzögerung {} ist zu lang", "le délai de nouvelle tentative {} est trop long", "el retardo de reintento {} es demasiado largo"]),
+    ("retry delay {} does not start with a number", ["Wiederholungsverzögerung {} beginnt nicht mit einer Zahl", "le délai de nouvelle tentative {} ne commence pas par un nombre", "el retardo de reintento {} no empieza por un número"]),
+    ("retry delay {} has no unit, expected ms, s or m", ["Wiederholungsverzögerung {} hat keine Einheit, erwartet ms, s oder m", "le délai de nouvelle tentative {} n'a pas d'unité, ms, s ou m attendu", "el retardo de reintento {} no tiene unidad, se esperaba ms, s o m"]),
+    ("throttle {} is too low", ["Drosselung {} ist zu niedrig", "la limite {} est trop basse", "el límite {} es demasiado bajo"]),
+    ("throttle must be a positive number of operations per second", ["Drosselung muss eine positive Anzahl Operationen pro Sekunde sein", "la limite doit être un nombre positif d'opérations par seconde", "el límite debe ser un número positivo de operaciones por segundo"]),
+    ("trailing backslash", ["abschließender Backslash", "barre oblique inverse finale", "barra invertida final"]),
+    ("unclosed (", ["nicht geschlossene (", "( non fermée", "( sin cerrar"]),
+    ("unclosed [", ["nicht geschlossene [", "[ non fermé", "[ sin cerrar"]),
+    ("unexpected end of expression", ["unerwartetes Ende des Ausdrucks", "fin d'expression inattendue", "fin de expresión inesperado"]),
+    ("unexpected entry of {}: {}", ["unerwarteter Eintrag in {}: {}", "entrée inattendue de {} : {}", "entrada inesperada de {}: {}"]),
+    ("unexpected output of git diff", ["unerwartete Ausgabe von git diff", "sortie inattendue de git diff", "salida inesperada de git diff"]),
+    ("unexpected output of git diff: {}", ["unerwartete Ausgabe von git diff: {}", "sortie inattendue de git diff : {}", "salida inesperada de git diff: {}"]),
+    ("unexpected output of git ls-tree: {}", ["unerwartete Ausgabe von git ls-tree: {}", "sortie inattendue de git ls-tree : {}", "salida inesperada de git ls-tree: {}"]),
+    ("unexpected reply {}", ["unerwartete Antwort {}", "réponse inattendue {}", "respuesta inesperada {}"]),
+    ("unexpected response {}", ["unerwartete Antwort {}", "réponse inattendue {}", "respuesta inesperada {}"]),
+    ("unexpected {} at token {}", ["unerwartetes {} bei Token {}", "{} inattendu au jeton {}", "{} inesperado en el token {}"]),
+    ("unknown category {}", ["unbekannte Kategorie {}", "catégorie inconnue {}", "categoría desconocida {}"]),
+    ("unknown color {}", ["unbekannte Farbe {}", "couleur inconnue {}", "color desconocido {}"]),
+    ("unknown field {}, expected size, mtime, ext or name", ["unbekanntes Feld {}, erwartet size, mtime, ext oder name", "champ inconnu {}, size, mtime, ext ou name attendu", "campo desconocido {}, se esperaba size, mtime, ext o name"]),
+    ("unknown unit in limit {}", ["unbekannte Einheit im Limit {}", "unité inconnue dans la limite {}", "unidad desconocida en el límite {}"]),
+    ("unknown unit {} in retry delay, expected ms, s or m", ["unbekannte Einheit {} in Wiederholungsverzögerung, erwartet ms, s oder m", "unité inconnue {} dans le délai de nouvelle tentative, ms, s ou m attendu", "unidad desconocida {} en el retardo de reintento, se esperaba ms, s o m"]),
+    ("unmatched ) at position {} of {}", ["unpassende ) an Position {} von {}", ") sans correspondance à la position {} de {}", ") sin pareja en la posición {} de {}"]),
+    ("unreadable request", ["unlesbare Anfrage", "requête illisible", "solicitud ilegible"]),
+    ("unsupported escape \\{}", ["nicht unterstützte Maskierung \\{}", "échappement non pris en charge \\{}", "escape no admitido \\{}"]),
+    ("unsupported request", ["nicht unterstützte Anfrage", "requête non prise en charge", "solicitud no admitida"]),
+    ("unterminated string {}", ["nicht abgeschlossene Zeichenkette {}", "chaîne non terminée {}", "cadena sin terminar {}"]),
+    ("warnings treated as errors: {}", ["als Fehler behandelte Warnungen: {}", "avertissements traités comme des erreurs : {}", "advertencias tratadas como errores: {}"]),
+    ("{} could not decompress the archive", ["{} konnte das Archiv nicht entpacken", "{} n'a pas pu décompresser l'archive", "{} no pudo descomprimir el archivo"]),
+    ("{} does not name a file", ["{} benennt keine Datei", "{} ne désigne pas un fichier", "{} no designa un archivo"]),
+    ("{} is not a directory", ["{} ist kein Verzeichnis", "{} n'est pas un répertoire", "{} no es un directorio"]),
+    ("{} is not available for ftp:// and http:// roots", ["{} ist für ftp://- und http://-Wurzeln nicht verfügbar", "{} n'est pas disponible pour les racines ftp:// et http://", "{} no está disponible para raíces ftp:// y http://"]),
+    ("{} is not below {}", ["{} liegt nicht unter {}", "{} n'est pas sous {}", "{} no está bajo {}"]),
+    ("{} is the checkpoint of a different scan", ["{} ist der Checkpoint eines anderen Scans", "{} est le point de reprise d'une autre analyse", "{} es el punto de control de otro análisis"]),
+    ("{} must hold a token on a single line", ["{} muss ein Token in einer einzigen Zeile enthalten", "{} doit contenir un jeton sur une seule ligne", "{} debe contener un token en una sola línea"]),
+    ("~ only applies to ext and name", ["~ gilt nur für ext und name", "~ ne s'applique qu'à ext et name", "~ solo se aplica a ext y name"]),
+];
+
+impl Locale {
+    /// Locale of the environment, from `LC_ALL`, `LC_MESSAGES` or `LANG` as in POSIX, falling back
+    /// to English for languages without translations.
+    pub fn detect() -> Self {
+        ["LC_ALL", "LC_MESSAGES", "LANG"]
+            .iter()
+            .filter_map(std::env::var_os)
+            .find(|value| !value.is_empty())
+            .map(|value| Self::from_posix(&value.to_string_lossy()))
+            .unwrap_or_default()
+    }
+
+    /// Locale of a POSIX locale name such as `de_DE.UTF-8`.
+    fn from_posix(name: &str) -> Self {
+        match name.split(['_', '.', '@']).next().unwrap_or_default() {
+            "de" => Locale::De,
+            "fr" => Locale::Fr,
+            "es" => Locale::Es,
+            _ => Locale::En,
+        }
+    }
+
+    /// Translation of an English text, or the text itself if it has none.
+    pub fn text(self, english: &'static str) -> &'static str {
+        let idx = match self {
+            Locale::En => return english,
+            Locale::De => 0,
+            Locale::Fr => 1,
+            Locale::Es => 2,
+        };
+        TRANSLATIONS
+            .iter()
+            .find(|(text, _)| *text == english)
+            .map_or(english, |(_, translations)| translations[idx])
+    }
+
+    /// Translation of an error message, or the message itself if it matches no template, like
+    /// the errors of the operating system.
+    pub fn message(self, message: &str) -> String {
+        let idx = match self {
+            Locale::En => return message.to_string(),
+            Locale::De => 0,
+            Locale::Fr => 1,
+            Locale::Es => 2,
+        };
+        // The template with the most text wins, so `invalid size {} of {}` beats `invalid size {}`.
+        MESSAGES
+            .iter()
+            .filter_map(|(template, translations)| {
+                let values = match_template(template, message)?;
+                Some((template.len() - 2 * values.len(), translations[idx], values))
+            })
+            .max_by_key(|(literal, _, _)| *literal)
+            .map_or_else(
+                || message.to_string(),
+                |(_, translation, values)| {
+                    let mut parts = translation.split("{}");
+                    let mut translated = parts.next().unwrap_or_default().to_string();
+                    for (part, value) in parts.zip(values) {
+                        translated.push_str(value);
+                        translated.push_str(part);
+                    }
+                    translated
+                },
+            )
+    }
+
+    /// Suffixes of bytes and of their binary multiples up to tebibytes.
+    pub fn size_units(self) -> [&'static str; 5] {
+        match self {
+            // French counts in octets.
+            Locale::Fr => ["o", "Kio", "Mio", "Gio", "Tio"],
+            _ => ["B", "kiB", "MiB", "GiB", "TiB"],
+        }
+    }
+
+    /// A number with the decimal separator of the locale.
+    pub fn decimal(self, number: f64, decimals: usize) -> String {
+        let formatted = format!("{:.1$}", number, decimals);
+        match self {
+            Locale::En => formatted,
+            _ => formatted.replace('.', ","),
+        }
+    }
+}
+
+/// Values standing for the `{}` of a template in a message, or `None` if it does not match.
+fn match_template<'a>(template: &str, message: &'a str) -> Option<Vec<&'a str>> {
+    let literals: Vec<_> = template.split("{}").collect();
+    let (first, rest) = literals.split_first()?;
+    let (last, middle) = match rest.split_last() {
+        Some(split) => split,
+        None => return (template == message).then(Vec::new),
+    };
+    let mut remaining = message.strip_prefix(first)?.strip_suffix(last)?;
+    let mut values = Vec::new();
+    for literal in middle {
+        let end = remaining.find(literal)?;
+        values.push(&remaining[..end]);
+        remaining = &remaining[end + literal.len()..];
+    }
+    values.push(remaining);
+    Some(values)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_locale() {
+        assert_eq!(Locale::from_posix("de_DE.UTF-8"), Locale::De);
+        assert_eq!(Locale::from_posix("fr"), Locale::Fr);
+        assert_eq!(Locale::from_posix("C.UTF-8"), Locale::En);
+        assert_eq!(Locale::De.text("quotas"), "Kontingente");
+        assert_eq!(Locale::Es.text("N/A"), "N/D");
+        assert_eq!(Locale::Fr.text("untranslated"), "untranslated");
+        assert_eq!(Locale::En.text("quotas"), "quotas");
+        assert_eq!(Locale::De.decimal(1.505, 1), "1,5");
+        assert_eq!(Locale::En.decimal(1.505, 1), "1.5");
+    }
+
+    #[test]
+    fn test_message() {
+        assert_eq!(Locale::De.message("could not read /x y"), "konnte /x y nicht lesen");
+        assert_eq!(Locale::Fr.message("invalid size 1x of /a"), "taille invalide 1x de /a");
+        assert_eq!(Locale::Es.message("invalid size 1x"), "tamaño no válido 1x");
+        assert_eq!(
+            Locale::De.message("line 3: invalid size -1"),
+            "Zeile 3: ungültige Größe -1"
+        );
+        assert_eq!(Locale::Fr.message("unclosed ("), "( non fermée");
+        assert_eq!(
+            Locale::De.message("No such file or directory (os error 2)"),
+            "No such file or directory (os error 2)"
+        );
+        assert_eq!(Locale::En.message("could not read /x"), "could not read /x");
+    }
+}
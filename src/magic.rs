@@ -0,0 +1,300 @@
+//! Content sniffing from the first bytes of a file, used to find files whose extension does not
+//! match their content, files that look encrypted or compressed, and to tell text from binary
+//! files.
+
+/// Number of bytes read from the start of a file, enough for every signature below.
+pub const HEADER_LEN: usize = 16;
+
+/// A file type, identified by bytes found at fixed offsets.
+struct Signature {
+    patterns: &'static [(usize, &'static [u8])],
+    extensions: &'static [&'static str],
+}
+
+const ZIP_EXTENSIONS: &[&str] = &[
+    "zip", "jar", "war", "apk", "whl", "epub", "docx", "xlsx", "pptx", "odt", "ods", "odp",
+];
+
+#[rustfmt::skip]
+const SIGNATURES: &[Signature] = &[
+    Signature { patterns: &[(0, b"\xFF\xD8\xFF")], extensions: &["jpg", "jpeg", "jpe"] },
+    Signature { patterns: &[(0, b"\x89PNG\r\n\x1A\n")], extensions: &["png"] },
+    Signature { patterns: &[(0, b"GIF8")], extensions: &["gif"] },
+    Signature { patterns: &[(0, b"BM")], extensions: &["bmp"] },
+    Signature { patterns: &[(0, b"II*\0")], extensions: &["tif", "tiff"] },
+    Signature { patterns: &[(0, b"MM\0*")], extensions: &["tif", "tiff"] },
+    Signature { patterns: &[(0, b"RIFF"), (8, b"WEBP")], extensions: &["webp"] },
+    Signature { patterns: &[(0, b"\0\0\x01\0")], extensions: &["ico"] },
+    Signature { patterns: &[(0, b"8BPS")], extensions: &["psd"] },
+    Signature { patterns: &[(0, b"%PDF-")], extensions: &["pdf"] },
+    Signature { patterns: &[(0, b"PK\x03\x04")], extensions: ZIP_EXTENSIONS },
+    Signature { patterns: &[(0, b"PK\x05\x06")], extensions: ZIP_EXTENSIONS },
+    Signature { patterns: &[(0, b"\x1F\x8B")], extensions: &["gz", "tgz"] },
+    Signature { patterns: &[(0, b"BZh")], extensions: &["bz2", "tbz2"] },
+    Signature { patterns: &[(0, b"\xFD7zXZ\0")], extensions: &["xz", "txz"] },
+    Signature { patterns: &[(0, b"\x28\xB5\x2F\xFD")], extensions: &["zst"] },
+    Signature { patterns: &[(0, b"7z\xBC\xAF\x27\x1C")], extensions: &["7z"] },
+    Signature { patterns: &[(0, b"Rar!\x1A\x07")], extensions: &["rar"] },
+    Signature { patterns: &[(0, b"RIFF"), (8, b"WAVE")], extensions: &["wav"] },
+    Signature { patterns: &[(0, b"RIFF"), (8, b"AVI ")], extensions: &["avi"] },
+    Signature { patterns: &[(0, b"fLaC")], extensions: &["flac"] },
+    Signature { patterns: &[(0, b"OggS")], extensions: &["ogg", "oga", "ogv", "opus"] },
+    Signature { patterns: &[(4, b"ftyp")], extensions: &["mp4", "m4a", "m4v", "mov", "heic"] },
+    Signature { patterns: &[(0, b"SQLite format 3\0")], extensions: &["sqlite", "sqlite3"] },
+    Signature { patterns: &[(0, b"MZ")], extensions: &["exe", "dll"] },
+    Signature { patterns: &[(0, b"\x7FELF")], extensions: &["so"] },
+    Signature { patterns: &[(0, b"\xCA\xFE\xBA\xBE")], extensions: &["class"] },
+    Signature { patterns: &[(0, b"\0asm")], extensions: &["wasm"] },
+];
+
+/// Headers of native executables and libraries: ELF, PE and Mach-O, in both byte orders.
+const BINARY_MAGICS: &[&[u8]] = &[
+    b"\x7FELF",
+    b"MZ",
+    b"\xFE\xED\xFA\xCE",
+    b"\xFE\xED\xFA\xCF",
+    b"\xCE\xFA\xED\xFE",
+    b"\xCF\xFA\xED\xFE",
+];
+
+/// Number of bytes sampled from the start of a file to estimate its entropy.
+pub const ENTROPY_SAMPLE_LEN: usize = 4096;
+
+/// Samples shorter than this are too small for their entropy to tell random data apart.
+const ENTROPY_MIN_SAMPLE_LEN: usize = 1024;
+
+/// Entropy in bits per byte above which content is considered random. Text is usually below 5,
+/// executables below 7, while compressed or encrypted data comes close to 8.
+const HIGH_ENTROPY: f64 = 7.5;
+
+/// Extensions of formats that are compressed by design, and expected to look random.
+const COMPRESSED_EXTENSIONS: &[&str] = &[
+    "zip", "jar", "war", "apk", "whl", "epub", "docx", "xlsx", "pptx", "odt", "ods", "odp", "gz", "tgz", "bz2", "tbz2",
+    "xz", "txz", "zst", "7z", "rar", "lz4", "br", "jpg", "jpeg", "jpe", "png", "gif", "webp", "heic", "avif", "mp3",
+    "mp4", "m4a", "m4v", "mov", "mkv", "webm", "ogg", "oga", "ogv", "opus", "flac", "pdf", "woff", "woff2",
+];
+
+impl Signature {
+    fn matches(&self, header: &[u8]) -> bool {
+        self.patterns
+            .iter()
+            .all(|(offset, bytes)| header.get(*offset..offset + bytes.len()) == Some(*bytes))
+    }
+}
+
+/// Whether the extension is one whose content can be verified, yet the header does not match it.
+/// Empty files are never reported.
+pub fn is_mismatch(extension: &str, header: &[u8]) -> bool {
+    let extension = extension.to_ascii_lowercase();
+    let mut expected = SIGNATURES
+        .iter()
+        .filter(|s| s.extensions.contains(&extension.as_str()))
+        .peekable();
+    expected.peek().is_some() && !header.is_empty() && !expected.any(|s| s.matches(header))
+}
+
+/// Kind of a file without an extension, told from its header: `script` for files starting with a
+/// shebang, `binary` for native executables and libraries, `None` otherwise.
+pub fn classify_extensionless(header: &[u8]) -> Option<&'static str> {
+    if header.starts_with(b"#!") {
+        Some("script")
+    } else if BINARY_MAGICS.iter().any(|magic| header.starts_with(magic)) {
+        Some("binary")
+    } else {
+        None
+    }
+}
+
+/// Shannon entropy of the bytes, in bits per byte.
+fn entropy(bytes: &[u8]) -> f64 {
+    let mut counts = [0usize; 256];
+    for &byte in bytes {
+        counts[byte as usize] += 1;
+    }
+    counts
+        .iter()
+        .filter(|&&count| count > 0)
+        .map(|&count| {
+            let p = count as f64 / bytes.len() as f64;
+            -p * p.log2()
+        })
+        .sum()
+}
+
+/// Number of bytes sampled from the start of a file to tell whether it is text.
+pub const TEXT_SAMPLE_LEN: usize = 8192;
+
+/// Whether a sample from the start of a file looks like text: UTF-16 with a byte order mark, or
+/// UTF-8 without NUL bytes. A character cut off at the end of a sample of `TEXT_SAMPLE_LEN` bytes
+/// is not held against it, while one cut off at the end of a shorter sample, holding the whole
+/// file, is.
+pub fn is_text(sample: &[u8]) -> bool {
+    if sample.starts_with(b"\xFF\xFE") || sample.starts_with(b"\xFE\xFF") {
+        return true;
+    }
+    if sample.contains(&0) {
+        return false;
+    }
+    match std::str::from_utf8(sample) {
+        Ok(_) => true,
+        Err(e) => e.error_len().is_none() && sample.len() == TEXT_SAMPLE_LEN,
+    }
+}
+
+/// Text encoding of a file, as told from a sample of its start.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Encoding {
+    /// Only 7-bit characters, which every other encoding here shares.
+    Ascii,
+    Utf8,
+    Utf8Bom,
+    Utf16LeBom,
+    Utf16BeBom,
+    /// UTF-16 without a byte order mark, told apart by the NUL bytes of ASCII characters.
+    Utf16Le,
+    Utf16Be,
+    /// Any other 8-bit text, such as Latin-1 or Windows-1252, which cannot be told apart.
+    Latin1,
+}
+
+impl Encoding {
+    pub fn label(self) -> &'static str {
+        match self {
+            Encoding::Ascii => "ASCII",
+            Encoding::Utf8 => "UTF-8",
+            Encoding::Utf8Bom => "UTF-8 BOM",
+            Encoding::Utf16LeBom => "UTF-16LE BOM",
+            Encoding::Utf16BeBom => "UTF-16BE BOM",
+            Encoding::Utf16Le => "UTF-16LE",
+            Encoding::Utf16Be => "UTF-16BE",
+            Encoding::Latin1 => "Latin-1",
+        }
+    }
+
+    /// Whether the encoding is a legacy one, or UTF-16, rather than ASCII or UTF-8.
+    pub fn is_legacy(self) -> bool {
+        !matches!(self, Encoding::Ascii | Encoding::Utf8 | Encoding::Utf8Bom)
+    }
+}
+
+/// Encoding of a sample from the start of a text file, or `None` if it does not look like text.
+pub fn detect_encoding(sample: &[u8]) -> Option<Encoding> {
+    if sample.starts_with(b"\xEF\xBB\xBF") {
+        return Some(Encoding::Utf8Bom);
+    }
+    if sample.starts_with(b"\xFF\xFE") {
+        return Some(Encoding::Utf16LeBom);
+    }
+    if sample.starts_with(b"\xFE\xFF") {
+        return Some(Encoding::Utf16BeBom);
+    }
+
+    // ASCII text in UTF-16 has a NUL byte in every other position.
+    let pairs = sample.len() / 2;
+    if pairs > 0 {
+        let even_nuls = sample.iter().step_by(2).filter(|&&b| b == 0).count();
+        let odd_nuls = sample.iter().skip(1).step_by(2).filter(|&&b| b == 0).count();
+        if odd_nuls * 10 >= pairs * 9 && even_nuls == 0 {
+            return Some(Encoding::Utf16Le);
+        }
+        if even_nuls * 10 >= pairs * 9 && odd_nuls == 0 {
+            return Some(Encoding::Utf16Be);
+        }
+    }
+
+    let is_control = |b: &u8| b.is_ascii_control() && !b"\t\n\r\x0C\x1B".contains(b);
+    if sample.iter().any(is_control) {
+        return None;
+    }
+    if sample.is_ascii() {
+        return Some(Encoding::Ascii);
+    }
+    match std::str::from_utf8(sample) {
+        Ok(_) => Some(Encoding::Utf8),
+        Err(e) if e.error_len().is_none() && sample.len() == TEXT_SAMPLE_LEN => Some(Encoding::Utf8),
+        Err(_) => Some(Encoding::Latin1),
+    }
+}
+
+/// Whether a sample from the start of a file looks encrypted or compressed, although its extension
+/// is not that of a compressed format.
+pub fn is_unexpectedly_random(extension: Option<&str>, sample: &[u8]) -> bool {
+    let compressed = extension.is_some_and(|e| COMPRESSED_EXTENSIONS.contains(&e.to_ascii_lowercase().as_str()));
+    !compressed && sample.len() >= ENTROPY_MIN_SAMPLE_LEN && entropy(sample) > HIGH_ENTROPY
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_mismatch() {
+        assert!(!is_mismatch("jpg", b"\xFF\xD8\xFF\xE0\0\x10JFIF"));
+        assert!(!is_mismatch("JPG", b"\xFF\xD8\xFF\xE0\0\x10JFIF"));
+        assert!(is_mismatch("jpg", b"PK\x03\x04\x14\0\0\0"));
+        assert!(!is_mismatch("docx", b"PK\x03\x04\x14\0\0\0"));
+        assert!(!is_mismatch("tiff", b"MM\0*\0\0\0\x08"));
+        assert!(!is_mismatch("webp", b"RIFF\0\0\0\0WEBPVP8 "));
+        assert!(is_mismatch("webp", b"RIFF\0\0\0\0WAVEfmt "));
+        assert!(!is_mismatch("txt", b"PK\x03\x04\x14\0\0\0"));
+        assert!(!is_mismatch("png", b""));
+    }
+
+    #[test]
+    fn test_classify_extensionless() {
+        assert_eq!(classify_extensionless(b"#!/bin/sh\n"), Some("script"));
+        assert_eq!(classify_extensionless(b"\x7FELF\x02\x01\x01"), Some("binary"));
+        assert_eq!(classify_extensionless(b"\xCF\xFA\xED\xFE\x07"), Some("binary"));
+        assert_eq!(classify_extensionless(b"all: build\n"), None);
+        assert_eq!(classify_extensionless(b""), None);
+    }
+
+    #[test]
+    fn test_is_text() {
+        assert!(is_text(b"fn main() {}\n"));
+        assert!(is_text("caf\u{e9}".as_bytes()));
+        assert!(!is_text(&"caf\u{e9}".as_bytes()[..4]));
+        assert!(!is_text(b"\x45\x03\x30\xE6"));
+        let mut sample = vec![b'a'; TEXT_SAMPLE_LEN - 1];
+        sample.push(0xC3);
+        assert!(is_text(&sample));
+        assert!(is_text(b"\xFF\xFEh\0i\0"));
+        assert!(is_text(b""));
+        assert!(!is_text(b"\x7FELF\x02\x01\x01\0"));
+        assert!(!is_text(b"caf\xE9 au lait"));
+    }
+
+    #[test]
+    fn test_detect_encoding() {
+        assert_eq!(detect_encoding(b"plain\r\n"), Some(Encoding::Ascii));
+        assert_eq!(detect_encoding("caf\u{e9}".as_bytes()), Some(Encoding::Utf8));
+        assert_eq!(detect_encoding(b"\xEF\xBB\xBFcaf\xC3\xA9"), Some(Encoding::Utf8Bom));
+        assert_eq!(detect_encoding(b"\xFF\xFEh\0i\0"), Some(Encoding::Utf16LeBom));
+        assert_eq!(detect_encoding(b"h\0i\0!\0"), Some(Encoding::Utf16Le));
+        assert_eq!(detect_encoding(b"\0h\0i\0!"), Some(Encoding::Utf16Be));
+        assert_eq!(detect_encoding(b"caf\xE9;cr\xE8me\n"), Some(Encoding::Latin1));
+        assert_eq!(detect_encoding(b"caf\xC3"), Some(Encoding::Latin1));
+        assert_eq!(detect_encoding(b"\x7FELF\x02\x01\x01\0"), None);
+        assert!(Encoding::Latin1.is_legacy());
+        assert!(!Encoding::Utf8Bom.is_legacy());
+    }
+
+    #[test]
+    fn test_entropy() {
+        assert_eq!(entropy(b"aaaa"), 0.0);
+        assert_eq!(entropy(b"abab"), 1.0);
+        let all_bytes: Vec<u8> = (0..=255).collect();
+        assert_eq!(entropy(&all_bytes), 8.0);
+    }
+
+    #[test]
+    fn test_is_unexpectedly_random() {
+        let random: Vec<u8> = (0..ENTROPY_SAMPLE_LEN).map(|i| (i * 167 % 256) as u8).collect();
+        let text = "the quick brown fox jumps over the lazy dog ".repeat(100);
+        assert!(is_unexpectedly_random(Some("docx2"), &random));
+        assert!(is_unexpectedly_random(None, &random));
+        assert!(!is_unexpectedly_random(Some("ZIP"), &random));
+        assert!(!is_unexpectedly_random(Some("txt"), text.as_bytes()));
+        assert!(!is_unexpectedly_random(Some("txt"), &random[..100]));
+    }
+}
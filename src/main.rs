@@ -1,33 +1,1127 @@
+mod agent;
+mod audit;
+mod bench;
+mod budget;
+mod checkpoint;
+mod classify;
+mod config;
+mod dirent;
 mod file;
+mod filter;
+mod fs;
+mod ftp;
+mod git;
+mod group;
+mod ignore;
+mod languages;
+mod listing;
+mod locale;
+mod magic;
+mod manifest;
+mod media;
+mod memory;
+mod normalize;
+mod preset;
+mod progress;
+mod quota;
+mod regex;
+mod report;
+mod retry;
+mod sanity;
+mod sha256;
+mod storage;
+mod tar;
+mod theme;
+mod throttle;
+mod trash;
+#[cfg(target_os = "linux")]
+mod uring;
+mod webdav;
+mod width;
 
-use anyhow::Result;
-use clap::Parser;
-use file::{Directory, ExtensionSortingMethod};
-use std::path::PathBuf;
+use anyhow::{Context, Result};
+use audit::Finding;
+use clap::{ArgEnum, ArgMatches, CommandFactory, FromArgMatches, Parser, Subcommand};
+use file::{
+    size_human_readable, Directory, DrawOptions, ExtensionSortingMethod, Extensionless, PathDisplay, ScanOptions,
+};
+use locale::Locale;
+use preset::Preset;
+use report::{Warning, Warnings};
+use retry::Retry;
+use std::collections::HashSet;
+use std::io::{self, BufWriter, IsTerminal, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+use theme::Palette;
+use throttle::{ByteThrottle, Throttle};
 
 #[derive(Parser, Debug)]
-#[clap(author, version, about)]
+#[clap(
+    author,
+    version,
+    about,
+    args_conflicts_with_subcommands = true,
+    subcommand_negates_reqs = true
+)]
 struct Args {
-    /// Root directory for extension count.
-    directory: PathBuf,
+    #[clap(subcommand)]
+    command: Option<Command>,
+
+    /// Root directory for extension count, or a directory on an FTP server given as
+    /// ftp://[user[:password]@]host[:port]/path, or on a WebDAV server given as
+    /// http://[user[:password]@]host[:port]/path.
+    #[clap(required_unless_present_any = &["input-tar", "input-listing", "agent"])]
+    directory: Option<PathBuf>,
+
+    /// Count the members of a tar archive, or of one read from stdin if '-', instead of a
+    /// directory. Archives compressed with gzip, zstd, bzip2 or xz are decompressed with the
+    /// corresponding command.
+    #[clap(
+        long,
+        value_name = "PATH",
+        conflicts_with_all = &[
+            "directory", "checkpoint", "baseline", "stream", "du", "io-uring", "follow-junctions",
+            "verify-types", "entropy", "media-duration", "text-binary", "line-endings", "encodings",
+        ]
+    )]
+    input_tar: Option<PathBuf>,
+
+    /// Count the files of a listing taken with find, ls -lR or as path,size CSV, or of one read from
+    /// stdin if '-', instead of a directory.
+    #[clap(
+        long,
+        value_name = "PATH",
+        conflicts_with_all = &[
+            "directory", "input-tar", "checkpoint", "baseline", "stream", "du", "io-uring",
+            "follow-junctions", "verify-types", "entropy", "media-duration", "text-binary", "line-endings", "encodings",
+        ]
+    )]
+    input_listing: Option<PathBuf>,
+
+    /// Count only the files added or modified in the git index of the repository holding the
+    /// directory, with the size of their staged contents, e.g. to check --budgets from a pre-commit
+    /// hook.
+    #[clap(
+        long,
+        conflicts_with_all = &[
+            "input-tar", "input-listing", "agent", "checkpoint", "baseline", "stream", "du", "io-uring",
+            "follow-junctions", "verify-types", "entropy", "media-duration", "text-binary", "line-endings", "encodings",
+        ]
+    )]
+    staged: bool,
+
+    /// Count the files of a git revision, such as a tag or commit, below the directory, with the
+    /// sizes stored by git, without checking it out.
+    #[clap(
+        long,
+        value_name = "COMMIT-ISH",
+        conflicts_with_all = &[
+            "input-tar", "input-listing", "agent", "staged", "checkpoint", "baseline", "stream", "du", "io-uring",
+            "follow-junctions", "verify-types", "entropy", "media-duration", "text-binary", "line-endings", "encodings",
+        ]
+    )]
+    git_rev: Option<String>,
+
+    /// Count only the files below the directory added or modified since this commit, as listed by
+    /// git diff --name-only, e.g. to check the files of a pull request without scanning the whole
+    /// repository. Untracked files are left out.
+    #[clap(
+        long,
+        value_name = "COMMIT",
+        conflicts_with_all = &["input-tar", "input-listing", "agent", "staged", "git-rev", "checkpoint"]
+    )]
+    since: Option<String>,
+
+    /// Have an agent started with `rextc agent` scan a directory on its host, given as
+    /// host:port=path, instead of scanning here. Repeat to scan on several hosts at once; the
+    /// subtrees are merged under their host names. Only --depth and --no-size are passed on.
+    /// Agents must have been allowed the path with --allow-root.
+    #[clap(
+        long,
+        value_name = "HOST:PORT=PATH",
+        multiple_occurrences = true,
+        conflicts_with_all = &[
+            "directory", "input-tar", "input-listing", "checkpoint", "baseline", "stream", "du",
+            "io-uring", "system", "progress-json", "group-by", "group-by-regex",
+        ]
+    )]
+    agent: Vec<String>,
+
+    /// Send the token held in this file to the agents, which they must have been given with
+    /// --token-file.
+    #[clap(long, value_name = "FILE", requires = "agent")]
+    agent_token_file: Option<PathBuf>,
+
+    /// Format of the listing given with --input-listing.
+    #[clap(long, arg_enum, default_value = "auto", requires = "input-listing")]
+    listing_format: listing::ListingFormat,
 
     /// Sorting mode for extensions only.
     #[clap(short, long, arg_enum, default_value = "file-size")]
     sort: ExtensionSortingMethod,
 
-    /// Depth of recursion.
-    #[clap(short, long, default_value_t = 0)]
+    /// Preset of options for a kind of tree. Options given explicitly take precedence, and the
+    /// patterns of --exclude replace those of the preset.
+    #[clap(long, arg_enum)]
+    preset: Option<Preset>,
+
+    /// Depth of recursion. Also accepted as -L, like tree.
+    #[clap(short, long, short_alias = 'L', default_value_t = 0)]
     depth: usize,
 
+    /// Accepted for compatibility with tree. Hidden files are always counted.
+    #[clap(short = 'a', long = "all")]
+    _all: bool,
+
+    #[clap(flatten)]
+    classifier: classify::ClassifierArgs,
+
+    /// Scan pseudo file systems such as /proc, /sys, /dev and /run, which are skipped by default as
+    /// their sizes are meaningless and reading them can block.
+    #[clap(long)]
+    include_pseudo_fs: bool,
+
+    /// Scan a whole system from / or C:\: file systems mounted more than once are scanned once,
+    /// directories that cannot be read for lack of permission are counted as empty, mounts are
+    /// marked and a summary per file system follows the tree. The depth defaults to 1.
+    #[clap(
+        long,
+        conflicts_with_all = &["include-pseudo-fs", "fail-fast", "input-tar", "input-listing", "stream", "du"]
+    )]
+    system: bool,
+
+    /// Label of the group of files without an extension. Defaults to N/A in the language of
+    /// --lang.
+    #[clap(long, value_name = "LABEL")]
+    extensionless_label: Option<String>,
+
+    /// Print the total playing time in hours of each audio and video extension, read from the
+    /// container metadata of MP4/QuickTime, WAV and FLAC files.
+    #[clap(long, conflicts_with = "checkpoint")]
+    media_duration: bool,
+
     /// Print empty directories.
     #[clap(short, long)]
     empty: bool,
+
+    /// How directories and listed files are identified in the tree and reports.
+    #[clap(short, long, arg_enum, default_value = "name")]
+    paths: PathDisplay,
+
+    /// Only count files, skipping the metadata lookups needed for sizes.
+    #[clap(long)]
+    no_size: bool,
+
+    /// Maximum number of directory reads and metadata lookups per second.
+    #[clap(long)]
+    throttle: Option<f64>,
+
+    /// Maximum number of bytes of file contents read per second, e.g. 20M, by the options reading
+    /// files such as --verify-types, --entropy or --line-endings.
+    #[clap(long, value_name = "SIZE")]
+    read_throttle: Option<String>,
+
+    /// Number of times a directory read or metadata lookup failing with a transient error (EIO,
+    /// timeout) is retried, waiting twice as long each time.
+    #[clap(long)]
+    retries: Option<u32>,
+
+    /// Time waited before the first retry, e.g. 500ms, 2s.
+    #[clap(long, default_value = "500ms", parse(try_from_str = retry::parse_delay))]
+    retry_delay: Duration,
+
+    /// Look up file sizes in batches through io_uring (Linux only), as many at once as suits the
+    /// storage the directory lives on. Without it, sizes are looked up one at a time.
+    #[clap(long)]
+    io_uring: bool,
+
+    /// Number of lookups submitted at once with --io-uring. Defaults to a value suited to the
+    /// storage the directory lives on: spinning disk, solid-state or network. Scans without
+    /// --io-uring are not tuned to the storage.
+    #[clap(long)]
+    queue_depth: Option<u32>,
+
+    /// Scan the directories Windows junctions and directory reparse points lead to, rather than
+    /// only counting them. Each target is scanned at most once.
+    #[clap(long)]
+    follow_junctions: bool,
+
+    /// Record completed directories in this file, and skip those already recorded there by an
+    /// interrupted scan of the same directory.
+    #[clap(long, conflicts_with_all = &["group-by", "group-by-regex"])]
+    checkpoint: Option<PathBuf>,
+
+    /// Print how the count and size of each extension changed since the scan recorded in this
+    /// checkpoint file, which must be of the same directory and depth.
+    #[clap(long, conflicts_with = "stream")]
+    baseline: Option<PathBuf>,
+
+    /// Report extensions found in none of the directories of the --baseline scan, such as .env or
+    /// .pem files appearing in a repository, and warn about them or fail.
+    #[clap(long, arg_enum, value_name = "ACTION", requires = "baseline", conflicts_with = "du")]
+    new_extensions: Option<report::NewExtensions>,
+
+    /// Check the sizes of extensions, categories or extension patterns, over the whole scan or in
+    /// every directory, against the limits in this budget file, and fail if any is exceeded.
+    #[clap(long, value_name = "FILE", conflicts_with_all = &["no-size", "checkpoint", "stream", "du"])]
+    budgets: Option<PathBuf>,
+
+    /// Check that the tree still matches a manifest, either a listing as read with --input-listing
+    /// or the output of sha256sum, instead of printing it, and fail if any file is missing,
+    /// unexpected or changed.
+    #[clap(
+        long,
+        value_name = "MANIFEST",
+        conflicts_with_all = &["stream", "du", "split-output", "checkpoint", "agent", "budgets"]
+    )]
+    check: Option<PathBuf>,
+
+    /// Print how many files of each extension have more than one hard link, and their size.
+    #[clap(long, conflicts_with = "no-size")]
+    hardlinks: bool,
+
+    /// Print how much of the size of each extension is stored locally and how much only in the
+    /// cloud, as placeholders of OneDrive Files On-Demand or iCloud Drive (Windows and macOS), or
+    /// how many files are placeholders with --no-size.
+    #[clap(long)]
+    cloud: bool,
+
+    /// Print how many files of each extension are text and how many binary, sniffing the first
+    /// block of each file for NUL bytes and invalid UTF-8.
+    #[clap(long, conflicts_with = "checkpoint")]
+    text_binary: bool,
+
+    /// Report how many text files of each extension use LF, CRLF or mixed line endings, and the
+    /// directories holding files with CRLF line endings. Reads every text file in full.
+    #[clap(long, conflicts_with = "checkpoint")]
+    line_endings: bool,
+
+    /// Report the encodings of the text files of each extension (ASCII, UTF-8, UTF-16 and 8-bit
+    /// encodings such as Latin-1, with or without byte order mark), and the directories holding
+    /// files in UTF-16 or 8-bit encodings.
+    #[clap(long, conflicts_with = "checkpoint")]
+    encodings: bool,
+
+    /// Print a stable, tab-separated summary for scripts instead of the tree and reports.
+    #[clap(long)]
+    porcelain: bool,
+
+    /// Write the tree of every directory directly below the root to a file of its own in DIR,
+    /// named after it, instead of printing the tree, along with an index listing each of them.
+    /// With --porcelain, the files hold porcelain records instead. The depth is at least 1.
+    #[clap(long, value_name = "DIR", conflicts_with_all = &["stream", "du"])]
+    split_output: Option<PathBuf>,
+
+    /// Print every directory as soon as its subtree has been scanned, children before parents, as a
+    /// block with the extensions of everything below it, or as tab-separated lines with
+    /// --porcelain. Only the ancestors of the directory being scanned are kept in memory, which
+    /// suits long scans and trees too large to hold in memory. The scan stops quietly when the
+    /// output is closed, e.g. by piping it into head.
+    #[clap(long, conflicts_with_all = &["top-dirs", "devices", "quota"])]
+    stream: bool,
+
+    /// Report extensions appearing in more than one casing or spelling (e.g. JPG/jpg/jpeg).
+    #[clap(long)]
+    variants: bool,
+
+    /// Report file name and path length statistics, and the longest file name and path per
+    /// directory.
+    #[clap(long)]
+    name_lengths: bool,
+
+    /// Report extensions per file system, for trees spanning several mounts.
+    #[clap(long)]
+    devices: bool,
+
+    /// Compare the space used by each user against the limits in this file, with one user name or
+    /// ID and limit (e.g. 10G) per line.
+    #[clap(long, conflicts_with_all = &["no-size", "checkpoint"])]
+    quota: Option<PathBuf>,
+
+    /// Mark directories on a different file system than their parent, along with its type.
+    #[clap(long)]
+    mounts: bool,
+
+    /// Mark each directory with the extension taking up most of the size of the files below it,
+    /// or most of the files with --no-size, and its share, e.g. builds [zip 92%].
+    #[clap(long)]
+    dominant: bool,
+
+    /// Count file and directory names with control characters, invalid UTF-8 or trailing spaces or
+    /// dots.
+    #[clap(long)]
+    name_audit: bool,
+
+    /// Count files whose content does not match their extension, e.g. a ZIP archive named .jpg.
+    #[clap(long)]
+    verify_types: bool,
+
+    /// Sample the start of every file and report those that look encrypted or compressed without
+    /// having the extension of a compressed format.
+    #[clap(long)]
+    entropy: bool,
+
+    /// Count symlinks whose target does not exist, per directory.
+    #[clap(long)]
+    broken_symlinks: bool,
+
+    /// With --broken-symlinks, list every broken symlink along with its target.
+    #[clap(long, requires = "broken-symlinks")]
+    symlink_targets: bool,
+
+    /// List world-writable directories, the extensions and sizes of world-writable files, and
+    /// setuid or setgid files.
+    #[clap(long, conflicts_with = "no-size")]
+    security: bool,
+
+    /// Count files with mode 777, world- or group-writable files and setuid or setgid files.
+    #[clap(long, conflicts_with = "no-size")]
+    permission_audit: bool,
+
+    /// Count files whose absolute path is longer than N characters, 260 if not given, per
+    /// directory, and list the longest of them. Windows programs often fail on longer paths.
+    #[clap(long, value_name = "N", min_values = 0, default_missing_value = "260")]
+    max_path_length: Option<usize>,
+
+    /// Count the files and directories whose name only differs by case from another one in the
+    /// same directory, e.g. Readme.md and README.md, which clash on case-insensitive file systems.
+    #[clap(long)]
+    case_collisions: bool,
+
+    /// Count the files and directories that cannot be synced to Windows, named after a device
+    /// such as CON, NUL, COM1 or LPT1 (with any extension), or ending in a space or dot.
+    #[clap(long)]
+    windows_names: bool,
+
+    /// List every file or directory found by the name, type, permission, path length, case
+    /// collision or Windows name checks below the count of its directory.
+    #[clap(long)]
+    list_offenders: bool,
+
+    /// Stop at the first file or directory that cannot be read, naming it, instead of skipping it.
+    #[clap(long)]
+    fail_fast: bool,
+
+    /// Fail after printing the output if there were any warnings, such as unreadable or vanished
+    /// entries, broken symlinks or audit findings.
+    #[clap(long)]
+    warnings_as_errors: bool,
+
+    /// Soft limit on the memory held by the tree, e.g. 512M. Once exceeded, directories scanned
+    /// afterwards are kept to fewer levels, with the files of deeper ones counted in their parent,
+    /// and a warning is counted.
+    #[clap(long, value_name = "SIZE")]
+    max_memory: Option<String>,
+
+    /// Print the time taken and the peak memory use to stderr once done.
+    #[clap(long)]
+    timings: bool,
+
+    /// Print progress events to stderr as JSON lines, one per second and one once done, with the
+    /// files and bytes counted so far, the current path and, when scanning a whole file system,
+    /// the estimated remaining time.
+    #[clap(long)]
+    progress_json: bool,
+
+    /// List the N directories with the largest recursive size across the whole tree, along with
+    /// the extensions taking up most of their size. The depth defaults to unlimited.
+    #[clap(long, value_name = "N", conflicts_with = "no-size")]
+    top_dirs: Option<usize>,
+
+    /// Report the maximum and average depth of the directories below the root, the deepest of
+    /// them, and how many directories there are at each depth, including below --depth.
+    #[clap(long, conflicts_with = "agent")]
+    depth_stats: bool,
+
+    /// Report how many files of each extension have a compressed copy next to them, such as
+    /// access.log and access.log.gz, with the size of those files and of their copies.
+    #[clap(long, conflicts_with_all = &["no-size", "agent"])]
+    compressed_pairs: bool,
+
+    /// List the N largest files across the whole scan, with their size and extension.
+    #[clap(long, value_name = "N", conflicts_with_all = &["no-size", "agent"])]
+    largest: Option<usize>,
+
+    /// Count every package installed in node_modules as a whole, including scoped packages, instead
+    /// of descending into it, and report the heaviest packages with the extensions they ship. The
+    /// depth defaults to unlimited, so that node_modules directories are found wherever they are.
+    #[clap(long, conflicts_with_all = &["checkpoint", "agent"])]
+    node_modules: bool,
+
+    /// Report the files of each package of a monorepo, marked by a Cargo.toml with a [package]
+    /// table, a package.json or a --workspace-marker, instead of by directory. Dependencies
+    /// installed in node_modules are counted with the package installing them.
+    #[clap(long, conflicts_with_all = &["checkpoint", "agent"])]
+    workspaces: bool,
+
+    /// Name of a file marking the directory holding it as a package for --workspaces, such as
+    /// OWNERS. Repeat for several names.
+    #[clap(long, value_name = "NAME", multiple_occurrences = true, requires = "workspaces")]
+    workspace_marker: Vec<String>,
+
+    /// Only show the files counted under this extension, compared regardless of case, and the
+    /// directories holding any of them, e.g. to trace where all .log files are. The depth defaults
+    /// to unlimited.
+    #[clap(long, value_name = "EXTENSION", conflicts_with = "stream")]
+    focus: Option<String>,
+
+    /// Draw a treemap of the sizes of the directories directly below the scanned one, or of the
+    /// extensions of all files, sized to the COLUMNS of the terminal. With directories, the depth
+    /// defaults to 1.
+    #[clap(
+        long,
+        arg_enum,
+        value_name = "BY",
+        min_values = 0,
+        default_missing_value = "directory",
+        conflicts_with = "no-size"
+    )]
+    treemap: Option<report::TreemapBy>,
+
+    /// Report the number and size of the files last modified in each month, oldest first.
+    #[clap(long, conflicts_with_all = &["by-year", "no-size", "agent"])]
+    by_month: bool,
+
+    /// Report the number and size of the files last modified in each year, oldest first.
+    #[clap(long, conflicts_with_all = &["no-size", "agent"])]
+    by_year: bool,
+
+    /// With --by-month or --by-year, break every period down by extension.
+    #[clap(long)]
+    by_period_extension: bool,
+
+    /// Only print the recursive size of every directory, in the layout of du, children before
+    /// parents. Directories are printed as soon as they have been scanned. Sizes are those of the
+    /// files below, without the directories themselves, so they are a little below those of du.
+    #[clap(long, conflicts_with_all = &["no-size", "porcelain"])]
+    du: bool,
+
+    /// With --du, print sizes in bytes rather than human-readable.
+    #[clap(long, requires = "du")]
+    bytes: bool,
+
+    /// Colors of growth and shrinkage: red and green, or orange and blue, which remain distinct
+    /// with the common forms of color blindness.
+    #[clap(long, arg_enum, default_value = "default")]
+    palette: Palette,
+
+    /// Override the colors of the palette with those in this file, with lines such as 'growth =
+    /// "#E69F00"' mapping an element (growth, shrinkage) to a color.
+    #[clap(long, value_name = "PATH")]
+    theme: Option<PathBuf>,
+
+    /// Print each item on a line of its own with its depth, e.g. 'level 2: src — rs — 120 files —
+    /// 3.40 MiB', instead of drawing a tree, for screen readers and plain-text processing.
+    #[clap(long, conflicts_with_all = &["porcelain", "du"])]
+    plain: bool,
+
+    /// Language of labels, units, report text and error messages. Defaults to the language of
+    /// LC_ALL, LC_MESSAGES or LANG, or English if it has no translation.
+    #[clap(long, arg_enum)]
+    lang: Option<Locale>,
 }
 
-fn main() -> Result<()> {
-    let args = Args::parse();
-    let mut directory = Directory::new(args.directory, 0, args.depth)?;
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Manage the configuration files in ~/.config/extension-count.
+    #[clap(subcommand)]
+    Config(config::ConfigCommand),
+
+    /// Time repeated scans of a directory, and compare them against saved timings.
+    Bench(bench::BenchArgs),
+
+    /// Scan directories on request of a coordinator run with --agent, sending back the extensions
+    /// of each directory.
+    Agent(agent::AgentArgs),
+
+    /// Explain how a file is counted: the extension it is counted under, how each rule led there,
+    /// and the filters that would skip it.
+    Classify(classify::ClassifyArgs),
+}
+
+fn main() {
+    let matches = Args::command().get_matches();
+    let locale = matches
+        .value_of("lang")
+        .and_then(|lang| Locale::from_str(lang, true).ok())
+        .unwrap_or_else(Locale::detect);
+    let start = Instant::now();
+    let result = run(&matches, locale).or_else(|error| if is_broken_pipe(&error) { Ok(()) } else { Err(error) });
+    if matches.is_present("timings") {
+        print_timings(start.elapsed());
+    }
+    if let Err(error) = result {
+        eprintln!("{}: {}", locale.text("Error"), locale.message(&error.to_string()));
+        let mut causes = error.chain().skip(1).peekable();
+        if causes.peek().is_some() {
+            eprintln!("\n{}:", locale.text("Caused by"));
+            for cause in causes {
+                eprintln!("    {}", locale.message(&cause.to_string()));
+            }
+        }
+        std::process::exit(1);
+    }
+}
+
+/// Print the time taken and the peak memory use to stderr.
+fn print_timings(elapsed: Duration) {
+    eprintln!("elapsed     ── {:>10.3} s", elapsed.as_secs_f64());
+    match memory::peak_resident() {
+        Some(bytes) => eprintln!(
+            "peak memory ── {:>10}",
+            size_human_readable(bytes, 2, Locale::En).trim_end()
+        ),
+        None => eprintln!("peak memory ──        n/a"),
+    }
+}
+
+fn run(matches: &ArgMatches, locale: Locale) -> Result<()> {
+    // The directory is only required without a subcommand, so the arguments are not parsed as a
+    // whole when one is given.
+    match matches.subcommand() {
+        Some(("config", matches)) => return config::run(&config::ConfigCommand::from_arg_matches(matches)?),
+        Some(("bench", matches)) => return bench::run(&bench::BenchArgs::from_arg_matches(matches)?),
+        Some(("agent", matches)) => return agent::run(&agent::AgentArgs::from_arg_matches(matches)?),
+        Some(("classify", matches)) => return classify::run(&classify::ClassifyArgs::from_arg_matches(matches)?),
+        _ => {}
+    }
+    let mut args = Args::from_arg_matches(matches)?;
+    if let Some(preset) = args.preset.map(Preset::options) {
+        if matches.occurrences_of("sort") == 0 {
+            args.sort = preset.sort.unwrap_or(args.sort);
+        }
+        args.classifier.exclude = args.classifier.exclude.or(Some(preset.exclude));
+        args.classifier.languages |= preset.languages;
+        // Playing time is not recorded in checkpoints.
+        args.media_duration |= preset.media_duration && args.checkpoint.is_none();
+        args.classifier.split_archives |= preset.split_archives;
+        args.variants |= preset.variants;
+        if matches.occurrences_of("trash") == 0 {
+            args.classifier.trash = preset.trash.unwrap_or(args.classifier.trash);
+        }
+    }
+    if args.system {
+        args.devices = true;
+        args.mounts = true;
+        // File systems are only told apart down to the recursion limit.
+        if matches.occurrences_of("depth") == 0 {
+            args.depth = 1;
+        }
+    }
+    // Files of the extension focused on are traced down to the deepest directories.
+    if args.focus.is_some() && matches.occurrences_of("depth") == 0 {
+        args.depth = usize::MAX;
+    }
+    // The largest directories are looked for across the whole tree.
+    if args.top_dirs.is_some() && matches.occurrences_of("depth") == 0 {
+        args.depth = usize::MAX;
+    }
+    // Packages are found in node_modules directories at any depth, and are not descended into.
+    if args.node_modules && matches.occurrences_of("depth") == 0 {
+        args.depth = usize::MAX;
+    }
+    // Every directory directly below the scanned one gets a report of its own.
+    if args.split_output.is_some() {
+        args.depth = args.depth.max(1);
+    }
+    // The tiles of the treemap are the directories directly below the scanned one.
+    if args.treemap == Some(report::TreemapBy::Directory) && matches.occurrences_of("depth") == 0 {
+        args.depth = 1;
+    }
+    let max_memory = args.max_memory.as_deref().map(quota::parse_size).transpose()?;
+    let classifier = args.classifier.scan_options()?;
+    if args.no_size && classifier.filter.as_ref().is_some_and(|f| f.needs_metadata()) {
+        anyhow::bail!("--where cannot look at size or mtime with --no-size");
+    }
+    let remote = args
+        .directory
+        .as_deref()
+        .and_then(Path::to_str)
+        .filter(|directory| ftp::is_ftp_url(directory) || webdav::is_webdav_url(directory));
+    if remote.is_some() {
+        let unavailable = [
+            (args.checkpoint.is_some(), "--checkpoint"),
+            (args.baseline.is_some(), "--baseline"),
+            (args.stream, "--stream"),
+            (args.du, "--du"),
+            (args.io_uring, "--io-uring"),
+            (args.verify_types, "--verify-types"),
+            (args.entropy, "--entropy"),
+            (args.media_duration, "--media-duration"),
+            (args.text_binary, "--text-binary"),
+            (args.line_endings, "--line-endings"),
+            (args.encodings, "--encodings"),
+        ];
+        if let Some((_, option)) = unavailable.iter().find(|(given, _)| *given) {
+            anyhow::bail!("{} is not available for ftp:// and http:// roots", option);
+        }
+    }
+    if args.by_period_extension && !args.by_month && !args.by_year {
+        anyhow::bail!("--by-period-extension requires --by-month or --by-year");
+    }
+    let listing = match (&args.input_tar, &args.input_listing, remote) {
+        (Some(input), _, _) => Some(tar::read_tar(input)?),
+        (None, Some(input), _) => Some(listing::read_listing(input, args.listing_format)?),
+        (None, None, Some(url)) if webdav::is_webdav_url(url) => Some(webdav::read_webdav(url)?),
+        (None, None, Some(url)) => Some(ftp::read_ftp(url)?),
+        (None, None, None) if args.staged => Some(git::read_staged(
+            args.directory
+                .as_deref()
+                .expect("the directory is required with --staged"),
+        )?),
+        (None, None, None) => match &args.git_rev {
+            Some(revision) => Some(git::read_revision(
+                args.directory
+                    .as_deref()
+                    .expect("the directory is required with --git-rev"),
+                revision,
+            )?),
+            None => None,
+        },
+    };
+    let root = match &listing {
+        Some(listing) => listing.root().to_path_buf(),
+        // Agents scan their own directories, the local one only serves for setting up.
+        None if !args.agent.is_empty() => PathBuf::from("/"),
+        None => args
+            .directory
+            .clone()
+            .expect("the directory is required without --input-tar or --input-listing"),
+    };
+    if listing.is_some() && args.classifier.extensionless == Extensionless::Content {
+        anyhow::bail!("--extensionless content cannot read the contents of archive members, listed or remote files");
+    }
+    let is_local = listing.is_none();
+    let file_system: Box<dyn fs::FileSystem> = match listing {
+        Some(listing) => Box::new(listing),
+        None => Box::new(fs::OsFileSystem {
+            #[cfg(target_os = "linux")]
+            ring: if args.io_uring {
+                let queue_depth = args
+                    .queue_depth
+                    .unwrap_or_else(|| storage::Storage::detect(&root).queue_depth());
+                Some(uring::Ring::new(queue_depth).context("could not set up io_uring")?)
+            } else {
+                None
+            },
+        }),
+    };
+    let file_system: Box<dyn fs::FileSystem> = match &args.since {
+        Some(commit) => Box::new(git::read_changed(&root, commit, file_system)?),
+        None => file_system,
+    };
+    let settings = counting_options(&args);
+    let options = ScanOptions {
+        max_depth: args.depth,
+        ignore: args.classifier.ignore(&file_system.canonicalize(&root)?)?,
+        media_duration: args.media_duration,
+        text_binary: args.text_binary,
+        line_endings: args.line_endings,
+        encodings: args.encodings,
+        name_lengths: args.name_lengths,
+        no_size: args.no_size,
+        name_audit: args.name_audit,
+        permission_audit: args.permission_audit,
+        max_path_length: args.max_path_length,
+        case_collisions: args.case_collisions,
+        windows_names: args.windows_names,
+        compressed_pairs: args.compressed_pairs,
+        security: args.security,
+        verify_types: args.verify_types,
+        entropy: args.entropy,
+        broken_symlinks: args.broken_symlinks,
+        owners: args.quota.is_some(),
+        devices: args.devices || args.mounts,
+        throttle: args.throttle.map(Throttle::new).transpose()?,
+        read_throttle: args
+            .read_throttle
+            .as_deref()
+            .map(|rate| ByteThrottle::new(quota::parse_size(rate)?))
+            .transpose()?,
+        retry: match args.retries {
+            Some(retries) => Some(Retry::new(retries, args.retry_delay)),
+            None => None,
+        },
+        follow_junctions: args.follow_junctions,
+        followed_junctions: Default::default(),
+        file_system,
+        checkpoint: args
+            .checkpoint
+            .as_deref()
+            .map(|path| checkpoint::Checkpoint::open(path, &root, args.depth, &settings))
+            .transpose()?,
+        fail_fast: args.fail_fast,
+        skip_unreadable: args.system,
+        once_per_volume: args.system,
+        volumes: Default::default(),
+        max_memory,
+        tree_memory: Default::default(),
+        warnings: Default::default(),
+        kept_depth: Default::default(),
+        scan_root: Default::default(),
+        largest: args.largest.map(report::LargestFiles::new),
+        periods: match (args.by_month, args.by_year) {
+            (true, _) => Some(report::Periods::new(report::Period::Month, args.by_period_extension)),
+            (_, true) => Some(report::Periods::new(report::Period::Year, args.by_period_extension)),
+            _ => None,
+        },
+        depths: args.depth_stats.then(report::DepthStats::default),
+        node_modules: args.node_modules,
+        workspaces: args
+            .workspaces
+            .then(|| report::Workspaces::new(args.workspace_marker.clone())),
+        progress: args
+            .progress_json
+            .then(|| progress::Progress::new(if is_local { storage::used_bytes(&root) } else { None })),
+        pseudo_mounts: if args.include_pseudo_fs {
+            HashSet::new()
+        } else {
+            storage::pseudo_mounts()
+        },
+        ..classifier
+    };
+    #[cfg(not(target_os = "linux"))]
+    if args.io_uring {
+        anyhow::bail!("io_uring is only available on Linux");
+    }
+    let draw_options = DrawOptions {
+        empty: args.empty,
+        paths: args.paths,
+        size: !args.no_size,
+        mounts: args.mounts,
+        dominant: args.dominant,
+        hardlinks: args.hardlinks,
+        cloud: args.cloud,
+        text_binary: args.text_binary,
+        baseline: args
+            .baseline
+            .as_deref()
+            .map(|path| checkpoint::read_baseline(path, &root, args.depth, &settings))
+            .transpose()?,
+        color: Some(theme::read_theme(
+            args.palette,
+            args.theme
+                .or_else(|| config::config_file(config::THEME_FILE))
+                .as_deref(),
+        )?)
+        // Reports written with --split-output are files, which should not hold escape codes.
+        .filter(|_| io::stdout().is_terminal() && !args.plain && args.split_output.is_none()),
+        extensionless_label: args
+            .extensionless_label
+            .unwrap_or_else(|| locale.text("N/A").to_string()),
+        media_duration: args.media_duration,
+        locale,
+        plain: args.plain,
+    };
+
+    if args.stream && args.porcelain {
+        let scan_root = std::fs::canonicalize(&root)?;
+        let mut out = BufWriter::new(io::stdout().lock());
+        report::write_porcelain_header(&mut out)?;
+        let directory = Directory::new_streaming(root, 0, &options, &mut |d| {
+            report::write_porcelain_directory(&mut out, d, &scan_root, &draw_options)?;
+            Ok(out.flush()?)
+        })?;
+        report::write_porcelain_summary(&mut out, &directory, &draw_options)?;
+        options.warnings.add_found(&directory);
+        write_porcelain_end(&mut out, &options.warnings)?;
+        out.flush()?;
+        return check_warnings(&options.warnings, args.warnings_as_errors);
+    }
+
+    if args.du {
+        let scan_root = std::fs::canonicalize(&root)?;
+        let given_root = root.clone();
+        let mut out = BufWriter::new(io::stdout().lock());
+        let directory = Directory::new_streaming(root, 0, &options, &mut |d| {
+            report::write_du_directory(&mut out, d, &scan_root, &given_root, args.bytes)?;
+            Ok(out.flush()?)
+        })?;
+        options.warnings.add_found(&directory);
+        return check_warnings(&options.warnings, args.warnings_as_errors);
+    }
+
+    if let Some(manifest) = &args.check {
+        let manifest = manifest::read_manifest(manifest)?;
+        let scan_root = options.file_system.canonicalize(&root)?;
+        let discrepancies = manifest::check_manifest(&scan_root, &manifest, &options)?;
+        if args.porcelain {
+            let mut out = BufWriter::new(io::stdout().lock());
+            report::write_porcelain_header(&mut out)?;
+            report::write_porcelain_mismatches(&mut out, &discrepancies)?;
+            out.flush()?;
+        } else {
+            report::draw_manifest_check(&discrepancies, &draw_options)?;
+        }
+        if !discrepancies.is_empty() {
+            anyhow::bail!("files not matching the manifest: {}", discrepancies.len());
+        }
+        return Ok(());
+    }
+
+    let quotas = args.quota.as_deref().map(quota::read_quotas).transpose()?;
+    let budgets = args.budgets.as_deref().map(budget::read_budgets).transpose()?;
+    let mut directory = if !args.agent.is_empty() {
+        let token = args
+            .agent_token_file
+            .as_deref()
+            .map(agent::read_token)
+            .transpose()?
+            .unwrap_or_default();
+        agent::gather(&args.agent, &token, args.depth, args.no_size)?
+    } else if args.stream {
+        let scan_root = std::fs::canonicalize(&root)?;
+        let mut out = BufWriter::new(io::stdout().lock());
+        Directory::new_streaming(root, 0, &options, &mut |d| {
+            d.draw_block(&mut out, &scan_root, &args.sort, &draw_options)?;
+            Ok(out.flush()?)
+        })?
+    } else {
+        Directory::new(root, 0, &options)?
+    };
     directory.sort_by(args.sort);
-    directory.draw(args.empty)?;
+    options.warnings.add_found(&directory);
+    if let Some(extension) = &args.focus {
+        directory.focus(extension.trim_start_matches('.'), &draw_options.extensionless_label);
+    }
+    let new_extensions = match (args.new_extensions, &draw_options.baseline) {
+        (Some(_), Some(baseline)) => report::new_extensions(&directory, baseline),
+        _ => Vec::new(),
+    };
+    options.warnings.add_many(Warning::NewExtension, new_extensions.len());
+    let budget_checks = budgets
+        .as_deref()
+        .map(|budgets| report::check_budgets(&directory, budgets))
+        .unwrap_or_default();
+    let exceeded = budget_checks.iter().filter(|check| !check.passed()).count();
+    if let Some(output) = &args.split_output {
+        report::write_split_output(&directory, output, args.porcelain, &draw_options)?;
+    }
+    if args.porcelain {
+        if args.split_output.is_none() {
+            let mut out = BufWriter::new(io::stdout().lock());
+            report::write_porcelain(&mut out, &directory, &draw_options)?;
+            report::write_porcelain_new_extensions(&mut out, &new_extensions, &draw_options)?;
+            report::write_porcelain_budgets(&mut out, &budget_checks)?;
+            write_porcelain_end(&mut out, &options.warnings)?;
+            out.flush()?;
+        }
+        check_warnings(&options.warnings, args.warnings_as_errors)?;
+        check_budgets(exceeded)?;
+        return check_new_extensions(new_extensions.len(), args.new_extensions);
+    }
+
+    if !args.stream && args.split_output.is_none() {
+        directory.draw(&draw_options)?;
+    }
+    if options.warnings.count(Warning::Vanished) > 0 {
+        report::draw_tree_changed(options.warnings.count(Warning::Vanished), &draw_options)?;
+    }
+    if args.variants {
+        report::draw_extension_variants(&directory, &draw_options)?;
+    }
+    if args.name_lengths {
+        report::draw_name_lengths(&directory, &draw_options)?;
+    }
+    if let Some(count) = args.top_dirs {
+        report::draw_top_directories(&directory, count, &draw_options)?;
+    }
+    if let Some(largest) = &options.largest {
+        report::draw_largest_files(largest, directory.path(), &draw_options)?;
+    }
+    if let Some(periods) = &options.periods {
+        report::draw_periods(periods, &draw_options)?;
+    }
+    if args.node_modules {
+        report::draw_node_modules(&directory, &draw_options)?;
+    }
+    if let Some(by) = args.treemap {
+        report::draw_treemap(&directory, by, &draw_options)?;
+    }
+    if let Some(workspaces) = &options.workspaces {
+        report::draw_workspaces(workspaces, directory.path(), &draw_options)?;
+    }
+    if args.compressed_pairs {
+        report::draw_compressed_pairs(&directory, &draw_options)?;
+    }
+    if let Some(depths) = &options.depths {
+        report::draw_depths(depths, directory.path(), &draw_options)?;
+    }
+    if args.devices {
+        report::draw_devices(&directory, &draw_options)?;
+    }
+    if let Some(quotas) = &quotas {
+        report::draw_quotas(&directory, quotas, &draw_options)?;
+    }
+    if args.name_audit {
+        report::draw_findings(
+            &directory,
+            "name audit",
+            Finding::NAME_AUDIT,
+            args.list_offenders,
+            &draw_options,
+        )?;
+    }
+    if args.verify_types {
+        report::draw_findings(
+            &directory,
+            "type verification",
+            Finding::TYPE_VERIFICATION,
+            args.list_offenders,
+            &draw_options,
+        )?;
+    }
+    if args.entropy {
+        report::draw_high_entropy(&directory, &draw_options)?;
+    }
+    if args.line_endings {
+        report::draw_line_endings(&directory, &draw_options)?;
+    }
+    if args.encodings {
+        report::draw_encodings(&directory, &draw_options)?;
+    }
+    if args.broken_symlinks {
+        report::draw_broken_symlinks(&directory, args.symlink_targets, &draw_options)?;
+    }
+    if args.security {
+        report::draw_security(&directory, &draw_options)?;
+    }
+    if args.permission_audit {
+        report::draw_findings(
+            &directory,
+            "permission audit",
+            Finding::PERMISSION_AUDIT,
+            args.list_offenders,
+            &draw_options,
+        )?;
+    }
+    if args.max_path_length.is_some() {
+        report::draw_findings(
+            &directory,
+            "long paths",
+            Finding::PATH_LENGTH,
+            args.list_offenders,
+            &draw_options,
+        )?;
+        report::draw_longest_paths(&directory, &draw_options)?;
+    }
+    if args.case_collisions {
+        report::draw_findings(
+            &directory,
+            "case collisions",
+            Finding::CASE_COLLISIONS,
+            args.list_offenders,
+            &draw_options,
+        )?;
+    }
+    if args.windows_names {
+        report::draw_findings(
+            &directory,
+            "Windows names",
+            Finding::WINDOWS_NAMES,
+            args.list_offenders,
+            &draw_options,
+        )?;
+    }
+    if args.classifier.trash == trash::Trash::Report {
+        report::draw_trash(&directory, &draw_options)?;
+    }
+    if args.new_extensions.is_some() {
+        report::draw_new_extensions(&directory, &new_extensions, &draw_options)?;
+    }
+    if budgets.is_some() {
+        report::draw_budgets(&directory, &budget_checks, &draw_options)?;
+    }
+    report::draw_warnings(&options.warnings, &draw_options)?;
+    check_warnings(&options.warnings, args.warnings_as_errors)?;
+    check_budgets(exceeded)?;
+    check_new_extensions(new_extensions.len(), args.new_extensions)
+}
+
+/// The options changing what a scan counts, recorded in checkpoints so that scans are only resumed
+/// or compared with the same ones.
+fn counting_options(args: &Args) -> String {
+    [
+        ("no-size", args.no_size.to_string()),
+        ("exclude", args.classifier.exclude.clone().unwrap_or_default()),
+        ("include", args.classifier.include.clone().unwrap_or_default()),
+        ("where", args.classifier.filter.clone().unwrap_or_default()),
+        (
+            "ignore-file",
+            args.classifier
+                .ignore_file
+                .as_deref()
+                .map(|path| path.to_string_lossy().into_owned())
+                .unwrap_or_default(),
+        ),
+        ("no-ignore", args.classifier.no_ignore.to_string()),
+        ("no-global-ignore", args.classifier.no_global_ignore.to_string()),
+        ("include-pseudo-fs", args.include_pseudo_fs.to_string()),
+        ("trash", format!("{:?}", args.classifier.trash)),
+        ("extensionless", format!("{:?}", args.classifier.extensionless)),
+        (
+            "max-extension-length",
+            args.classifier
+                .max_extension_length
+                .map(|len| len.to_string())
+                .unwrap_or_default(),
+        ),
+        (
+            "no-numeric-extensions",
+            args.classifier.no_numeric_extensions.to_string(),
+        ),
+        (
+            "extension-chars",
+            args.classifier.extension_chars.clone().unwrap_or_default(),
+        ),
+        ("bogus-extensions", format!("{:?}", args.classifier.bogus_extensions)),
+        ("split-archives", args.classifier.split_archives.to_string()),
+        ("normalize", format!("{:?}", args.classifier.normalize)),
+    ]
+    .iter()
+    .map(|(option, value)| format!("--{}={}", option, value))
+    .collect::<Vec<_>>()
+    .join(" ")
+}
+
+/// Write the porcelain records closing the output: the entries that vanished during the scan, and
+/// the warnings of each kind.
+fn write_porcelain_end(out: &mut impl Write, warnings: &Warnings) -> io::Result<()> {
+    if warnings.count(Warning::Vanished) > 0 {
+        report::write_porcelain_changed(out, warnings.count(Warning::Vanished))?;
+    }
+    report::write_porcelain_warnings(out, warnings)
+}
+
+/// Whether an error comes from writing to stdout after it was closed, e.g. by piping the output
+/// into head, which ends the output quietly rather than being reported.
+fn is_broken_pipe(error: &anyhow::Error) -> bool {
+    error
+        .chain()
+        .any(|cause| cause.downcast_ref::<io::Error>().map(io::Error::kind) == Some(io::ErrorKind::BrokenPipe))
+}
+
+/// Fail if extensions absent from the baseline were found and they are to fail the scan.
+fn check_new_extensions(count: usize, action: Option<report::NewExtensions>) -> Result<()> {
+    if action == Some(report::NewExtensions::Fail) && count > 0 {
+        anyhow::bail!("extensions not in the baseline: {}", count);
+    }
+    Ok(())
+}
+
+/// Fail if any budget was exceeded.
+fn check_budgets(exceeded: usize) -> Result<()> {
+    if exceeded > 0 {
+        anyhow::bail!("budgets exceeded: {}", exceeded);
+    }
+    Ok(())
+}
+
+/// Fail if there were warnings and they are to be treated as errors.
+fn check_warnings(warnings: &Warnings, as_errors: bool) -> Result<()> {
+    if as_errors && warnings.total() > 0 {
+        anyhow::bail!("warnings treated as errors: {}", warnings.total());
+    }
     Ok(())
 }
@@ -0,0 +1,337 @@
+//! Manifests of the files expected in a tree, checked with `--check`. A manifest is either one of
+//! the listings read with `--input-listing`, giving the size of every file, or the output of
+//! `sha256sum`, giving the hash of every file:
+//!
+//! ```text
+//! e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855  ./docs/empty.txt
+//! ```
+//!
+//! Paths are relative to the scanned directory. Only regular files are compared; symlinks are not
+//! followed.
+
+use crate::dirent::EntryKind;
+use crate::file::ScanOptions;
+use crate::fs::FileSystem;
+use crate::listing::{self, ListingFormat};
+use crate::sha256::Sha256;
+use anyhow::{bail, Context, Result};
+use std::collections::{BTreeMap, HashSet};
+use std::io::Read;
+use std::path::{Component, Path, PathBuf};
+
+/// Bytes read at a time when hashing a file.
+const CHUNK_LEN: usize = 1 << 20;
+
+/// What a manifest records about a file.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Expected {
+    pub size: Option<u64>,
+    pub hash: Option<[u8; 32]>,
+}
+
+/// Files expected in a tree, by path relative to its root.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct Manifest {
+    files: BTreeMap<PathBuf, Expected>,
+}
+
+/// How a file differs from the manifest.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Mismatch {
+    /// In the manifest but not in the tree.
+    Missing,
+
+    /// In the tree but not in the manifest.
+    Unexpected,
+
+    Size,
+    Hash,
+}
+
+impl Mismatch {
+    /// Name of the mismatch in porcelain records.
+    pub fn key(self) -> &'static str {
+        match self {
+            Mismatch::Missing => "missing",
+            Mismatch::Unexpected => "unexpected",
+            Mismatch::Size => "size",
+            Mismatch::Hash => "hash",
+        }
+    }
+
+    /// English label of the mismatch in reports.
+    pub fn label(self) -> &'static str {
+        match self {
+            Mismatch::Missing => "missing files",
+            Mismatch::Unexpected => "unexpected files",
+            Mismatch::Size => "changed sizes",
+            Mismatch::Hash => "changed contents",
+        }
+    }
+}
+
+/// A file differing from the manifest, with the extension it is counted under.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Discrepancy {
+    pub mismatch: Mismatch,
+
+    /// Path relative to the root of the tree.
+    pub path: PathBuf,
+
+    pub extension: Option<String>,
+}
+
+/// Path of a manifest entry relative to the root, without `.` components and leading `/`.
+fn relative(listed: &Path) -> PathBuf {
+    listed
+        .components()
+        .filter(|component| matches!(component, Component::Normal(_) | Component::ParentDir))
+        .collect()
+}
+
+fn parse_hex(hex: &str) -> Option<[u8; 32]> {
+    if hex.len() != 64 || !hex.bytes().all(|b| b.is_ascii_hexdigit()) {
+        return None;
+    }
+    let mut digest = [0; 32];
+    for (idx, byte) in digest.iter_mut().enumerate() {
+        *byte = u8::from_str_radix(&hex[2 * idx..2 * idx + 2], 16).ok()?;
+    }
+    Some(digest)
+}
+
+/// Whether the first non-empty line is that of `sha256sum`: a hash, a space, and a space or `*`.
+fn is_sha256sum(contents: &str) -> bool {
+    let first = contents
+        .lines()
+        .find(|line| !line.trim().is_empty())
+        .unwrap_or_default();
+    first.len() > 66 && first.get(..64).and_then(parse_hex).is_some() && matches!(first.get(64..66), Some("  " | " *"))
+}
+
+fn parse_sha256sum(contents: &str) -> Result<Manifest> {
+    let mut manifest = Manifest::default();
+    for (idx, line) in contents.lines().enumerate().filter(|(_, l)| !l.trim().is_empty()) {
+        let hash = line.get(..64).and_then(parse_hex);
+        match (hash, line.get(64..66), line.get(66..)) {
+            (Some(hash), Some("  " | " *"), Some(path)) if !path.is_empty() => {
+                manifest.files.insert(
+                    relative(Path::new(path)),
+                    Expected {
+                        size: None,
+                        hash: Some(hash),
+                    },
+                );
+            }
+            _ => bail!("line {}: expected a SHA-256 hash and a path", idx + 1),
+        }
+    }
+    Ok(manifest)
+}
+
+/// Add the sizes of the regular files of a listing below a directory to the manifest.
+fn add_listed(manifest: &mut Manifest, listing: &dyn FileSystem, root: &Path, dir: &Path) -> Result<()> {
+    for entry in listing.read_dir(dir)? {
+        match entry.kind {
+            EntryKind::Directory => add_listed(manifest, listing, root, &entry.path)?,
+            EntryKind::File => {
+                let size = listing.metadata(&entry.path)?.len;
+                manifest.files.insert(
+                    entry.path.strip_prefix(root)?.to_path_buf(),
+                    Expected {
+                        size: Some(size),
+                        hash: None,
+                    },
+                );
+            }
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+/// Read a manifest from a file, or from stdin if the path is `-`.
+pub fn read_manifest(path: &Path) -> Result<Manifest> {
+    let (mut listing, mut reader) = listing::open_input(path)?;
+    let mut contents = String::new();
+    reader
+        .read_to_string(&mut contents)
+        .with_context(|| format!("could not read {}", path.display()))?;
+    if is_sha256sum(&contents) {
+        return parse_sha256sum(&contents).with_context(|| format!("invalid manifest {}", path.display()));
+    }
+
+    listing::read_contents(&contents, ListingFormat::Auto, &mut listing)
+        .with_context(|| format!("invalid manifest {}", path.display()))?;
+    let mut manifest = Manifest::default();
+    let root = listing.root().to_path_buf();
+    add_listed(&mut manifest, &listing, &root, &root)?;
+    Ok(manifest)
+}
+
+/// SHA-256 hash of the contents of a file.
+fn hash_file(file_system: &dyn FileSystem, file: &Path) -> Result<[u8; 32]> {
+    let mut hasher = Sha256::default();
+    let mut offset = 0;
+    loop {
+        let chunk = file_system
+            .read_at(file, offset, CHUNK_LEN)
+            .with_context(|| format!("could not read {}", file.display()))?;
+        hasher.update(&chunk);
+        if chunk.len() < CHUNK_LEN {
+            return Ok(hasher.finish());
+        }
+        offset += chunk.len() as u64;
+    }
+}
+
+/// Collect the regular files below a directory by path relative to the root.
+fn collect_files(file_system: &dyn FileSystem, root: &Path, dir: &Path, files: &mut Vec<PathBuf>) -> Result<()> {
+    let entries = file_system
+        .read_dir(dir)
+        .with_context(|| format!("could not read {}", dir.display()))?;
+    for entry in entries {
+        match entry.kind {
+            EntryKind::Directory => collect_files(file_system, root, &entry.path, files)?,
+            EntryKind::File => files.push(entry.path.strip_prefix(root)?.to_path_buf()),
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+/// Compare the regular files below the root against the manifest, checking the sizes and hashes
+/// it records. Discrepancies are sorted by kind, then path.
+pub fn check_manifest(root: &Path, manifest: &Manifest, options: &ScanOptions) -> Result<Vec<Discrepancy>> {
+    let file_system = options.file_system.as_ref();
+    let mut files = Vec::new();
+    collect_files(file_system, root, root, &mut files)?;
+
+    let mut discrepancies = Vec::new();
+    let mut discrepancy = |mismatch, path: &Path| {
+        discrepancies.push(Discrepancy {
+            mismatch,
+            path: path.to_path_buf(),
+            extension: options.extension_rules.extension(path),
+        })
+    };
+    for file in &files {
+        let expected = match manifest.files.get(file) {
+            Some(expected) => expected,
+            None => {
+                discrepancy(Mismatch::Unexpected, file);
+                continue;
+            }
+        };
+        let path = root.join(file);
+        if let Some(size) = expected.size {
+            let len = file_system
+                .metadata(&path)
+                .with_context(|| format!("could not read {}", path.display()))?
+                .len;
+            if len != size {
+                discrepancy(Mismatch::Size, file);
+                continue;
+            }
+        }
+        if let Some(hash) = expected.hash {
+            if hash_file(file_system, &path)? != hash {
+                discrepancy(Mismatch::Hash, file);
+            }
+        }
+    }
+    let found: HashSet<&PathBuf> = files.iter().collect();
+    for file in manifest.files.keys().filter(|file| !found.contains(file)) {
+        discrepancy(Mismatch::Missing, file);
+    }
+
+    discrepancies.sort_by(|a, b| (a.mismatch, &a.path).cmp(&(b.mismatch, &b.path)));
+    Ok(discrepancies)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fs::MemoryFileSystem;
+
+    #[test]
+    fn test_parse_sha256sum() {
+        let hash = "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855";
+        let contents = format!("{}  ./docs/a b.txt\n{} */c.bin\n", hash, hash);
+        assert!(is_sha256sum(&contents));
+        assert!(!is_sha256sum("120 ./src/main.rs\n"));
+        // Multibyte characters across the hash or the separator must not panic.
+        assert!(!is_sha256sum(&format!("{}é  a\n", &hash[..63])));
+        assert!(!is_sha256sum(&format!("{}é a\n", hash)));
+
+        let manifest = parse_sha256sum(&contents).expect("could not parse manifest");
+        assert_eq!(
+            manifest.files.keys().collect::<Vec<_>>(),
+            vec![Path::new("c.bin"), Path::new("docs/a b.txt")]
+        );
+        assert_eq!(manifest.files[Path::new("c.bin")].hash, parse_hex(hash));
+        assert!(parse_sha256sum(&format!("{}  a\nnot a hash\n", hash)).is_err());
+    }
+
+    #[test]
+    fn test_check_manifest() {
+        let mut file_system = MemoryFileSystem::default();
+        file_system
+            .add_file_with_contents("/tree/same.txt", b"abc")
+            .add_file_with_contents("/tree/docs/edited.txt", b"abd")
+            .add_file("/tree/docs/grown.md", 20)
+            .add_file("/tree/new.jpg", 5);
+        let options = ScanOptions {
+            file_system: Box::new(file_system),
+            ..Default::default()
+        };
+        let abc = parse_hex("ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad");
+        let manifest = Manifest {
+            files: BTreeMap::from([
+                (
+                    PathBuf::from("same.txt"),
+                    Expected {
+                        size: Some(3),
+                        hash: abc,
+                    },
+                ),
+                (
+                    PathBuf::from("docs/edited.txt"),
+                    Expected {
+                        size: Some(3),
+                        hash: abc,
+                    },
+                ),
+                (
+                    PathBuf::from("docs/grown.md"),
+                    Expected {
+                        size: Some(10),
+                        hash: None,
+                    },
+                ),
+                (
+                    PathBuf::from("gone.png"),
+                    Expected {
+                        size: Some(1),
+                        hash: None,
+                    },
+                ),
+            ]),
+        };
+
+        let discrepancies = check_manifest(Path::new("/tree"), &manifest, &options).expect("could not check");
+        let found: Vec<_> = discrepancies
+            .iter()
+            .map(|d| (d.mismatch, d.path.to_str().unwrap(), d.extension.as_deref()))
+            .collect();
+        assert_eq!(
+            found,
+            vec![
+                (Mismatch::Missing, "gone.png", Some("png")),
+                (Mismatch::Unexpected, "new.jpg", Some("jpg")),
+                (Mismatch::Size, "docs/grown.md", Some("md")),
+                (Mismatch::Hash, "docs/edited.txt", Some("txt")),
+            ]
+        );
+    }
+}
@@ -0,0 +1,204 @@
+//! Playing time of audio and video files, read from the metadata of their container. Only
+//! containers recording their duration at a known place are supported: MP4 and QuickTime, WAV and
+//! FLAC. Formats such as MP3 or Ogg, whose duration can only be estimated by reading the whole
+//! stream, are not.
+
+use std::io;
+use std::time::Duration;
+
+/// Reads up to `len` bytes at an offset of a file, fewer at its end.
+pub type ReadAt<'a> = &'a dyn Fn(u64, usize) -> io::Result<Vec<u8>>;
+
+const MP4_EXTENSIONS: &[&str] = &["mp4", "m4a", "m4v", "m4b", "mov"];
+
+/// Playing time of a file with the given extension, or `None` if its format is not supported or
+/// its metadata does not record a duration.
+pub fn duration(extension: &str, read_at: ReadAt) -> io::Result<Option<Duration>> {
+    let extension = extension.to_ascii_lowercase();
+    match extension.as_str() {
+        e if MP4_EXTENSIONS.contains(&e) => mp4_duration(read_at),
+        "wav" => wav_duration(read_at),
+        "flac" => flac_duration(read_at),
+        _ => Ok(None),
+    }
+}
+
+fn be_u32(bytes: &[u8]) -> u64 {
+    u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as u64
+}
+
+fn be_u64(bytes: &[u8]) -> u64 {
+    u64::from_be_bytes([
+        bytes[0], bytes[1], bytes[2], bytes[3], bytes[4], bytes[5], bytes[6], bytes[7],
+    ])
+}
+
+fn le_u32(bytes: &[u8]) -> u64 {
+    u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as u64
+}
+
+/// Find the MP4 box of the given type among those between two offsets, and return the range of
+/// its content.
+fn find_box(read_at: ReadAt, start: u64, end: u64, kind: &[u8; 4]) -> io::Result<Option<(u64, u64)>> {
+    let mut offset = start;
+    while offset.saturating_add(8) <= end {
+        let header = read_at(offset, 16)?;
+        if header.len() < 8 {
+            return Ok(None);
+        }
+        let (header_len, size) = match be_u32(&header) {
+            // The size is stored in 64 bits after the type.
+            1 if header.len() == 16 => (16, be_u64(&header[8..])),
+            1 => return Ok(None),
+            // The box extends to the end of the file.
+            0 => (8, end - offset),
+            size => (8, size),
+        };
+        if size < header_len {
+            return Ok(None);
+        }
+        if &header[4..8] == kind {
+            return Ok(Some((offset + header_len, offset.saturating_add(size))));
+        }
+        offset = offset.saturating_add(size);
+    }
+    Ok(None)
+}
+
+/// Duration from the movie header, found in the movie box, which may come after the media data.
+fn mp4_duration(read_at: ReadAt) -> io::Result<Option<Duration>> {
+    let (moov_start, moov_end) = match find_box(read_at, 0, u64::MAX, b"moov")? {
+        Some(moov) => moov,
+        None => return Ok(None),
+    };
+    let (mvhd_start, _) = match find_box(read_at, moov_start, moov_end, b"mvhd")? {
+        Some(mvhd) => mvhd,
+        None => return Ok(None),
+    };
+
+    let mvhd = read_at(mvhd_start, 32)?;
+    let (timescale, duration) = match mvhd.first() {
+        Some(0) if mvhd.len() >= 20 => (be_u32(&mvhd[12..]), be_u32(&mvhd[16..])),
+        Some(1) if mvhd.len() >= 32 => (be_u32(&mvhd[20..]), be_u64(&mvhd[24..])),
+        _ => return Ok(None),
+    };
+    // All ones mark a duration that is not known.
+    if timescale == 0 || duration == u32::MAX as u64 || duration == u64::MAX {
+        return Ok(None);
+    }
+    Ok(Duration::try_from_secs_f64(duration as f64 / timescale as f64).ok())
+}
+
+/// Duration from the size of the sample data and the byte rate in the format chunk.
+fn wav_duration(read_at: ReadAt) -> io::Result<Option<Duration>> {
+    let header = read_at(0, 12)?;
+    if header.len() < 12 || &header[..4] != b"RIFF" || &header[8..] != b"WAVE" {
+        return Ok(None);
+    }
+
+    let (mut byte_rate, mut data_len) = (None, None);
+    let mut offset = 12;
+    while byte_rate.is_none() || data_len.is_none() {
+        let chunk = read_at(offset, 20)?;
+        if chunk.len() < 8 {
+            return Ok(None);
+        }
+        let size = le_u32(&chunk[4..]);
+        match &chunk[..4] {
+            b"fmt " if chunk.len() >= 20 => byte_rate = Some(le_u32(&chunk[16..])),
+            b"data" => data_len = Some(size),
+            _ => {}
+        }
+        // Chunks are aligned on two bytes.
+        offset += 8 + size + size % 2;
+    }
+
+    match (byte_rate, data_len) {
+        (Some(byte_rate), Some(data_len)) if byte_rate > 0 => {
+            Ok(Duration::try_from_secs_f64(data_len as f64 / byte_rate as f64).ok())
+        }
+        _ => Ok(None),
+    }
+}
+
+/// Duration from the sample rate and number of samples in the stream info block, which always
+/// comes first.
+fn flac_duration(read_at: ReadAt) -> io::Result<Option<Duration>> {
+    let header = read_at(0, 42)?;
+    if header.len() < 42 || &header[..4] != b"fLaC" || header[4] & 0x7F != 0 {
+        return Ok(None);
+    }
+
+    let info = &header[8..];
+    let sample_rate = (info[10] as u64) << 12 | (info[11] as u64) << 4 | (info[12] as u64) >> 4;
+    let samples = ((info[13] & 0x0F) as u64) << 32 | be_u32(&info[14..]);
+    // Zero samples mark a stream of unknown length.
+    if sample_rate == 0 || samples == 0 {
+        return Ok(None);
+    }
+    Ok(Duration::try_from_secs_f64(samples as f64 / sample_rate as f64).ok())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn duration_of(extension: &str, contents: &[u8]) -> Option<Duration> {
+        let read_at = |offset: u64, len: usize| {
+            let start = (offset as usize).min(contents.len());
+            Ok(contents[start..(start + len).min(contents.len())].to_vec())
+        };
+        duration(extension, &read_at).expect("could not read duration")
+    }
+
+    fn mp4_box(kind: &[u8; 4], content: &[u8]) -> Vec<u8> {
+        let mut bytes = ((content.len() + 8) as u32).to_be_bytes().to_vec();
+        bytes.extend_from_slice(kind);
+        bytes.extend_from_slice(content);
+        bytes
+    }
+
+    #[test]
+    fn test_mp4_duration() {
+        let mut mvhd = vec![0; 100];
+        mvhd[12..16].copy_from_slice(&1000u32.to_be_bytes());
+        mvhd[16..20].copy_from_slice(&90_500u32.to_be_bytes());
+        let mut file = mp4_box(b"ftyp", b"isom\0\0\0\0");
+        file.extend(mp4_box(b"mdat", &[0; 64]));
+        file.extend(mp4_box(b"moov", &mp4_box(b"mvhd", &mvhd)));
+
+        assert_eq!(duration_of("MP4", &file), Some(Duration::from_millis(90_500)));
+        assert_eq!(duration_of("mp4", &file[..80]), None);
+        assert_eq!(duration_of("mkv", &file), None);
+
+        // A version 1 header with a duration too long for a Duration.
+        let mut mvhd = vec![0; 100];
+        mvhd[0] = 1;
+        mvhd[20..24].copy_from_slice(&1u32.to_be_bytes());
+        mvhd[24..32].copy_from_slice(&(u64::MAX - 1).to_be_bytes());
+        let file = mp4_box(b"moov", &mp4_box(b"mvhd", &mvhd));
+        assert_eq!(duration_of("mp4", &file), None);
+    }
+
+    #[test]
+    fn test_wav_duration() {
+        let mut file = b"RIFF\0\0\0\0WAVE".to_vec();
+        file.extend(b"fmt \x10\0\0\0\x01\0\x02\0\x44\xAC\0\0\x10\xB1\x02\0\x04\0\x10\0");
+        file.extend(b"LIST\x03\0\0\0abc\0");
+        file.extend(b"data\x20\x62\x05\0");
+
+        assert_eq!(duration_of("wav", &file), Some(Duration::from_secs(2)));
+    }
+
+    #[test]
+    fn test_flac_duration() {
+        let mut file = b"fLaC\x80\0\0\x22".to_vec();
+        let mut info = [0u8; 34];
+        // 44100 Hz, then 441000 samples.
+        info[10..14].copy_from_slice(&[0x0A, 0xC4, 0x40, 0xF0]);
+        info[14..18].copy_from_slice(&441_000u32.to_be_bytes());
+        file.extend(info);
+
+        assert_eq!(duration_of("flac", &file), Some(Duration::from_secs(10)));
+    }
+}
@@ -0,0 +1,34 @@
+//! Memory used by the process, as reported by the operating system.
+
+/// Highest resident memory of the process so far in bytes, on platforms that report it.
+pub fn peak_resident() -> Option<u64> {
+    #[cfg(target_os = "linux")]
+    {
+        let status = std::fs::read_to_string("/proc/self/status").ok()?;
+        parse_peak_resident(&status)
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    {
+        None
+    }
+}
+
+/// Read the peak resident set size from the contents of /proc/self/status, see proc(5).
+#[cfg(target_os = "linux")]
+fn parse_peak_resident(status: &str) -> Option<u64> {
+    let line = status.lines().find_map(|line| line.strip_prefix("VmHWM:"))?;
+    let kib: u64 = line.trim().strip_suffix("kB")?.trim().parse().ok()?;
+    Some(kib * 1024)
+}
+
+#[cfg(test)]
+mod tests {
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_parse_peak_resident() {
+        let status = "Name:\trextc\nVmPeak:\t   12000 kB\nVmHWM:\t    2048 kB\nVmRSS:\t    1024 kB\n";
+        assert_eq!(super::parse_peak_resident(status), Some(2 * 1024 * 1024));
+        assert_eq!(super::parse_peak_resident("Name:\trextc\n"), None);
+    }
+}
@@ -0,0 +1,186 @@
+mod tables;
+
+use clap::ArgEnum;
+use tables::{COMBINING_CLASSES, COMPOSITIONS, DECOMPOSITIONS};
+
+const HANGUL_S_BASE: u32 = 0xAC00;
+const HANGUL_L_BASE: u32 = 0x1100;
+const HANGUL_V_BASE: u32 = 0x1161;
+const HANGUL_T_BASE: u32 = 0x11A7;
+const HANGUL_L_COUNT: u32 = 19;
+const HANGUL_V_COUNT: u32 = 21;
+const HANGUL_T_COUNT: u32 = 28;
+const HANGUL_N_COUNT: u32 = HANGUL_V_COUNT * HANGUL_T_COUNT;
+const HANGUL_S_COUNT: u32 = HANGUL_L_COUNT * HANGUL_N_COUNT;
+
+/// Unicode normalization form applied to file and directory names before they are compared.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ArgEnum)]
+pub enum Normalization {
+    /// Canonical composition, as produced by most Linux and Windows software.
+    Nfc,
+
+    /// Canonical decomposition, as produced by macOS file systems.
+    Nfd,
+}
+
+/// Normalize a string to the given form.
+pub fn normalize(s: &str, form: Normalization) -> String {
+    let decomposed = decompose(s);
+    match form {
+        Normalization::Nfd => decomposed.into_iter().collect(),
+        Normalization::Nfc => compose(&decomposed).into_iter().collect(),
+    }
+}
+
+/// Full canonical decomposition, with combining marks in canonical order.
+fn decompose(s: &str) -> Vec<char> {
+    let mut decomposed = Vec::with_capacity(s.len());
+    for c in s.chars() {
+        decompose_char(c, &mut decomposed);
+    }
+
+    // Stable sort of each run of combining marks by combining class.
+    let mut start = 0;
+    while start < decomposed.len() {
+        if combining_class(decomposed[start]) == 0 {
+            start += 1;
+            continue;
+        }
+        let end = decomposed[start..]
+            .iter()
+            .position(|&c| combining_class(c) == 0)
+            .map_or(decomposed.len(), |len| start + len);
+        decomposed[start..end].sort_by_key(|&c| combining_class(c));
+        start = end;
+    }
+    decomposed
+}
+
+fn combining_class(c: char) -> u8 {
+    COMBINING_CLASSES
+        .binary_search_by(|&(first, last, _)| {
+            if last < c {
+                std::cmp::Ordering::Less
+            } else if first > c {
+                std::cmp::Ordering::Greater
+            } else {
+                std::cmp::Ordering::Equal
+            }
+        })
+        .map_or(0, |idx| COMBINING_CLASSES[idx].2)
+}
+
+fn decompose_char(c: char, decomposed: &mut Vec<char>) {
+    let s_index = (c as u32).wrapping_sub(HANGUL_S_BASE);
+    if s_index < HANGUL_S_COUNT {
+        let l = HANGUL_L_BASE + s_index / HANGUL_N_COUNT;
+        let v = HANGUL_V_BASE + (s_index % HANGUL_N_COUNT) / HANGUL_T_COUNT;
+        let t = HANGUL_T_BASE + s_index % HANGUL_T_COUNT;
+        decomposed.extend([l, v].iter().filter_map(|&c| char::from_u32(c)));
+        if t != HANGUL_T_BASE {
+            decomposed.extend(char::from_u32(t));
+        }
+        return;
+    }
+
+    match DECOMPOSITIONS.binary_search_by_key(&c, |&(composed, _)| composed) {
+        Ok(idx) => {
+            for &part in DECOMPOSITIONS[idx].1 {
+                decompose_char(part, decomposed);
+            }
+        }
+        Err(_) => decomposed.push(c),
+    }
+}
+
+/// Canonical composition of a decomposed string. A mark is combined with the last starter unless
+/// a character in between has a combining class of zero or at least its own.
+fn compose(decomposed: &[char]) -> Vec<char> {
+    let mut composed: Vec<char> = Vec::with_capacity(decomposed.len());
+    let mut starter = None;
+    let mut last_class = 0;
+    for &c in decomposed {
+        let class = combining_class(c);
+        if let Some(idx) = starter {
+            let blocked = composed.len() > idx + 1 && (last_class == 0 || last_class >= class);
+            if let Some(combined) = compose_pair(composed[idx], c).filter(|_| !blocked) {
+                composed[idx] = combined;
+                continue;
+            }
+        }
+        if class == 0 {
+            starter = Some(composed.len());
+        }
+        composed.push(c);
+        last_class = class;
+    }
+    composed
+}
+
+fn compose_pair(base: char, mark: char) -> Option<char> {
+    let (base_u32, mark_u32) = (base as u32, mark as u32);
+
+    // Hangul leading consonant followed by a vowel.
+    let l_index = base_u32.wrapping_sub(HANGUL_L_BASE);
+    let v_index = mark_u32.wrapping_sub(HANGUL_V_BASE);
+    if l_index < HANGUL_L_COUNT && v_index < HANGUL_V_COUNT {
+        return char::from_u32(HANGUL_S_BASE + (l_index * HANGUL_V_COUNT + v_index) * HANGUL_T_COUNT);
+    }
+
+    // Hangul syllable without a trailing consonant, followed by one.
+    let s_index = base_u32.wrapping_sub(HANGUL_S_BASE);
+    let t_index = mark_u32.wrapping_sub(HANGUL_T_BASE);
+    if s_index < HANGUL_S_COUNT && s_index % HANGUL_T_COUNT == 0 && t_index > 0 && t_index < HANGUL_T_COUNT {
+        return char::from_u32(base_u32 + t_index);
+    }
+
+    COMPOSITIONS
+        .binary_search_by_key(&(base, mark), |&(base, mark, _)| (base, mark))
+        .ok()
+        .map(|idx| COMPOSITIONS[idx].2)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_latin() {
+        let nfc = "caf\u{e9}.r\u{e9}sum\u{e9}";
+        let nfd = "cafe\u{301}.re\u{301}sume\u{301}";
+        assert_eq!(normalize(nfd, Normalization::Nfc), nfc);
+        assert_eq!(normalize(nfc, Normalization::Nfd), nfd);
+        assert_eq!(normalize(nfc, Normalization::Nfc), nfc);
+    }
+
+    #[test]
+    fn test_multiple_marks() {
+        // LATIN SMALL LETTER E WITH CIRCUMFLEX AND DOT BELOW
+        assert_eq!(normalize("\u{1ec7}", Normalization::Nfd), "e\u{323}\u{302}");
+        assert_eq!(normalize("e\u{323}\u{302}", Normalization::Nfc), "\u{1ec7}");
+    }
+
+    #[test]
+    fn test_reordered_marks() {
+        // dot below and acute accent, in either order
+        assert_eq!(normalize("a\u{301}\u{323}", Normalization::Nfd), "a\u{323}\u{301}");
+        assert_eq!(normalize("a\u{301}\u{323}", Normalization::Nfc), "\u{1ea1}\u{301}");
+        assert_eq!(normalize("e\u{302}\u{323}", Normalization::Nfc), "\u{1ec7}");
+    }
+
+    #[test]
+    fn test_singletons_and_exclusions() {
+        // ANGSTROM SIGN and OHM SIGN
+        assert_eq!(normalize("\u{212b}", Normalization::Nfc), "\u{c5}");
+        assert_eq!(normalize("\u{212b}", Normalization::Nfd), "A\u{30a}");
+        assert_eq!(normalize("\u{2126}", Normalization::Nfc), "\u{3a9}");
+        // DEVANAGARI LETTER QA is excluded from composition
+        assert_eq!(normalize("\u{958}", Normalization::Nfc), "\u{915}\u{93c}");
+    }
+
+    #[test]
+    fn test_hangul() {
+        assert_eq!(normalize("\u{d55c}", Normalization::Nfd), "\u{1112}\u{1161}\u{11ab}");
+        assert_eq!(normalize("\u{1112}\u{1161}\u{11ab}", Normalization::Nfc), "\u{d55c}");
+    }
+}
@@ -0,0 +1,80 @@
+//! Bundles of options suited to common kinds of trees. Options given explicitly on the command line
+//! take precedence over those of a preset.
+
+use crate::file::ExtensionSortingMethod;
+use crate::trash::Trash;
+use clap::ArgEnum;
+
+/// Clutter left by desktop environments and file managers, excluded by every preset.
+const DESKTOP_CLUTTER: &str = ".DS_Store|Thumbs.db|desktop.ini|.AppleDouble|.Trashes|.Spotlight-V100";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ArgEnum)]
+pub enum Preset {
+    /// Source trees: build output, dependencies and version control directories are excluded and
+    /// files are counted by language.
+    Dev,
+
+    /// Audio and video libraries: playing time is shown and split archives are grouped.
+    Media,
+
+    /// Photo archives: thumbnail caches are excluded, extensions are ordered by number of files
+    /// and spelling variants such as JPG/jpeg are listed.
+    Photos,
+
+    /// Office shares: lock and temporary files of office suites are excluded.
+    Docs,
+
+    /// Home directories and disks being cleaned up: trash locations are reported separately and
+    /// extensions are ordered by size.
+    Tidy,
+}
+
+/// Options set by a preset.
+#[derive(Debug, Default)]
+pub struct PresetOptions {
+    pub exclude: String,
+    pub sort: Option<ExtensionSortingMethod>,
+    pub languages: bool,
+    pub media_duration: bool,
+    pub split_archives: bool,
+    pub variants: bool,
+    pub trash: Option<Trash>,
+}
+
+impl Preset {
+    pub fn options(self) -> PresetOptions {
+        let exclude = |patterns: &str| format!("{}|{}", patterns, DESKTOP_CLUTTER);
+        match self {
+            Preset::Dev => PresetOptions {
+                exclude: exclude(".git|.hg|.svn|target|node_modules|__pycache__|.venv|.tox|dist|build"),
+                sort: Some(ExtensionSortingMethod::FileCount),
+                languages: true,
+                ..Default::default()
+            },
+            Preset::Media => PresetOptions {
+                exclude: exclude("@eaDir|.@__thumb|*.nfo|*.part"),
+                sort: Some(ExtensionSortingMethod::FileSize),
+                media_duration: true,
+                split_archives: true,
+                ..Default::default()
+            },
+            Preset::Photos => PresetOptions {
+                exclude: exclude("@eaDir|.thumbnails|.picasaoriginals|*.tmp"),
+                sort: Some(ExtensionSortingMethod::FileCount),
+                variants: true,
+                ..Default::default()
+            },
+            Preset::Docs => PresetOptions {
+                exclude: exclude("~$*|.~lock.*#|~*.tmp|*.bak"),
+                sort: Some(ExtensionSortingMethod::FileSize),
+                ..Default::default()
+            },
+            Preset::Tidy => PresetOptions {
+                exclude: DESKTOP_CLUTTER.to_string(),
+                sort: Some(ExtensionSortingMethod::FileSize),
+                trash: Some(Trash::Report),
+                ..Default::default()
+            },
+        }
+    }
+}
@@ -0,0 +1,126 @@
+//! Progress of a scan reported as JSON lines on stderr, so that programs wrapping the tool can show
+//! their own progress.
+
+use std::cell::Cell;
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+/// Time between two progress events.
+const INTERVAL: Duration = Duration::from_secs(1);
+
+/// Counts the files scanned so far and periodically prints a progress event.
+#[derive(Debug)]
+pub struct Progress {
+    /// When the scan started.
+    start: Instant,
+
+    /// Earliest time at which the next event is printed.
+    next: Cell<Instant>,
+
+    /// Files counted so far.
+    files: Cell<u64>,
+
+    /// Bytes counted so far.
+    bytes: Cell<u64>,
+
+    /// Bytes expected in total, if known, from which the remaining time is estimated.
+    expected_bytes: Option<u64>,
+}
+
+impl Progress {
+    pub fn new(expected_bytes: Option<u64>) -> Self {
+        let start = Instant::now();
+        Self {
+            start,
+            next: Cell::new(start + INTERVAL),
+            files: Cell::new(0),
+            bytes: Cell::new(0),
+            expected_bytes,
+        }
+    }
+
+    /// Count a file, printing an event if the last one is old enough.
+    pub fn file(&self, path: &Path, len: u64) {
+        self.files.set(self.files.get() + 1);
+        self.bytes.set(self.bytes.get() + len);
+        let now = Instant::now();
+        if now >= self.next.get() {
+            self.next.set(now + INTERVAL);
+            eprintln!("{}", self.event("progress", Some(path), now - self.start));
+        }
+    }
+
+    /// Print the final event, once the scan is complete.
+    pub fn finish(&self) {
+        eprintln!("{}", self.event("done", None, self.start.elapsed()));
+    }
+
+    /// One event as a line of JSON. The remaining time is extrapolated from the rate so far, and
+    /// is null when the expected total is unknown or nothing has been counted yet.
+    fn event(&self, name: &str, path: Option<&Path>, elapsed: Duration) -> String {
+        let bytes = self.bytes.get();
+        let eta = match (name, self.expected_bytes) {
+            ("done", _) => Some(0.0),
+            (_, Some(expected)) if bytes > 0 => {
+                Some(elapsed.as_secs_f64() * expected.saturating_sub(bytes) as f64 / bytes as f64)
+            }
+            _ => None,
+        };
+        format!(
+            "{{\"event\":\"{}\",\"files\":{},\"bytes\":{},\"path\":{},\"elapsed_secs\":{:.3},\"eta_secs\":{}}}",
+            name,
+            self.files.get(),
+            bytes,
+            path.map_or_else(|| "null".to_string(), |path| json_string(&path.to_string_lossy())),
+            elapsed.as_secs_f64(),
+            eta.map_or_else(|| "null".to_string(), |eta| format!("{:.0}", eta)),
+        )
+    }
+}
+
+/// Quote a string for JSON, escaping quotes, backslashes and control characters.
+fn json_string(text: &str) -> String {
+    let mut quoted = String::with_capacity(text.len() + 2);
+    quoted.push('"');
+    for c in text.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\t' => quoted.push_str("\\t"),
+            c if c.is_control() => quoted.push_str(&format!("\\u{:04x}", c as u32)),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_event() {
+        let progress = Progress::new(Some(4000));
+        progress.file(Path::new("/data/a.txt"), 1000);
+        assert_eq!(
+            progress.event("progress", Some(Path::new("/data/a \"b\".txt")), Duration::from_secs(2)),
+            r#"{"event":"progress","files":1,"bytes":1000,"path":"/data/a \"b\".txt","elapsed_secs":2.000,"eta_secs":6}"#
+        );
+        assert_eq!(
+            progress.event("done", None, Duration::from_secs(8)),
+            r#"{"event":"done","files":1,"bytes":1000,"path":null,"elapsed_secs":8.000,"eta_secs":0}"#
+        );
+
+        let unknown = Progress::new(None);
+        assert!(unknown
+            .event("progress", None, Duration::from_secs(1))
+            .ends_with(r#""eta_secs":null}"#));
+    }
+
+    #[test]
+    fn test_json_string() {
+        assert_eq!(json_string("a\\b\n\u{1}"), r#""a\\b\n\u0001""#);
+    }
+}
@@ -0,0 +1,117 @@
+//! Per-user disk quotas, read from a file with one user and byte limit per line:
+//!
+//! ```text
+//! # user   limit
+//! alice    10G
+//! 1001     500M
+//! ```
+//!
+//! Users are given by name or numeric user ID. Limits are in bytes, optionally followed by a
+//! binary unit: K, M, G or T. Empty lines and lines starting with `#` are ignored.
+
+use anyhow::{anyhow, bail, Context, Result};
+use std::path::Path;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Quota {
+    /// User as written in the quota file.
+    pub user: String,
+
+    /// Numeric user ID owning the files counted against the quota.
+    pub uid: u32,
+
+    /// Limit in bytes.
+    pub limit: u64,
+}
+
+/// Parse a size such as `512`, `10K` or `1.5G`, in binary units.
+pub(crate) fn parse_size(limit: &str) -> Result<u64> {
+    let (number, unit) = match limit.find(|c: char| c.is_ascii_alphabetic()) {
+        Some(idx) => limit.split_at(idx),
+        None => (limit, ""),
+    };
+    let multiplier = match unit.to_ascii_uppercase().trim_end_matches("IB").trim_end_matches('B') {
+        "" => 1,
+        "K" => 1024u64,
+        "M" => 1024u64.pow(2),
+        "G" => 1024u64.pow(3),
+        "T" => 1024u64.pow(4),
+        _ => bail!("unknown unit in limit {}", limit),
+    };
+    let number: f64 = number.parse().with_context(|| format!("invalid limit {}", limit))?;
+    if number < 0.0 {
+        bail!("negative limit {}", limit);
+    }
+    Ok((number * multiplier as f64) as u64)
+}
+
+/// Look up the user ID of a user name in a passwd(5) database.
+fn lookup_uid(passwd: &str, user: &str) -> Option<u32> {
+    passwd.lines().find_map(|line| {
+        let mut fields = line.split(':');
+        if fields.next()? != user {
+            return None;
+        }
+        fields.nth(1)?.parse().ok()
+    })
+}
+
+/// Parse the contents of a quota file, resolving user names through the given passwd database.
+fn parse_quotas(quotas: &str, passwd: &str) -> Result<Vec<Quota>> {
+    let mut parsed = Vec::new();
+    for (idx, line) in quotas.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        let (user, limit) = match fields[..] {
+            [user, limit] => (user, limit),
+            _ => bail!("line {}: expected a user and a limit", idx + 1),
+        };
+        let uid = match user.parse() {
+            Ok(uid) => uid,
+            Err(_) => lookup_uid(passwd, user).ok_or_else(|| anyhow!("line {}: unknown user {}", idx + 1, user))?,
+        };
+        parsed.push(Quota {
+            user: user.to_string(),
+            uid,
+            limit: parse_size(limit).with_context(|| format!("line {}", idx + 1))?,
+        });
+    }
+    Ok(parsed)
+}
+
+/// Read a quota file, resolving user names through `/etc/passwd`.
+pub fn read_quotas(path: &Path) -> Result<Vec<Quota>> {
+    let quotas = std::fs::read_to_string(path).with_context(|| format!("could not read {}", path.display()))?;
+    let passwd = std::fs::read_to_string("/etc/passwd").unwrap_or_default();
+    parse_quotas(&quotas, &passwd)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_size() {
+        assert_eq!(parse_size("512").ok(), Some(512));
+        assert_eq!(parse_size("10K").ok(), Some(10 * 1024));
+        assert_eq!(parse_size("1.5GiB").ok(), Some(3 * 1024u64.pow(3) / 2));
+        assert!(parse_size("10X").is_err());
+        assert!(parse_size("lots").is_err());
+    }
+
+    #[test]
+    fn test_parse_quotas() {
+        let passwd = "root:x:0:0:root:/root:/bin/sh\nalice:x:1000:1000::/home/alice:/bin/sh\n";
+        let quotas = parse_quotas("# user limit\nalice 10G\n\n1001  500M\n", passwd).expect("could not parse quotas");
+        assert_eq!(quotas.len(), 2);
+        assert_eq!((quotas[0].uid, quotas[0].limit), (1000, 10 * 1024u64.pow(3)));
+        assert_eq!((quotas[1].user.as_str(), quotas[1].uid), ("1001", 1001));
+
+        assert!(parse_quotas("mallory 1G", passwd).is_err());
+        assert!(parse_quotas("alice", passwd).is_err());
+    }
+}
@@ -0,0 +1,390 @@
+//! A small backtracking regular expression engine, enough for grouping files by a part of their
+//! name with `--group-by-regex`.
+//!
+//! Supported are literals, `.`, character classes such as `[a-z0-9_]` and `[^.]`, the escapes
+//! `\d`, `\w`, `\s` and their negations, anchors `^` and `$`, groups `(...)` and `(?:...)`,
+//! alternation `|`, and the quantifiers `*`, `+`, `?` and `{m,n}`, each of which may be made lazy
+//! with a trailing `?`.
+
+use anyhow::{anyhow, bail, Result};
+use std::str::FromStr;
+
+#[derive(Debug, Clone)]
+enum Node {
+    Char(char),
+    Any,
+    Class {
+        ranges: Vec<(char, char)>,
+        negated: bool,
+    },
+    Start,
+    End,
+    Group {
+        node: Box<Node>,
+        capture: Option<usize>,
+    },
+    Alternation(Vec<Node>),
+    Concatenation(Vec<Node>),
+    Repeat {
+        node: Box<Node>,
+        min: usize,
+        max: Option<usize>,
+        greedy: bool,
+    },
+}
+
+/// Compiled regular expression.
+#[derive(Debug, Clone)]
+pub struct Regex {
+    node: Node,
+    captures: usize,
+}
+
+type Captures = Vec<Option<(usize, usize)>>;
+
+impl FromStr for Regex {
+    type Err = anyhow::Error;
+
+    fn from_str(pattern: &str) -> Result<Self> {
+        let mut parser = Parser {
+            chars: pattern.chars().collect(),
+            position: 0,
+            captures: 0,
+        };
+        let node = parser.alternation()?;
+        if parser.position < parser.chars.len() {
+            bail!("unmatched ) at position {} of {}", parser.position, pattern);
+        }
+        Ok(Self {
+            node,
+            captures: parser.captures,
+        })
+    }
+}
+
+impl Regex {
+    /// Text of the first capture group in the leftmost match in the text, or of the whole match if
+    /// the expression has no groups. `None` if there is no match, or the group did not take part in
+    /// it.
+    pub fn first_capture(&self, text: &str) -> Option<String> {
+        let chars: Vec<char> = text.chars().collect();
+        for start in 0..=chars.len() {
+            let mut captures = vec![None; self.captures + 1];
+            let mut end = None;
+            if matches(&self.node, &chars, start, &mut captures, &mut |pos, _| {
+                end = Some(pos);
+                true
+            }) {
+                let (from, to) = if self.captures > 0 { captures[1]? } else { (start, end?) };
+                return Some(chars[from..to].iter().collect());
+            }
+        }
+        None
+    }
+}
+
+/// Match a node at a position, then hand the end position to the continuation, backtracking into
+/// the node for as long as the continuation fails.
+fn matches(
+    node: &Node,
+    text: &[char],
+    pos: usize,
+    captures: &mut Captures,
+    next: &mut dyn FnMut(usize, &mut Captures) -> bool,
+) -> bool {
+    match node {
+        Node::Char(c) => text.get(pos) == Some(c) && next(pos + 1, captures),
+        Node::Any => pos < text.len() && next(pos + 1, captures),
+        Node::Class { ranges, negated } => match text.get(pos) {
+            Some(c) => {
+                let inside = ranges.iter().any(|(low, high)| (low..=high).contains(&c));
+                inside != *negated && next(pos + 1, captures)
+            }
+            None => false,
+        },
+        Node::Start => pos == 0 && next(pos, captures),
+        Node::End => pos == text.len() && next(pos, captures),
+        Node::Group { node, capture } => matches(node, text, pos, captures, &mut |end, captures| match capture {
+            Some(idx) => {
+                let previous = captures[*idx].replace((pos, end));
+                if next(end, captures) {
+                    return true;
+                }
+                captures[*idx] = previous;
+                false
+            }
+            None => next(end, captures),
+        }),
+        Node::Alternation(branches) => branches.iter().any(|branch| matches(branch, text, pos, captures, next)),
+        Node::Concatenation(nodes) => sequence(nodes, text, pos, captures, next),
+        Node::Repeat { node, min, max, greedy } => repeat(node, *min, *max, *greedy, 0, text, pos, captures, next),
+    }
+}
+
+/// Match nodes one after the other.
+fn sequence(
+    nodes: &[Node],
+    text: &[char],
+    pos: usize,
+    captures: &mut Captures,
+    next: &mut dyn FnMut(usize, &mut Captures) -> bool,
+) -> bool {
+    match nodes.split_first() {
+        Some((first, rest)) => matches(first, text, pos, captures, &mut |end, captures| {
+            sequence(rest, text, end, captures, next)
+        }),
+        None => next(pos, captures),
+    }
+}
+
+/// Match a node repeatedly, having matched it `count` times so far.
+#[allow(clippy::too_many_arguments)]
+fn repeat(
+    node: &Node,
+    min: usize,
+    max: Option<usize>,
+    greedy: bool,
+    count: usize,
+    text: &[char],
+    pos: usize,
+    captures: &mut Captures,
+    next: &mut dyn FnMut(usize, &mut Captures) -> bool,
+) -> bool {
+    let once_more = |captures: &mut Captures, next: &mut dyn FnMut(usize, &mut Captures) -> bool| {
+        max.is_none_or(|max| count < max)
+            && matches(node, text, pos, captures, &mut |end, captures| {
+                // A repetition that consumes nothing would otherwise loop forever.
+                (end > pos || count < min) && repeat(node, min, max, greedy, count + 1, text, end, captures, next)
+            })
+    };
+    // Greedy repetitions try one more match before stopping, lazy ones the other way round.
+    if greedy && once_more(captures, next) {
+        return true;
+    }
+    (count >= min && next(pos, captures)) || (!greedy && once_more(captures, next))
+}
+
+struct Parser {
+    chars: Vec<char>,
+    position: usize,
+    captures: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.position).copied()
+    }
+
+    fn eat(&mut self, c: char) -> bool {
+        let found = self.peek() == Some(c);
+        if found {
+            self.position += 1;
+        }
+        found
+    }
+
+    fn alternation(&mut self) -> Result<Node> {
+        let mut branches = vec![self.concatenation()?];
+        while self.eat('|') {
+            branches.push(self.concatenation()?);
+        }
+        Ok(if branches.len() == 1 {
+            branches.pop().expect("one branch")
+        } else {
+            Node::Alternation(branches)
+        })
+    }
+
+    fn concatenation(&mut self) -> Result<Node> {
+        let mut nodes = Vec::new();
+        while let Some(c) = self.peek() {
+            if c == '|' || c == ')' {
+                break;
+            }
+            let atom = self.atom()?;
+            nodes.push(self.quantified(atom)?);
+        }
+        Ok(Node::Concatenation(nodes))
+    }
+
+    fn quantified(&mut self, node: Node) -> Result<Node> {
+        let (min, max) = match self.peek() {
+            Some('*') => (0, None),
+            Some('+') => (1, None),
+            Some('?') => (0, Some(1)),
+            Some('{') => return self.counted(node),
+            _ => return Ok(node),
+        };
+        self.position += 1;
+        Ok(self.repeat(node, min, max))
+    }
+
+    /// Parse `{n}`, `{m,}` or `{m,n}`. A brace that starts none of these is a literal.
+    fn counted(&mut self, node: Node) -> Result<Node> {
+        let start = self.position;
+        let close = match self.chars[start..].iter().position(|c| *c == '}') {
+            Some(close) => start + close,
+            None => return Ok(node),
+        };
+        let bounds: String = self.chars[start + 1..close].iter().collect();
+        let parse = |bound: &str| bound.parse::<usize>().ok();
+        let (min, max) = match bounds.split_once(',') {
+            Some((min, "")) => (parse(min), None),
+            Some((min, max)) => match parse(max) {
+                Some(max) => (parse(min), Some(max)),
+                None => return Ok(node),
+            },
+            None => (parse(&bounds), parse(&bounds)),
+        };
+        let min = match min {
+            Some(min) => min,
+            None => return Ok(node),
+        };
+        if max.is_some_and(|max| max < min) {
+            bail!("invalid repetition {{{}}}", bounds);
+        }
+        self.position = close + 1;
+        Ok(self.repeat(node, min, max))
+    }
+
+    fn repeat(&mut self, node: Node, min: usize, max: Option<usize>) -> Node {
+        let greedy = !self.eat('?');
+        Node::Repeat {
+            node: Box::new(node),
+            min,
+            max,
+            greedy,
+        }
+    }
+
+    fn atom(&mut self) -> Result<Node> {
+        let c = self.peek().ok_or_else(|| anyhow!("unexpected end of expression"))?;
+        self.position += 1;
+        Ok(match c {
+            '.' => Node::Any,
+            '^' => Node::Start,
+            '$' => Node::End,
+            '(' => {
+                let capture = if self.eat('?') {
+                    if !self.eat(':') {
+                        bail!("only (?:...) groups are supported");
+                    }
+                    None
+                } else {
+                    self.captures += 1;
+                    Some(self.captures)
+                };
+                let node = self.alternation()?;
+                if !self.eat(')') {
+                    bail!("unclosed (");
+                }
+                Node::Group {
+                    node: Box::new(node),
+                    capture,
+                }
+            }
+            '[' => self.class()?,
+            '\\' => self.escape()?,
+            '*' | '+' | '?' => bail!("nothing to repeat before {}", c),
+            c => Node::Char(c),
+        })
+    }
+
+    fn escape(&mut self) -> Result<Node> {
+        let c = self.peek().ok_or_else(|| anyhow!("trailing backslash"))?;
+        self.position += 1;
+        let class = |ranges: &[(char, char)], negated| Node::Class {
+            ranges: ranges.to_vec(),
+            negated,
+        };
+        Ok(match c {
+            'd' | 'D' => class(DIGITS, c == 'D'),
+            'w' | 'W' => class(WORD, c == 'W'),
+            's' | 'S' => class(SPACE, c == 'S'),
+            c if c.is_ascii_alphanumeric() => bail!("unsupported escape \\{}", c),
+            c => Node::Char(c),
+        })
+    }
+
+    fn class(&mut self) -> Result<Node> {
+        let negated = self.eat('^');
+        let mut ranges = Vec::new();
+        let mut first = true;
+        loop {
+            let c = self.peek().ok_or_else(|| anyhow!("unclosed ["))?;
+            self.position += 1;
+            let low = match c {
+                ']' if !first => break,
+                '\\' => {
+                    let escaped = self.peek().ok_or_else(|| anyhow!("trailing backslash"))?;
+                    self.position += 1;
+                    match escaped {
+                        'd' => {
+                            ranges.extend_from_slice(DIGITS);
+                            continue;
+                        }
+                        'w' => {
+                            ranges.extend_from_slice(WORD);
+                            continue;
+                        }
+                        's' => {
+                            ranges.extend_from_slice(SPACE);
+                            continue;
+                        }
+                        escaped => escaped,
+                    }
+                }
+                c => c,
+            };
+            first = false;
+            if self.peek() == Some('-') && self.chars.get(self.position + 1).is_some_and(|c| *c != ']') {
+                let high = self.chars[self.position + 1];
+                self.position += 2;
+                if high < low {
+                    bail!("invalid range {}-{}", low, high);
+                }
+                ranges.push((low, high));
+            } else {
+                ranges.push((low, low));
+            }
+        }
+        Ok(Node::Class { ranges, negated })
+    }
+}
+
+const DIGITS: &[(char, char)] = &[('0', '9')];
+const WORD: &[(char, char)] = &[('a', 'z'), ('A', 'Z'), ('0', '9'), ('_', '_')];
+const SPACE: &[(char, char)] = &[(' ', ' '), ('\t', '\r')];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn capture(pattern: &str, text: &str) -> Option<String> {
+        pattern.parse::<Regex>().unwrap().first_capture(text)
+    }
+
+    #[test]
+    fn test_first_capture() {
+        assert_eq!(capture("^(IMG|DSC|MVI)", "IMG_1234.JPG").as_deref(), Some("IMG"));
+        assert_eq!(capture("^(IMG|DSC|MVI)", "P1000.JPG"), None);
+        assert_eq!(
+            capture(r"^([a-z]+)-\d{4}-\d\d", "nginx-2023-01.log").as_deref(),
+            Some("nginx")
+        );
+        assert_eq!(capture(r"(\w+?)_", "web_api_1.log").as_deref(), Some("web"));
+        assert_eq!(capture(r"(\w+)_", "web_api_1.log").as_deref(), Some("web_api"));
+        assert_eq!(capture(r"\.([^.]+)$", "a.tar.gz").as_deref(), Some("gz"));
+        assert_eq!(capture(r"(?:x|y)(\d*)z", "ay12z").as_deref(), Some("12"));
+        assert_eq!(capture(r"\d+", "v10.2").as_deref(), Some("10"));
+        assert_eq!(capture("(a)|b", "b"), None);
+        assert_eq!(capture("(a*)*b", "aab").as_deref(), Some("aa"));
+    }
+
+    #[test]
+    fn test_invalid() {
+        for pattern in ["(a", "a)", "[a", "*a", r"\q", "a{3,1}", "(?=a)"] {
+            assert!(pattern.parse::<Regex>().is_err(), "{}", pattern);
+        }
+    }
+}
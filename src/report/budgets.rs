@@ -0,0 +1,147 @@
+use crate::budget::{Budget, Scope};
+use crate::file::{print_item, print_title, size_human_readable, Directory, DrawOptions};
+use anyhow::Result;
+
+/// A budget along with the size counted against it: that of the whole scan, or that of the
+/// directory using most of it, along with every directory over it.
+pub struct BudgetCheck<'a> {
+    pub budget: &'a Budget,
+    used: u64,
+    over: Vec<(&'a Directory, u64)>,
+}
+
+impl BudgetCheck<'_> {
+    /// Size counted against the budget, in the directory using most of it for budgets applying
+    /// to each directory.
+    pub fn used(&self) -> u64 {
+        self.used
+    }
+
+    pub fn passed(&self) -> bool {
+        self.used <= self.budget.limit
+    }
+}
+
+/// Check every budget against the directory, in the order of the budget file.
+pub fn check_budgets<'a>(directory: &'a Directory, budgets: &'a [Budget]) -> Vec<BudgetCheck<'a>> {
+    let totals = directory.extension_totals();
+    budgets
+        .iter()
+        .map(|budget| match budget.scope {
+            Scope::Repo => BudgetCheck {
+                budget,
+                used: budget.used(&totals),
+                over: Vec::new(),
+            },
+            Scope::Directory => {
+                let mut used = 0;
+                let mut over = Vec::new();
+                directory.walk(&mut |d| {
+                    let size = budget.used(&d.extension_totals());
+                    used = used.max(size);
+                    if size > budget.limit {
+                        over.push((d, size));
+                    }
+                });
+                BudgetCheck { budget, used, over }
+            }
+        })
+        .collect()
+}
+
+/// Print every budget with the size counted against it and whether it was kept to, and for
+/// budgets applying to each directory, the directories exceeding them.
+pub fn draw_budgets(directory: &Directory, checks: &[BudgetCheck], options: &DrawOptions) -> Result<()> {
+    print_title(options.locale.text("budgets"))?;
+    if checks.is_empty() {
+        print_item(options.locale.text("no budgets defined"), true, 1, &[], options)?;
+        return Ok(());
+    }
+
+    let mut skipped = Vec::new();
+    for (idx, check) in checks.iter().enumerate() {
+        let last = idx + 1 == checks.len();
+        let scope = match check.budget.scope {
+            Scope::Repo => options.locale.text("whole scan"),
+            Scope::Directory => options.locale.text("per directory"),
+        };
+        let outcome = if check.passed() { "pass" } else { "fail" };
+        print_item(
+            &format!(
+                "{} ({}) ── {} {} {} ── {}",
+                check.budget.key,
+                scope,
+                size_human_readable(check.used, 2, options.locale).trim_end(),
+                options.locale.text("of"),
+                size_human_readable(check.budget.limit, 2, options.locale).trim_end(),
+                options.locale.text(outcome)
+            ),
+            last,
+            1,
+            &skipped,
+            options,
+        )?;
+        if last {
+            skipped.push(1);
+        }
+        for (idx, (d, size)) in check.over.iter().enumerate() {
+            print_item(
+                &format!(
+                    "{} ── {}",
+                    d.display_name(directory.path(), options.paths.qualified())?,
+                    size_human_readable(*size, 2, options.locale).trim_end()
+                ),
+                idx + 1 == check.over.len(),
+                2,
+                &skipped,
+                options,
+            )?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::budget::Selector;
+    use crate::file::ScanOptions;
+    use crate::fs::MemoryFileSystem;
+    use std::path::PathBuf;
+
+    #[test]
+    fn test_check_budgets() {
+        let mut file_system = MemoryFileSystem::default();
+        file_system
+            .add_file("/repo/a.mp4", 100)
+            .add_file("/repo/art/b.psd", 60)
+            .add_file("/repo/art/old/c.psd", 50);
+        let options = ScanOptions {
+            max_depth: 2,
+            file_system: Box::new(file_system),
+            ..Default::default()
+        };
+        let directory = Directory::new(PathBuf::from("/repo"), 0, &options).expect("could not create directory");
+        let budgets = [
+            Budget {
+                scope: Scope::Repo,
+                key: "mp4".to_string(),
+                selector: Selector::Extension("mp4".to_string()),
+                limit: 100,
+            },
+            Budget {
+                scope: Scope::Directory,
+                key: "psd".to_string(),
+                selector: Selector::Extension("psd".to_string()),
+                limit: 80,
+            },
+        ];
+
+        let checks = check_budgets(&directory, &budgets);
+        assert!(checks[0].passed());
+        assert_eq!(checks[0].used, 100);
+        assert!(!checks[1].passed());
+        assert_eq!(checks[1].used, 110);
+        assert_eq!(checks[1].over.len(), 2);
+    }
+}
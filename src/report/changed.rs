@@ -0,0 +1,16 @@
+use crate::file::{print_item, print_title, DrawOptions};
+use anyhow::Result;
+
+/// Note that the tree changed while it was scanned, with the number of entries that disappeared
+/// between being listed and being read. They are left out of the counts above.
+pub fn draw_tree_changed(vanished: usize, options: &DrawOptions) -> Result<()> {
+    print_title(options.locale.text("tree changed during scan"))?;
+    print_item(
+        &format!("{} ── {}", options.locale.text("vanished entries"), vanished),
+        true,
+        1,
+        &[],
+        options,
+    )?;
+    Ok(())
+}
@@ -0,0 +1,113 @@
+use crate::file::{print_item, print_title, size_human_readable, Directory, DrawOptions};
+use crate::width;
+use anyhow::Result;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+/// Suffixes of single-file compression formats, whose files hold a compressed copy of the file
+/// named without the suffix.
+const COMPRESSION_SUFFIXES: &[&str] = &["gz", "bz2", "xz", "zst", "lz4", "br", "lzma", "Z"];
+
+/// Files of one extension found next to a compressed copy of themselves.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CompressedPairs {
+    /// Number of files with a compressed copy.
+    pub count: usize,
+
+    /// Total size of those files.
+    pub original_bytes: u64,
+
+    /// Total size of their compressed copies.
+    pub compressed_bytes: u64,
+}
+
+impl CompressedPairs {
+    pub fn add(&mut self, original_bytes: u64, compressed_bytes: u64) {
+        self.count += 1;
+        self.original_bytes += original_bytes;
+        self.compressed_bytes += compressed_bytes;
+    }
+
+    pub fn merge(&mut self, other: &CompressedPairs) {
+        self.count += other.count;
+        self.original_bytes += other.original_bytes;
+        self.compressed_bytes += other.compressed_bytes;
+    }
+}
+
+/// File a compressed file is a copy of, such as `access.log` for `access.log.gz`, or `None` if the
+/// file does not have the suffix of a compression format.
+pub fn original_of(file: &Path) -> Option<PathBuf> {
+    let suffix = file.extension()?.to_str()?;
+    COMPRESSION_SUFFIXES.contains(&suffix).then(|| file.with_extension(""))
+}
+
+/// Print, for each extension, how many of its files have a compressed copy next to them, out of
+/// all its files, with the size of those files and of their compressed copies.
+pub fn draw_compressed_pairs(directory: &Directory, options: &DrawOptions) -> Result<()> {
+    let mut totals: BTreeMap<Option<String>, CompressedPairs> = BTreeMap::new();
+    directory.walk(&mut |d| {
+        for (extension, pairs) in &d.compressed_pairs {
+            totals.entry(extension.clone()).or_default().merge(pairs);
+        }
+    });
+
+    print_title(options.locale.text("compressed copies"))?;
+    if totals.is_empty() {
+        print_item(options.locale.text("none found"), true, 1, &[], options)?;
+        return Ok(());
+    }
+
+    let counts: BTreeMap<Option<String>, usize> = directory
+        .extension_totals()
+        .into_iter()
+        .map(|e| (e.name, e.count))
+        .collect();
+    let mut totals: Vec<_> = totals.into_iter().collect();
+    totals.sort_by_key(|(_, pairs)| std::cmp::Reverse(pairs.original_bytes));
+    let label = |extension: &Option<String>| extension.clone().unwrap_or_else(|| options.extensionless_label.clone());
+    let max_label_chars = totals.iter().map(|(e, _)| width::width(&label(e))).max().unwrap_or(0);
+    let max_count_chars = counts.values().max().copied().unwrap_or(0).to_string().len();
+    for (idx, (extension, pairs)) in totals.iter().enumerate() {
+        print_item(
+            &format!(
+                "{} ── {:>max_count_chars$} {} {:>max_count_chars$} ── {:>10} ── {} {:>10}",
+                width::pad(&label(extension), max_label_chars),
+                pairs.count,
+                options.locale.text("of"),
+                counts.get(extension).copied().unwrap_or(0),
+                size_human_readable(pairs.original_bytes, 2, options.locale),
+                options.locale.text("compressed"),
+                size_human_readable(pairs.compressed_bytes, 2, options.locale),
+            ),
+            idx + 1 == totals.len(),
+            1,
+            &[],
+            options,
+        )?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_original_of() {
+        assert_eq!(
+            original_of(Path::new("/logs/access.log.gz")),
+            Some(PathBuf::from("/logs/access.log"))
+        );
+        assert_eq!(
+            original_of(Path::new("/data/data.csv.zst")),
+            Some(PathBuf::from("/data/data.csv"))
+        );
+        assert_eq!(
+            original_of(Path::new("/data/archive.tar.xz")),
+            Some(PathBuf::from("/data/archive.tar"))
+        );
+        assert_eq!(original_of(Path::new("/data/data.csv")), None);
+        assert_eq!(original_of(Path::new("/data/photo.zip")), None);
+    }
+}
@@ -0,0 +1,118 @@
+use crate::file::{print_item, print_title, DrawOptions};
+use anyhow::Result;
+use std::cell::RefCell;
+use std::path::{Path, PathBuf};
+
+/// Depth of every directory reached during a scan, including those below the recursion limit,
+/// relative to the scan root.
+#[derive(Debug, Default)]
+pub struct DepthStats {
+    /// Number of directories at each depth, indexed by depth.
+    directories: RefCell<Vec<usize>>,
+
+    /// First directory found at the greatest depth.
+    deepest: RefCell<Option<PathBuf>>,
+}
+
+impl DepthStats {
+    pub fn add(&self, dir: &Path, depth: usize) {
+        let mut directories = self.directories.borrow_mut();
+        if directories.len() <= depth {
+            directories.resize(depth + 1, 0);
+            *self.deepest.borrow_mut() = Some(dir.to_path_buf());
+        }
+        directories[depth] += 1;
+    }
+
+    /// Number of directories at each depth, indexed by depth.
+    pub fn directories(&self) -> Vec<usize> {
+        self.directories.borrow().clone()
+    }
+
+    /// Average depth of all directories, or 0 if there are none.
+    fn average(&self) -> f64 {
+        let directories = self.directories.borrow();
+        let count: usize = directories.iter().sum();
+        let total: usize = directories.iter().enumerate().map(|(depth, n)| depth * n).sum();
+        if count == 0 {
+            0.0
+        } else {
+            total as f64 / count as f64
+        }
+    }
+}
+
+/// Print the maximum and average depth of the directories below the scan root, the deepest of
+/// them, and how many directories there are at each depth.
+pub fn draw_depths(stats: &DepthStats, scan_root: &Path, options: &DrawOptions) -> Result<()> {
+    let directories = stats.directories();
+
+    print_title(options.locale.text("depths"))?;
+    print_item(
+        &format!(
+            "{} ── {}",
+            options.locale.text("maximum"),
+            directories.len().saturating_sub(1)
+        ),
+        false,
+        1,
+        &[],
+        options,
+    )?;
+    print_item(
+        &format!(
+            "{} ── {}",
+            options.locale.text("average"),
+            options.locale.decimal(stats.average(), 2)
+        ),
+        false,
+        1,
+        &[],
+        options,
+    )?;
+    if let Some(deepest) = stats.deepest.borrow().as_deref() {
+        print_item(
+            &format!(
+                "{} ── {}",
+                options.locale.text("deepest"),
+                options.paths.qualified().format(deepest, scan_root)
+            ),
+            false,
+            1,
+            &[],
+            options,
+        )?;
+    }
+
+    print_item(options.locale.text("directories per depth"), true, 1, &[], options)?;
+    let depth_chars = directories.len().saturating_sub(1).to_string().len();
+    let count_chars = directories.iter().max().copied().unwrap_or(0).to_string().len();
+    for (depth, count) in directories.iter().enumerate() {
+        print_item(
+            &format!("{:>depth_chars$} ── {:>count_chars$}", depth, count),
+            depth + 1 == directories.len(),
+            2,
+            &[1],
+            options,
+        )?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_add() {
+        let stats = DepthStats::default();
+        stats.add(Path::new("/r"), 0);
+        stats.add(Path::new("/r/a"), 1);
+        stats.add(Path::new("/r/a/b"), 2);
+        stats.add(Path::new("/r/c"), 1);
+        stats.add(Path::new("/r/c/d"), 2);
+        assert_eq!(stats.directories(), vec![1, 2, 2]);
+        assert_eq!(stats.deepest.borrow().as_deref(), Some(Path::new("/r/a/b")));
+        assert_eq!(stats.average(), 1.2);
+    }
+}
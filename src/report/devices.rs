@@ -0,0 +1,111 @@
+use crate::file::{
+    max_count_chars, max_extension_chars, merge_extension, print_item, print_title, Directory, DrawOptions, Extension,
+};
+use crate::storage::Storage;
+use anyhow::Result;
+
+/// Files found on a single file system, along with the first directory of the tree living on it.
+struct DeviceTotals<'a> {
+    device: u64,
+    mount: &'a Directory,
+    extensions: Vec<Extension>,
+}
+
+/// Merge the extensions of every directory by the file system it lives on, in the order the file
+/// systems are first encountered.
+fn device_totals(directory: &Directory) -> Vec<DeviceTotals<'_>> {
+    let mut totals: Vec<DeviceTotals> = Vec::new();
+    directory.walk(&mut |d| {
+        let device = match d.device {
+            Some(device) => device,
+            None => return,
+        };
+        let index = match totals.iter().position(|t| t.device == device) {
+            Some(index) => index,
+            None => {
+                totals.push(DeviceTotals {
+                    device,
+                    mount: d,
+                    extensions: Vec::new(),
+                });
+                totals.len() - 1
+            }
+        };
+        for extension in d.extensions() {
+            merge_extension(&mut totals[index].extensions, extension.clone());
+        }
+    });
+    totals
+}
+
+/// Print the extensions found on each file system the tree spans, labelled by the topmost
+/// directory on that file system and the kind of storage behind it.
+pub fn draw_devices(directory: &Directory, options: &DrawOptions) -> Result<()> {
+    let totals = device_totals(directory);
+
+    print_title(options.locale.text("file systems"))?;
+    let mut skipped = Vec::new();
+    for (idx, device) in totals.iter().enumerate() {
+        let last = idx + 1 == totals.len();
+        let name = device.mount.display_name(directory.path(), options.paths.qualified())?;
+        print_item(
+            &format!("{} ({})", name, Storage::detect(device.mount.path()).label()),
+            last,
+            1,
+            &skipped,
+            options,
+        )?;
+        if last {
+            skipped.push(1);
+        }
+
+        let max_extension_chars = max_extension_chars(&device.extensions, options);
+        let max_count_chars = max_count_chars(&device.extensions);
+        for (idx, extension) in device.extensions.iter().enumerate() {
+            print_item(
+                &extension.to_string_formatted(max_extension_chars, max_count_chars, options),
+                idx + 1 == device.extensions.len(),
+                2,
+                &skipped,
+                options,
+            )?;
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::file::ScanOptions;
+    use crate::fs::MemoryFileSystem;
+    use std::path::{Path, PathBuf};
+
+    #[test]
+    fn test_device_totals() {
+        let mut file_system = MemoryFileSystem::default();
+        file_system
+            .add_file("/home/a.txt", 1)
+            .add_file("/home/nfs/b.txt", 2)
+            .add_file("/home/nfs/deep/c.txt", 3)
+            .add_file("/home/local/d.txt", 4)
+            .mount("/home/nfs", 7);
+        let options = ScanOptions {
+            max_depth: 1,
+            devices: true,
+            file_system: Box::new(file_system),
+            ..Default::default()
+        };
+        let directory = Directory::new(PathBuf::from("/home"), 0, &options).expect("could not create directory");
+
+        let totals = device_totals(&directory);
+        assert_eq!(totals.len(), 2);
+        assert_eq!(totals[0].device, 0);
+        assert_eq!(totals[0].extensions[0].count, 2);
+        assert_eq!(totals[0].extensions[0].total_size_bytes, 5);
+        assert_eq!(totals[1].device, 7);
+        assert_eq!(totals[1].mount.path(), Path::new("/home/nfs"));
+        assert_eq!(totals[1].extensions[0].total_size_bytes, 5);
+    }
+}
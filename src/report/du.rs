@@ -0,0 +1,64 @@
+use crate::file::{size_human_readable, Directory};
+use crate::locale::Locale;
+use anyhow::Result;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+/// Path of a directory as `du` prints it: below the directory given on the command line, as it
+/// was given. Directories outside the scan root, reached through junctions, keep their full path.
+fn du_path(directory: &Directory, scan_root: &Path, given_root: &Path) -> PathBuf {
+    match directory.path().strip_prefix(scan_root) {
+        Ok(relative) if relative.as_os_str().is_empty() => given_root.to_path_buf(),
+        Ok(relative) => given_root.join(relative),
+        Err(_) => directory.path().to_path_buf(),
+    }
+}
+
+fn du_line(directory: &Directory, scan_root: &Path, given_root: &Path, bytes: bool) -> String {
+    let size_bytes: u64 = directory.extension_totals().iter().map(|e| e.total_size_bytes).sum();
+    let size = if bytes {
+        size_bytes.to_string()
+    } else {
+        size_human_readable(size_bytes, 1, Locale::En).trim_end().to_string()
+    };
+    format!("{}\t{}", size, du_path(directory, scan_root, given_root).display())
+}
+
+/// Write the recursive size of a directory whose subtree has been scanned, in the layout of `du`.
+/// The size is that of the files below the directory, without the directories themselves, in bytes
+/// with `bytes` and human-readable otherwise.
+pub fn write_du_directory(
+    out: &mut impl Write,
+    directory: &Directory,
+    scan_root: &Path,
+    given_root: &Path,
+    bytes: bool,
+) -> Result<()> {
+    writeln!(out, "{}", du_line(directory, scan_root, given_root, bytes))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::file::ScanOptions;
+
+    #[test]
+    fn test_du_lines() {
+        let root = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests");
+        let options = ScanOptions {
+            max_depth: 1,
+            ..Default::default()
+        };
+        let mut lines = Vec::new();
+        let scan_root = std::fs::canonicalize(&root).expect("could not canonicalize");
+        Directory::new_streaming(root, 0, &options, &mut |d| {
+            lines.push(du_line(d, &scan_root, Path::new("tests"), true));
+            Ok(())
+        })
+        .expect("could not create directory");
+
+        assert_eq!(lines.first().map(String::as_str), Some("21\ttests/dirA"));
+        assert_eq!(lines.last().map(String::as_str), Some("55\ttests"));
+    }
+}
@@ -0,0 +1,107 @@
+use crate::file::{print_item, print_title, Directory, DrawOptions};
+use crate::magic::Encoding;
+use crate::width;
+use anyhow::Result;
+use std::collections::BTreeMap;
+
+/// Number of text files of one extension by their encoding.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Encodings(BTreeMap<Encoding, usize>);
+
+impl Encodings {
+    pub fn add(&mut self, encoding: Encoding) {
+        *self.0.entry(encoding).or_default() += 1;
+    }
+
+    pub fn merge(&mut self, other: &Encodings) {
+        for (encoding, count) in &other.0 {
+            *self.0.entry(*encoding).or_default() += count;
+        }
+    }
+
+    fn has_legacy(&self) -> bool {
+        self.0.keys().any(|encoding| encoding.is_legacy())
+    }
+
+    /// Format as ``$NAME ── $COUNT $ENCODING ── ...``, listing only the encodings found.
+    fn to_string_formatted(&self, label: &str, max_label_chars: usize) -> String {
+        let mut formatted = width::pad(label, max_label_chars);
+        for (encoding, count) in &self.0 {
+            formatted.push_str(&format!(" ── {} {}", count, encoding.label()));
+        }
+        formatted
+    }
+}
+
+/// Print the encodings of the text files of each extension, then every directory holding files in
+/// a legacy encoding or in UTF-16, along with the encodings of its files by extension.
+pub fn draw_encodings(directory: &Directory, options: &DrawOptions) -> Result<()> {
+    let mut totals: BTreeMap<Option<String>, Encodings> = BTreeMap::new();
+    let mut affected = Vec::new();
+    directory.walk(&mut |d| {
+        for (extension, encodings) in &d.encodings {
+            totals.entry(extension.clone()).or_default().merge(encodings);
+        }
+        if d.encodings.values().any(Encodings::has_legacy) {
+            affected.push(d);
+        }
+    });
+
+    print_title(options.locale.text("encodings"))?;
+    if totals.is_empty() {
+        print_item(options.locale.text("none found"), true, 1, &[], options)?;
+        return Ok(());
+    }
+
+    print_item(
+        options.locale.text("by extension"),
+        affected.is_empty(),
+        1,
+        &[],
+        options,
+    )?;
+    let skipped = if affected.is_empty() { vec![1] } else { Vec::new() };
+    draw_extensions(&totals, 2, &skipped, options)?;
+    if affected.is_empty() {
+        return Ok(());
+    }
+
+    print_item(options.locale.text("with legacy encodings"), true, 1, &[], options)?;
+    let mut skipped = vec![1];
+    for (idx, d) in affected.iter().enumerate() {
+        let last = idx + 1 == affected.len();
+        print_item(
+            &d.display_name(directory.path(), options.paths.qualified())?,
+            last,
+            2,
+            &skipped,
+            options,
+        )?;
+        if last {
+            skipped.push(2);
+        }
+        draw_extensions(&d.encodings, 3, &skipped, options)?;
+    }
+    Ok(())
+}
+
+/// Print the encodings of each extension at the given depth.
+fn draw_extensions(
+    extensions: &BTreeMap<Option<String>, Encodings>,
+    depth: usize,
+    skipped: &[usize],
+    options: &DrawOptions,
+) -> Result<()> {
+    let label = |extension: &Option<String>| extension.clone().unwrap_or_else(|| options.extensionless_label.clone());
+    let max_label_chars = extensions.keys().map(|e| width::width(&label(e))).max().unwrap_or(0);
+    for (idx, (extension, encodings)) in extensions.iter().enumerate() {
+        print_item(
+            &encodings.to_string_formatted(&label(extension), max_label_chars),
+            idx + 1 == extensions.len(),
+            depth,
+            skipped,
+            options,
+        )?;
+    }
+    Ok(())
+}
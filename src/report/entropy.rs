@@ -0,0 +1,49 @@
+use crate::file::{max_count_chars, max_extension_chars, print_item, print_title, Directory, DrawOptions};
+use anyhow::Result;
+
+/// Print every directory containing files that look encrypted or compressed without having the
+/// extension of a compressed format, along with the extensions, counts and sizes of those files.
+pub fn draw_high_entropy(directory: &Directory, options: &DrawOptions) -> Result<()> {
+    let mut affected = Vec::new();
+    directory.walk(&mut |d| {
+        if !d.high_entropy_files.is_empty() {
+            affected.push(d);
+        }
+    });
+
+    print_title(options.locale.text("high entropy"))?;
+    if affected.is_empty() {
+        print_item(options.locale.text("none found"), true, 1, &[], options)?;
+        return Ok(());
+    }
+
+    let mut skipped = Vec::new();
+    for (idx, d) in affected.iter().enumerate() {
+        let last = idx + 1 == affected.len();
+        print_item(
+            &d.display_name(directory.path(), options.paths.qualified())?,
+            last,
+            1,
+            &skipped,
+            options,
+        )?;
+        if last {
+            skipped.push(1);
+        }
+
+        let files = &d.high_entropy_files;
+        let max_extension_chars = max_extension_chars(files, options);
+        let max_count_chars = max_count_chars(files);
+        for (idx, extension) in files.iter().enumerate() {
+            print_item(
+                &extension.to_string_formatted(max_extension_chars, max_count_chars, options),
+                idx + 1 == files.len(),
+                2,
+                &skipped,
+                options,
+            )?;
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,84 @@
+use crate::audit::Finding;
+use crate::file::{print_item, print_title, Directory, DrawOptions};
+use crate::width;
+use anyhow::Result;
+
+/// Number of files per finding in a single directory, for the given kinds of findings only.
+fn count_findings(directory: &Directory, kinds: &[Finding]) -> Vec<(Finding, usize)> {
+    kinds
+        .iter()
+        .map(|&kind| (kind, directory.findings.iter().filter(|(f, _)| *f == kind).count()))
+        .filter(|(_, count)| *count > 0)
+        .collect()
+}
+
+/// Print, for every directory containing any of the given kinds of findings, how many files are
+/// affected by each of them, optionally followed by each of those files.
+pub fn draw_findings(
+    directory: &Directory,
+    title: &'static str,
+    kinds: &[Finding],
+    list: bool,
+    options: &DrawOptions,
+) -> Result<()> {
+    let mut affected = Vec::new();
+    directory.walk(&mut |d| {
+        let counts = count_findings(d, kinds);
+        if !counts.is_empty() {
+            affected.push((d, counts));
+        }
+    });
+
+    print_title(options.locale.text(title))?;
+    if affected.is_empty() {
+        print_item(options.locale.text("none found"), true, 1, &[], options)?;
+        return Ok(());
+    }
+
+    let mut skipped = Vec::new();
+    for (idx, (d, counts)) in affected.iter().enumerate() {
+        let last = idx + 1 == affected.len();
+        print_item(
+            &d.display_name(directory.path(), options.paths.qualified())?,
+            last,
+            1,
+            &skipped,
+            options,
+        )?;
+        if last {
+            skipped.push(1);
+        }
+
+        let max_label_chars = counts.iter().map(|(f, _)| width::width(f.label())).max().unwrap_or(0);
+        for (idx, (finding, count)) in counts.iter().enumerate() {
+            let last = idx + 1 == counts.len();
+            print_item(
+                &format!("{} ── {}", width::pad(finding.label(), max_label_chars), count),
+                last,
+                2,
+                &skipped,
+                options,
+            )?;
+
+            if !list {
+                continue;
+            }
+            let mut skipped = skipped.clone();
+            if last {
+                skipped.push(2);
+            }
+            let files: Vec<_> = d.findings.iter().filter(|(f, _)| f == finding).collect();
+            for (idx, (_, file)) in files.iter().enumerate() {
+                print_item(
+                    &options.paths.qualified().format(file, directory.path()),
+                    idx + 1 == files.len(),
+                    3,
+                    &skipped,
+                    options,
+                )?;
+            }
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,108 @@
+use crate::file::{print_item, print_title, size_human_readable, DrawOptions};
+use crate::width;
+use anyhow::Result;
+use std::cell::RefCell;
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+use std::path::{Path, PathBuf};
+
+/// Size, path and extension of a file.
+type File = (u64, PathBuf, Option<String>);
+
+/// The largest files seen during a scan, kept in a bounded heap so that memory does not grow with
+/// the number of files.
+#[derive(Debug)]
+pub struct LargestFiles {
+    count: usize,
+
+    /// Smallest of the kept files on top, ready to be replaced by a larger one.
+    heap: RefCell<BinaryHeap<Reverse<File>>>,
+}
+
+impl LargestFiles {
+    pub fn new(count: usize) -> Self {
+        Self {
+            count,
+            heap: RefCell::new(BinaryHeap::with_capacity(count + 1)),
+        }
+    }
+
+    /// Offer a file with its size and the extension it is counted under.
+    pub fn add(&self, file: &Path, size_bytes: u64, extension: Option<&str>) {
+        let mut heap = self.heap.borrow_mut();
+        if heap.len() == self.count
+            && heap
+                .peek()
+                .is_none_or(|Reverse((smallest, _, _))| size_bytes <= *smallest)
+        {
+            return;
+        }
+        heap.push(Reverse((size_bytes, file.to_path_buf(), extension.map(str::to_string))));
+        if heap.len() > self.count {
+            heap.pop();
+        }
+    }
+
+    /// The kept files, largest first.
+    fn sorted(&self) -> Vec<File> {
+        let mut files: Vec<_> = self.heap.borrow().iter().map(|Reverse(file)| file.clone()).collect();
+        files.sort_by(|a, b| b.0.cmp(&a.0).then_with(|| a.1.cmp(&b.1)));
+        files
+    }
+}
+
+/// Print the largest files across the whole scan as a ranked list, with their size and extension.
+/// Paths are printed relative to the scan root.
+pub fn draw_largest_files(largest: &LargestFiles, scan_root: &Path, options: &DrawOptions) -> Result<()> {
+    let files = largest.sorted();
+
+    print_title(options.locale.text("largest files"))?;
+    if files.is_empty() {
+        print_item(options.locale.text("none found"), true, 1, &[], options)?;
+        return Ok(());
+    }
+
+    let rank_chars = files.len().to_string().len();
+    let label = |extension: &Option<String>| extension.clone().unwrap_or_else(|| options.extensionless_label.clone());
+    let max_label_chars = files.iter().map(|(_, _, e)| width::width(&label(e))).max().unwrap_or(0);
+    for (idx, (size_bytes, file, extension)) in files.iter().enumerate() {
+        print_item(
+            &format!(
+                "{:>rank_chars$}. {:>10} ── {} ── {}",
+                idx + 1,
+                size_human_readable(*size_bytes, 2, options.locale),
+                width::pad(&label(extension), max_label_chars),
+                file.strip_prefix(scan_root).unwrap_or(file).display(),
+            ),
+            idx + 1 == files.len(),
+            1,
+            &[],
+            options,
+        )?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_largest_files() {
+        let largest = LargestFiles::new(2);
+        largest.add(Path::new("/a.iso"), 300, Some("iso"));
+        largest.add(Path::new("/b.txt"), 10, Some("txt"));
+        largest.add(Path::new("/c.mkv"), 500, Some("mkv"));
+        largest.add(Path::new("/d"), 20, None);
+        let sizes: Vec<_> = largest
+            .sorted()
+            .into_iter()
+            .map(|(size, file, _)| (size, file))
+            .collect();
+        assert_eq!(
+            sizes,
+            vec![(500, PathBuf::from("/c.mkv")), (300, PathBuf::from("/a.iso"))]
+        );
+        assert!(LargestFiles::new(0).sorted().is_empty());
+    }
+}
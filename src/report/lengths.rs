@@ -0,0 +1,237 @@
+use crate::audit::{self, Finding};
+use crate::file::{print_item, print_title, Directory, DrawOptions};
+use crate::width;
+use anyhow::Result;
+use std::path::{Path, PathBuf};
+
+/// Length statistics of the files in a directory, measured in characters.
+#[derive(Debug, Default)]
+pub struct NameLengths {
+    /// Histogram of file name lengths, indexed by length.
+    names: Vec<usize>,
+
+    /// Histogram of absolute path lengths, indexed by length.
+    paths: Vec<usize>,
+
+    /// File with the longest name.
+    longest: Option<PathBuf>,
+
+    /// File with the longest absolute path.
+    longest_path: Option<PathBuf>,
+}
+
+impl NameLengths {
+    pub fn add(&mut self, file: &Path) {
+        record(&mut self.names, name_length(file));
+        record(&mut self.paths, path_length(file));
+        keep_longest(&mut self.longest, file, name_length);
+        keep_longest(&mut self.longest_path, file, path_length);
+    }
+
+    pub fn merge(&mut self, other: &NameLengths) {
+        if let Some(file) = &other.longest {
+            keep_longest(&mut self.longest, file, name_length);
+        }
+        if let Some(file) = &other.longest_path {
+            keep_longest(&mut self.longest_path, file, path_length);
+        }
+        for (length, count) in other.names.iter().enumerate() {
+            record_many(&mut self.names, length, *count);
+        }
+        for (length, count) in other.paths.iter().enumerate() {
+            record_many(&mut self.paths, length, *count);
+        }
+    }
+}
+
+fn name_length(file: &Path) -> usize {
+    file.file_name()
+        .map_or(0, |name| name.to_string_lossy().chars().count())
+}
+
+fn path_length(file: &Path) -> usize {
+    file.to_string_lossy().chars().count()
+}
+
+/// Replace the longest file kept with the given one if it is strictly longer, the first of equally
+/// long files being kept.
+fn keep_longest(longest: &mut Option<PathBuf>, file: &Path, length: fn(&Path) -> usize) {
+    if longest.as_deref().is_none_or(|longest| length(file) > length(longest)) {
+        *longest = Some(file.to_path_buf());
+    }
+}
+
+fn record(histogram: &mut Vec<usize>, length: usize) {
+    record_many(histogram, length, 1)
+}
+
+fn record_many(histogram: &mut Vec<usize>, length: usize, count: usize) {
+    if count == 0 {
+        return;
+    }
+    if histogram.len() <= length {
+        histogram.resize(length + 1, 0);
+    }
+    histogram[length] += count;
+}
+
+/// Smallest length such that at least the given fraction of all entries are no longer than it.
+/// Returns 0 for an empty histogram.
+fn percentile(histogram: &[usize], fraction: f64) -> usize {
+    let total: usize = histogram.iter().sum();
+    let threshold = (total as f64 * fraction).ceil() as usize;
+    let mut cumulative = 0;
+    for (length, count) in histogram.iter().enumerate() {
+        cumulative += count;
+        if cumulative >= threshold.max(1) {
+            return length;
+        }
+    }
+    0
+}
+
+/// Print the maximum and 95th percentile of file name and path lengths under the directory, then
+/// the longest file name and the longest path in each directory.
+pub fn draw_name_lengths(directory: &Directory, options: &DrawOptions) -> Result<()> {
+    let mut totals = NameLengths::default();
+    let mut longest = Vec::new();
+    directory.walk(&mut |d| {
+        totals.merge(&d.name_lengths);
+        if let (Some(name), Some(path)) = (&d.name_lengths.longest, &d.name_lengths.longest_path) {
+            longest.push((d, name, path));
+        }
+    });
+
+    print_title(options.locale.text("file name lengths"))?;
+    for (idx, (label, histogram)) in [("names", &totals.names), ("paths", &totals.paths)].iter().enumerate() {
+        print_item(
+            &format!(
+                "{} ── max {:>4} ── p95 {:>4}",
+                options.locale.text(label),
+                histogram.len().saturating_sub(1),
+                percentile(histogram, 0.95)
+            ),
+            idx == 1 && longest.is_empty(),
+            1,
+            &[],
+            options,
+        )?;
+    }
+
+    if longest.is_empty() {
+        return Ok(());
+    }
+
+    let names = longest
+        .iter()
+        .map(|(d, _, _)| d.display_name(directory.path(), options.paths.qualified()))
+        .collect::<Result<Vec<_>>>()?;
+    let max_name_chars = names.iter().map(|n| width::width(n)).max().unwrap_or(0);
+    // The longest names come first, then the longest paths.
+    for paths in [false, true] {
+        let label = if paths {
+            "longest path per directory"
+        } else {
+            "longest name per directory"
+        };
+        print_item(options.locale.text(label), paths, 1, &[], options)?;
+        let skipped: &[usize] = if paths { &[1] } else { &[] };
+        for (idx, (name, (_, longest_name, longest_path))) in names.iter().zip(longest.iter()).enumerate() {
+            let (file, length) = if paths {
+                (longest_path, path_length(longest_path))
+            } else {
+                (longest_name, name_length(longest_name))
+            };
+            print_item(
+                &format!(
+                    "{} ── {:>4} ── {}",
+                    width::pad(name, max_name_chars),
+                    length,
+                    options.paths.format(file, directory.path())
+                ),
+                idx + 1 == longest.len(),
+                2,
+                skipped,
+                options,
+            )?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Number of paths listed by `draw_longest_paths`.
+const LONGEST_PATHS: usize = 10;
+
+/// Print the longest of the paths found to exceed the path length limit, with their length.
+pub fn draw_longest_paths(directory: &Directory, options: &DrawOptions) -> Result<()> {
+    let mut paths = Vec::new();
+    directory.walk(&mut |d| {
+        for (finding, file) in &d.findings {
+            if *finding == Finding::LongPath {
+                paths.push((audit::path_length(file), file));
+            }
+        }
+    });
+    paths.sort_unstable_by(|a, b| b.0.cmp(&a.0).then_with(|| a.1.cmp(b.1)));
+    paths.truncate(LONGEST_PATHS);
+
+    print_title(options.locale.text("longest paths"))?;
+    if paths.is_empty() {
+        print_item(options.locale.text("none found"), true, 1, &[], options)?;
+        return Ok(());
+    }
+
+    let length_chars = paths[0].0.to_string().len();
+    for (idx, (length, file)) in paths.iter().enumerate() {
+        print_item(
+            &format!("{:>length_chars$} ── {}", length, file.display()),
+            idx + 1 == paths.len(),
+            1,
+            &[],
+            options,
+        )?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_percentile() {
+        let mut histogram = Vec::new();
+        for length in 1..=100 {
+            record(&mut histogram, length);
+        }
+        assert_eq!(percentile(&histogram, 0.95), 95);
+        assert_eq!(percentile(&histogram, 1.0), 100);
+        assert_eq!(percentile(&[], 0.95), 0);
+    }
+
+    #[test]
+    fn test_add() {
+        let mut lengths = NameLengths::default();
+        lengths.add(Path::new("/a/short.rs"));
+        lengths.add(Path::new("/a/much_longer.rs"));
+        lengths.add(Path::new("/a/mid.rs"));
+        lengths.add(Path::new("/a/deeper/directory/x.rs"));
+        assert_eq!(lengths.longest.as_deref(), Some(Path::new("/a/much_longer.rs")));
+        assert_eq!(
+            lengths.longest_path.as_deref(),
+            Some(Path::new("/a/deeper/directory/x.rs"))
+        );
+        assert_eq!(lengths.names.len() - 1, 14);
+        assert_eq!(lengths.paths.len() - 1, 24);
+
+        let mut merged = NameLengths::default();
+        merged.add(Path::new("/b/longest_name_of_all.rs"));
+        merged.merge(&lengths);
+        assert_eq!(merged.longest.as_deref(), Some(Path::new("/b/longest_name_of_all.rs")));
+        assert_eq!(
+            merged.longest_path.as_deref(),
+            Some(Path::new("/b/longest_name_of_all.rs"))
+        );
+    }
+}
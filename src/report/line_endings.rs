@@ -0,0 +1,205 @@
+use crate::file::{print_item, print_title, Directory, DrawOptions};
+use crate::locale::Locale;
+use crate::width;
+use anyhow::Result;
+use std::collections::BTreeMap;
+
+/// Number of text files of one extension by the line endings they use.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LineEndings {
+    /// Files whose lines all end with LF.
+    pub lf: usize,
+
+    /// Files whose lines all end with CRLF.
+    pub crlf: usize,
+
+    /// Files using both.
+    pub mixed: usize,
+}
+
+impl LineEndings {
+    pub fn merge(&mut self, other: &LineEndings) {
+        self.lf += other.lf;
+        self.crlf += other.crlf;
+        self.mixed += other.mixed;
+    }
+
+    fn has_crlf(&self) -> bool {
+        self.crlf + self.mixed > 0
+    }
+
+    fn to_string_formatted(
+        &self,
+        label: &str,
+        max_label_chars: usize,
+        max_count_chars: usize,
+        locale: Locale,
+    ) -> String {
+        format!(
+            "{} ── {:max_count_chars$} LF ── {:max_count_chars$} CRLF ── {:max_count_chars$} {}",
+            width::pad(label, max_label_chars),
+            self.lf,
+            self.crlf,
+            self.mixed,
+            locale.text("mixed"),
+        )
+    }
+}
+
+/// Counts the line endings of a file, fed in chunks.
+#[derive(Debug, Default)]
+pub struct LineEndingCounter {
+    lf: usize,
+    crlf: usize,
+
+    /// Whether the previous chunk ended with CR, so that CRLF split across chunks is recognized.
+    after_cr: bool,
+}
+
+impl LineEndingCounter {
+    pub fn feed(&mut self, chunk: &[u8]) {
+        for &byte in chunk {
+            if byte == b'\n' {
+                if self.after_cr {
+                    self.crlf += 1;
+                } else {
+                    self.lf += 1;
+                }
+            }
+            self.after_cr = byte == b'\r';
+        }
+    }
+
+    /// Line endings of the file as a single count, or `None` for a file without line breaks.
+    pub fn finish(self) -> Option<LineEndings> {
+        match (self.lf, self.crlf) {
+            (0, 0) => None,
+            (_, 0) => Some(LineEndings {
+                lf: 1,
+                ..Default::default()
+            }),
+            (0, _) => Some(LineEndings {
+                crlf: 1,
+                ..Default::default()
+            }),
+            _ => Some(LineEndings {
+                mixed: 1,
+                ..Default::default()
+            }),
+        }
+    }
+}
+
+/// Print the line endings of the text files of each extension, then every directory holding files
+/// with CRLF line endings, along with the line endings of its files by extension.
+pub fn draw_line_endings(directory: &Directory, options: &DrawOptions) -> Result<()> {
+    let mut totals: BTreeMap<Option<String>, LineEndings> = BTreeMap::new();
+    let mut affected = Vec::new();
+    directory.walk(&mut |d| {
+        for (extension, endings) in &d.line_endings {
+            totals.entry(extension.clone()).or_default().merge(endings);
+        }
+        if d.line_endings.values().any(LineEndings::has_crlf) {
+            affected.push(d);
+        }
+    });
+
+    print_title(options.locale.text("line endings"))?;
+    if totals.is_empty() {
+        print_item(options.locale.text("none found"), true, 1, &[], options)?;
+        return Ok(());
+    }
+
+    print_item(
+        options.locale.text("by extension"),
+        affected.is_empty(),
+        1,
+        &[],
+        options,
+    )?;
+    let skipped = if affected.is_empty() { vec![1] } else { Vec::new() };
+    draw_extensions(&totals, 2, &skipped, options)?;
+    if affected.is_empty() {
+        return Ok(());
+    }
+
+    print_item(options.locale.text("with CRLF"), true, 1, &[], options)?;
+    let mut skipped = vec![1];
+    for (idx, d) in affected.iter().enumerate() {
+        let last = idx + 1 == affected.len();
+        print_item(
+            &d.display_name(directory.path(), options.paths.qualified())?,
+            last,
+            2,
+            &skipped,
+            options,
+        )?;
+        if last {
+            skipped.push(2);
+        }
+        draw_extensions(&d.line_endings, 3, &skipped, options)?;
+    }
+    Ok(())
+}
+
+/// Print the line endings of each extension at the given depth.
+fn draw_extensions(
+    extensions: &BTreeMap<Option<String>, LineEndings>,
+    depth: usize,
+    skipped: &[usize],
+    options: &DrawOptions,
+) -> Result<()> {
+    let label = |extension: &Option<String>| extension.clone().unwrap_or_else(|| options.extensionless_label.clone());
+    let max_label_chars = extensions.keys().map(|e| width::width(&label(e))).max().unwrap_or(0);
+    let max_count_chars = extensions
+        .values()
+        .map(|e| e.lf.max(e.crlf).max(e.mixed).to_string().len())
+        .max()
+        .unwrap_or(0);
+    for (idx, (extension, endings)) in extensions.iter().enumerate() {
+        print_item(
+            &endings.to_string_formatted(&label(extension), max_label_chars, max_count_chars, options.locale),
+            idx + 1 == extensions.len(),
+            depth,
+            skipped,
+            options,
+        )?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_line_ending_counter() {
+        let endings = |chunks: &[&[u8]]| {
+            let mut counter = LineEndingCounter::default();
+            chunks.iter().for_each(|chunk| counter.feed(chunk));
+            counter.finish()
+        };
+        assert_eq!(
+            endings(&[b"a\nb\n"]),
+            Some(LineEndings {
+                lf: 1,
+                ..Default::default()
+            })
+        );
+        assert_eq!(
+            endings(&[b"a\r", b"\nb\r\n"]),
+            Some(LineEndings {
+                crlf: 1,
+                ..Default::default()
+            })
+        );
+        assert_eq!(
+            endings(&[b"a\r\nb\n"]),
+            Some(LineEndings {
+                mixed: 1,
+                ..Default::default()
+            })
+        );
+        assert_eq!(endings(&[b"no break"]), None);
+    }
+}
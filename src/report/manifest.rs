@@ -0,0 +1,118 @@
+use crate::file::{print_item, print_title, DrawOptions};
+use crate::manifest::{Discrepancy, Mismatch};
+use crate::width;
+use anyhow::Result;
+use std::collections::BTreeMap;
+use std::path::Path;
+
+/// Number of files with each mismatch, by extension, then by directory relative to the root.
+type Counts<'a> = BTreeMap<Mismatch, BTreeMap<Option<&'a str>, BTreeMap<&'a Path, usize>>>;
+
+fn count<'a>(discrepancies: &'a [Discrepancy]) -> Counts<'a> {
+    let mut counts = Counts::new();
+    for discrepancy in discrepancies {
+        *counts
+            .entry(discrepancy.mismatch)
+            .or_default()
+            .entry(discrepancy.extension.as_deref())
+            .or_default()
+            .entry(discrepancy.path.parent().unwrap_or(Path::new("")))
+            .or_default() += 1;
+    }
+    counts
+}
+
+/// Print the number of files differing from the manifest in each way, broken down by extension
+/// and by the directory holding them.
+pub fn draw_manifest_check(discrepancies: &[Discrepancy], options: &DrawOptions) -> Result<()> {
+    print_title(options.locale.text("manifest check"))?;
+    if discrepancies.is_empty() {
+        print_item(options.locale.text("no discrepancies"), true, 1, &[], options)?;
+        return Ok(());
+    }
+
+    let counts = count(discrepancies);
+    let mut skipped = Vec::new();
+    for (idx, (mismatch, extensions)) in counts.iter().enumerate() {
+        let last = idx + 1 == counts.len();
+        let total: usize = extensions.values().flat_map(BTreeMap::values).sum();
+        skipped.clear();
+        print_item(
+            &format!("{} ── {}", options.locale.text(mismatch.label()), total),
+            last,
+            1,
+            &skipped,
+            options,
+        )?;
+        if last {
+            skipped.push(1);
+        }
+
+        let label =
+            |extension: Option<&str>| extension.map_or_else(|| options.extensionless_label.clone(), str::to_string);
+        let max_label_chars = extensions.keys().map(|e| width::width(&label(*e))).max().unwrap_or(0);
+        for (idx, (extension, directories)) in extensions.iter().enumerate() {
+            let last = idx + 1 == extensions.len();
+            skipped.retain(|&level| level < 2);
+            print_item(
+                &format!(
+                    "{} ── {}",
+                    width::pad(&label(*extension), max_label_chars),
+                    directories.values().sum::<usize>()
+                ),
+                last,
+                2,
+                &skipped,
+                options,
+            )?;
+            if last {
+                skipped.push(2);
+            }
+            for (idx, (directory, count)) in directories.iter().enumerate() {
+                let name = if directory.as_os_str().is_empty() {
+                    ".".to_string()
+                } else {
+                    directory.display().to_string()
+                };
+                print_item(
+                    &format!("{} ── {}", name, count),
+                    idx + 1 == directories.len(),
+                    3,
+                    &skipped,
+                    options,
+                )?;
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    #[test]
+    fn test_count() {
+        let discrepancy = |mismatch, path: &str, extension: &str| Discrepancy {
+            mismatch,
+            path: PathBuf::from(path),
+            extension: Some(extension.to_string()),
+        };
+        let discrepancies = [
+            discrepancy(Mismatch::Missing, "photos/a.jpg", "jpg"),
+            discrepancy(Mismatch::Missing, "photos/b.jpg", "jpg"),
+            discrepancy(Mismatch::Missing, "c.jpg", "jpg"),
+            discrepancy(Mismatch::Hash, "photos/d.png", "png"),
+        ];
+
+        let counts = count(&discrepancies);
+        assert_eq!(
+            counts.keys().copied().collect::<Vec<_>>(),
+            vec![Mismatch::Missing, Mismatch::Hash]
+        );
+        let jpg = &counts[&Mismatch::Missing][&Some("jpg")];
+        assert_eq!(jpg[Path::new("photos")], 2);
+        assert_eq!(jpg[Path::new("")], 1);
+    }
+}
@@ -0,0 +1,58 @@
+mod budgets;
+mod changed;
+mod compressed;
+mod depths;
+mod devices;
+mod du;
+mod encodings;
+mod entropy;
+mod findings;
+mod largest;
+mod lengths;
+mod line_endings;
+mod manifest;
+mod new_extensions;
+mod node_modules;
+mod periods;
+mod porcelain;
+mod quota;
+mod security;
+mod split;
+mod symlinks;
+mod top;
+mod trash;
+mod treemap;
+mod variants;
+mod warnings;
+mod workspaces;
+
+pub use budgets::{check_budgets, draw_budgets};
+pub use changed::draw_tree_changed;
+pub use compressed::{draw_compressed_pairs, original_of, CompressedPairs};
+pub use depths::{draw_depths, DepthStats};
+pub use devices::draw_devices;
+pub use du::write_du_directory;
+pub use encodings::{draw_encodings, Encodings};
+pub use entropy::draw_high_entropy;
+pub use findings::draw_findings;
+pub use largest::{draw_largest_files, LargestFiles};
+pub use lengths::{draw_longest_paths, draw_name_lengths, NameLengths};
+pub use line_endings::{draw_line_endings, LineEndingCounter, LineEndings};
+pub use manifest::draw_manifest_check;
+pub use new_extensions::{draw_new_extensions, new_extensions, NewExtensions};
+pub use node_modules::{draw_node_modules, package_name};
+pub use periods::{draw_periods, Period, Periods};
+pub use porcelain::{
+    write_porcelain, write_porcelain_budgets, write_porcelain_changed, write_porcelain_directory,
+    write_porcelain_header, write_porcelain_mismatches, write_porcelain_new_extensions, write_porcelain_summary,
+};
+pub use quota::draw_quotas;
+pub use security::draw_security;
+pub use split::write_split_output;
+pub use symlinks::draw_broken_symlinks;
+pub use top::draw_top_directories;
+pub use trash::draw_trash;
+pub use treemap::{draw_treemap, TreemapBy};
+pub use variants::draw_extension_variants;
+pub use warnings::{draw_warnings, write_porcelain_warnings, Warning, Warnings};
+pub use workspaces::{draw_workspaces, Workspaces};
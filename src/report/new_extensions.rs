@@ -0,0 +1,124 @@
+use crate::file::{max_count_chars, max_extension_chars, print_item, print_title, Directory, DrawOptions, Extension};
+use anyhow::Result;
+use clap::ArgEnum;
+use std::collections::{BTreeMap, HashSet};
+use std::path::PathBuf;
+
+/// What to do about extensions that were not found by the baseline scan.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ArgEnum)]
+pub enum NewExtensions {
+    /// Report them and count them as warnings.
+    Warn,
+
+    /// Report them and fail.
+    Fail,
+}
+
+/// An extension absent from the baseline, with the directories directly holding files of it.
+pub struct NewExtension<'a> {
+    pub extension: Extension,
+    directories: Vec<&'a Directory>,
+}
+
+/// Extensions found under the directory but in none of the directories recorded in the baseline,
+/// in the order of the extensions of the directory.
+pub fn new_extensions<'a>(
+    directory: &'a Directory,
+    baseline: &BTreeMap<PathBuf, Vec<Extension>>,
+) -> Vec<NewExtension<'a>> {
+    let known: HashSet<Option<&str>> = baseline
+        .values()
+        .flatten()
+        .map(|extension| extension.name.as_deref())
+        .collect();
+    let mut new: Vec<NewExtension> = directory
+        .extension_totals()
+        .into_iter()
+        .filter(|extension| !known.contains(&extension.name.as_deref()))
+        .map(|extension| NewExtension {
+            extension,
+            directories: Vec::new(),
+        })
+        .collect();
+    directory.walk(&mut |d| {
+        for new in new.iter_mut() {
+            if d.extensions().iter().any(|e| e.name == new.extension.name) {
+                new.directories.push(d);
+            }
+        }
+    });
+    new
+}
+
+/// Print every extension absent from the baseline with its count and size, and the directories
+/// holding files of it.
+pub fn draw_new_extensions(directory: &Directory, new: &[NewExtension], options: &DrawOptions) -> Result<()> {
+    print_title(options.locale.text("new extensions since baseline"))?;
+    if new.is_empty() {
+        print_item(options.locale.text("none found"), true, 1, &[], options)?;
+        return Ok(());
+    }
+
+    let extensions: Vec<Extension> = new.iter().map(|n| n.extension.clone()).collect();
+    let max_extension_chars = max_extension_chars(&extensions, options);
+    let max_count_chars = max_count_chars(&extensions);
+    let mut skipped = Vec::new();
+    for (idx, new) in new.iter().enumerate() {
+        let last = idx + 1 == extensions.len();
+        print_item(
+            &new.extension
+                .to_string_formatted(max_extension_chars, max_count_chars, options),
+            last,
+            1,
+            &skipped,
+            options,
+        )?;
+        if last {
+            skipped.push(1);
+        }
+        for (idx, d) in new.directories.iter().enumerate() {
+            print_item(
+                &d.display_name(directory.path(), options.paths.qualified())?,
+                idx + 1 == new.directories.len(),
+                2,
+                &skipped,
+                options,
+            )?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::file::ScanOptions;
+
+    #[test]
+    fn test_new_extensions() {
+        let root = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests");
+        let options = ScanOptions {
+            max_depth: 1,
+            ..Default::default()
+        };
+        let directory = Directory::new(root.clone(), 0, &options).expect("could not create directory");
+        let baseline = BTreeMap::from([(
+            root.join("dirB"),
+            vec![Extension {
+                name: Some("foo".to_string()),
+                count: 1,
+                ..Default::default()
+            }],
+        )]);
+
+        let mut names: Vec<_> = new_extensions(&directory, &baseline)
+            .iter()
+            .map(|new| (new.extension.name.clone(), new.directories.len()))
+            .collect();
+        names.sort();
+        assert_eq!(
+            names,
+            vec![(None, 1), (Some("bar".to_string()), 1), (Some("baz".to_string()), 1)]
+        );
+    }
+}
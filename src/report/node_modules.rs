@@ -0,0 +1,153 @@
+use crate::file::{
+    max_count_chars, max_extension_chars, merge_extension, print_item, print_title, size_human_readable, Directory,
+    DrawOptions, Extension,
+};
+use anyhow::Result;
+use std::cmp::Reverse;
+use std::collections::BTreeMap;
+use std::path::Path;
+
+const NODE_MODULES: &str = "node_modules";
+
+/// Name of the npm package installed in a directory, if it is one: a directory directly in
+/// `node_modules`, or in a scope such as `node_modules/@types`, in which case the name includes it.
+pub fn package_name(dir: &Path) -> Option<String> {
+    let name = dir.file_name()?.to_str()?;
+    let parent = dir.parent()?;
+    let parent_name = parent.file_name()?.to_str()?;
+    if parent_name == NODE_MODULES && !name.starts_with('@') {
+        return Some(name.to_string());
+    }
+    let grandparent = parent.parent()?.file_name()?;
+    (parent_name.starts_with('@') && grandparent == NODE_MODULES).then(|| format!("{}/{}", parent_name, name))
+}
+
+/// A package with the number of copies installed across the tree, and their extensions together.
+struct Package {
+    copies: usize,
+    extensions: Vec<Extension>,
+}
+
+impl Package {
+    fn size_bytes(&self) -> u64 {
+        self.extensions.iter().map(|e| e.total_size_bytes).sum()
+    }
+}
+
+/// Every package installed in a `node_modules` directory of the tree, by name.
+fn packages(directory: &Directory) -> BTreeMap<String, Package> {
+    let mut packages: BTreeMap<String, Package> = BTreeMap::new();
+    directory.walk(&mut |d| {
+        if let Some(name) = package_name(d.path()) {
+            let package = packages.entry(name).or_insert(Package {
+                copies: 0,
+                extensions: Vec::new(),
+            });
+            package.copies += 1;
+            for extension in d.extension_totals() {
+                merge_extension(&mut package.extensions, extension);
+            }
+        }
+    });
+    packages
+}
+
+/// Print the packages installed in `node_modules` directories, heaviest first, with the extensions
+/// of the files they ship. Copies of a package installed in several places are counted together.
+pub fn draw_node_modules(directory: &Directory, options: &DrawOptions) -> Result<()> {
+    print_title(options.locale.text("node_modules packages"))?;
+    let mut packages: Vec<_> = packages(directory).into_iter().collect();
+    if packages.is_empty() {
+        print_item(options.locale.text("none found"), true, 1, &[], options)?;
+        return Ok(());
+    }
+    packages.sort_by_key(|(_, package)| Reverse(package.size_bytes()));
+
+    let count = packages.len();
+    let mut skipped = Vec::new();
+    for (idx, (name, package)) in packages.iter_mut().enumerate() {
+        let last = idx + 1 == count;
+        let copies = if package.copies > 1 {
+            format!(" ({} {})", package.copies, options.locale.text("copies"))
+        } else {
+            String::new()
+        };
+        print_item(
+            &format!(
+                "{}{} ── {}",
+                name,
+                copies,
+                size_human_readable(package.size_bytes(), 2, options.locale).trim_end()
+            ),
+            last,
+            1,
+            &skipped,
+            options,
+        )?;
+        if last {
+            skipped.push(1);
+        }
+
+        package.extensions.sort_unstable_by_key(|e| Reverse(e.total_size_bytes));
+        let max_extension_chars = max_extension_chars(&package.extensions, options);
+        let max_count_chars = max_count_chars(&package.extensions);
+        for (idx, extension) in package.extensions.iter().enumerate() {
+            print_item(
+                &extension.to_string_formatted(max_extension_chars, max_count_chars, options),
+                idx + 1 == package.extensions.len(),
+                2,
+                &skipped,
+                options,
+            )?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::file::ScanOptions;
+    use crate::fs::MemoryFileSystem;
+    use std::path::PathBuf;
+
+    #[test]
+    fn test_package_name() {
+        let name = |path: &str| package_name(Path::new(path));
+        assert_eq!(name("/app/node_modules/react").as_deref(), Some("react"));
+        assert_eq!(name("/app/node_modules/@types/node").as_deref(), Some("@types/node"));
+        assert_eq!(name("/app/node_modules/@types"), None);
+        assert_eq!(name("/app/node_modules/react/lib"), None);
+        assert_eq!(name("/app/src"), None);
+    }
+
+    #[test]
+    fn test_packages() {
+        let mut file_system = MemoryFileSystem::default();
+        file_system
+            .add_file("/app/index.js", 1)
+            .add_file("/app/node_modules/react/index.js", 10)
+            .add_file("/app/node_modules/react/cjs/react.js", 20)
+            .add_file("/app/node_modules/@types/node/index.d.ts", 5)
+            .add_file("/app/node_modules/other/node_modules/react/index.js", 3)
+            .add_file("/app/admin/node_modules/react/index.js", 7);
+        let options = ScanOptions {
+            max_depth: 8,
+            node_modules: true,
+            file_system: Box::new(file_system),
+            ..Default::default()
+        };
+        let directory = Directory::new(PathBuf::from("/app"), 0, &options).expect("could not create directory");
+
+        let packages = packages(&directory);
+        assert_eq!(
+            packages.keys().map(String::as_str).collect::<Vec<_>>(),
+            vec!["@types/node", "other", "react"]
+        );
+        assert_eq!(packages["react"].copies, 2);
+        assert_eq!(packages["react"].size_bytes(), 37);
+        // Packages are counted as a whole, along with the dependencies installed inside them.
+        assert_eq!(packages["other"].size_bytes(), 3);
+        directory.walk(&mut |d| assert!(!d.path().ends_with("react/cjs")));
+    }
+}
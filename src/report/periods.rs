@@ -0,0 +1,196 @@
+use crate::file::{print_item, print_title, size_human_readable, DrawOptions};
+use crate::group::year_month;
+use crate::width;
+use anyhow::Result;
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::time::SystemTime;
+
+/// Length of the periods files are bucketed into by modification time.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Period {
+    Month,
+    Year,
+}
+
+/// Year, and month unless bucketing by year, of a modification time. `None` for files without one.
+type Key = Option<(i64, u32)>;
+
+/// Number and size of files.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+struct Totals {
+    count: usize,
+    size_bytes: u64,
+}
+
+impl Totals {
+    fn add(&mut self, size_bytes: u64) {
+        self.count += 1;
+        self.size_bytes += size_bytes;
+    }
+}
+
+/// Files of the whole scan bucketed by the period they were last modified in, each broken down by
+/// extension.
+#[derive(Debug)]
+pub struct Periods {
+    period: Period,
+    by_extension: bool,
+    buckets: RefCell<BTreeMap<Key, BTreeMap<Option<String>, Totals>>>,
+}
+
+impl Periods {
+    pub fn new(period: Period, by_extension: bool) -> Self {
+        Self {
+            period,
+            by_extension,
+            buckets: RefCell::default(),
+        }
+    }
+
+    /// Count a file with its modification time, size and the extension it is counted under.
+    pub fn add(&self, modified: Option<SystemTime>, size_bytes: u64, extension: Option<&str>) {
+        let key = modified.map(|time| {
+            let (year, month) = year_month(time);
+            match self.period {
+                Period::Month => (year, month),
+                Period::Year => (year, 0),
+            }
+        });
+        self.buckets
+            .borrow_mut()
+            .entry(key)
+            .or_default()
+            .entry(extension.map(str::to_string))
+            .or_default()
+            .add(size_bytes);
+    }
+
+    fn label(&self, key: Key, options: &DrawOptions) -> String {
+        match (key, self.period) {
+            (Some((year, month)), Period::Month) => format!("{}-{:02}", year, month),
+            (Some((year, _)), Period::Year) => year.to_string(),
+            (None, _) => options.extensionless_label.clone(),
+        }
+    }
+}
+
+/// Print the number and size of the files last modified in each period, oldest first, followed by
+/// files without a modification time. When asked for, every period is broken down by extension,
+/// largest first.
+pub fn draw_periods(periods: &Periods, options: &DrawOptions) -> Result<()> {
+    let buckets = periods.buckets.borrow();
+    let header = match periods.period {
+        Period::Month => "files by month",
+        Period::Year => "files by year",
+    };
+    print_title(options.locale.text(header))?;
+    if buckets.is_empty() {
+        print_item(options.locale.text("none found"), true, 1, &[], options)?;
+        return Ok(());
+    }
+
+    // Files without a modification time come last.
+    let mut buckets: Vec<_> = buckets.iter().collect();
+    buckets.sort_by_key(|(key, _)| key.is_none());
+    let totals: Vec<(String, Totals)> = buckets
+        .iter()
+        .map(|(key, extensions)| {
+            let totals = extensions.values().fold(Totals::default(), |sum, totals| Totals {
+                count: sum.count + totals.count,
+                size_bytes: sum.size_bytes + totals.size_bytes,
+            });
+            (periods.label(**key, options), totals)
+        })
+        .collect();
+    let max_label_chars = totals.iter().map(|(label, _)| width::width(label)).max().unwrap_or(0);
+    let max_count_chars = totals.iter().map(|(_, t)| t.count.to_string().len()).max().unwrap_or(0);
+
+    let mut skipped = Vec::new();
+    for (idx, ((label, total), (_, extensions))) in totals.iter().zip(&buckets).enumerate() {
+        let last = idx + 1 == totals.len();
+        print_item(
+            &format!(
+                "{} ── {:>max_count_chars$} ── {:>10}",
+                width::pad(label, max_label_chars),
+                total.count,
+                size_human_readable(total.size_bytes, 2, options.locale),
+            ),
+            last,
+            1,
+            &skipped,
+            options,
+        )?;
+        if periods.by_extension {
+            if last {
+                skipped.push(1);
+            }
+            draw_extensions(extensions, &skipped, options)?;
+        }
+    }
+    Ok(())
+}
+
+/// Print the number and size of the files of each extension in a period, largest first.
+fn draw_extensions(
+    extensions: &BTreeMap<Option<String>, Totals>,
+    skipped: &[usize],
+    options: &DrawOptions,
+) -> Result<()> {
+    let mut extensions: Vec<_> = extensions.iter().collect();
+    extensions.sort_by(|a, b| b.1.size_bytes.cmp(&a.1.size_bytes).then_with(|| a.0.cmp(b.0)));
+    let label = |extension: &Option<String>| extension.clone().unwrap_or_else(|| options.extensionless_label.clone());
+    let max_label_chars = extensions
+        .iter()
+        .map(|(e, _)| width::width(&label(e)))
+        .max()
+        .unwrap_or(0);
+    let max_count_chars = extensions
+        .iter()
+        .map(|(_, t)| t.count.to_string().len())
+        .max()
+        .unwrap_or(0);
+    for (idx, (extension, totals)) in extensions.iter().enumerate() {
+        print_item(
+            &format!(
+                "{} ── {:>max_count_chars$} ── {:>10}",
+                width::pad(&label(extension), max_label_chars),
+                totals.count,
+                size_human_readable(totals.size_bytes, 2, options.locale),
+            ),
+            idx + 1 == extensions.len(),
+            2,
+            skipped,
+            options,
+        )?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::filter::parse_date;
+
+    #[test]
+    fn test_add() {
+        let periods = Periods::new(Period::Year, true);
+        periods.add(Some(parse_date("2021-03-04").unwrap()), 10, Some("jpg"));
+        periods.add(Some(parse_date("2021-11-30").unwrap()), 5, Some("jpg"));
+        periods.add(Some(parse_date("2019-01-01").unwrap()), 7, None);
+        periods.add(None, 1, Some("jpg"));
+
+        let buckets = periods.buckets.borrow();
+        assert_eq!(
+            buckets.keys().copied().collect::<Vec<_>>(),
+            vec![None, Some((2019, 0)), Some((2021, 0))]
+        );
+        assert_eq!(
+            buckets[&Some((2021, 0))][&Some("jpg".to_string())],
+            Totals {
+                count: 2,
+                size_bytes: 15
+            }
+        );
+    }
+}
@@ -0,0 +1,264 @@
+//! Stable, line-oriented output for shell scripts. Every line is a record of tab-separated fields,
+//! the first of which names the record type:
+//!
+//! ```text
+//! version      <format version>
+//! total_files  <count>
+//! total_bytes  <bytes>
+//! extension    <name>  <count>  <bytes>
+//! ```
+//!
+//! When streaming, every directory is additionally reported as soon as its subtree has been
+//! scanned, children before parents, with the totals of all files below it:
+//!
+//! ```text
+//! directory    <path>  <count>  <bytes>
+//! ```
+//!
+//! The index written with `--split-output` additionally names the report of every directory directly
+//! below the scan root, with the totals of all files below it:
+//!
+//! ```text
+//! report       <path>  <count>  <bytes>  <report file>
+//! ```
+//!
+//! At the end, when the tree changed during the scan, a record gives the number of entries that
+//! disappeared between being listed and being read:
+//!
+//! ```text
+//! changed      <count>
+//! ```
+//!
+//! With `--new-extensions`, every extension absent from the baseline gets a record:
+//!
+//! ```text
+//! new_extension  <name>  <count>  <bytes>
+//! ```
+//!
+//! With `--budgets`, every budget gets a record with the bytes counted against it, over the whole
+//! scan (`repo`) or in the directory using most of it (`directory`), and whether it was kept to:
+//!
+//! ```text
+//! budget       <scope>  <key>  <bytes>  <limit>  <pass|fail>
+//! ```
+//!
+//! With `--check`, the porcelain output is instead made of a record for every file differing from
+//! the manifest, where the mismatch is one of `missing`, `unexpected`, `size` and `hash`:
+//!
+//! ```text
+//! mismatch     <mismatch>  <path>
+//! ```
+//!
+//! Last come the number of warnings of each kind met, if any, where the kind is one of
+//! `unreadable`, `unreadable_directory`, `vanished`, `broken_symlink`, `finding`,
+//! `new_extension` and `memory_limit`:
+//!
+//! ```text
+//! warning      <kind>  <count>
+//! ```
+//!
+//! Files without an extension have an empty name. Byte fields are `-` when sizes were not
+//! gathered. Tabs, newlines and backslashes in names are escaped as `\t`, `\n` and `\\`. New record
+//! types may be added in later versions, but existing ones never change.
+
+use super::budgets::BudgetCheck;
+use super::new_extensions::NewExtension;
+use crate::budget::Scope;
+use crate::file::{Directory, DrawOptions};
+use crate::manifest::Discrepancy;
+use anyhow::Result;
+use std::io::{self, Write};
+use std::path::Path;
+
+const VERSION: u32 = 1;
+
+fn escape(name: &str) -> String {
+    name.replace('\\', "\\\\").replace('\t', "\\t").replace('\n', "\\n")
+}
+
+fn bytes(b: u64, options: &DrawOptions) -> String {
+    if options.size {
+        b.to_string()
+    } else {
+        "-".to_string()
+    }
+}
+
+fn summary(directory: &Directory, options: &DrawOptions) -> String {
+    let mut extensions = directory.extension_totals();
+    extensions.sort_unstable_by(|e1, e2| e1.name.cmp(&e2.name));
+
+    let mut lines = vec![
+        format!("total_files\t{}", extensions.iter().map(|e| e.count).sum::<usize>()),
+        format!(
+            "total_bytes\t{}",
+            bytes(extensions.iter().map(|e| e.total_size_bytes).sum(), options)
+        ),
+    ];
+    for extension in extensions.iter() {
+        lines.push(format!(
+            "extension\t{}\t{}\t{}",
+            escape(extension.name.as_deref().unwrap_or_default()),
+            extension.count,
+            bytes(extension.total_size_bytes, options)
+        ));
+    }
+
+    lines.join("\n")
+}
+
+fn totals(directory: &Directory, options: &DrawOptions) -> String {
+    let extensions = directory.extension_totals();
+    format!(
+        "{}\t{}",
+        extensions.iter().map(|e| e.count).sum::<usize>(),
+        bytes(extensions.iter().map(|e| e.total_size_bytes).sum(), options)
+    )
+}
+
+/// Write the porcelain record of a directory whose subtree has been scanned.
+pub fn write_porcelain_directory(
+    out: &mut impl Write,
+    directory: &Directory,
+    scan_root: &Path,
+    options: &DrawOptions,
+) -> Result<()> {
+    writeln!(
+        out,
+        "directory\t{}\t{}",
+        escape(&directory.display_name(scan_root, options.paths.qualified())?),
+        totals(directory, options)
+    )?;
+    Ok(())
+}
+
+/// Write the format version, which comes first in the porcelain output.
+pub fn write_porcelain_header(out: &mut impl Write) -> io::Result<()> {
+    writeln!(out, "version\t{}", VERSION)
+}
+
+/// Write the porcelain totals and extensions of the directory.
+pub fn write_porcelain_summary(out: &mut impl Write, directory: &Directory, options: &DrawOptions) -> io::Result<()> {
+    writeln!(out, "{}", summary(directory, options))
+}
+
+/// Write the number of entries that vanished during the scan.
+pub fn write_porcelain_changed(out: &mut impl Write, vanished: usize) -> io::Result<()> {
+    writeln!(out, "changed\t{}", vanished)
+}
+
+/// Write a record for every extension absent from the baseline.
+pub fn write_porcelain_new_extensions(
+    out: &mut impl Write,
+    new: &[NewExtension],
+    options: &DrawOptions,
+) -> io::Result<()> {
+    for new in new {
+        writeln!(
+            out,
+            "new_extension\t{}\t{}\t{}",
+            escape(new.extension.name.as_deref().unwrap_or_default()),
+            new.extension.count,
+            bytes(new.extension.total_size_bytes, options)
+        )?;
+    }
+    Ok(())
+}
+
+/// Write a record for every budget.
+pub fn write_porcelain_budgets(out: &mut impl Write, checks: &[BudgetCheck]) -> io::Result<()> {
+    for check in checks {
+        let scope = match check.budget.scope {
+            Scope::Repo => "repo",
+            Scope::Directory => "directory",
+        };
+        writeln!(
+            out,
+            "budget\t{}\t{}\t{}\t{}\t{}",
+            scope,
+            escape(&check.budget.key),
+            check.used(),
+            check.budget.limit,
+            if check.passed() { "pass" } else { "fail" }
+        )?;
+    }
+    Ok(())
+}
+
+/// Write a record for every file differing from the manifest.
+pub fn write_porcelain_mismatches(out: &mut impl Write, discrepancies: &[Discrepancy]) -> io::Result<()> {
+    for discrepancy in discrepancies {
+        writeln!(
+            out,
+            "mismatch\t{}\t{}",
+            discrepancy.mismatch.key(),
+            escape(&discrepancy.path.to_string_lossy())
+        )?;
+    }
+    Ok(())
+}
+
+/// Write the complete porcelain output for the directory.
+pub fn write_porcelain(out: &mut impl Write, directory: &Directory, options: &DrawOptions) -> io::Result<()> {
+    write_porcelain_header(out)?;
+    write_porcelain_summary(out, directory, options)
+}
+
+/// Write the porcelain record naming the report file of a directory.
+pub fn write_porcelain_report(
+    out: &mut impl Write,
+    directory: &Directory,
+    scan_root: &Path,
+    file_name: &str,
+    options: &DrawOptions,
+) -> Result<()> {
+    writeln!(
+        out,
+        "report\t{}\t{}\t{}",
+        escape(&directory.display_name(scan_root, options.paths.qualified())?),
+        totals(directory, options),
+        escape(file_name)
+    )?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::file::{PathDisplay, ScanOptions};
+    use crate::locale::Locale;
+    use std::path::PathBuf;
+
+    #[test]
+    fn test_porcelain() {
+        let root = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests");
+        let directory = Directory::new(root, 0, &ScanOptions::default()).expect("could not create directory");
+        let options = DrawOptions {
+            empty: false,
+            paths: PathDisplay::Name,
+            size: true,
+            mounts: false,
+            dominant: false,
+            hardlinks: false,
+            cloud: false,
+            text_binary: false,
+            baseline: None,
+            color: None,
+            extensionless_label: "N/A".to_string(),
+            locale: Locale::En,
+            plain: false,
+            media_duration: false,
+        };
+
+        assert_eq!(
+            summary(&directory, &options),
+            "total_files\t5\ntotal_bytes\t55\nextension\t\t1\t20\nextension\tbar\t1\t5\n\
+             extension\tbaz\t1\t10\nextension\tfoo\t2\t20"
+        );
+    }
+
+    #[test]
+    fn test_escape() {
+        assert_eq!(escape("a\tb\nc\\d"), "a\\tb\\nc\\\\d");
+    }
+}
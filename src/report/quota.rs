@@ -0,0 +1,123 @@
+use crate::file::{
+    max_count_chars, max_extension_chars, merge_extension, print_item, print_title, size_human_readable, Directory,
+    DrawOptions, Extension,
+};
+use crate::quota::Quota;
+use anyhow::Result;
+use std::cmp::Reverse;
+use std::collections::BTreeMap;
+
+/// Number of extensions listed under each user, largest first.
+const TOP_EXTENSIONS: usize = 3;
+
+/// Merge the extensions owned by each user over the whole tree.
+fn usage_by_owner(directory: &Directory) -> BTreeMap<u32, Vec<Extension>> {
+    let mut usage: BTreeMap<u32, Vec<Extension>> = BTreeMap::new();
+    directory.walk(&mut |d| {
+        for (uid, extensions) in d.owners.iter() {
+            let owned = usage.entry(*uid).or_default();
+            for extension in extensions {
+                merge_extension(owned, extension.clone());
+            }
+        }
+    });
+    usage
+}
+
+/// Print, for every user with a quota, how much of it the tree uses, how much is left, and which
+/// extensions take up most of it.
+pub fn draw_quotas(directory: &Directory, quotas: &[Quota], options: &DrawOptions) -> Result<()> {
+    let usage = usage_by_owner(directory);
+
+    print_title(options.locale.text("quotas"))?;
+    if quotas.is_empty() {
+        print_item(options.locale.text("no quotas defined"), true, 1, &[], options)?;
+        return Ok(());
+    }
+
+    let mut skipped = Vec::new();
+    for (idx, quota) in quotas.iter().enumerate() {
+        let last = idx + 1 == quotas.len();
+        let mut extensions = usage.get(&quota.uid).cloned().unwrap_or_default();
+        let used: u64 = extensions.iter().map(|e| e.total_size_bytes).sum();
+        let headroom = if used <= quota.limit {
+            format!(
+                "{} {}",
+                size_human_readable(quota.limit - used, 2, options.locale).trim_end(),
+                options.locale.text("left")
+            )
+        } else {
+            format!(
+                "{} {}",
+                size_human_readable(used - quota.limit, 2, options.locale).trim_end(),
+                options.locale.text("over")
+            )
+        };
+        print_item(
+            &format!(
+                "{} ── {} {} {} ── {}",
+                quota.user,
+                size_human_readable(used, 2, options.locale).trim_end(),
+                options.locale.text("of"),
+                size_human_readable(quota.limit, 2, options.locale).trim_end(),
+                headroom
+            ),
+            last,
+            1,
+            &skipped,
+            options,
+        )?;
+        if last {
+            skipped.push(1);
+        }
+
+        extensions.sort_unstable_by_key(|e| Reverse(e.total_size_bytes));
+        extensions.truncate(TOP_EXTENSIONS);
+        let max_extension_chars = max_extension_chars(&extensions, options);
+        let max_count_chars = max_count_chars(&extensions);
+        for (idx, extension) in extensions.iter().enumerate() {
+            print_item(
+                &extension.to_string_formatted(max_extension_chars, max_count_chars, options),
+                idx + 1 == extensions.len(),
+                2,
+                &skipped,
+                options,
+            )?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::file::ScanOptions;
+    use crate::fs::MemoryFileSystem;
+    use std::path::PathBuf;
+
+    #[test]
+    fn test_usage_by_owner() {
+        let mut file_system = MemoryFileSystem::default();
+        file_system
+            .add_file("/home/a.mp4", 100)
+            .add_file("/home/alice/b.mp4", 200)
+            .add_file("/home/alice/c.txt", 3)
+            .add_file("/home/bob/d.txt", 4)
+            .chown("/home/alice/b.mp4", 1000)
+            .chown("/home/alice/c.txt", 1000)
+            .chown("/home/bob/d.txt", 1001);
+        let options = ScanOptions {
+            max_depth: 1,
+            owners: true,
+            file_system: Box::new(file_system),
+            ..Default::default()
+        };
+        let directory = Directory::new(PathBuf::from("/home"), 0, &options).expect("could not create directory");
+
+        let usage = usage_by_owner(&directory);
+        assert_eq!(usage.keys().copied().collect::<Vec<_>>(), vec![0, 1000, 1001]);
+        let alice: u64 = usage[&1000].iter().map(|e| e.total_size_bytes).sum();
+        assert_eq!(alice, 203);
+        assert_eq!(usage[&1001][0].name.as_deref(), Some("txt"));
+    }
+}
@@ -0,0 +1,109 @@
+use crate::file::{max_count_chars, max_extension_chars, print_item, print_title, Directory, DrawOptions};
+use anyhow::Result;
+use std::path::PathBuf;
+
+/// Which of the setuid and setgid bits are set in a file mode.
+fn set_id_label(mode: u32) -> &'static str {
+    match (mode & 0o4000 != 0, mode & 0o2000 != 0) {
+        (true, true) => "setuid, setgid",
+        (true, false) => "setuid",
+        _ => "setgid",
+    }
+}
+
+/// Print every directory that is world-writable or contains world-writable files, along with the
+/// extensions, counts and sizes of those files.
+fn draw_world_writable(directory: &Directory, options: &DrawOptions, skipped: &mut Vec<usize>) -> Result<()> {
+    let mut affected = Vec::new();
+    directory.walk(&mut |d| {
+        if d.world_writable || !d.world_writable_files.is_empty() {
+            affected.push(d);
+        }
+    });
+
+    print_item(options.locale.text("world-writable"), false, 1, skipped, options)?;
+    if affected.is_empty() {
+        print_item(options.locale.text("none found"), true, 2, skipped, options)?;
+        return Ok(());
+    }
+
+    for (idx, d) in affected.iter().enumerate() {
+        let last = idx + 1 == affected.len();
+        let mut name = d.display_name(directory.path(), options.paths.qualified())?;
+        if d.world_writable {
+            name.push_str(" (world-writable)");
+        }
+        print_item(&name, last, 2, skipped, options)?;
+        if last {
+            skipped.push(2);
+        }
+
+        let files = &d.world_writable_files;
+        let max_extension_chars = max_extension_chars(files, options);
+        let max_count_chars = max_count_chars(files);
+        for (idx, extension) in files.iter().enumerate() {
+            print_item(
+                &extension.to_string_formatted(max_extension_chars, max_count_chars, options),
+                idx + 1 == files.len(),
+                3,
+                skipped,
+                options,
+            )?;
+        }
+    }
+    skipped.pop();
+
+    Ok(())
+}
+
+/// Print every setuid or setgid file, with the bits it has set.
+fn draw_set_id(directory: &Directory, options: &DrawOptions, skipped: &mut Vec<usize>) -> Result<()> {
+    let mut files: Vec<&(PathBuf, u32)> = Vec::new();
+    directory.walk(&mut |d| files.extend(d.set_id_files.iter()));
+
+    print_item(
+        &format!("{} ── {}", options.locale.text("setuid or setgid"), files.len()),
+        true,
+        1,
+        skipped,
+        options,
+    )?;
+    skipped.push(1);
+    for (idx, (file, mode)) in files.iter().enumerate() {
+        print_item(
+            &format!(
+                "{} ({})",
+                options.paths.qualified().format(file, directory.path()),
+                set_id_label(*mode)
+            ),
+            idx + 1 == files.len(),
+            2,
+            skipped,
+            options,
+        )?;
+    }
+    skipped.pop();
+    Ok(())
+}
+
+/// Print the findings of the security scan: world-writable directories and files, and setuid or
+/// setgid files.
+pub fn draw_security(directory: &Directory, options: &DrawOptions) -> Result<()> {
+    print_title(options.locale.text("security"))?;
+    let mut skipped = Vec::new();
+    draw_world_writable(directory, options, &mut skipped)?;
+    draw_set_id(directory, options, &mut skipped)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_set_id_label() {
+        assert_eq!(set_id_label(0o4755), "setuid");
+        assert_eq!(set_id_label(0o2755), "setgid");
+        assert_eq!(set_id_label(0o6755), "setuid, setgid");
+    }
+}
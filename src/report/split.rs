@@ -0,0 +1,175 @@
+use super::porcelain::{write_porcelain, write_porcelain_report};
+use crate::file::{size_human_readable, write_item, Directory, DrawOptions};
+use crate::width;
+use anyhow::{Context, Result};
+use std::collections::HashSet;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+/// Name of the index file, without its extension.
+const INDEX: &str = "index";
+
+/// Write the tree of every directory directly below the scan root to a file of its own in
+/// `output`, named after the directory, along with an index listing each of them with the number
+/// and size of the files below it. Reports are porcelain records when asked for, trees otherwise.
+pub fn write_split_output(directory: &Directory, output: &Path, porcelain: bool, options: &DrawOptions) -> Result<()> {
+    std::fs::create_dir_all(output).with_context(|| format!("could not create {}", output.display()))?;
+    let suffix = if porcelain { "tsv" } else { "txt" };
+    let create = |file_name: &str| -> Result<BufWriter<File>> {
+        let path = output.join(file_name);
+        Ok(BufWriter::new(
+            File::create(&path).with_context(|| format!("could not create {}", path.display()))?,
+        ))
+    };
+
+    let mut used = HashSet::from([INDEX.to_string()]);
+    let mut reports = Vec::new();
+    for subdirectory in directory.subdirectories() {
+        if !options.empty && subdirectory.is_empty() {
+            continue;
+        }
+        let name = subdirectory
+            .path()
+            .file_name()
+            .map_or_else(|| INDEX.into(), |name| name.to_string_lossy());
+        let file_name = format!("{}.{}", unique_name(&name, &mut used), suffix);
+        let mut out = create(&file_name)?;
+        if porcelain {
+            write_porcelain(&mut out, subdirectory, options)?;
+        } else {
+            subdirectory.draw_to(&mut out, options)?;
+        }
+        out.flush()?;
+        reports.push((subdirectory, file_name));
+    }
+
+    let mut out = create(&format!("{}.{}", INDEX, suffix))?;
+    if porcelain {
+        write_porcelain(&mut out, directory, options)?;
+        for (subdirectory, file_name) in &reports {
+            write_porcelain_report(&mut out, subdirectory, directory.path(), file_name, options)?;
+        }
+    } else {
+        write_index(&mut out, directory, &reports, options)?;
+    }
+    out.flush()?;
+    Ok(())
+}
+
+/// Write the number and size of the files below each directory with a report, and the name of
+/// its report file, under the name of the scan root.
+fn write_index(
+    out: &mut impl Write,
+    directory: &Directory,
+    reports: &[(&Directory, String)],
+    options: &DrawOptions,
+) -> Result<()> {
+    writeln!(out, "{}", directory.display_name(directory.path(), options.paths)?)?;
+    let rows = reports
+        .iter()
+        .map(|(subdirectory, file_name)| {
+            let extensions = subdirectory.extension_totals();
+            Ok((
+                subdirectory.display_name(directory.path(), options.paths.qualified())?,
+                extensions.iter().map(|e| e.count).sum::<usize>(),
+                extensions.iter().map(|e| e.total_size_bytes).sum::<u64>(),
+                file_name,
+            ))
+        })
+        .collect::<Result<Vec<_>>>()?;
+    let max_name_chars = rows.iter().map(|(name, ..)| width::width(name)).max().unwrap_or(0);
+    let max_count_chars = rows
+        .iter()
+        .map(|(_, count, ..)| count.to_string().len())
+        .max()
+        .unwrap_or(0);
+    for (idx, (name, count, size_bytes, file_name)) in rows.iter().enumerate() {
+        let size = if options.size {
+            format!(
+                " ── {:>10}",
+                size_human_readable(*size_bytes, 2, options.locale).trim_end()
+            )
+        } else {
+            String::new()
+        };
+        write_item(
+            out,
+            &format!(
+                "{} ── {:>max_count_chars$}{} ── {}",
+                width::pad(name, max_name_chars),
+                count,
+                size,
+                file_name
+            ),
+            idx + 1 == rows.len(),
+            1,
+            &[],
+            options,
+        )?;
+    }
+    Ok(())
+}
+
+/// The name itself, or with a number appended if it is already in use, e.g. by the index.
+fn unique_name(name: &str, used: &mut HashSet<String>) -> String {
+    let mut unique = name.to_string();
+    let mut number = 2;
+    while !used.insert(unique.clone()) {
+        unique = format!("{}-{}", name, number);
+        number += 1;
+    }
+    unique
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::file::{PathDisplay, ScanOptions};
+    use crate::locale::Locale;
+    use std::path::PathBuf;
+
+    #[test]
+    fn test_unique_name() {
+        let mut used = HashSet::from([INDEX.to_string()]);
+        assert_eq!(unique_name("docs", &mut used), "docs");
+        assert_eq!(unique_name("index", &mut used), "index-2");
+        assert_eq!(unique_name("index", &mut used), "index-3");
+    }
+
+    #[test]
+    fn test_write_split_output() {
+        let root = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests");
+        let options = ScanOptions {
+            max_depth: 1,
+            ..Default::default()
+        };
+        let directory = Directory::new(root, 0, &options).expect("could not create directory");
+        let draw_options = DrawOptions {
+            empty: false,
+            paths: PathDisplay::Name,
+            size: true,
+            mounts: false,
+            dominant: false,
+            hardlinks: false,
+            cloud: false,
+            text_binary: false,
+            baseline: None,
+            color: None,
+            extensionless_label: "N/A".to_string(),
+            locale: Locale::En,
+            plain: false,
+            media_duration: false,
+        };
+
+        let output = std::env::temp_dir().join(format!("rextc-split-{}", std::process::id()));
+        write_split_output(&directory, &output, false, &draw_options).expect("could not write reports");
+        let index = std::fs::read_to_string(output.join("index.txt")).expect("could not read index");
+        let report = std::fs::read_to_string(output.join("dirA.txt")).expect("could not read report");
+        std::fs::remove_dir_all(&output).expect("could not remove reports");
+
+        assert!(index.starts_with("tests\n"));
+        assert!(index.contains("dirA.txt"));
+        assert!(report.starts_with("dirA\n"));
+    }
+}
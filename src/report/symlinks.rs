@@ -0,0 +1,54 @@
+use crate::file::{print_item, print_title, Directory, DrawOptions};
+use anyhow::Result;
+
+/// Print, for every directory containing symlinks whose target does not exist, how many there are,
+/// optionally followed by each of them along with its target.
+pub fn draw_broken_symlinks(directory: &Directory, targets: bool, options: &DrawOptions) -> Result<()> {
+    let mut affected = Vec::new();
+    directory.walk(&mut |d| {
+        if !d.broken_symlinks.is_empty() {
+            affected.push(d);
+        }
+    });
+
+    print_title(options.locale.text("broken symlinks"))?;
+    if affected.is_empty() {
+        print_item(options.locale.text("none found"), true, 1, &[], options)?;
+        return Ok(());
+    }
+
+    let mut skipped = Vec::new();
+    for (idx, d) in affected.iter().enumerate() {
+        let last = idx + 1 == affected.len();
+        let name = d.display_name(directory.path(), options.paths.qualified())?;
+        print_item(
+            &format!("{} ── {}", name, d.broken_symlinks.len()),
+            last,
+            1,
+            &skipped,
+            options,
+        )?;
+        if last {
+            skipped.push(1);
+        }
+
+        if !targets {
+            continue;
+        }
+        for (idx, (symlink, target)) in d.broken_symlinks.iter().enumerate() {
+            print_item(
+                &format!(
+                    "{} → {}",
+                    options.paths.qualified().format(symlink, directory.path()),
+                    target.display()
+                ),
+                idx + 1 == d.broken_symlinks.len(),
+                2,
+                &skipped,
+                options,
+            )?;
+        }
+    }
+
+    Ok(())
+}
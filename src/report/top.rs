@@ -0,0 +1,121 @@
+use crate::file::{
+    max_count_chars, max_extension_chars, print_item, print_title, size_human_readable, Directory, DrawOptions,
+    Extension,
+};
+use anyhow::Result;
+use std::cmp::Reverse;
+
+/// Number of extensions listed under each directory, those taking up most of its size.
+const DOMINANT_EXTENSIONS: usize = 3;
+
+/// A directory below the scan root, with the extensions of every file below it, largest first.
+struct DirectoryTotals<'a> {
+    directory: &'a Directory,
+    size_bytes: u64,
+    extensions: Vec<Extension>,
+}
+
+/// The `count` directories below the scan root with the largest recursive size, largest first.
+fn largest_directories(directory: &Directory, count: usize) -> Vec<DirectoryTotals<'_>> {
+    let mut totals = Vec::new();
+    directory.walk(&mut |d| {
+        if d.path() == directory.path() {
+            return;
+        }
+        let mut extensions = d.extension_totals();
+        extensions.sort_unstable_by_key(|e| Reverse(e.total_size_bytes));
+        totals.push(DirectoryTotals {
+            directory: d,
+            size_bytes: extensions.iter().map(|e| e.total_size_bytes).sum(),
+            extensions,
+        });
+    });
+
+    totals.sort_by_key(|t| Reverse(t.size_bytes));
+    totals.truncate(count);
+    totals
+}
+
+/// Print the directories with the largest recursive size across the whole tree as a ranked list,
+/// each with the extensions taking up most of its size.
+pub fn draw_top_directories(directory: &Directory, count: usize, options: &DrawOptions) -> Result<()> {
+    let totals = largest_directories(directory, count);
+
+    print_title(options.locale.text("largest directories"))?;
+    if totals.is_empty() {
+        print_item(options.locale.text("none found"), true, 1, &[], options)?;
+        return Ok(());
+    }
+
+    let rank_chars = totals.len().to_string().len();
+    let mut skipped = Vec::new();
+    for (idx, total) in totals.iter().enumerate() {
+        let last = idx + 1 == totals.len();
+        print_item(
+            &format!(
+                "{:>rank_chars$}. {} ── {}",
+                idx + 1,
+                total
+                    .directory
+                    .display_name(directory.path(), options.paths.qualified())?,
+                size_human_readable(total.size_bytes, 2, options.locale).trim_end(),
+            ),
+            last,
+            1,
+            &skipped,
+            options,
+        )?;
+        if last {
+            skipped.push(1);
+        }
+
+        let dominant = &total.extensions[..total.extensions.len().min(DOMINANT_EXTENSIONS)];
+        let max_extension_chars = max_extension_chars(dominant, options);
+        let max_count_chars = max_count_chars(dominant);
+        for (idx, extension) in dominant.iter().enumerate() {
+            print_item(
+                &extension.to_string_formatted(max_extension_chars, max_count_chars, options),
+                idx + 1 == dominant.len(),
+                2,
+                &skipped,
+                options,
+            )?;
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::file::ScanOptions;
+    use crate::fs::MemoryFileSystem;
+    use std::path::{Path, PathBuf};
+
+    #[test]
+    fn test_largest_directories() {
+        let mut file_system = MemoryFileSystem::default();
+        file_system
+            .add_file("/data/a.txt", 100)
+            .add_file("/data/logs/b.log", 10)
+            .add_file("/data/media/c.mp4", 50)
+            .add_file("/data/media/clips/d.mp4", 40)
+            .add_file("/data/media/clips/e.txt", 1);
+        let options = ScanOptions {
+            max_depth: 3,
+            file_system: Box::new(file_system),
+            ..Default::default()
+        };
+        let directory = Directory::new(PathBuf::from("/data"), 0, &options).expect("could not create directory");
+
+        let totals = largest_directories(&directory, 2);
+        assert_eq!(totals.len(), 2);
+        assert_eq!(totals[0].directory.path(), Path::new("/data/media"));
+        assert_eq!(totals[0].size_bytes, 91);
+        assert_eq!(totals[0].extensions[0].name.as_deref(), Some("mp4"));
+        assert_eq!(totals[0].extensions[0].total_size_bytes, 90);
+        assert_eq!(totals[1].directory.path(), Path::new("/data/media/clips"));
+        assert_eq!(totals[1].size_bytes, 41);
+    }
+}
@@ -0,0 +1,103 @@
+use crate::file::{
+    max_count_chars, max_extension_chars, print_item, print_title, size_human_readable, Directory, DrawOptions,
+    Extension,
+};
+use anyhow::Result;
+use std::cmp::Reverse;
+
+/// Trash locations anywhere in the tree, including those inside other trash locations.
+fn trash_locations(directory: &Directory) -> Vec<&Directory> {
+    let mut locations = Vec::new();
+    let mut pending = Vec::new();
+    directory.walk(&mut |d| pending.extend(d.trash.iter()));
+    while let Some(trash) = pending.pop() {
+        pending.extend(trash.trash.iter());
+        locations.push(trash);
+    }
+    locations.sort_by_key(|trash| trash.path().to_path_buf());
+    locations
+}
+
+/// Print every trash location with the total number and size of the files in it, followed by its
+/// extensions, largest first.
+pub fn draw_trash(directory: &Directory, options: &DrawOptions) -> Result<()> {
+    let locations = trash_locations(directory);
+
+    print_title(options.locale.text("trash"))?;
+    if locations.is_empty() {
+        print_item(options.locale.text("none found"), true, 1, &[], options)?;
+        return Ok(());
+    }
+
+    let mut skipped = Vec::new();
+    for (idx, trash) in locations.iter().enumerate() {
+        let last = idx + 1 == locations.len();
+        let mut extensions: Vec<Extension> = trash.extensions().to_vec();
+        extensions.sort_unstable_by_key(|e| Reverse(e.total_size_bytes));
+        let count: usize = extensions.iter().map(|e| e.count).sum();
+        let size_bytes = extensions.iter().map(|e| e.total_size_bytes).sum();
+        print_item(
+            &format!(
+                "{} ── {} ── {}",
+                trash.display_name(directory.path(), options.paths.qualified())?,
+                count,
+                size_human_readable(size_bytes, 2, options.locale).trim_end(),
+            ),
+            last,
+            1,
+            &skipped,
+            options,
+        )?;
+        if last {
+            skipped.push(1);
+        }
+
+        let max_extension_chars = max_extension_chars(&extensions, options);
+        let max_count_chars = max_count_chars(&extensions);
+        for (idx, extension) in extensions.iter().enumerate() {
+            print_item(
+                &extension.to_string_formatted(max_extension_chars, max_count_chars, options),
+                idx + 1 == extensions.len(),
+                2,
+                &skipped,
+                options,
+            )?;
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::file::ScanOptions;
+    use crate::fs::MemoryFileSystem;
+    use crate::trash::Trash;
+    use std::path::{Path, PathBuf};
+
+    #[test]
+    fn test_trash_locations() {
+        let mut file_system = MemoryFileSystem::default();
+        file_system
+            .add_file("/home/a.txt", 100)
+            .add_file("/home/.local/share/Trash/files/b.txt", 10)
+            .add_file("/home/usb/deep/.Trash-1000/files/c.mp4", 50)
+            .add_file("/home/usb/deep/.Trash-1000/files/d.mp4", 40);
+        let options = ScanOptions {
+            max_depth: 1,
+            trash: Trash::Report,
+            file_system: Box::new(file_system),
+            ..Default::default()
+        };
+        let directory = Directory::new(PathBuf::from("/home"), 0, &options).expect("could not create directory");
+        assert_eq!(directory.extension_totals().iter().map(|e| e.count).sum::<usize>(), 1);
+
+        let locations = trash_locations(&directory);
+        assert_eq!(locations.len(), 2);
+        assert_eq!(locations[0].path(), Path::new("/home/.local/share/Trash"));
+        assert_eq!(locations[0].extensions()[0].total_size_bytes, 10);
+        assert_eq!(locations[1].path(), Path::new("/home/usb/deep/.Trash-1000"));
+        assert_eq!(locations[1].extensions()[0].count, 2);
+    }
+}
@@ -0,0 +1,275 @@
+use crate::file::{print_item, print_title, size_human_readable, Directory, DrawOptions};
+use crate::width;
+use anyhow::Result;
+use clap::ArgEnum;
+use std::cmp::Reverse;
+use std::io::{self, Write};
+
+/// Characters filling the tiles, in turn from the largest tile down.
+const SHADES: [char; 4] = ['█', '▓', '▒', '░'];
+
+/// Number of tiles drawn, the smaller ones beyond it being drawn together as a single tile.
+const MAX_TILES: usize = 12;
+
+/// Width of the treemap in columns when the terminal does not say, through `COLUMNS`.
+const DEFAULT_COLUMNS: usize = 80;
+
+/// What the tiles of a treemap stand for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ArgEnum)]
+pub enum TreemapBy {
+    /// The directories directly below the scanned one, and the files directly in it together.
+    Directory,
+
+    /// The extensions of all files.
+    Extension,
+}
+
+/// A rectangle of the treemap, in columns horizontally and half rows vertically, so that tiles
+/// of equal sides look square in a terminal.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Rect {
+    x: f64,
+    y: f64,
+    w: f64,
+    h: f64,
+}
+
+/// The worst aspect ratio among the tiles of a row of the given areas laid along a side.
+fn worst_ratio(row: &[f64], side: f64) -> f64 {
+    let sum: f64 = row.iter().sum();
+    row.iter()
+        .map(|&area| {
+            let ratio = side * side * area / (sum * sum);
+            ratio.max(1.0 / ratio)
+        })
+        .fold(0.0, f64::max)
+}
+
+/// Lay out tiles of the given areas, largest first and adding up to that of the bounds, so that
+/// they are as square as possible, following the squarified treemap algorithm of Bruls, Huizing
+/// and van Wijk: tiles are added to a row along the shorter side as long as that improves the
+/// worst aspect ratio in the row, which then takes up a strip of the remaining space.
+fn squarify(areas: &[f64], bounds: Rect) -> Vec<Rect> {
+    let mut rects = Vec::with_capacity(areas.len());
+    let mut rest = bounds;
+    let mut start = 0;
+    while start < areas.len() {
+        let side = rest.w.min(rest.h);
+        let mut end = start + 1;
+        while end < areas.len() && worst_ratio(&areas[start..=end], side) <= worst_ratio(&areas[start..end], side) {
+            end += 1;
+        }
+        let sum: f64 = areas[start..end].iter().sum();
+        if rest.w >= rest.h {
+            let thickness = sum / rest.h;
+            let mut y = rest.y;
+            for area in &areas[start..end] {
+                let h = area / thickness;
+                rects.push(Rect {
+                    x: rest.x,
+                    y,
+                    w: thickness,
+                    h,
+                });
+                y += h;
+            }
+            rest.x += thickness;
+            rest.w -= thickness;
+        } else {
+            let thickness = sum / rest.w;
+            let mut x = rest.x;
+            for area in &areas[start..end] {
+                let w = area / thickness;
+                rects.push(Rect {
+                    x,
+                    y: rest.y,
+                    w,
+                    h: thickness,
+                });
+                x += w;
+            }
+            rest.y += thickness;
+            rest.h -= thickness;
+        }
+        start = end;
+    }
+    rects
+}
+
+/// Render tiles of the given sizes, largest first, into rows of characters, each cell taking the
+/// shade of the tile holding its center. Labels are written at the top left of tiles wide enough.
+fn render(tiles: &[(String, u64)], columns: usize, rows: usize) -> Vec<String> {
+    let total: u64 = tiles.iter().map(|(_, size)| size).sum();
+    let bounds = Rect {
+        x: 0.0,
+        y: 0.0,
+        w: columns as f64,
+        h: 2.0 * rows as f64,
+    };
+    let areas: Vec<f64> = tiles
+        .iter()
+        .map(|(_, size)| *size as f64 / total as f64 * bounds.w * bounds.h)
+        .collect();
+    let rects = squarify(&areas, bounds);
+
+    let mut grid = vec![vec![0; columns]; rows];
+    for (row, cells) in grid.iter_mut().enumerate() {
+        for (column, cell) in cells.iter_mut().enumerate() {
+            let (x, y) = (column as f64 + 0.5, 2.0 * row as f64 + 1.0);
+            // Rounding errors may leave the last tiles a little short of the bounds.
+            *cell = rects
+                .iter()
+                .position(|r| x < r.x + r.w && y < r.y + r.h)
+                .unwrap_or(rects.len() - 1);
+        }
+    }
+
+    let mut lines: Vec<Vec<char>> = grid
+        .iter()
+        .map(|cells| cells.iter().map(|&tile| SHADES[tile % SHADES.len()]).collect())
+        .collect();
+    let mut labeled = vec![false; tiles.len()];
+    for (row, cells) in grid.iter().enumerate() {
+        for (column, &tile) in cells.iter().enumerate() {
+            if labeled[tile] {
+                continue;
+            }
+            labeled[tile] = true;
+            let span = cells[column..].iter().take_while(|&&t| t == tile).count();
+            let label = &tiles[tile].0;
+            // Wide characters would throw the columns off.
+            if width::width(label) == label.chars().count() && label.chars().count() <= span {
+                lines[row].splice(column..column + label.chars().count(), label.chars());
+            }
+        }
+    }
+    lines.into_iter().map(|line| line.into_iter().collect()).collect()
+}
+
+/// Keep the largest tiles, drawing the rest together under `others`, and leave out empty ones.
+fn limit_tiles(mut tiles: Vec<(String, u64)>, others: &str) -> Vec<(String, u64)> {
+    tiles.retain(|(_, size)| *size > 0);
+    tiles.sort_by_key(|(_, size)| Reverse(*size));
+    if tiles.len() > MAX_TILES {
+        let rest: u64 = tiles.drain(MAX_TILES - 1..).map(|(_, size)| size).sum();
+        tiles.push((others.to_string(), rest));
+    }
+    tiles
+}
+
+/// Print a treemap of the scanned directory, in which each tile takes up a share of the area
+/// proportional to the size of a directory directly below it or of an extension, followed by a
+/// legend giving the size and share of each tile.
+pub fn draw_treemap(directory: &Directory, by: TreemapBy, options: &DrawOptions) -> Result<()> {
+    print_title(options.locale.text("treemap"))?;
+    let tiles = match by {
+        TreemapBy::Directory => {
+            let mut tiles = vec![(
+                ".".to_string(),
+                directory.extensions().iter().map(|e| e.total_size_bytes).sum(),
+            )];
+            for subdirectory in directory.subdirectories() {
+                tiles.push((
+                    subdirectory.display_name(directory.path(), options.paths)?,
+                    subdirectory.extension_totals().iter().map(|e| e.total_size_bytes).sum(),
+                ));
+            }
+            tiles
+        }
+        TreemapBy::Extension => directory
+            .extension_totals()
+            .iter()
+            .map(|e| (e.label(options).to_string(), e.total_size_bytes))
+            .collect(),
+    };
+    let tiles = limit_tiles(tiles, options.locale.text("others"));
+    if tiles.is_empty() {
+        print_item(options.locale.text("none found"), true, 1, &[], options)?;
+        return Ok(());
+    }
+
+    let columns = std::env::var("COLUMNS")
+        .ok()
+        .and_then(|columns| columns.parse().ok())
+        .unwrap_or(DEFAULT_COLUMNS)
+        .max(SHADES.len());
+    let mut out = io::stdout().lock();
+    for line in render(&tiles, columns, columns / 4) {
+        writeln!(out, "{}", line)?;
+    }
+
+    let total: u64 = tiles.iter().map(|(_, size)| size).sum();
+    let max_label_chars = tiles.iter().map(|(label, _)| width::width(label)).max().unwrap_or(0);
+    for (idx, (label, size)) in tiles.iter().enumerate() {
+        print_item(
+            &format!(
+                "{} {} ── {:>10} ── {:>5} %",
+                SHADES[idx % SHADES.len()],
+                width::pad(label, max_label_chars),
+                size_human_readable(*size, 2, options.locale),
+                options.locale.decimal(*size as f64 / total as f64 * 100.0, 1)
+            ),
+            idx + 1 == tiles.len(),
+            1,
+            &[],
+            options,
+        )?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_squarify() {
+        // The example of the paper, in a 6 by 4 rectangle.
+        let areas = [6.0, 6.0, 4.0, 3.0, 2.0, 2.0, 1.0];
+        let rects = squarify(
+            &areas,
+            Rect {
+                x: 0.0,
+                y: 0.0,
+                w: 6.0,
+                h: 4.0,
+            },
+        );
+        assert_eq!(rects.len(), areas.len());
+        assert_eq!(
+            rects[0],
+            Rect {
+                x: 0.0,
+                y: 0.0,
+                w: 3.0,
+                h: 2.0
+            }
+        );
+        // 4 and 3 then share a row along the top of the remaining 3 by 4 rectangle.
+        assert!((rects[2].h - 7.0 / 3.0).abs() < 1e-9 && (rects[3].h - 7.0 / 3.0).abs() < 1e-9);
+        assert!((rects[3].x + rects[3].w - 6.0).abs() < 1e-9);
+        for (rect, area) in rects.iter().zip(areas) {
+            assert!((rect.w * rect.h - area).abs() < 1e-9);
+        }
+    }
+
+    #[test]
+    fn test_render() {
+        let tiles = [("videos".to_string(), 300), ("docs".to_string(), 100)];
+        let lines = render(&tiles, 16, 4);
+        assert_eq!(lines.len(), 4);
+        assert!(lines.iter().all(|line| line.chars().count() == 16));
+        assert_eq!(lines[0], "videos██████docs");
+        assert_eq!(lines[3], "████████████▓▓▓▓");
+    }
+
+    #[test]
+    fn test_limit_tiles() {
+        let mut tiles: Vec<_> = (1..=15).map(|size| (size.to_string(), size)).collect();
+        tiles.push(("empty".to_string(), 0));
+        let tiles = limit_tiles(tiles, "others");
+        assert_eq!(tiles.len(), MAX_TILES);
+        assert_eq!(tiles[0], ("15".to_string(), 15));
+        assert_eq!(tiles[MAX_TILES - 1], ("others".to_string(), 1 + 2 + 3 + 4));
+    }
+}
@@ -0,0 +1,115 @@
+use crate::file::{max_count_chars, max_extension_chars, print_item, print_title, Directory, DrawOptions, Extension};
+use anyhow::Result;
+use std::cmp::Reverse;
+
+/// Alternative spellings of the same extension, mapped onto their most common form.
+const SPELLINGS: &[(&str, &str)] = &[
+    ("jpeg", "jpg"),
+    ("jpe", "jpg"),
+    ("tif", "tiff"),
+    ("htm", "html"),
+    ("yml", "yaml"),
+    ("mpeg", "mpg"),
+    ("markdown", "md"),
+    ("text", "txt"),
+];
+
+/// Lowercase form of an extension, with alternative spellings replaced by their common form.
+fn canonical(extension: &str) -> String {
+    let lowercase = extension.to_lowercase();
+    SPELLINGS
+        .iter()
+        .find(|(alternative, _)| *alternative == lowercase)
+        .map_or(lowercase, |(_, common)| common.to_string())
+}
+
+/// Group extensions by their canonical form, keeping only the groups with more than one variant.
+/// Groups are sorted alphabetically, variants within a group by descending file count.
+fn extension_variants(extensions: Vec<Extension>) -> Vec<(String, Vec<Extension>)> {
+    let mut groups: Vec<(String, Vec<Extension>)> = Vec::new();
+    for extension in extensions {
+        let key = match extension.name.as_deref() {
+            Some(name) => canonical(name),
+            None => continue,
+        };
+
+        if let Some((_, variants)) = groups.iter_mut().find(|(k, _)| *k == key) {
+            variants.push(extension);
+        } else {
+            groups.push((key, vec![extension]));
+        }
+    }
+
+    groups.retain(|(_, variants)| variants.len() > 1);
+    groups.sort_unstable_by(|(k1, _), (k2, _)| k1.cmp(k2));
+    for (_, variants) in groups.iter_mut() {
+        variants.sort_unstable_by_key(|e| Reverse(e.count));
+    }
+    groups
+}
+
+/// Print every extension found under the directory in more than one casing (JPG/jpg) or spelling
+/// (jpeg/jpg), along with the count and size of each variant.
+pub fn draw_extension_variants(directory: &Directory, options: &DrawOptions) -> Result<()> {
+    let groups = extension_variants(directory.extension_totals());
+
+    print_title(options.locale.text("extension variants"))?;
+    let mut skipped = Vec::new();
+    for (idx, (key, variants)) in groups.iter().enumerate() {
+        let last = idx + 1 == groups.len();
+        print_item(key, last, 1, &skipped, options)?;
+        if last {
+            skipped.push(1);
+        }
+
+        let max_extension_chars = max_extension_chars(variants, options);
+        let max_count_chars = max_count_chars(variants);
+        for (idx, variant) in variants.iter().enumerate() {
+            print_item(
+                &variant.to_string_formatted(max_extension_chars, max_count_chars, options),
+                idx + 1 == variants.len(),
+                2,
+                &skipped,
+                options,
+            )?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn extension(name: &str, count: usize) -> Extension {
+        Extension {
+            name: Some(name.to_string()),
+            count,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_canonical() {
+        assert_eq!(canonical("JPG"), "jpg");
+        assert_eq!(canonical("Jpeg"), "jpg");
+        assert_eq!(canonical("tar.GZ"), "tar.gz");
+    }
+
+    #[test]
+    fn test_extension_variants() {
+        let groups = extension_variants(vec![
+            extension("jpg", 2),
+            extension("rs", 5),
+            extension("JPEG", 3),
+            extension("Yml", 1),
+            extension("yaml", 4),
+        ]);
+
+        assert_eq!(groups.len(), 2);
+        assert_eq!(groups[0].0, "jpg");
+        assert_eq!(groups[0].1[0].name.as_deref(), Some("JPEG"));
+        assert_eq!(groups[0].1[1].name.as_deref(), Some("jpg"));
+        assert_eq!(groups[1].0, "yaml");
+    }
+}
@@ -0,0 +1,144 @@
+use crate::file::{print_item, print_title, Directory, DrawOptions};
+use crate::width;
+use anyhow::Result;
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::io::{self, Write};
+
+/// Kind of non-fatal issue met during a scan, after which the scan went on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Warning {
+    /// File or directory that could not be read, and was left out.
+    Unreadable,
+
+    /// Directory skipped for lack of permission.
+    UnreadableDirectory,
+
+    /// Entry that disappeared between being listed and being read.
+    Vanished,
+
+    /// Symlink whose target does not exist.
+    BrokenSymlink,
+
+    /// Name or permission problem found by one of the audits.
+    Finding,
+
+    /// Extension absent from the baseline.
+    NewExtension,
+
+    /// Lowering of the depth to which directories are kept, after the memory limit was exceeded.
+    MemoryLimit,
+}
+
+impl Warning {
+    fn label(self) -> &'static str {
+        match self {
+            Warning::Unreadable => "unreadable entries",
+            Warning::UnreadableDirectory => "unreadable directories",
+            Warning::Vanished => "vanished entries",
+            Warning::BrokenSymlink => "broken symlinks",
+            Warning::Finding => "audit findings",
+            Warning::NewExtension => "new extensions",
+            Warning::MemoryLimit => "depth lowered for memory",
+        }
+    }
+
+    /// Name of the kind in porcelain records, which never changes.
+    fn key(self) -> &'static str {
+        match self {
+            Warning::Unreadable => "unreadable",
+            Warning::UnreadableDirectory => "unreadable_directory",
+            Warning::Vanished => "vanished",
+            Warning::BrokenSymlink => "broken_symlink",
+            Warning::Finding => "finding",
+            Warning::NewExtension => "new_extension",
+            Warning::MemoryLimit => "memory_limit",
+        }
+    }
+}
+
+/// Number of warnings of each kind, collected during and after a scan.
+#[derive(Debug, Default)]
+pub struct Warnings(RefCell<BTreeMap<Warning, usize>>);
+
+impl Warnings {
+    pub fn add(&self, warning: Warning) {
+        self.add_many(warning, 1);
+    }
+
+    pub fn add_many(&self, warning: Warning, count: usize) {
+        if count > 0 {
+            *self.0.borrow_mut().entry(warning).or_default() += count;
+        }
+    }
+
+    pub fn count(&self, warning: Warning) -> usize {
+        self.0.borrow().get(&warning).copied().unwrap_or(0)
+    }
+
+    pub fn total(&self) -> usize {
+        self.0.borrow().values().sum()
+    }
+
+    /// Add the broken symlinks and audit findings recorded in the scanned tree.
+    pub fn add_found(&self, directory: &Directory) {
+        directory.walk(&mut |d| {
+            self.add_many(Warning::BrokenSymlink, d.broken_symlinks.len());
+            self.add_many(Warning::Finding, d.findings.len());
+        });
+    }
+}
+
+/// Print the number of warnings of each kind, if there were any.
+pub fn draw_warnings(warnings: &Warnings, options: &DrawOptions) -> Result<()> {
+    let warnings = warnings.0.borrow();
+    if warnings.is_empty() {
+        return Ok(());
+    }
+
+    print_title(options.locale.text("warnings"))?;
+    let max_label_chars = warnings
+        .keys()
+        .map(|w| width::width(options.locale.text(w.label())))
+        .max()
+        .unwrap_or(0);
+    for (idx, (warning, count)) in warnings.iter().enumerate() {
+        print_item(
+            &format!(
+                "{} ── {}",
+                width::pad(options.locale.text(warning.label()), max_label_chars),
+                count
+            ),
+            idx + 1 == warnings.len(),
+            1,
+            &[],
+            options,
+        )?;
+    }
+    Ok(())
+}
+
+/// Write a porcelain record with the number of warnings of each kind.
+pub fn write_porcelain_warnings(out: &mut impl Write, warnings: &Warnings) -> io::Result<()> {
+    for (warning, count) in warnings.0.borrow().iter() {
+        writeln!(out, "warning\t{}\t{}", warning.key(), count)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_warnings() {
+        let warnings = Warnings::default();
+        warnings.add(Warning::Vanished);
+        warnings.add(Warning::Unreadable);
+        warnings.add(Warning::Vanished);
+        warnings.add_many(Warning::Finding, 0);
+        assert_eq!(warnings.count(Warning::Vanished), 2);
+        assert_eq!(warnings.count(Warning::Finding), 0);
+        assert_eq!(warnings.total(), 3);
+    }
+}
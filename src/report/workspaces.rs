@@ -0,0 +1,283 @@
+use crate::dirent::EntryKind;
+use crate::file::{
+    max_count_chars, max_extension_chars, merge_extension, print_item, print_title, size_human_readable, DrawOptions,
+    Extension, ScanOptions,
+};
+use crate::fs::FileSystem;
+use anyhow::Result;
+use std::cell::RefCell;
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+
+/// Bytes of a manifest read to find the name of its package.
+const MANIFEST_LEN: usize = 1 << 16;
+
+/// Directory of installed npm dependencies, whose manifests do not mark workspace packages.
+const NODE_MODULES: &str = "node_modules";
+
+/// The value of the `name` key of the `[package]` table of a Cargo manifest. Manifests of virtual
+/// workspaces, which have no such table, describe no package.
+fn cargo_package_name(manifest: &str) -> Option<Option<String>> {
+    let mut in_package = false;
+    let mut is_package = false;
+    let mut name = None;
+    for line in manifest.lines().map(str::trim) {
+        if line.starts_with('[') {
+            in_package = line == "[package]";
+            is_package |= in_package;
+        } else if in_package {
+            if let Some((key, value)) = line.split_once('=') {
+                if key.trim() == "name" {
+                    name = Some(value.trim().trim_matches('"').to_string());
+                }
+            }
+        }
+    }
+    is_package.then_some(name)
+}
+
+/// The top-level `name` of an npm manifest, skipping keys of nested objects such as `author`.
+fn npm_package_name(manifest: &str) -> Option<String> {
+    let mut depth = 0;
+    let mut expect_key = false;
+    let mut key = None;
+    let mut chars = manifest.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '{' => {
+                depth += 1;
+                expect_key = depth == 1;
+            }
+            '[' => depth += 1,
+            '}' | ']' => depth -= 1,
+            ',' if depth == 1 => expect_key = true,
+            '"' => {
+                let mut string = String::new();
+                while let Some(c) = chars.next() {
+                    match c {
+                        '"' => break,
+                        '\\' => string.extend(chars.next()),
+                        c => string.push(c),
+                    }
+                }
+                if depth != 1 {
+                    continue;
+                }
+                if expect_key {
+                    key = Some(string);
+                    expect_key = false;
+                } else if key.as_deref() == Some("name") {
+                    return Some(string);
+                }
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Files of the whole scan grouped by the package, or workspace member, whose directory is
+/// nearest above them.
+#[derive(Debug)]
+pub struct Workspaces {
+    /// Names of files marking the directory holding them as a package, besides manifests.
+    markers: Vec<String>,
+
+    /// Name of the package in each directory looked at, `None` for directories holding none.
+    packages: RefCell<HashMap<PathBuf, Option<String>>>,
+
+    /// Extensions of the files of each package directory, `None` for files outside any package.
+    extensions: RefCell<BTreeMap<Option<PathBuf>, Vec<Extension>>>,
+}
+
+impl Workspaces {
+    pub fn new(markers: Vec<String>) -> Self {
+        Self {
+            markers,
+            packages: RefCell::default(),
+            extensions: RefCell::default(),
+        }
+    }
+
+    fn read(file_system: &dyn FileSystem, manifest: &Path) -> Option<String> {
+        let is_file = matches!(file_system.file_type(manifest), Ok(EntryKind::File));
+        is_file.then(|| {
+            let bytes = file_system.read_header(manifest, MANIFEST_LEN).unwrap_or_default();
+            String::from_utf8_lossy(&bytes).into_owned()
+        })
+    }
+
+    /// Name of the package in a directory, if it holds one: the name its Cargo or npm manifest
+    /// gives, or that of the directory if the manifest gives none or the directory holds a marker.
+    fn package(&self, dir: &Path, file_system: &dyn FileSystem) -> Option<String> {
+        let dir_name = || dir.file_name().map(|name| name.to_string_lossy().into_owned());
+        if let Some(manifest) = Self::read(file_system, &dir.join("Cargo.toml")) {
+            if let Some(name) = cargo_package_name(&manifest) {
+                return name.or_else(dir_name);
+            }
+        }
+        if let Some(manifest) = Self::read(file_system, &dir.join("package.json")) {
+            return npm_package_name(&manifest).or_else(dir_name);
+        }
+        let has_marker = self
+            .markers
+            .iter()
+            .any(|marker| file_system.file_type(&dir.join(marker)).is_ok());
+        has_marker.then(dir_name).flatten()
+    }
+
+    /// Count a file with the extension it is counted under against the nearest package above it.
+    /// Dependencies installed in `node_modules` are counted against the package installing them.
+    pub fn add(&self, file: &Path, extension: Extension, options: &ScanOptions) {
+        let mut package = None;
+        for dir in file.ancestors().skip(1) {
+            let relative = match options.scan_root.get() {
+                Some(root) if !dir.starts_with(root) => break,
+                Some(root) => dir.strip_prefix(root).unwrap_or(dir),
+                None => dir,
+            };
+            if relative.components().any(|c| c.as_os_str() == NODE_MODULES) {
+                continue;
+            }
+            let mut packages = self.packages.borrow_mut();
+            let name = packages
+                .entry(dir.to_path_buf())
+                .or_insert_with(|| self.package(dir, options.file_system.as_ref()));
+            if name.is_some() {
+                package = Some(dir.to_path_buf());
+                break;
+            }
+        }
+        merge_extension(self.extensions.borrow_mut().entry(package).or_default(), extension);
+    }
+}
+
+/// Print every package with the number and size of its files, largest first, each broken down by
+/// extension. Files outside any package come last.
+pub fn draw_workspaces(workspaces: &Workspaces, scan_root: &Path, options: &DrawOptions) -> Result<()> {
+    print_title(options.locale.text("workspaces"))?;
+    let extensions = workspaces.extensions.borrow();
+    if extensions.is_empty() {
+        print_item(options.locale.text("none found"), true, 1, &[], options)?;
+        return Ok(());
+    }
+
+    let packages = workspaces.packages.borrow();
+    let mut groups: Vec<_> = extensions.iter().collect();
+    groups.sort_by_key(|(dir, extensions)| {
+        (
+            dir.is_none(),
+            Reverse(extensions.iter().map(|e| e.total_size_bytes).sum::<u64>()),
+        )
+    });
+
+    let mut skipped = Vec::new();
+    for (idx, (dir, extensions)) in groups.iter().enumerate() {
+        let last = idx + 1 == groups.len();
+        let label = match dir {
+            Some(dir) => {
+                let name = packages.get(dir).cloned().flatten().unwrap_or_default();
+                let relative = dir.strip_prefix(scan_root).unwrap_or(dir);
+                if relative.as_os_str().is_empty() {
+                    name
+                } else {
+                    format!("{} ({})", name, relative.display())
+                }
+            }
+            None => options.locale.text("outside packages").to_string(),
+        };
+        print_item(
+            &format!(
+                "{} ── {} ── {}",
+                label,
+                extensions.iter().map(|e| e.count).sum::<usize>(),
+                size_human_readable(extensions.iter().map(|e| e.total_size_bytes).sum(), 2, options.locale).trim_end()
+            ),
+            last,
+            1,
+            &skipped,
+            options,
+        )?;
+        if last {
+            skipped.push(1);
+        }
+
+        let mut extensions = extensions.to_vec();
+        extensions.sort_unstable_by_key(|e| Reverse(e.total_size_bytes));
+        let max_extension_chars = max_extension_chars(&extensions, options);
+        let max_count_chars = max_count_chars(&extensions);
+        for (idx, extension) in extensions.iter().enumerate() {
+            print_item(
+                &extension.to_string_formatted(max_extension_chars, max_count_chars, options),
+                idx + 1 == extensions.len(),
+                2,
+                &skipped,
+                options,
+            )?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::file::Directory;
+    use crate::fs::MemoryFileSystem;
+
+    #[test]
+    fn test_package_names() {
+        assert_eq!(
+            cargo_package_name("[package]\nname = \"core\"\nversion = \"0.1.0\"\n"),
+            Some(Some("core".to_string()))
+        );
+        assert_eq!(cargo_package_name("[workspace]\nmembers = [\"crates/*\"]\n"), None);
+        assert_eq!(
+            npm_package_name("{\"author\": {\"name\": \"someone\"}, \"name\": \"@scope/web\"}"),
+            Some("@scope/web".to_string())
+        );
+        assert_eq!(npm_package_name("{\"private\": true}"), None);
+    }
+
+    #[test]
+    fn test_add() {
+        let mut file_system = MemoryFileSystem::default();
+        file_system
+            .add_file_with_contents("/repo/Cargo.toml", b"[workspace]\nmembers = [\"crates/*\"]\n")
+            .add_file_with_contents("/repo/crates/core/Cargo.toml", b"[package]\nname = \"core\"\n")
+            .add_file("/repo/crates/core/src/lib.rs", 100)
+            .add_file_with_contents("/repo/web/package.json", b"{\"name\": \"web\"}")
+            .add_file("/repo/web/src/app.ts", 50)
+            .add_file_with_contents("/repo/web/node_modules/@scope/pkg/package.json", b"{\"name\": \"pkg\"}")
+            .add_file("/repo/web/node_modules/@scope/pkg/index.js", 20)
+            .add_file("/repo/tools/OWNERS", 1)
+            .add_file("/repo/tools/run.sh", 5)
+            .add_file("/repo/README.md", 10);
+        let options = ScanOptions {
+            max_depth: 1,
+            file_system: Box::new(file_system),
+            workspaces: Some(Workspaces::new(vec!["OWNERS".to_string()])),
+            ..Default::default()
+        };
+        Directory::new(PathBuf::from("/repo"), 0, &options).expect("could not create directory");
+
+        let workspaces = options.workspaces.as_ref().unwrap();
+        let extensions = workspaces.extensions.borrow();
+        let size = |dir: Option<&str>| -> u64 {
+            extensions[&dir.map(PathBuf::from)]
+                .iter()
+                .map(|e| e.total_size_bytes)
+                .sum()
+        };
+        assert_eq!(size(Some("/repo/crates/core")), 100 + 24);
+        assert_eq!(size(Some("/repo/web")), 50 + 15 + 20 + 15);
+        assert!(!extensions.contains_key(&Some(PathBuf::from("/repo/web/node_modules/@scope/pkg"))));
+        assert_eq!(size(Some("/repo/tools")), 6);
+        assert_eq!(size(None), 10 + 35);
+        assert_eq!(
+            workspaces.packages.borrow()[Path::new("/repo/crates/core")].as_deref(),
+            Some("core")
+        );
+    }
+}
@@ -0,0 +1,115 @@
+use anyhow::{anyhow, bail, Result};
+use std::io;
+use std::time::Duration;
+
+/// EIO, which network file systems return for failed requests, has the same number on every Unix.
+const EIO: i32 = 5;
+
+/// Repeats file system operations failing with errors that may be transient, as happens on flaky
+/// network mounts, waiting twice as long before each new attempt.
+#[derive(Debug)]
+pub struct Retry {
+    /// Number of attempts made after the first one has failed.
+    retries: u32,
+
+    /// Time waited before the first retry.
+    delay: Duration,
+}
+
+impl Retry {
+    pub fn new(retries: u32, delay: Duration) -> Self {
+        Self { retries, delay }
+    }
+
+    /// Retry an operation whose first attempt gave `result`, for as long as it fails with a
+    /// transient error and retries are left, and return the last result.
+    pub fn run<T>(&self, mut result: io::Result<T>, mut operation: impl FnMut() -> io::Result<T>) -> io::Result<T> {
+        let mut delay = self.delay;
+        for _ in 0..self.retries {
+            match &result {
+                Err(e) if is_transient(e) => {}
+                _ => break,
+            }
+            std::thread::sleep(delay);
+            delay = delay.saturating_mul(2);
+            result = operation();
+        }
+        result
+    }
+}
+
+/// Whether an error may go away when the operation is repeated.
+fn is_transient(error: &io::Error) -> bool {
+    matches!(error.kind(), io::ErrorKind::TimedOut | io::ErrorKind::Interrupted)
+        || (cfg!(unix) && error.raw_os_error() == Some(EIO))
+}
+
+/// Parse a delay such as `500ms`, `2s` or `1.5m`.
+pub fn parse_delay(delay: &str) -> Result<Duration> {
+    let split = delay
+        .find(|c: char| !c.is_ascii_digit() && c != '.')
+        .ok_or_else(|| anyhow!("retry delay {:?} has no unit, expected ms, s or m", delay))?;
+    let (value, unit) = delay.split_at(split);
+    let value: f64 = value
+        .parse()
+        .map_err(|_| anyhow!("retry delay {:?} does not start with a number", delay))?;
+    let seconds = match unit {
+        "ms" => value / 1000.0,
+        "s" => value,
+        "m" => value * 60.0,
+        _ => bail!("unknown unit {:?} in retry delay, expected ms, s or m", unit),
+    };
+    Duration::try_from_secs_f64(seconds).map_err(|_| anyhow!("retry delay {:?} is too long", delay))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Instant;
+
+    #[test]
+    fn test_run() {
+        let retry = Retry::new(3, Duration::from_millis(1));
+        let timed_out = || io::Error::from(io::ErrorKind::TimedOut);
+
+        let mut attempts = 0;
+        let start = Instant::now();
+        let result = retry.run(Err(timed_out()), || {
+            attempts += 1;
+            if attempts < 2 {
+                Err(timed_out())
+            } else {
+                Ok(attempts)
+            }
+        });
+        assert_eq!(result.expect("retries did not succeed"), 2);
+        assert!(start.elapsed() >= Duration::from_millis(3));
+
+        let mut attempts = 0;
+        let result = retry.run(Err(timed_out()), || -> io::Result<()> {
+            attempts += 1;
+            Err(timed_out())
+        });
+        assert!(result.is_err());
+        assert_eq!(attempts, 3);
+
+        let mut attempts = 0;
+        let result = retry.run(Err(io::Error::from(io::ErrorKind::NotFound)), || -> io::Result<()> {
+            attempts += 1;
+            Ok(())
+        });
+        assert!(result.is_err());
+        assert_eq!(attempts, 0);
+    }
+
+    #[test]
+    fn test_parse_delay() {
+        assert_eq!(parse_delay("500ms").ok(), Some(Duration::from_millis(500)));
+        assert_eq!(parse_delay("2s").ok(), Some(Duration::from_secs(2)));
+        assert_eq!(parse_delay("1.5m").ok(), Some(Duration::from_secs(90)));
+        assert!(parse_delay("500").is_err());
+        assert!(parse_delay("ms").is_err());
+        assert!(parse_delay("5h").is_err());
+        assert!(parse_delay("99999999999999999999999m").is_err());
+    }
+}
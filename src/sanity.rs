@@ -0,0 +1,195 @@
+//! Rules telling real extensions apart from suffixes that merely follow a dot, such as the
+//! timestamp in `backup.2024-01-15T10:33` or the sequence number in `data.00000123`, and
+//! recognizing the parts of split archives.
+
+use anyhow::{bail, Result};
+use clap::ArgEnum;
+use std::path::Path;
+
+/// What becomes of a suffix rejected by the extension rules.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ArgEnum)]
+pub enum BogusExtensions {
+    /// The file is counted as having no extension.
+    #[default]
+    None,
+
+    /// The file is counted under the shape of the suffix, in which every digit is replaced with
+    /// `#`, so that e.g. all `.00000123`-like suffixes are counted together as `<########>`.
+    Pattern,
+}
+
+/// Conditions a suffix has to meet to be counted as an extension. The default accepts everything.
+#[derive(Debug, Default)]
+pub struct ExtensionRules {
+    /// Maximum number of characters.
+    pub max_length: Option<usize>,
+
+    /// Reject suffixes made of digits only.
+    pub reject_numeric: bool,
+
+    /// Inclusive ranges of the characters allowed, or `None` to allow any.
+    pub allowed: Option<Vec<(char, char)>>,
+
+    /// What becomes of the suffixes rejected.
+    pub bogus: BogusExtensions,
+
+    /// Count the numbered parts of split archives under a single extension.
+    pub split_archives: bool,
+}
+
+/// Extension shared by all parts of a split archive, e.g. `7z.split` for `backup.7z.001`, or
+/// `None` if the file is not one. Parts are recognized from the numbering schemes of 7-Zip and
+/// HJSplit (`.001`), old RAR volumes (`.r00`) and split ZIP archives (`.z01`).
+pub fn split_archive_extension(file: &Path) -> Option<String> {
+    let extension = file.extension()?.to_string_lossy().to_ascii_lowercase();
+    let (prefix, number) = extension.split_at(extension.len().checked_sub(2)?);
+    if !number.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    let archive = match prefix {
+        "r" => "rar".to_string(),
+        "z" => "zip".to_string(),
+        _ if prefix.len() == 1 && prefix.chars().all(|c| c.is_ascii_digit()) => {
+            match Path::new(file.file_stem()?).extension() {
+                Some(inner) => inner.to_string_lossy().to_string(),
+                None => return Some("split".to_string()),
+            }
+        }
+        _ => return None,
+    };
+    Some(format!("{}.split", archive))
+}
+
+/// Parse a character set such as `a-zA-Z0-9_`, in which `-` between two characters denotes a range
+/// and anywhere else stands for itself.
+pub fn parse_character_set(set: &str) -> Result<Vec<(char, char)>> {
+    let chars: Vec<char> = set.chars().collect();
+    let mut ranges = Vec::new();
+    let mut idx = 0;
+    while idx < chars.len() {
+        match chars.get(idx + 1..idx + 3) {
+            Some(&['-', end]) => {
+                if end < chars[idx] {
+                    bail!("invalid range {}-{} in character set", chars[idx], end);
+                }
+                ranges.push((chars[idx], end));
+                idx += 3;
+            }
+            _ => {
+                ranges.push((chars[idx], chars[idx]));
+                idx += 1;
+            }
+        }
+    }
+    if ranges.is_empty() {
+        bail!("character set is empty");
+    }
+    Ok(ranges)
+}
+
+impl ExtensionRules {
+    /// The extension a file is counted under, or `None` if it is counted as having no extension.
+    pub fn extension(&self, file: &Path) -> Option<String> {
+        if self.split_archives {
+            if let Some(extension) = split_archive_extension(file) {
+                return Some(extension);
+            }
+        }
+        self.apply(file.extension()?.to_string_lossy().to_string())
+    }
+
+    /// Why a suffix is not counted as an extension, or `None` if it is.
+    pub fn rejection(&self, extension: &str) -> Option<&'static str> {
+        if self.max_length.is_some_and(|max| extension.chars().count() > max) {
+            Some("longer than --max-extension-length")
+        } else if self.reject_numeric && extension.chars().all(|c| c.is_ascii_digit()) {
+            Some("made of digits only")
+        } else if self.allowed.as_ref().is_some_and(|allowed| {
+            !extension
+                .chars()
+                .all(|c| allowed.iter().any(|(start, end)| (*start..=*end).contains(&c)))
+        }) {
+            Some("has characters outside --extension-chars")
+        } else {
+            None
+        }
+    }
+
+    /// The extension a file with this suffix is counted under, or `None` if it is counted as having
+    /// no extension.
+    fn apply(&self, extension: String) -> Option<String> {
+        if self.rejection(&extension).is_none() {
+            return Some(extension);
+        }
+        match self.bogus {
+            BogusExtensions::None => None,
+            BogusExtensions::Pattern => Some(format!(
+                "<{}>",
+                extension
+                    .chars()
+                    .map(|c| if c.is_ascii_digit() { '#' } else { c })
+                    .collect::<String>()
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_character_set() {
+        assert_eq!(
+            parse_character_set("a-z0-9_").ok(),
+            Some(vec![('a', 'z'), ('0', '9'), ('_', '_')])
+        );
+        assert_eq!(
+            parse_character_set("-a-").ok(),
+            Some(vec![('-', '-'), ('a', 'a'), ('-', '-')])
+        );
+        assert!(parse_character_set("z-a").is_err());
+        assert!(parse_character_set("").is_err());
+    }
+
+    #[test]
+    fn test_split_archive_extension() {
+        let split = |file: &str| split_archive_extension(Path::new(file));
+        assert_eq!(split("backup.7z.001").as_deref(), Some("7z.split"));
+        assert_eq!(split("backup.7z.012").as_deref(), Some("7z.split"));
+        assert_eq!(split("movie.001").as_deref(), Some("split"));
+        assert_eq!(split("backup.R01").as_deref(), Some("rar.split"));
+        assert_eq!(split("backup.z01").as_deref(), Some("zip.split"));
+        assert_eq!(split("backup.zip"), None);
+        assert_eq!(split("data.00000123"), None);
+        assert_eq!(split("photo.jpg"), None);
+        assert_eq!(split("v.01"), None);
+    }
+
+    #[test]
+    fn test_apply() {
+        let mut rules = ExtensionRules {
+            max_length: Some(8),
+            reject_numeric: true,
+            allowed: Some(parse_character_set("a-zA-Z0-9_").expect("could not parse character set")),
+            bogus: BogusExtensions::None,
+            split_archives: false,
+        };
+        assert_eq!(rules.apply("tar_gz".to_string()).as_deref(), Some("tar_gz"));
+        assert_eq!(rules.apply("mp3".to_string()).as_deref(), Some("mp3"));
+        assert_eq!(rules.apply("00000123".to_string()), None);
+        assert_eq!(rules.apply("2024-01-15T10:33".to_string()), None);
+        assert_eq!(rules.apply("verylongsuffix".to_string()), None);
+
+        rules.bogus = BogusExtensions::Pattern;
+        assert_eq!(rules.apply("00000123".to_string()).as_deref(), Some("<########>"));
+        assert_eq!(
+            rules.apply("2024-01-15T10:33".to_string()).as_deref(),
+            Some("<####-##-##T##:##>")
+        );
+        assert_eq!(
+            ExtensionRules::default().apply("00000123".to_string()).as_deref(),
+            Some("00000123")
+        );
+    }
+}
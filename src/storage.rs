@@ -0,0 +1,316 @@
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+/// Kind of storage a directory lives on, which determines how many requests are worth keeping in
+/// flight at once. Only lookups through io_uring are tuned by it, as the default scan makes one
+/// request at a time.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Storage {
+    /// Spinning disk, where too many concurrent requests cause seeking.
+    Rotational,
+
+    /// Solid-state or memory-backed storage.
+    SolidState,
+
+    /// Network file system, where latency rather than the device is the bottleneck.
+    Network,
+
+    /// Storage that could not be identified.
+    Unknown,
+}
+
+impl Storage {
+    /// Detect the storage the given path lives on.
+    pub fn detect(path: &Path) -> Self {
+        #[cfg(target_os = "linux")]
+        {
+            linux::detect(path)
+        }
+
+        #[cfg(not(target_os = "linux"))]
+        {
+            let _ = path;
+            Storage::Unknown
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Storage::Rotational => "rotational disk",
+            Storage::SolidState => "solid-state",
+            Storage::Network => "network",
+            Storage::Unknown => "unknown storage",
+        }
+    }
+
+    /// Number of metadata lookups to submit at once through io_uring.
+    pub fn queue_depth(self) -> u32 {
+        match self {
+            Storage::Rotational => 32,
+            Storage::SolidState | Storage::Unknown => 128,
+            Storage::Network => 256,
+        }
+    }
+}
+
+/// Type of the file system mounted on the given directory (e.g. ext4, nfs), or `None` if no file
+/// system is mounted there or the mount table is unavailable.
+pub fn mount_type(path: &Path) -> Option<String> {
+    #[cfg(target_os = "linux")]
+    {
+        linux::mount_type(path)
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    {
+        let _ = path;
+        None
+    }
+}
+
+/// Bytes in use on the file system mounted on the given directory, or `None` if no file system is
+/// mounted there, in which case the directory holds only part of them.
+pub fn used_bytes(path: &Path) -> Option<u64> {
+    #[cfg(target_os = "linux")]
+    {
+        linux::used_bytes(path)
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    {
+        let _ = path;
+        None
+    }
+}
+
+/// Mount points of pseudo file systems, such as /proc, /sys and /dev, whose files are generated by
+/// the kernel rather than stored, so that their sizes are meaningless and reading them can block.
+pub fn pseudo_mounts() -> HashSet<PathBuf> {
+    #[cfg(target_os = "linux")]
+    {
+        linux::pseudo_mounts()
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    {
+        HashSet::new()
+    }
+}
+
+#[cfg(target_os = "linux")]
+mod linux {
+    use super::Storage;
+    use std::collections::HashSet;
+    use std::ffi::{CString, OsStr};
+    use std::os::unix::ffi::OsStrExt;
+    use std::os::unix::fs::MetadataExt;
+    use std::path::{Path, PathBuf};
+
+    /// Types of the file systems generated by the kernel. tmpfs is only counted as one below /dev
+    /// and /run, where it holds sockets, locks and device nodes rather than user files.
+    const PSEUDO_TYPES: &[&str] = &[
+        "proc",
+        "sysfs",
+        "devtmpfs",
+        "devpts",
+        "cgroup",
+        "cgroup2",
+        "debugfs",
+        "tracefs",
+        "securityfs",
+        "pstore",
+        "bpf",
+        "configfs",
+        "fusectl",
+        "mqueue",
+        "hugetlbfs",
+        "binfmt_misc",
+        "efivarfs",
+        "selinuxfs",
+        "autofs",
+        "nsfs",
+        "rpc_pipefs",
+    ];
+
+    /// `f_type` values of network file systems, from statfs(2).
+    const NETWORK_MAGICS: &[i64] = &[
+        0x6969,               // NFS
+        0x517B,               // SMB
+        0xFF534D42u32 as i64, // CIFS
+        0xFE534D42u32 as i64, // SMB2
+        0x00C36400,           // Ceph
+        0x5346414F,           // AFS
+        0x65735546,           // FUSE, as used by sshfs and most cloud mounts
+    ];
+
+    pub fn detect(path: &Path) -> Storage {
+        if is_network(path) {
+            return Storage::Network;
+        }
+
+        let dev = match std::fs::metadata(path) {
+            Ok(metadata) => metadata.dev(),
+            Err(_) => return Storage::Unknown,
+        };
+        let major = ((dev >> 8) & 0xfff) | ((dev >> 32) & !0xfff);
+        let minor = (dev & 0xff) | ((dev >> 12) & !0xff);
+
+        // Partitions have no queue of their own, the whole disk is their parent.
+        let block = Path::new("/sys/dev/block").join(format!("{}:{}", major, minor));
+        for queue in [block.join("queue"), block.join("..").join("queue")] {
+            match std::fs::read_to_string(queue.join("rotational"))
+                .as_deref()
+                .map(str::trim)
+            {
+                Ok("1") => return Storage::Rotational,
+                Ok("0") => return Storage::SolidState,
+                _ => {}
+            }
+        }
+        Storage::Unknown
+    }
+
+    pub fn mount_type(path: &Path) -> Option<String> {
+        let mountinfo = std::fs::read_to_string("/proc/self/mountinfo").ok()?;
+        parse_mount_type(&mountinfo, path)
+    }
+
+    pub fn pseudo_mounts() -> HashSet<PathBuf> {
+        std::fs::read_to_string("/proc/self/mountinfo")
+            .map(|mountinfo| parse_pseudo_mounts(&mountinfo))
+            .unwrap_or_default()
+    }
+
+    /// Mount points and file system types in the contents of mountinfo, see proc(5), in the order
+    /// they were mounted.
+    fn mounts(mountinfo: &str) -> impl DoubleEndedIterator<Item = (PathBuf, &str)> {
+        mountinfo.lines().filter_map(|line| {
+            let mut fields = line.split(' ');
+            let mount_point = fields.nth(4)?;
+            let file_system_type = fields.skip_while(|f| *f != "-").nth(1)?;
+            Some((
+                PathBuf::from(OsStr::from_bytes(&unescape(mount_point))),
+                file_system_type,
+            ))
+        })
+    }
+
+    /// Find the file system type of the mount on `path` in the contents of mountinfo. Later
+    /// mounts hide earlier ones on the same directory, so the last match wins.
+    pub(super) fn parse_mount_type(mountinfo: &str, path: &Path) -> Option<String> {
+        mounts(mountinfo)
+            .rev()
+            .find(|(mount_point, _)| mount_point == path)
+            .map(|(_, file_system_type)| file_system_type.to_string())
+    }
+
+    /// Find the mount points of pseudo file systems in the contents of mountinfo.
+    pub(super) fn parse_pseudo_mounts(mountinfo: &str) -> HashSet<PathBuf> {
+        mounts(mountinfo)
+            .filter(|(mount_point, file_system_type)| {
+                PSEUDO_TYPES.contains(file_system_type)
+                    || (*file_system_type == "tmpfs"
+                        && (mount_point.starts_with("/dev") || mount_point.starts_with("/run")))
+            })
+            .map(|(mount_point, _)| mount_point)
+            .collect()
+    }
+
+    /// Undo the octal escaping of spaces, tabs, newlines and backslashes in mountinfo paths.
+    fn unescape(field: &str) -> Vec<u8> {
+        let bytes = field.as_bytes();
+        let mut unescaped = Vec::with_capacity(bytes.len());
+        let mut idx = 0;
+        while idx < bytes.len() {
+            let octal = bytes.get(idx + 1..idx + 4).and_then(|digits| {
+                std::str::from_utf8(digits)
+                    .ok()
+                    .and_then(|digits| u8::from_str_radix(digits, 8).ok())
+            });
+            match (bytes[idx], octal) {
+                (b'\\', Some(byte)) => {
+                    unescaped.push(byte);
+                    idx += 4;
+                }
+                (byte, _) => {
+                    unescaped.push(byte);
+                    idx += 1;
+                }
+            }
+        }
+        unescaped
+    }
+
+    pub fn used_bytes(path: &Path) -> Option<u64> {
+        mount_type(&std::fs::canonicalize(path).ok()?)?;
+        let stat = statfs(path)?;
+        #[allow(clippy::unnecessary_cast)]
+        Some((stat.f_blocks - stat.f_bfree) as u64 * stat.f_frsize as u64)
+    }
+
+    fn is_network(path: &Path) -> bool {
+        #[allow(clippy::unnecessary_cast)]
+        statfs(path).is_some_and(|stat| NETWORK_MAGICS.contains(&(stat.f_type as i64)))
+    }
+
+    fn statfs(path: &Path) -> Option<libc::statfs> {
+        let c_path = CString::new(path.as_os_str().as_bytes()).ok()?;
+        // SAFETY: `statfs` is plain old data, for which all zeroes is a valid value.
+        let mut stat: libc::statfs = unsafe { std::mem::zeroed() };
+        // SAFETY: `c_path` is NUL-terminated and `stat` is valid for writes.
+        if unsafe { libc::statfs(c_path.as_ptr(), &mut stat) } != 0 {
+            return None;
+        }
+        Some(stat)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_queue_depth() {
+        assert!(Storage::Rotational.queue_depth() < Storage::SolidState.queue_depth());
+        assert!(Storage::SolidState.queue_depth() < Storage::Network.queue_depth());
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_parse_mount_type() {
+        let mountinfo = "\
+22 1 8:1 / / rw,relatime shared:1 - ext4 /dev/sda1 rw
+40 22 0:35 / /mnt/my\\040share rw - nfs4 server:/export rw,vers=4.2
+41 22 0:36 / /tmp rw - tmpfs tmpfs rw
+42 41 0:37 / /tmp rw - overlay overlay rw";
+        let parse = |path: &str| linux::parse_mount_type(mountinfo, Path::new(path));
+        assert_eq!(parse("/").as_deref(), Some("ext4"));
+        assert_eq!(parse("/mnt/my share").as_deref(), Some("nfs4"));
+        assert_eq!(parse("/tmp").as_deref(), Some("overlay"));
+        assert_eq!(parse("/mnt"), None);
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_parse_pseudo_mounts() {
+        let mountinfo = "\
+22 1 8:1 / / rw,relatime shared:1 - ext4 /dev/sda1 rw
+23 22 0:21 / /proc rw,nosuid - proc proc rw
+24 22 0:22 / /sys rw,nosuid - sysfs sysfs rw
+25 24 0:23 / /sys/fs/cgroup rw - cgroup2 cgroup2 rw
+26 22 0:5 / /dev rw,nosuid - devtmpfs udev rw
+27 26 0:24 / /dev/shm rw - tmpfs tmpfs rw
+28 22 0:25 / /run rw - tmpfs tmpfs rw
+29 22 0:26 / /tmp rw - tmpfs tmpfs rw
+30 22 0:27 / /home/alice/proc rw - ext4 /dev/sda2 rw";
+        let mut mounts: Vec<PathBuf> = linux::parse_pseudo_mounts(mountinfo).into_iter().collect();
+        mounts.sort();
+        assert_eq!(
+            mounts,
+            ["/dev", "/dev/shm", "/proc", "/run", "/sys", "/sys/fs/cgroup"]
+                .iter()
+                .map(PathBuf::from)
+                .collect::<Vec<_>>()
+        );
+    }
+}
@@ -0,0 +1,369 @@
+//! Reading the member headers of a tar archive into a listing, without extracting it. Archives
+//! compressed with gzip, zstd, bzip2 or xz are decompressed through the corresponding command,
+//! which has to be installed. POSIX ustar, pax and GNU archives are supported.
+
+use crate::fs::Metadata;
+use crate::listing::{self, ListingFileSystem};
+use anyhow::{bail, Context, Result};
+use std::collections::HashMap;
+use std::io::{self, BufRead, BufReader, Read};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+const BLOCK_LEN: usize = 512;
+
+/// Long names and pax headers larger than this are rejected rather than read into memory.
+const MAX_EXTENSION_HEADER_LEN: u64 = 1024 * 1024;
+
+/// Magic numbers of compressed streams and the commands decompressing them to stdout.
+const DECOMPRESSORS: &[(&[u8], &str)] = &[
+    (&[0x1F, 0x8B], "gzip"),
+    (&[0x28, 0xB5, 0x2F, 0xFD], "zstd"),
+    (b"BZh", "bzip2"),
+    (&[0xFD, b'7', b'z', b'X', b'Z', 0x00], "xz"),
+];
+
+/// Value of a numeric header field, stored in octal or, for large values, in base 256.
+fn parse_number(field: &[u8]) -> Result<u64> {
+    if field.first().is_some_and(|b| b & 0x80 != 0) {
+        return Ok(field[1..].iter().fold(0u64, |n, b| n << 8 | *b as u64));
+    }
+    let digits = String::from_utf8_lossy(field);
+    let digits = digits.trim_matches(|c: char| c == '\0' || c == ' ');
+    if digits.is_empty() {
+        return Ok(0);
+    }
+    u64::from_str_radix(digits, 8).with_context(|| format!("invalid number {:?}", digits))
+}
+
+/// Time of a modification time field, in seconds since the epoch. GNU tar stores times before
+/// the epoch in base 256 as two's complement, starting with 0xFF. `None` if the field is invalid or
+/// the time out of range.
+fn parse_time(field: &[u8]) -> Option<SystemTime> {
+    let seconds = match field.first() {
+        Some(&first) if first & 0x80 != 0 => {
+            // Negative values start with 0xFF and are two's complement over the whole field, while
+            // positive ones only have the highest bit set, marking base 256.
+            let (start, first) = if first == 0xFF {
+                (-1i128, first)
+            } else {
+                (0, first & 0x7F)
+            };
+            let value = std::iter::once(&first)
+                .chain(&field[1..])
+                .fold(start, |n, b| n << 8 | i128::from(*b));
+            i64::try_from(value).ok()?
+        }
+        _ => i64::try_from(parse_number(field).ok()?).ok()?,
+    };
+    let since_epoch = Duration::from_secs(seconds.unsigned_abs());
+    if seconds >= 0 {
+        UNIX_EPOCH.checked_add(since_epoch)
+    } else {
+        UNIX_EPOCH.checked_sub(since_epoch)
+    }
+}
+
+/// Text of a NUL-terminated header field.
+fn parse_string(field: &[u8]) -> String {
+    let end = field.iter().position(|b| *b == 0).unwrap_or(field.len());
+    String::from_utf8_lossy(&field[..end]).to_string()
+}
+
+/// Whether the checksum of a header matches its contents, computed with the checksum field taken
+/// as spaces.
+fn checksum_matches(header: &[u8; BLOCK_LEN]) -> bool {
+    let sum: u64 = header
+        .iter()
+        .enumerate()
+        .map(|(idx, b)| {
+            if (148..156).contains(&idx) {
+                b' ' as u64
+            } else {
+                *b as u64
+            }
+        })
+        .sum();
+    parse_number(&header[148..156]).is_ok_and(|stored| stored == sum)
+}
+
+/// Records of a pax extended header, such as `path` and `size`.
+fn parse_pax(data: &[u8]) -> HashMap<String, String> {
+    let mut records = HashMap::new();
+    let mut rest = data;
+    // Each record is "<length> <key>=<value>\n", the length counting the whole record.
+    while let Some(space) = rest.iter().position(|b| *b == b' ') {
+        let len = match std::str::from_utf8(&rest[..space])
+            .ok()
+            .and_then(|l| l.parse::<usize>().ok())
+        {
+            Some(len) if len > space && len <= rest.len() => len,
+            _ => break,
+        };
+        let record = String::from_utf8_lossy(&rest[space + 1..len]);
+        if let Some((key, value)) = record.trim_end_matches('\n').split_once('=') {
+            records.insert(key.to_string(), value.to_string());
+        }
+        rest = &rest[len..];
+    }
+    records
+}
+
+/// Fill a block, returning `false` at the end of the stream.
+fn read_block(reader: &mut impl Read, block: &mut [u8; BLOCK_LEN]) -> Result<bool> {
+    let mut filled = 0;
+    while filled < BLOCK_LEN {
+        match reader.read(&mut block[filled..]) {
+            Ok(0) if filled == 0 => return Ok(false),
+            Ok(0) => bail!("not a tar archive, or one ending in the middle of a header"),
+            Ok(n) => filled += n,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+            Err(e) => return Err(e.into()),
+        }
+    }
+    Ok(true)
+}
+
+/// Number of bytes taken up by member data of the given size, padded to whole blocks.
+fn padded(size: u64) -> u64 {
+    size.div_ceil(BLOCK_LEN as u64) * BLOCK_LEN as u64
+}
+
+/// Read the data of an extension header such as a long name or pax header.
+fn read_data(reader: &mut impl Read, size: u64) -> Result<Vec<u8>> {
+    if size > MAX_EXTENSION_HEADER_LEN {
+        bail!("extension header of {} bytes is too large", size);
+    }
+    let mut data = Vec::with_capacity(padded(size) as usize);
+    reader.take(padded(size)).read_to_end(&mut data)?;
+    if (data.len() as u64) < size {
+        bail!("archive ends in the middle of a member");
+    }
+    data.truncate(size as usize);
+    Ok(data)
+}
+
+/// Skip the data of a member.
+fn skip(reader: &mut impl Read, size: u64) -> Result<()> {
+    let skipped = io::copy(&mut reader.take(padded(size)), &mut io::sink())?;
+    if skipped < size {
+        bail!("archive ends in the middle of a member");
+    }
+    Ok(())
+}
+
+/// Add the members of an uncompressed tar stream to a listing.
+fn read_members(reader: &mut impl Read, listing: &mut ListingFileSystem) -> Result<()> {
+    let mut block = [0u8; BLOCK_LEN];
+    let mut long_name = None;
+    let mut long_link = None;
+    let mut pax = HashMap::new();
+    let mut offset = 0u64;
+    while read_block(reader, &mut block)? {
+        // The archive ends with zero blocks.
+        if block.iter().all(|b| *b == 0) {
+            break;
+        }
+        if !checksum_matches(&block) {
+            bail!("not a tar archive, or corrupt header at offset {}", offset);
+        }
+
+        let header_size = parse_number(&block[124..136])?;
+        let kind = block[156];
+        match kind {
+            b'L' => long_name = Some(parse_string(&read_data(reader, header_size)?)),
+            b'K' => long_link = Some(parse_string(&read_data(reader, header_size)?)),
+            b'x' => pax = parse_pax(&read_data(reader, header_size)?),
+            b'g' => skip(reader, header_size)?,
+            _ => {}
+        }
+        offset += BLOCK_LEN as u64 + padded(header_size);
+        if matches!(kind, b'L' | b'K' | b'x' | b'g') {
+            continue;
+        }
+
+        let name = match pax.remove("path").or_else(|| long_name.take()) {
+            Some(name) => name,
+            // Only POSIX ustar headers have a prefix, GNU headers store other fields there.
+            None if &block[257..263] == b"ustar\0" && block[345] != 0 => {
+                format!("{}/{}", parse_string(&block[345..500]), parse_string(&block[..100]))
+            }
+            None => parse_string(&block[..100]),
+        };
+        let link = pax.remove("linkpath").or_else(|| long_link.take());
+        let link = link.unwrap_or_else(|| parse_string(&block[157..257]));
+        let size = match pax.remove("size") {
+            Some(size) => size
+                .parse()
+                .with_context(|| format!("invalid size {:?} of {}", size, name))?,
+            None => header_size,
+        };
+        let metadata = Metadata {
+            len: size,
+            nlink: 1,
+            uid: parse_number(&block[108..116]).unwrap_or_default() as u32,
+            mode: parse_number(&block[100..108]).unwrap_or_default() as u32 & 0o7777,
+            modified: parse_time(&block[136..148]),
+            placeholder: false,
+        };
+        pax.clear();
+
+        let path = Path::new(&name);
+        match kind {
+            b'5' => listing.add_dir(path, Some(metadata)),
+            b'2' => listing.add_symlink(path, PathBuf::from(link)),
+            // Hard links share the metadata of the member they link to, which comes first.
+            b'1' => {
+                let target = listing.file_metadata_mut(Path::new(&link)).map(|target| {
+                    target.nlink += 1;
+                    target.clone()
+                });
+                listing.add_file(path, target.unwrap_or(Metadata { len: 0, ..metadata }));
+            }
+            // Character and block devices and FIFOs hold no data and are not counted.
+            b'3' | b'4' | b'6' => {}
+            _ => listing.add_file(path, metadata),
+        }
+        // Hard links, symlinks and directories have no data whatever their size field says.
+        if !matches!(kind, b'1' | b'2' | b'5') {
+            skip(reader, size)?;
+            offset += padded(size);
+        }
+    }
+    Ok(())
+}
+
+/// Read a tar archive from a file, or from stdin if the path is `-`, into a listing rooted at a
+/// directory named after the archive.
+pub fn read_tar(input: &Path) -> Result<ListingFileSystem> {
+    let (mut listing, reader) = listing::open_input(input)?;
+    let mut reader = BufReader::new(reader);
+    let start = reader.fill_buf()?;
+    let decompressor = DECOMPRESSORS
+        .iter()
+        .find(|(magic, _)| start.starts_with(magic))
+        .map(|(_, command)| *command);
+
+    let command = match decompressor {
+        Some(command) => command,
+        None => {
+            read_members(&mut reader, &mut listing)?;
+            return Ok(listing);
+        }
+    };
+
+    let mut child = Command::new(command)
+        .arg("-dc")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .with_context(|| format!("could not run {} to decompress the archive", command))?;
+    let mut stdin = child.stdin.take().expect("stdin is piped");
+    // Feed the decompressor from another thread, so that neither side blocks on a full pipe.
+    let feeder = std::thread::spawn(move || io::copy(&mut reader, &mut stdin));
+    let mut output = BufReader::new(child.stdout.take().expect("stdout is piped"));
+    // Whatever follows the end of the archive is drained, so that the decompressor does not fail
+    // writing it.
+    let result = read_members(&mut output, &mut listing)
+        .and_then(|()| io::copy(&mut output, &mut io::sink()).map_err(Into::into));
+    // Stop the decompressor if the archive ended early, which also ends the feeder.
+    if result.is_err() {
+        let _ = child.kill();
+    }
+    let status = child.wait()?;
+    result?;
+    match feeder.join().expect("the feeder does not panic") {
+        Err(e) if e.kind() != io::ErrorKind::BrokenPipe => return Err(e).context("could not read the archive"),
+        _ => {}
+    }
+    if !status.success() {
+        bail!("{} could not decompress the archive", command);
+    }
+    Ok(listing)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fs::{EntryKind, FileSystem};
+
+    fn header(name: &str, kind: u8, size: u64, link: &str) -> Vec<u8> {
+        let mut block = [0u8; BLOCK_LEN];
+        block[..name.len()].copy_from_slice(name.as_bytes());
+        block[100..107].copy_from_slice(b"0000644");
+        block[124..135].copy_from_slice(format!("{:011o}", size).as_bytes());
+        block[156] = kind;
+        block[157..157 + link.len()].copy_from_slice(link.as_bytes());
+        block[257..263].copy_from_slice(b"ustar\0");
+        block[263..265].copy_from_slice(b"00");
+        block[148..156].copy_from_slice(b"        ");
+        let sum: u64 = block.iter().map(|b| *b as u64).sum();
+        block[148..155].copy_from_slice(format!("{:06o}\0", sum).as_bytes());
+        block.to_vec()
+    }
+
+    fn member(archive: &mut Vec<u8>, name: &str, kind: u8, data: &[u8], link: &str) {
+        archive.extend(header(name, kind, data.len() as u64, link));
+        archive.extend(data);
+        archive.resize(padded(archive.len() as u64) as usize, 0);
+    }
+
+    #[test]
+    fn test_read_members() {
+        let mut archive = Vec::new();
+        member(&mut archive, "./src/", b'5', b"", "");
+        member(&mut archive, "./src/main.rs", b'0', &[b'x'; 600], "");
+        member(&mut archive, "./src/copy.rs", b'1', b"", "./src/main.rs");
+        member(&mut archive, "./latest", b'2', b"", "src/main.rs");
+        let long = format!("{}/long.txt", "d".repeat(120));
+        member(&mut archive, "././@LongLink", b'L', long.as_bytes(), "");
+        member(&mut archive, "truncated", b'0', b"abc", "");
+        member(&mut archive, "pax", b'x', b"20 path=pax/name.md\n", "");
+        member(&mut archive, "short", b'0', b"hello", "");
+        archive.extend([0; 2 * BLOCK_LEN]);
+
+        let mut listing = ListingFileSystem::new(PathBuf::from("/backup.tar"));
+        read_members(&mut archive.as_slice(), &mut listing).expect("could not read archive");
+        let root = Path::new("/backup.tar");
+        let metadata = |path: &str| listing.metadata(&root.join(path)).ok().map(|m| (m.len, m.nlink));
+        assert_eq!(metadata("src/main.rs"), Some((600, 2)));
+        assert_eq!(metadata("src/copy.rs"), Some((600, 2)));
+        assert_eq!(metadata("latest"), Some((600, 2)));
+        assert_eq!(metadata(&long), Some((3, 1)));
+        assert_eq!(metadata("pax/name.md"), Some((5, 1)));
+        assert_eq!(listing.file_type(&root.join("latest")).ok(), Some(EntryKind::Symlink));
+
+        archive[BLOCK_LEN + 10] ^= 1;
+        let mut listing = ListingFileSystem::new(PathBuf::from("/backup.tar"));
+        assert!(read_members(&mut archive.as_slice(), &mut listing).is_err());
+    }
+
+    #[test]
+    fn test_parse_number() {
+        assert_eq!(parse_number(b"0000644\0").ok(), Some(0o644));
+        assert_eq!(parse_number(b"\0\0\0\0").ok(), Some(0));
+        assert_eq!(parse_number(&[0x80, 0, 0, 0, 0, 0, 0, 0x01, 0x00]).ok(), Some(256));
+        assert!(parse_number(b"9").is_err());
+    }
+
+    #[test]
+    fn test_parse_time() {
+        assert_eq!(parse_time(b"00000000012\0"), Some(UNIX_EPOCH + Duration::from_secs(10)));
+        // 1960-01-01, as stored by GNU tar.
+        let mut field = [0xFF; 12];
+        field[8..].copy_from_slice(&(-315_619_200i32).to_be_bytes());
+        assert_eq!(
+            parse_time(&field),
+            UNIX_EPOCH.checked_sub(Duration::from_secs(315_619_200))
+        );
+        assert_eq!(
+            parse_time(&[0x80, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0x01]),
+            UNIX_EPOCH.checked_add(Duration::from_secs(1))
+        );
+        assert_eq!(
+            parse_time(&[0x80, 0x7F, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF]),
+            None
+        );
+    }
+}
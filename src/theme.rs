@@ -0,0 +1,136 @@
+//! Colors of the elements of the output. The built-in palettes can be overridden with a theme file
+//! in a subset of TOML, mapping each element to a color:
+//!
+//! ```toml
+//! # element = color
+//! growth = "orange"
+//! shrinkage = "#0072B2"
+//! ```
+//!
+//! Colors are one of the eight basic terminal color names, `orange`, an index into the 256-color
+//! palette such as `208`, or a hexadecimal RGB value such as `#E69F00`. Empty lines and lines
+//! starting with `#` are ignored.
+
+use anyhow::{bail, Context, Result};
+use clap::ArgEnum;
+use std::path::Path;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ArgEnum)]
+pub enum Palette {
+    /// Red for growth and green for shrinkage.
+    #[default]
+    Default,
+
+    /// Orange for growth and blue for shrinkage, from the Okabe-Ito palette, which remain distinct
+    /// with the common forms of color blindness.
+    ColorBlind,
+}
+
+/// Escape sequences setting the color of each element.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Theme {
+    /// Extensions that grew since the baseline.
+    pub growth: String,
+
+    /// Extensions that shrank since the baseline.
+    pub shrinkage: String,
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Theme::from_palette(Palette::Default)
+    }
+}
+
+/// Escape sequence setting the foreground color described by `color`.
+fn parse_color(color: &str) -> Result<String> {
+    const NAMES: [&str; 8] = ["black", "red", "green", "yellow", "blue", "magenta", "cyan", "white"];
+    if let Some(idx) = NAMES.iter().position(|name| name.eq_ignore_ascii_case(color)) {
+        return Ok(format!("\x1b[{}m", 30 + idx));
+    }
+    if color.eq_ignore_ascii_case("orange") {
+        return Ok("\x1b[38;5;208m".to_string());
+    }
+    if let Ok(idx) = color.parse::<u8>() {
+        return Ok(format!("\x1b[38;5;{}m", idx));
+    }
+    match color.strip_prefix('#') {
+        Some(hex) if hex.len() == 6 && hex.chars().all(|c| c.is_ascii_hexdigit()) => {
+            let channel = |idx: usize| u8::from_str_radix(&hex[idx..idx + 2], 16).unwrap_or_default();
+            Ok(format!("\x1b[38;2;{};{};{}m", channel(0), channel(2), channel(4)))
+        }
+        _ => bail!("unknown color {}", color),
+    }
+}
+
+impl Theme {
+    pub fn from_palette(palette: Palette) -> Self {
+        match palette {
+            Palette::Default => Theme {
+                growth: "\x1b[31m".to_string(),
+                shrinkage: "\x1b[32m".to_string(),
+            },
+            Palette::ColorBlind => Theme {
+                growth: "\x1b[38;2;230;159;0m".to_string(),
+                shrinkage: "\x1b[38;2;0;114;178m".to_string(),
+            },
+        }
+    }
+
+    /// Set the colors of the elements listed in a theme file.
+    fn apply_overrides(&mut self, overrides: &str) -> Result<()> {
+        for (idx, line) in overrides.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let (element, color) = match line.split_once('=') {
+                Some((element, color)) => (element.trim(), color.trim().trim_matches('"')),
+                None => bail!("line {}: expected an element and a color", idx + 1),
+            };
+            let color = parse_color(color).with_context(|| format!("line {}", idx + 1))?;
+            match element {
+                "growth" => self.growth = color,
+                "shrinkage" => self.shrinkage = color,
+                _ => bail!("line {}: unknown element {}", idx + 1, element),
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The colors of a palette, overridden with those of a theme file if one is given.
+pub fn read_theme(palette: Palette, path: Option<&Path>) -> Result<Theme> {
+    let mut theme = Theme::from_palette(palette);
+    if let Some(path) = path {
+        let contents = std::fs::read_to_string(path).with_context(|| format!("could not read {}", path.display()))?;
+        theme
+            .apply_overrides(&contents)
+            .with_context(|| format!("invalid theme {}", path.display()))?;
+    }
+    Ok(theme)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_apply_overrides() {
+        let mut theme = Theme::from_palette(Palette::ColorBlind);
+        theme
+            .apply_overrides("# element = color\n\ngrowth = \"magenta\"\nshrinkage = #0072B2\n")
+            .expect("could not apply overrides");
+        assert_eq!(theme.growth, "\x1b[35m");
+        assert_eq!(theme.shrinkage, "\x1b[38;2;0;114;178m");
+
+        theme
+            .apply_overrides("growth = 208")
+            .expect("could not apply overrides");
+        assert_eq!(theme.growth, "\x1b[38;5;208m");
+        assert!(theme.apply_overrides("growth = mauve").is_err());
+        assert!(theme.apply_overrides("background = red").is_err());
+        assert!(theme.apply_overrides("growth").is_err());
+    }
+}
@@ -0,0 +1,108 @@
+use anyhow::{bail, Context, Result};
+use std::cell::Cell;
+use std::time::{Duration, Instant};
+
+/// Limits the rate of file system operations by sleeping between them.
+#[derive(Debug)]
+pub struct Throttle {
+    /// Minimum time between two operations.
+    interval: Duration,
+
+    /// Earliest time at which the next operation may start.
+    next: Cell<Instant>,
+}
+
+impl Throttle {
+    pub fn new(operations_per_second: f64) -> Result<Self> {
+        if !operations_per_second.is_finite() || operations_per_second <= 0.0 {
+            bail!("throttle must be a positive number of operations per second");
+        }
+
+        // The interval must also leave room to schedule operations after it.
+        let now = Instant::now();
+        let interval = Duration::try_from_secs_f64(1.0 / operations_per_second)
+            .ok()
+            .filter(|&interval| now.checked_add(interval).is_some())
+            .with_context(|| format!("throttle {:?} is too low", operations_per_second))?;
+        Ok(Self {
+            interval,
+            next: Cell::new(now),
+        })
+    }
+
+    /// Block until the next operation is allowed.
+    pub fn wait(&self) {
+        let now = Instant::now();
+        let next = self.next.get();
+        if next > now {
+            std::thread::sleep(next - now);
+            self.next.set(next + self.interval);
+        } else {
+            self.next.set(now + self.interval);
+        }
+    }
+}
+
+/// Limits the rate at which file contents are read, by sleeping after each read for as long as
+/// reading its bytes should have taken at the given rate.
+#[derive(Debug)]
+pub struct ByteThrottle {
+    bytes_per_second: f64,
+
+    /// Time until which the bytes read so far are paid for.
+    paid_until: Cell<Instant>,
+}
+
+impl ByteThrottle {
+    pub fn new(bytes_per_second: u64) -> Result<Self> {
+        if bytes_per_second == 0 {
+            bail!("read throttle must be a positive number of bytes per second");
+        }
+
+        Ok(Self {
+            bytes_per_second: bytes_per_second as f64,
+            paid_until: Cell::new(Instant::now()),
+        })
+    }
+
+    /// Account for bytes just read, blocking until they are within the rate.
+    pub fn consume(&self, bytes: usize) {
+        let now = Instant::now();
+        let paid_until = self.paid_until.get().max(now) + Duration::from_secs_f64(bytes as f64 / self.bytes_per_second);
+        self.paid_until.set(paid_until);
+        std::thread::sleep(paid_until - now);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_wait() {
+        let throttle = Throttle::new(1000.0).expect("could not create throttle");
+        let start = Instant::now();
+        for _ in 0..11 {
+            throttle.wait();
+        }
+        assert!(start.elapsed() >= Duration::from_millis(10));
+    }
+
+    #[test]
+    fn test_invalid_rate() {
+        assert!(Throttle::new(0.0).is_err());
+        assert!(Throttle::new(-5.0).is_err());
+        assert!(Throttle::new(1e-300).is_err());
+        assert!(ByteThrottle::new(0).is_err());
+    }
+
+    #[test]
+    fn test_consume() {
+        let throttle = ByteThrottle::new(100_000).expect("could not create throttle");
+        let start = Instant::now();
+        for _ in 0..4 {
+            throttle.consume(500);
+        }
+        assert!(start.elapsed() >= Duration::from_millis(20));
+    }
+}
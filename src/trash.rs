@@ -0,0 +1,53 @@
+//! Trash locations of operating systems, where deleted files stay until the trash is emptied.
+
+use clap::ArgEnum;
+use std::path::Path;
+
+/// How files in trash locations are counted.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ArgEnum)]
+pub enum Trash {
+    /// Like any other files.
+    #[default]
+    Include,
+
+    /// Not at all.
+    Exclude,
+
+    /// Separately, in a report listing each trash location, rather than in the tree.
+    Report,
+}
+
+/// Whether a directory is a trash location: `.Trash-<uid>` and `.Trash` directories of
+/// freedesktop.org desktops, `~/.local/share/Trash`, `.Trashes` on macOS volumes, and the
+/// `$RECYCLE.BIN` and `RECYCLER` directories of Windows drives.
+pub fn is_trash(dir: &Path) -> bool {
+    let name = dir.file_name().unwrap_or_default().to_string_lossy();
+    name.starts_with(".Trash-")
+        || name == ".Trash"
+        || name == ".Trashes"
+        || name.eq_ignore_ascii_case("$RECYCLE.BIN")
+        || name.eq_ignore_ascii_case("RECYCLER")
+        || dir.ends_with(".local/share/Trash")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_trash() {
+        for dir in [
+            "/media/usb/.Trash-1000",
+            "/Users/alice/.Trash",
+            "/Volumes/usb/.Trashes",
+            "/mnt/d/$Recycle.Bin",
+            "/mnt/c/$RECYCLE.BIN",
+            "/home/alice/.local/share/Trash",
+        ] {
+            assert!(is_trash(Path::new(dir)), "{}", dir);
+        }
+        for dir in ["/home/alice/Trash", "/home/alice/.local/share", "/srv/.Trashcan"] {
+            assert!(!is_trash(Path::new(dir)), "{}", dir);
+        }
+    }
+}
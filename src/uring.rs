@@ -0,0 +1,363 @@
+//! Batched `statx` through io_uring, so that looking up the metadata of all files in a directory
+//! costs a handful of system calls rather than one per file. Only the small subset of the io_uring
+//! interface needed for this is implemented, directly on top of the raw system calls.
+
+use crate::fs::Metadata;
+use std::cell::Cell;
+use std::ffi::{CStr, CString};
+use std::io;
+use std::os::unix::ffi::OsStrExt;
+use std::os::unix::io::{AsRawFd, FromRawFd, OwnedFd};
+use std::path::{Path, PathBuf};
+use std::ptr;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+const IORING_OFF_SQ_RING: libc::off_t = 0;
+const IORING_OFF_CQ_RING: libc::off_t = 0x8000000;
+const IORING_OFF_SQES: libc::off_t = 0x10000000;
+const IORING_ENTER_GETEVENTS: libc::c_uint = 1;
+const IORING_OP_STATX: u8 = 21;
+
+/// Fields of `statx` looked up for every file, see `fs::Metadata`.
+const STATX_MASK: u32 = libc::STATX_SIZE | libc::STATX_NLINK | libc::STATX_UID | libc::STATX_MODE | libc::STATX_MTIME;
+
+const SQE_SIZE: usize = 64;
+const CQE_SIZE: usize = 16;
+
+#[repr(C)]
+#[derive(Default)]
+struct SqRingOffsets {
+    head: u32,
+    tail: u32,
+    ring_mask: u32,
+    ring_entries: u32,
+    flags: u32,
+    dropped: u32,
+    array: u32,
+    resv1: u32,
+    resv2: u64,
+}
+
+#[repr(C)]
+#[derive(Default)]
+struct CqRingOffsets {
+    head: u32,
+    tail: u32,
+    ring_mask: u32,
+    ring_entries: u32,
+    overflow: u32,
+    cqes: u32,
+    flags: u32,
+    resv1: u32,
+    resv2: u64,
+}
+
+#[repr(C)]
+#[derive(Default)]
+struct Params {
+    sq_entries: u32,
+    cq_entries: u32,
+    flags: u32,
+    sq_thread_cpu: u32,
+    sq_thread_idle: u32,
+    features: u32,
+    wq_fd: u32,
+    resv: [u32; 3],
+    sq_off: SqRingOffsets,
+    cq_off: CqRingOffsets,
+}
+
+/// A memory mapping shared with the kernel, unmapped on drop.
+struct Mapping {
+    ptr: *mut u8,
+    len: usize,
+}
+
+impl Mapping {
+    fn new(fd: &OwnedFd, len: usize, offset: libc::off_t) -> io::Result<Self> {
+        // SAFETY: mapping a region of the io_uring file descriptor, as documented by io_uring(7).
+        let ptr = unsafe {
+            libc::mmap(
+                ptr::null_mut(),
+                len,
+                libc::PROT_READ | libc::PROT_WRITE,
+                libc::MAP_SHARED | libc::MAP_POPULATE,
+                fd.as_raw_fd(),
+                offset,
+            )
+        };
+        if ptr == libc::MAP_FAILED {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(Self { ptr: ptr.cast(), len })
+    }
+
+    /// Atomic view of the `u32` at the given byte offset.
+    fn atomic(&self, offset: u32) -> &AtomicU32 {
+        // SAFETY: the kernel guarantees ring offsets are in bounds and 4-byte aligned.
+        unsafe { &*self.ptr.add(offset as usize).cast::<AtomicU32>() }
+    }
+
+    fn write<T>(&self, offset: usize, value: T) {
+        debug_assert!(offset + std::mem::size_of::<T>() <= self.len);
+        // SAFETY: callers stay within the mapping, see the assertion above.
+        unsafe { self.ptr.add(offset).cast::<T>().write_unaligned(value) }
+    }
+
+    fn read<T>(&self, offset: usize) -> T {
+        debug_assert!(offset + std::mem::size_of::<T>() <= self.len);
+        // SAFETY: callers stay within the mapping, see the assertion above.
+        unsafe { self.ptr.add(offset).cast::<T>().read_unaligned() }
+    }
+}
+
+impl Drop for Mapping {
+    fn drop(&mut self) {
+        // SAFETY: the region was mapped in `Mapping::new` with this length.
+        unsafe { libc::munmap(self.ptr.cast(), self.len) };
+    }
+}
+
+/// An io_uring instance used to look up file metadata in batches.
+pub struct Ring {
+    fd: OwnedFd,
+    params: Params,
+    sq: Mapping,
+    cq: Mapping,
+    sqes: Mapping,
+
+    /// Whether lookups go through plain `statx` calls instead, because the kernel does not support
+    /// `statx` through io_uring, or because a submission failed while lookups were in flight.
+    unusable: Cell<bool>,
+}
+
+impl std::fmt::Debug for Ring {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Ring").field("fd", &self.fd).finish()
+    }
+}
+
+impl Ring {
+    /// Set up a ring submitting up to `queue_depth` lookups at once, rounded up to a power of two.
+    /// Fails on kernels without io_uring, or where it has been disabled.
+    pub fn new(queue_depth: u32) -> io::Result<Self> {
+        let mut params = Params::default();
+        // SAFETY: `params` is a valid `struct io_uring_params` for the kernel to fill in.
+        let fd = unsafe { libc::syscall(libc::SYS_io_uring_setup, queue_depth, &mut params as *mut Params) };
+        if fd < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        // SAFETY: the file descriptor was just created and is exclusively owned here.
+        let fd = unsafe { OwnedFd::from_raw_fd(fd as libc::c_int) };
+
+        let sq_len = params.sq_off.array as usize + params.sq_entries as usize * 4;
+        let cq_len = params.cq_off.cqes as usize + params.cq_entries as usize * CQE_SIZE;
+        let sq = Mapping::new(&fd, sq_len, IORING_OFF_SQ_RING)?;
+        let cq = Mapping::new(&fd, cq_len, IORING_OFF_CQ_RING)?;
+        let sqes = Mapping::new(&fd, params.sq_entries as usize * SQE_SIZE, IORING_OFF_SQES)?;
+
+        Ok(Self {
+            fd,
+            params,
+            sq,
+            cq,
+            sqes,
+            unusable: Cell::new(false),
+        })
+    }
+
+    /// Look up the metadata of the given files, following symlinks like `std::fs::metadata`.
+    pub fn metadata(&self, files: &[PathBuf]) -> Vec<io::Result<Metadata>> {
+        let mut metadata = Vec::with_capacity(files.len());
+        for batch in files.chunks(self.params.sq_entries as usize) {
+            match self.stat_batch(batch) {
+                Ok(batch_metadata) => metadata.extend(batch_metadata),
+                Err(_) => metadata.extend(batch.iter().map(|file| statx(file))),
+            }
+        }
+        metadata
+    }
+
+    fn stat_batch(&self, files: &[PathBuf]) -> io::Result<Vec<io::Result<Metadata>>> {
+        if self.unusable.get() {
+            return Err(io::Error::from(io::ErrorKind::Unsupported));
+        }
+        let paths = files
+            .iter()
+            .map(|f| CString::new(f.as_os_str().as_bytes()))
+            .collect::<Result<Vec<_>, _>>()?;
+        // SAFETY: `statx` is plain old data, for which all zeroes is a valid value.
+        let mut buffers: Vec<libc::statx> = (0..files.len()).map(|_| unsafe { std::mem::zeroed() }).collect();
+
+        let sq_mask = self.sq.atomic(self.params.sq_off.ring_mask).load(Ordering::Relaxed);
+        let sq_tail = self.sq.atomic(self.params.sq_off.tail);
+        let mut tail = sq_tail.load(Ordering::Acquire);
+        for (idx, (path, buffer)) in paths.iter().zip(buffers.iter_mut()).enumerate() {
+            let slot = (tail & sq_mask) as usize;
+            let sqe = slot * SQE_SIZE;
+            self.sqes.write(sqe, [0u8; SQE_SIZE]);
+            self.sqes.write::<u8>(sqe, IORING_OP_STATX);
+            self.sqes.write::<i32>(sqe + 4, libc::AT_FDCWD);
+            self.sqes.write::<u64>(sqe + 8, buffer as *mut libc::statx as u64);
+            self.sqes.write::<u64>(sqe + 16, path.as_ptr() as u64);
+            self.sqes.write::<u32>(sqe + 24, STATX_MASK);
+            self.sqes.write::<u32>(sqe + 28, libc::AT_STATX_SYNC_AS_STAT as u32);
+            self.sqes.write::<u64>(sqe + 32, idx as u64);
+            self.sq
+                .write::<u32>(self.params.sq_off.array as usize + slot * 4, slot as u32);
+            tail = tail.wrapping_add(1);
+        }
+        sq_tail.store(tail, Ordering::Release);
+
+        let mut results: Vec<Option<io::Result<Metadata>>> = (0..files.len()).map(|_| None).collect();
+        let mut to_submit = files.len() as u32;
+        let mut completed = 0;
+        while completed < files.len() {
+            // SAFETY: the ring file descriptor is valid, no signal mask is passed.
+            let entered = unsafe {
+                libc::syscall(
+                    libc::SYS_io_uring_enter,
+                    self.fd.as_raw_fd(),
+                    to_submit,
+                    1u32,
+                    IORING_ENTER_GETEVENTS,
+                    ptr::null::<libc::sigset_t>(),
+                    0usize,
+                )
+            };
+            if entered < 0 {
+                let error = io::Error::last_os_error();
+                if error.kind() == io::ErrorKind::Interrupted {
+                    continue;
+                }
+                // Lookups already submitted may still write into the buffers, and their completions
+                // would be taken for those of a later batch: the buffers and paths are left to the
+                // kernel, and the ring is no longer used.
+                self.unusable.set(true);
+                std::mem::forget(buffers);
+                std::mem::forget(paths);
+                return Err(error);
+            }
+            to_submit -= (entered as u32).min(to_submit);
+
+            let cq_head = self.cq.atomic(self.params.cq_off.head);
+            let cq_mask = self.cq.atomic(self.params.cq_off.ring_mask).load(Ordering::Relaxed);
+            let cq_tail = self.cq.atomic(self.params.cq_off.tail).load(Ordering::Acquire);
+            let mut head = cq_head.load(Ordering::Relaxed);
+            while head != cq_tail {
+                let cqe = self.params.cq_off.cqes as usize + (head & cq_mask) as usize * CQE_SIZE;
+                let idx = self.cq.read::<u64>(cqe) as usize;
+                let res = self.cq.read::<i32>(cqe + 8);
+                if let Some(result) = results.get_mut(idx) {
+                    *result = Some(if res == -libc::EINVAL {
+                        // Kernels before 5.6 reject statx through io_uring.
+                        self.unusable.set(true);
+                        statx_c(&paths[idx])
+                    } else if res < 0 {
+                        Err(io::Error::from_raw_os_error(-res))
+                    } else {
+                        Ok(to_metadata(&buffers[idx]))
+                    });
+                    completed += 1;
+                }
+                head = head.wrapping_add(1);
+            }
+            cq_head.store(head, Ordering::Release);
+        }
+
+        Ok(results
+            .into_iter()
+            .map(|r| r.expect("every submission completed"))
+            .collect())
+    }
+}
+
+fn to_metadata(buffer: &libc::statx) -> Metadata {
+    Metadata {
+        len: buffer.stx_size,
+        nlink: buffer.stx_nlink.into(),
+        uid: buffer.stx_uid,
+        mode: u32::from(buffer.stx_mode) & 0o7777,
+        modified: modified(&buffer.stx_mtime),
+        placeholder: false,
+    }
+}
+
+/// Look up the metadata of a file with a plain `statx` system call, when the ring cannot be used.
+fn statx(file: &Path) -> io::Result<Metadata> {
+    let path = CString::new(file.as_os_str().as_bytes())?;
+    statx_c(&path)
+}
+
+fn statx_c(path: &CStr) -> io::Result<Metadata> {
+    // SAFETY: `statx` is plain old data, for which all zeroes is a valid value.
+    let mut buffer: libc::statx = unsafe { std::mem::zeroed() };
+    // SAFETY: the path is NUL-terminated and the buffer is a valid `struct statx`.
+    let ret = unsafe {
+        libc::statx(
+            libc::AT_FDCWD,
+            path.as_ptr(),
+            libc::AT_STATX_SYNC_AS_STAT,
+            STATX_MASK,
+            &mut buffer,
+        )
+    };
+    if ret < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(to_metadata(&buffer))
+}
+
+/// Convert a `statx` timestamp, which may predate the epoch.
+fn modified(timestamp: &libc::statx_timestamp) -> Option<SystemTime> {
+    let since_epoch = Duration::new(timestamp.tv_sec.unsigned_abs(), 0);
+    let time = if timestamp.tv_sec >= 0 {
+        UNIX_EPOCH.checked_add(since_epoch)?
+    } else {
+        UNIX_EPOCH.checked_sub(since_epoch)?
+    };
+    time.checked_add(Duration::from_nanos(timestamp.tv_nsec.into()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_metadata() {
+        // io_uring may be unavailable or forbidden, in which case there is nothing to test.
+        let ring = match Ring::new(2) {
+            Ok(ring) => ring,
+            Err(_) => return,
+        };
+
+        let root = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests");
+        let files = vec![
+            root.join("10.baz"),
+            root.join("dirA").join("16.foo"),
+            root.join("non-existent"),
+        ];
+        let metadata = ring.metadata(&files);
+        assert_eq!(metadata[0].as_ref().map(|m| m.len).ok(), Some(10));
+        assert_eq!(metadata[1].as_ref().map(|m| (m.len, m.nlink)).ok(), Some((16, 1)));
+        assert_eq!(
+            metadata[1].as_ref().ok().and_then(|m| m.modified),
+            std::fs::metadata(&files[1]).and_then(|m| m.modified()).ok()
+        );
+        assert_eq!(
+            metadata[2].as_ref().map_err(|e| e.kind()).err(),
+            Some(io::ErrorKind::NotFound)
+        );
+    }
+
+    #[test]
+    fn test_statx() {
+        let file = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests").join("10.baz");
+        assert_eq!(statx(&file).map(|m| m.len).ok(), Some(10));
+        assert_eq!(
+            statx(Path::new("/non-existent")).map_err(|e| e.kind()).err(),
+            Some(io::ErrorKind::NotFound)
+        );
+    }
+}